@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::convert::Infallible;
 use std::fs;
 use std::net::SocketAddr;
@@ -35,6 +36,15 @@ use wp_error::run_error::{RunReason, RunResult};
 use wp_log::{info_ctrl, warn_ctrl};
 
 const DEFAULT_AUTH_MODE: &str = "bearer_token";
+const WEBHOOK_ROUTE: &str = "/admin/v1/project/webhook";
+const WEBHOOK_ORIGIN: &str = "project_remote_webhook";
+/// Push payloads list commits and repository metadata and are well past the
+/// usual `admin_api.max_body_bytes`.
+const WEBHOOK_MAX_BODY_BYTES: usize = 1024 * 1024;
+/// How long a queued webhook update waits for the runtime to finish its
+/// reload before leaving it to the background monitor.
+const WEBHOOK_RELOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const RESTART_ROUTE: &str = "/admin/v1/runtime/restart";
 
 #[derive(Debug)]
 pub struct AdminApiRuntime {
@@ -56,29 +66,136 @@ impl AdminApiRuntime {
     }
 }
 
+/// Serialises update-and-reload runs between the admin API and the
+/// project_remote poller, and carries the poller status that
+/// `runtime/status` reports. A hot restart takes the same gate and keeps
+/// it while the old process drains.
+#[derive(Clone, Default)]
+pub struct ReloadCoordinator {
+    gate: Arc<Mutex<()>>,
+    poll_status: Arc<std::sync::Mutex<Option<crate::remote_poll::ProjectRemotePollStatus>>>,
+    hot_restart: Option<crate::hot_restart::HotRestart>,
+}
+
+impl ReloadCoordinator {
+    /// Enables `POST /admin/v1/runtime/restart`.
+    pub fn with_hot_restart(mut self, restart: crate::hot_restart::HotRestart) -> Self {
+        self.hot_restart = Some(restart);
+        self
+    }
+
+    pub(crate) fn hot_restart(&self) -> Option<&crate::hot_restart::HotRestart> {
+        self.hot_restart.as_ref()
+    }
+
+    pub(crate) fn try_gate(&self) -> Option<tokio::sync::MutexGuard<'_, ()>> {
+        self.gate.try_lock().ok()
+    }
+
+    /// Waits for the gate instead of giving up; webhook updates queue here.
+    pub(crate) async fn wait_gate(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.gate.lock().await
+    }
+
+    pub(crate) fn try_gate_owned(&self) -> Option<tokio::sync::OwnedMutexGuard<()>> {
+        self.gate.clone().try_lock_owned().ok()
+    }
+
+    pub(crate) async fn wait_gate_owned(&self) -> tokio::sync::OwnedMutexGuard<()> {
+        self.gate.clone().lock_owned().await
+    }
+
+    pub(crate) fn poll_status(&self) -> Option<crate::remote_poll::ProjectRemotePollStatus> {
+        self.poll_status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn update_poll_status<F>(&self, update: F)
+    where
+        F: FnOnce(&mut crate::remote_poll::ProjectRemotePollStatus),
+    {
+        let mut guard = self
+            .poll_status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(status) = guard.as_mut() {
+            update(status);
+        }
+    }
+
+    pub(crate) fn set_poll_status(&self, status: crate::remote_poll::ProjectRemotePollStatus) {
+        *self
+            .poll_status
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(status);
+    }
+}
+
 pub async fn start_if_enabled(
     work_root: &Path,
     dict: &EnvDict,
     control_handle: RuntimeControlHandle,
+) -> RunResult<Option<AdminApiRuntime>> {
+    start_with_coordinator(
+        work_root,
+        dict,
+        control_handle,
+        ReloadCoordinator::default(),
+    )
+    .await
+}
+
+/// Like [`start_if_enabled`], sharing `coordinator` with the daemon's
+/// project_remote poller.
+pub async fn start_with_coordinator(
+    work_root: &Path,
+    dict: &EnvDict,
+    control_handle: RuntimeControlHandle,
+    coordinator: ReloadCoordinator,
 ) -> RunResult<Option<AdminApiRuntime>> {
     let config = load_config(work_root, dict)?;
     let Some(config) = config else {
         return Ok(None);
     };
 
-    let listener = TcpListener::bind(config.bind)
-        .await
-        .map_err(|e| conf_err_source(format!("bind admin api on {} failed", config.bind), e))?;
+    // After a hot restart the old process's socket is adopted, so requests
+    // queued during the handoff are not refused.
+    let listener = match crate::hot_restart::take_inherited_tcp(config.bind) {
+        Some(inherited) => TcpListener::from_std(inherited).map_err(|e| {
+            conf_err_source(
+                format!("adopt inherited admin api listener {} failed", config.bind),
+                e,
+            )
+        })?,
+        None => TcpListener::bind(config.bind)
+            .await
+            .map_err(|e| conf_err_source(format!("bind admin api on {} failed", config.bind), e))?,
+    };
     let local_addr = listener
         .local_addr()
         .map_err(|e| conf_err_source("read admin api local addr failed", e))?;
+    if let Some(restart) = coordinator.hot_restart() {
+        restart.set_admin_listener(local_addr);
+    }
+    let webhook = crate::project_remote::load_webhook_conf(work_root, dict)?;
+    if webhook.is_some() {
+        info_ctrl!(
+            "admin api project webhook enabled work_root={} route={}",
+            work_root.display(),
+            WEBHOOK_ROUTE
+        );
+    }
     let instance_id = format!("{}:{}", hostname_for_instance(), std::process::id());
     let state = Arc::new(AppState {
         control_handle,
         work_root: work_root.to_path_buf(),
         dict: dict.clone(),
-        reload_gate: Mutex::new(()),
+        coordinator,
         bearer_token: config.bearer_token,
+        webhook,
+        webhook_queue: std::sync::Mutex::new(crate::project_remote::WebhookQueue::default()),
         request_timeout: config.request_timeout,
         max_body_bytes: config.max_body_bytes,
         instance_id,
@@ -384,8 +501,12 @@ struct AppState {
     control_handle: RuntimeControlHandle,
     work_root: PathBuf,
     dict: EnvDict,
-    reload_gate: Mutex<()>,
+    coordinator: ReloadCoordinator,
     bearer_token: String,
+    /// Set when `[project_remote.webhook]` is enabled.
+    webhook: Option<crate::project_remote::ProjectRemoteWebhookConf>,
+    /// Webhook updates waiting for the reload gate, drained by one task.
+    webhook_queue: std::sync::Mutex<crate::project_remote::WebhookQueue>,
     request_timeout: Duration,
     max_body_bytes: usize,
     instance_id: String,
@@ -397,6 +518,7 @@ struct ProjectRemoteReloadContext {
     snapshot: Option<crate::project_remote::ProjectRemoteSnapshot>,
     runtime_snapshot: Option<crate::project_remote::ProjectRuntimeArtifactSnapshot>,
     update_result: Option<crate::project_remote::ProjectRemoteUpdateResult>,
+    set_result: Option<crate::project_remote::ProjectRemoteSetResult>,
     #[allow(dead_code)]
     group: Option<crate::project_remote::RemoteGroup>,
 }
@@ -410,6 +532,13 @@ struct ReloadRequest {
     version: Option<String>,
     #[serde(default)]
     group: Option<String>,
+    /// `{group: version}` moved as one release; excludes `version`/`group`.
+    #[serde(default)]
+    release_set: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    skip_validate: bool,
+    #[serde(default)]
+    discard_local: bool,
     timeout_ms: Option<u64>,
     reason: Option<String>,
 }
@@ -435,6 +564,13 @@ struct ReloadResponse {
     warning: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation: Option<crate::project_remote::ProjectRemoteValidation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_notes: Option<crate::project_remote::ReleaseNotes>,
+    /// Per-group results of a release set, in group order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_set: Option<Vec<crate::project_remote::ProjectRemoteUpdateResult>>,
 }
 
 #[derive(Serialize)]
@@ -449,6 +585,45 @@ struct RuntimeStatusResponse {
     last_reload_result: Option<&'static str>,
     last_reload_started_at: Option<String>,
     last_reload_finished_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_remote_poll: Option<crate::remote_poll::ProjectRemotePollStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    project_drift: Vec<crate::project_remote::ManagedDriftStatus>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct RestartRequest {
+    timeout_ms: Option<u64>,
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RestartResponse {
+    request_id: String,
+    accepted: bool,
+    result: &'static str,
+    new_pid: u32,
+    listeners: Vec<crate::hot_restart::HandoffListener>,
+}
+
+#[derive(Serialize)]
+struct WebhookResponse {
+    request_id: String,
+    accepted: bool,
+    result: &'static str,
+    provider: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<WebhookTargetResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WebhookTargetResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    version: String,
+    tag: String,
 }
 
 #[derive(Serialize)]
@@ -468,6 +643,12 @@ async fn handle_request(
     let path = req.uri().path().to_string();
     let method = req.method().clone();
 
+    // Git servers cannot send the bearer token; deliveries carry a signature
+    // over the body instead.
+    if method == Method::POST && path == WEBHOOK_ROUTE && state.webhook.is_some() {
+        return Ok(webhook_response(req, &request_id, remote_addr, state).await);
+    }
+
     if !authorized(req.headers(), &state.bearer_token) {
         warn_ctrl!(
             "admin api unauthorized request_id={} remote={} method={} path={}",
@@ -494,6 +675,9 @@ async fn handle_request(
         (Method::POST, "/admin/v1/reloads/model") => {
             reload_response(req, &request_id, remote_addr, state).await
         }
+        (Method::POST, RESTART_ROUTE) => {
+            restart_response(req, &request_id, remote_addr, state).await
+        }
         _ => json_response(
             StatusCode::NOT_FOUND,
             &ErrorResponse {
@@ -526,6 +710,18 @@ fn status_response(
             None
         }
     };
+    let project_drift = match crate::project_remote::project_remote_drift_status(&state.work_root) {
+        Ok(drift) => drift,
+        Err(err) => {
+            warn_ctrl!(
+                "admin api status project drift read failed request_id={} remote={} error={}",
+                request_id,
+                remote_addr,
+                err
+            );
+            Vec::new()
+        }
+    };
     info_ctrl!(
         "admin api status request_id={} remote={} accepting={} reloading={}",
         request_id,
@@ -546,6 +742,8 @@ fn status_response(
             last_reload_result: snapshot.last_reload_result.as_ref().map(result_code),
             last_reload_started_at: snapshot.last_reload_started_at.map(system_time_to_rfc3339),
             last_reload_finished_at: snapshot.last_reload_finished_at.map(system_time_to_rfc3339),
+            project_remote_poll: state.coordinator.poll_status(),
+            project_drift,
         },
     )
 }
@@ -556,9 +754,9 @@ async fn reload_response(
     remote_addr: SocketAddr,
     state: Arc<AppState>,
 ) -> Response<Full<Bytes>> {
-    let _reload_guard = match state.reload_gate.try_lock() {
-        Ok(guard) => guard,
-        Err(_) => {
+    let _reload_guard = match state.coordinator.try_gate() {
+        Some(guard) => guard,
+        None => {
             return json_response(
                 StatusCode::CONFLICT,
                 &ReloadResponse {
@@ -573,6 +771,9 @@ async fn reload_response(
                     force_replaced: None,
                     warning: None,
                     error: None,
+                    validation: None,
+                    release_notes: None,
+                    release_set: None,
                 },
             )
         }
@@ -638,27 +839,57 @@ async fn reload_response(
             },
         );
     }
+    let release_set = match reload_req.release_set.as_ref() {
+        None => None,
+        Some(_) if !reload_req.update => {
+            return invalid_reload_request(request_id, "release_set requires update=true");
+        }
+        Some(_)
+            if reload_req.version.is_some()
+                || reload_req.group.as_deref().is_some_and(|g| !g.is_empty()) =>
+        {
+            return invalid_reload_request(
+                request_id,
+                "release_set cannot be combined with version or group",
+            );
+        }
+        Some(map) => match crate::project_remote::release_set_from_map(map) {
+            Ok(set) if set.is_empty() => {
+                return invalid_reload_request(request_id, "release_set names no group");
+            }
+            Ok(set) => Some(set),
+            Err(err) => return invalid_reload_request(request_id, &err),
+        },
+    };
 
-    // In dual-repo mode, update requires --group
-    if reload_req.update && reload_req.group.as_deref().map_or(true, |g| g.is_empty()) {
+    // In multi-repo mode, update requires --group
+    if reload_req.update
+        && release_set.is_none()
+        && reload_req.group.as_deref().map_or(true, |g| g.is_empty())
+    {
         if let Ok(config) = load_engine_config(&state.work_root, &state.dict) {
             let remote_conf = config.project_remote();
-            if remote_conf.enabled
-                && matches!(
-                    crate::project_remote::resolve_project_remote_mode(remote_conf),
-                    Ok(crate::project_remote::ProjectRemoteMode::Dual { .. })
-                )
-            {
-                return json_response(
-                    StatusCode::BAD_REQUEST,
-                    &ErrorResponse {
-                        request_id: request_id.to_string(),
-                        accepted: false,
-                        result: "invalid_request",
-                        error: "dual-repo mode requires group (models|infra) with update=true"
-                            .to_string(),
-                    },
-                );
+            if remote_conf.enabled {
+                if let Ok(mode @ crate::project_remote::ProjectRemoteMode::Groups(_)) =
+                    crate::project_remote::resolve_project_remote_mode(
+                        &state.work_root,
+                        remote_conf,
+                        &state.dict,
+                    )
+                {
+                    return json_response(
+                        StatusCode::BAD_REQUEST,
+                        &ErrorResponse {
+                            request_id: request_id.to_string(),
+                            accepted: false,
+                            result: "invalid_request",
+                            error: format!(
+                                "multi-repo mode requires group ({}) with update=true",
+                                mode.group_names().join("|")
+                            ),
+                        },
+                    );
+                }
             }
         }
     }
@@ -690,6 +921,9 @@ async fn reload_response(
                 force_replaced: None,
                 warning: None,
                 error: None,
+                validation: None,
+                release_notes: None,
+                release_set: None,
             },
         );
     }
@@ -711,6 +945,9 @@ async fn reload_response(
                     force_replaced: None,
                     warning: None,
                     error: Some(err.to_string()),
+                    validation: None,
+                    release_notes: None,
+                    release_set: None,
                 },
             );
         }
@@ -737,7 +974,7 @@ async fn reload_response(
     let rollback_snapshot = if reload_req.update {
         match crate::project_remote::capture_project_remote_snapshot_with_group(
             &state.work_root,
-            update_group,
+            update_group.clone(),
         ) {
             Ok(snapshot) => Some(snapshot),
             Err(err) => {
@@ -774,7 +1011,96 @@ async fn reload_response(
         None
     };
 
-    let update_result = if reload_req.update {
+    let set_result = match release_set.as_ref() {
+        None => None,
+        Some(set) => {
+            let listing = set
+                .iter()
+                .map(|(group, version)| format!("{}={}", group, version))
+                .collect::<Vec<_>>()
+                .join(",");
+            info_ctrl!(
+                "admin api project release set start request_id={} remote={} groups={}",
+                request_id,
+                remote_addr,
+                listing
+            );
+            let work_root = state.work_root.clone();
+            let dict = state.dict.clone();
+            let set = set.clone();
+            let opts = sync_options(&reload_req);
+            let synced = run_sync_blocking(move || {
+                crate::project_remote::sync_project_remote_release_set_with_dict(
+                    &work_root, &set, &dict, &opts,
+                )
+            })
+            .await;
+            match synced {
+                Ok(result) if result.validation_failed() => {
+                    let summary = result
+                        .validation
+                        .as_ref()
+                        .map(crate::project_remote::ProjectRemoteValidation::summary)
+                        .unwrap_or_default();
+                    warn_ctrl!(
+                        "admin api project release set blocked by validation request_id={} remote={} groups={} failures={}",
+                        request_id,
+                        remote_addr,
+                        listing,
+                        summary
+                    );
+                    return json_response(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        &ReloadResponse {
+                            request_id: request_id.to_string(),
+                            accepted: false,
+                            result: "validation_failed",
+                            update: Some(true),
+                            requested_version: None,
+                            current_version: None,
+                            resolved_tag: None,
+                            group: None,
+                            force_replaced: None,
+                            warning: None,
+                            error: Some(format!("project validation failed: {}", summary)),
+                            validation: result.validation,
+                            release_notes: None,
+                            release_set: Some(result.groups),
+                        },
+                    );
+                }
+                Ok(result) => {
+                    info_ctrl!(
+                        "admin api project release set done request_id={} remote={} groups={} changed={}",
+                        request_id,
+                        remote_addr,
+                        result.summary(),
+                        result.changed()
+                    );
+                    Some(result)
+                }
+                Err(err) => {
+                    warn_ctrl!(
+                        "admin api project release set failed request_id={} remote={} groups={} error={}",
+                        request_id,
+                        remote_addr,
+                        listing,
+                        err
+                    );
+                    return json_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &ErrorResponse {
+                            request_id: request_id.to_string(),
+                            accepted: false,
+                            result: "update_failed",
+                            error: err.to_string(),
+                        },
+                    );
+                }
+            }
+        }
+    };
+    let update_result = if reload_req.update && release_set.is_none() {
         info_ctrl!(
             "admin api project update start request_id={} remote={} requested_version={} group={}",
             request_id,
@@ -782,20 +1108,49 @@ async fn reload_response(
             reload_req.version.as_deref().unwrap_or("(auto)"),
             reload_req.group.as_deref().unwrap_or("-")
         );
-        let sync_result = match update_group {
-            Some(group) => crate::project_remote::sync_project_remote_group_with_dict(
-                &state.work_root,
-                group,
-                reload_req.version.as_deref(),
-                &state.dict,
-            ),
-            None => crate::project_remote::sync_project_remote_with_dict(
-                &state.work_root,
-                reload_req.version.as_deref(),
-                &state.dict,
-            ),
-        };
+        let sync_result = sync_remote_blocking(
+            state.work_root.clone(),
+            state.dict.clone(),
+            update_group.clone(),
+            reload_req.version.clone(),
+            sync_options(&reload_req),
+        )
+        .await;
         match sync_result {
+            Ok(result) if result.validation_failed() => {
+                let summary = result
+                    .validation
+                    .as_ref()
+                    .map(crate::project_remote::ProjectRemoteValidation::summary)
+                    .unwrap_or_default();
+                warn_ctrl!(
+                    "admin api project update blocked by validation request_id={} remote={} requested_version={} resolved_tag={} failures={}",
+                    request_id,
+                    remote_addr,
+                    reload_req.version.as_deref().unwrap_or("(auto)"),
+                    result.resolved_tag,
+                    summary
+                );
+                return json_response(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    &ReloadResponse {
+                        request_id: request_id.to_string(),
+                        accepted: false,
+                        result: "validation_failed",
+                        update: Some(true),
+                        requested_version: result.requested_version.clone(),
+                        current_version: None,
+                        resolved_tag: Some(result.resolved_tag.clone()),
+                        group: result.group.clone(),
+                        force_replaced: None,
+                        warning: None,
+                        error: Some(format!("project validation failed: {}", summary)),
+                        validation: result.validation,
+                        release_notes: result.release_notes,
+                        release_set: None,
+                    },
+                );
+            }
             Ok(result) => {
                 info_ctrl!(
                     "admin api project update done request_id={} remote={} requested_version={} current_version={} resolved_tag={} from_revision={} to_revision={} changed={}",
@@ -837,6 +1192,7 @@ async fn reload_response(
         snapshot: rollback_snapshot,
         runtime_snapshot,
         update_result: update_result.clone(),
+        set_result: set_result.clone(),
         group: update_group,
     });
 
@@ -883,6 +1239,9 @@ async fn reload_response(
                         force_replaced: None,
                         warning: None,
                         error: None,
+                        validation: None,
+                        release_notes: update_result.as_ref().and_then(|r| r.release_notes.clone()),
+                        release_set: set_result.as_ref().map(|s| s.groups.clone()),
                     },
                 );
             }
@@ -912,6 +1271,7 @@ async fn reload_response(
                         remote_addr,
                         reason,
                         update_result.as_ref(),
+                        set_result.as_ref(),
                         rollback_warning,
                     )
                 }
@@ -941,6 +1301,9 @@ async fn reload_response(
                             "response_channel_closed",
                         ),
                         error: Some("runtime response channel closed".to_string()),
+                        validation: None,
+                        release_notes: update_result.as_ref().and_then(|r| r.release_notes.clone()),
+                        release_set: set_result.as_ref().map(|s| s.groups.clone()),
                     },
                 ),
                 Err(_) => {
@@ -980,6 +1343,11 @@ async fn reload_response(
                             force_replaced: None,
                             warning: None,
                             error: None,
+                            validation: None,
+                            release_notes: update_result
+                                .as_ref()
+                                .and_then(|r| r.release_notes.clone()),
+                            release_set: set_result.as_ref().map(|s| s.groups.clone()),
                         },
                     )
                 }
@@ -1003,6 +1371,7 @@ fn map_runtime_response(
     remote_addr: SocketAddr,
     reason: &str,
     update_result: Option<&crate::project_remote::ProjectRemoteUpdateResult>,
+    set_result: Option<&crate::project_remote::ProjectRemoteSetResult>,
     rollback_warning: Option<String>,
 ) -> Response<Full<Bytes>> {
     match resp.result {
@@ -1019,7 +1388,7 @@ fn map_runtime_response(
                     request_id: resp.request_id,
                     accepted: resp.accepted,
                     result: "reload_done",
-                    update: update_result.map(|_| true),
+                    update: (update_result.is_some() || set_result.is_some()).then_some(true),
                     requested_version: update_result
                         .and_then(|result| result.requested_version.clone()),
                     current_version: update_result.map(|result| result.current_version.clone()),
@@ -1028,6 +1397,9 @@ fn map_runtime_response(
                     force_replaced: Some(false),
                     warning: rollback_warning,
                     error: None,
+                    validation: None,
+                    release_notes: update_result.and_then(|r| r.release_notes.clone()),
+                    release_set: set_result.map(|s| s.groups.clone()),
                 },
             )
         }
@@ -1044,7 +1416,7 @@ fn map_runtime_response(
                     request_id: resp.request_id,
                     accepted: resp.accepted,
                     result: "reload_done",
-                    update: update_result.map(|_| true),
+                    update: (update_result.is_some() || set_result.is_some()).then_some(true),
                     requested_version: update_result
                         .and_then(|result| result.requested_version.clone()),
                     current_version: update_result.map(|result| result.current_version.clone()),
@@ -1055,6 +1427,9 @@ fn map_runtime_response(
                         Some("graceful drain timed out, fallback to force replace".to_string())
                     }),
                     error: None,
+                    validation: None,
+                    release_notes: update_result.and_then(|r| r.release_notes.clone()),
+                    release_set: set_result.map(|s| s.groups.clone()),
                 },
             )
         }
@@ -1072,7 +1447,7 @@ fn map_runtime_response(
                     request_id: resp.request_id,
                     accepted: resp.accepted,
                     result: "reload_failed",
-                    update: update_result.map(|_| true),
+                    update: (update_result.is_some() || set_result.is_some()).then_some(true),
                     requested_version: update_result
                         .and_then(|result| result.requested_version.clone()),
                     current_version: update_result.map(|result| result.current_version.clone()),
@@ -1081,6 +1456,9 @@ fn map_runtime_response(
                     force_replaced: None,
                     warning: rollback_warning,
                     error: Some(err),
+                    validation: None,
+                    release_notes: update_result.and_then(|r| r.release_notes.clone()),
+                    release_set: set_result.map(|s| s.groups.clone()),
                 },
             )
         }
@@ -1091,7 +1469,7 @@ fn rollback_updated_project(
     work_root: &Path,
     reload_ctx: Option<&ProjectRemoteReloadContext>,
     request_id: &str,
-    remote_addr: SocketAddr,
+    remote_addr: impl std::fmt::Display,
     stage: &str,
 ) -> Option<String> {
     let ctx = match reload_ctx {
@@ -1106,6 +1484,42 @@ fn rollback_updated_project(
             return None;
         }
     };
+    if let (Some(set), Some(snapshot), Some(runtime_snapshot)) = (
+        ctx.set_result.as_ref(),
+        ctx.snapshot.as_ref(),
+        ctx.runtime_snapshot.as_ref(),
+    ) {
+        let mut errs = Vec::new();
+        if let Err(err) =
+            crate::project_remote::restore_project_remote_release_set(work_root, snapshot, set)
+        {
+            errs.push(format!("restore project failed: {}", err));
+        }
+        if let Err(err) =
+            crate::project_remote::restore_runtime_artifact_snapshot(work_root, runtime_snapshot)
+        {
+            errs.push(format!("restore runtime artifacts failed: {}", err));
+        }
+        if errs.is_empty() {
+            info_ctrl!(
+                "admin api project release set rollback done request_id={} remote={} stage={} groups={}",
+                request_id,
+                remote_addr,
+                stage,
+                set.summary()
+            );
+            return None;
+        }
+        let warning = errs.join("; ");
+        warn_ctrl!(
+            "admin api project release set rollback failed request_id={} remote={} stage={} error={}",
+            request_id,
+            remote_addr,
+            stage,
+            warning
+        );
+        return Some(format!("project rollback failed: {}", warning));
+    }
     let (snapshot, runtime_snapshot, changed, version) = match (
         ctx.snapshot.as_ref(),
         ctx.runtime_snapshot.as_ref(),
@@ -1204,13 +1618,15 @@ fn rollback_project_and_runtime(
         .with_detail(errs.join("; ")))
 }
 
-async fn monitor_reload_result(
+async fn monitor_reload_result<R>(
     reply_rx: oneshot::Receiver<RuntimeCommandResp>,
     work_root: PathBuf,
     reload_ctx: ProjectRemoteReloadContext,
-    remote_addr: SocketAddr,
+    remote_addr: R,
     reason: String,
-) {
+) where
+    R: std::fmt::Display + Send + 'static,
+{
     match reply_rx.await {
         Ok(resp) => {
             if matches!(resp.result, RuntimeCommandResult::ReloadFailed { .. }) {
@@ -1218,7 +1634,7 @@ async fn monitor_reload_result(
                     &work_root,
                     Some(&reload_ctx),
                     &resp.request_id,
-                    remote_addr,
+                    &remote_addr,
                     "background_reload_failed",
                 );
             }
@@ -1235,7 +1651,7 @@ async fn monitor_reload_result(
                 &work_root,
                 Some(&reload_ctx),
                 "<unknown>",
-                remote_addr,
+                &remote_addr,
                 "background_channel_closed",
             );
             warn_ctrl!(
@@ -1247,52 +1663,681 @@ async fn monitor_reload_result(
     }
 }
 
-fn read_project_version(work_root: &Path) -> RunResult<Option<serde_json::Value>> {
-    match crate::project_remote::current_project_group_versions(work_root)? {
-        Some(group_versions) => Ok(Some(group_versions)),
-        None => Ok(crate::project_remote::current_project_version(work_root)?
-            .map(serde_json::Value::String)),
-    }
-}
-
-fn map_send_error(
+/// `POST /admin/v1/runtime/restart`: hands the listening sockets to a new
+/// process started from the binary on disk and, once it is ready, drains
+/// this one. The reply is sent before the drain starts.
+async fn restart_response(
+    req: Request<Incoming>,
     request_id: &str,
     remote_addr: SocketAddr,
-    reason: &str,
-    err: RuntimeCommandSendError,
+    state: Arc<AppState>,
 ) -> Response<Full<Bytes>> {
-    match err {
-        RuntimeCommandSendError::ReloadBusy => {
-            warn_ctrl!(
-                "admin api reload busy request_id={} remote={} reason={}",
+    let Some(restart) = state.coordinator.hot_restart().cloned() else {
+        return invalid_reload_request(request_id, "hot restart is not available");
+    };
+    let Some(gate) = state.coordinator.try_gate_owned() else {
+        return json_response(
+            StatusCode::CONFLICT,
+            &ErrorResponse {
+                request_id: request_id.to_string(),
+                accepted: false,
+                result: "reload_in_progress",
+                error: "another reload, update or restart is running".to_string(),
+            },
+        );
+    };
+    let body = match read_body(req.into_body(), state.max_body_bytes).await {
+        Ok(body) => body,
+        Err(ReadBodyError::TooLarge(limit)) => {
+            return json_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &ErrorResponse {
+                    request_id: request_id.to_string(),
+                    accepted: false,
+                    result: "payload_too_large",
+                    error: format!("request body exceeds {} bytes", limit),
+                },
+            );
+        }
+        Err(ReadBodyError::InvalidJson(err)) | Err(ReadBodyError::Read(err)) => {
+            return invalid_reload_request(request_id, &err);
+        }
+    };
+    let restart_req = if body.is_empty() {
+        RestartRequest::default()
+    } else {
+        match serde_json::from_slice::<RestartRequest>(&body) {
+            Ok(payload) => payload,
+            Err(err) => {
+                return invalid_reload_request(request_id, &format!("invalid JSON body: {}", err))
+            }
+        }
+    };
+    let ready_timeout = restart_req
+        .timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(crate::hot_restart::DEFAULT_READY_TIMEOUT);
+    info_ctrl!(
+        "admin api hot restart requested request_id={} remote={} timeout_ms={} reason={}",
+        request_id,
+        remote_addr,
+        ready_timeout.as_millis(),
+        restart_req.reason.as_deref().unwrap_or("")
+    );
+    match restart.handoff(ready_timeout).await {
+        Ok(outcome) => {
+            info_ctrl!(
+                "admin api hot restart handed off request_id={} successor_pid={} listeners={}",
                 request_id,
-                remote_addr,
-                reason
+                outcome.pid,
+                crate::hot_restart::listener_names(&outcome.listeners)
             );
+            restart.drain_after_handoff(gate, outcome.pid);
             json_response(
-                StatusCode::CONFLICT,
-                &ReloadResponse {
+                StatusCode::OK,
+                &RestartResponse {
                     request_id: request_id.to_string(),
-                    accepted: false,
-                    result: "reload_in_progress",
-                    update: None,
-                    requested_version: None,
-                    current_version: None,
-                    resolved_tag: None,
-                    group: None,
-                    force_replaced: None,
-                    warning: None,
-                    error: None,
+                    accepted: true,
+                    result: "restart_done",
+                    new_pid: outcome.pid,
+                    listeners: outcome.listeners,
                 },
             )
         }
-        RuntimeCommandSendError::RuntimeNotReady => json_response(
-            StatusCode::SERVICE_UNAVAILABLE,
-            &ErrorResponse {
-                request_id: request_id.to_string(),
-                accepted: false,
-                result: "runtime_not_ready",
-                error: "runtime command receiver not ready".to_string(),
+        Err(err) => {
+            warn_ctrl!(
+                "admin api hot restart failed request_id={} error={}",
+                request_id,
+                err
+            );
+            json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &ErrorResponse {
+                    request_id: request_id.to_string(),
+                    accepted: false,
+                    result: "restart_failed",
+                    error: err.to_string(),
+                },
+            )
+        }
+    }
+}
+
+/// `POST /admin/v1/project/webhook`: verifies a Git server's tag push and
+/// queues the matching update-and-reload behind the reload gate. The
+/// delivery is answered before the update runs, since Git servers give up
+/// on slow hooks after a few seconds. Redelivered IDs are acknowledged
+/// without queueing anything.
+async fn webhook_response(
+    req: Request<Incoming>,
+    request_id: &str,
+    remote_addr: SocketAddr,
+    state: Arc<AppState>,
+) -> Response<Full<Bytes>> {
+    let headers = req.headers().clone();
+    let body = match read_body(req.into_body(), WEBHOOK_MAX_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(ReadBodyError::TooLarge(limit)) => {
+            return json_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &ErrorResponse {
+                    request_id: request_id.to_string(),
+                    accepted: false,
+                    result: "payload_too_large",
+                    error: format!("request body exceeds {} bytes", limit),
+                },
+            );
+        }
+        Err(ReadBodyError::InvalidJson(err)) | Err(ReadBodyError::Read(err)) => {
+            return invalid_reload_request(request_id, &err);
+        }
+    };
+    let Some(conf) = state.webhook.as_ref() else {
+        return invalid_reload_request(request_id, "project webhook is disabled");
+    };
+    let event = match crate::project_remote::verify_webhook(
+        conf,
+        |name| headers.get(name).and_then(|value| value.to_str().ok()),
+        &body,
+    ) {
+        Ok(event) => event,
+        Err(crate::project_remote::WebhookError::Unauthorized(err)) => {
+            warn_ctrl!(
+                "admin api webhook unauthorized request_id={} remote={} error={}",
+                request_id,
+                remote_addr,
+                err
+            );
+            return json_response(
+                StatusCode::UNAUTHORIZED,
+                &ErrorResponse {
+                    request_id: request_id.to_string(),
+                    accepted: false,
+                    result: "unauthorized",
+                    error: err,
+                },
+            );
+        }
+        Err(crate::project_remote::WebhookError::Invalid(err)) => {
+            return invalid_reload_request(request_id, &err);
+        }
+    };
+    let provider = event.provider.as_str();
+    let action =
+        match crate::project_remote::plan_webhook_action(&state.work_root, &state.dict, &event) {
+            Ok(action) => action,
+            Err(err) => {
+                return json_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &ErrorResponse {
+                        request_id: request_id.to_string(),
+                        accepted: false,
+                        result: "update_failed",
+                        error: err.to_string(),
+                    },
+                );
+            }
+        };
+    let targets = match action {
+        crate::project_remote::WebhookAction::Update(targets) => targets,
+        crate::project_remote::WebhookAction::Ignored(reason) => {
+            info_ctrl!(
+                "admin api webhook ignored request_id={} remote={} provider={} event={} reason={}",
+                request_id,
+                remote_addr,
+                provider,
+                event.event,
+                reason
+            );
+            return json_response(
+                StatusCode::OK,
+                &WebhookResponse {
+                    request_id: request_id.to_string(),
+                    accepted: false,
+                    result: "ignored",
+                    provider,
+                    targets: Vec::new(),
+                    reason: Some(reason),
+                },
+            );
+        }
+    };
+    if let Some(delivery) = event.delivery.as_deref() {
+        if lock_webhook_queue(&state).is_duplicate(delivery) {
+            info_ctrl!(
+                "admin api webhook duplicate request_id={} remote={} provider={} delivery={}",
+                request_id,
+                remote_addr,
+                provider,
+                delivery
+            );
+            return json_response(
+                StatusCode::OK,
+                &WebhookResponse {
+                    request_id: request_id.to_string(),
+                    accepted: false,
+                    result: "duplicate",
+                    provider,
+                    targets: Vec::new(),
+                    reason: Some(format!("delivery {} was already received", delivery)),
+                },
+            );
+        }
+    }
+    let listing: Vec<WebhookTargetResponse> = targets
+        .iter()
+        .map(|target| WebhookTargetResponse {
+            group: target.group.as_ref().map(|g| g.name().to_string()),
+            version: target.version.clone(),
+            tag: target.tag.clone(),
+        })
+        .collect();
+    info_ctrl!(
+        "admin api webhook queued request_id={} remote={} provider={} tag={} targets={}",
+        request_id,
+        remote_addr,
+        provider,
+        event.tag.as_deref().unwrap_or("-"),
+        listing.len()
+    );
+    if lock_webhook_queue(&state).push(request_id.to_string(), targets) {
+        tokio::spawn(run_webhook_queue(state.clone()));
+    }
+    json_response(
+        StatusCode::ACCEPTED,
+        &WebhookResponse {
+            request_id: request_id.to_string(),
+            accepted: true,
+            result: "queued",
+            provider,
+            targets: listing,
+            reason: None,
+        },
+    )
+}
+
+fn lock_webhook_queue(
+    state: &AppState,
+) -> std::sync::MutexGuard<'_, crate::project_remote::WebhookQueue> {
+    state
+        .webhook_queue
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Drains the webhook queue. Deliveries that arrive while an update waits
+/// for the gate are merged into the next one, so at most one update is
+/// pending however many tags are pushed.
+async fn run_webhook_queue(state: Arc<AppState>) {
+    loop {
+        let _reload_guard = state.coordinator.wait_gate().await;
+        let next = lock_webhook_queue(&state).take();
+        let Some((request_id, targets)) = next else {
+            return;
+        };
+        run_webhook_update(&state, request_id, targets).await;
+    }
+}
+
+/// Applies the targets of one update in order once the runtime is free,
+/// stopping at the first one that does not settle. Targets the deployed
+/// release has overtaken since the delivery are skipped. Outcomes are
+/// logged as `project remote webhook ...` lines.
+async fn run_webhook_update(
+    state: &AppState,
+    request_id: String,
+    targets: Vec<crate::project_remote::WebhookTarget>,
+) {
+    let multiple = targets.len() > 1;
+    for target in targets {
+        let group = target.group.as_ref().map(|g| g.name()).unwrap_or("-");
+        let reload_id = if multiple {
+            format!("{}-{}", request_id, group)
+        } else {
+            request_id.clone()
+        };
+        if let Err(reason) = wait_runtime_idle(&state.control_handle, WEBHOOK_RELOAD_TIMEOUT).await
+        {
+            warn_ctrl!(
+                "project remote webhook failed request_id={} group={} tag={} error={}",
+                reload_id,
+                group,
+                target.tag,
+                reason
+            );
+            return;
+        }
+        let lock_guard = match crate::project_remote::acquire_project_remote_lock(&state.work_root)
+        {
+            Ok(lock) => lock,
+            Err(err) => {
+                warn_ctrl!(
+                    "project remote webhook failed request_id={} group={} tag={} error={}",
+                    reload_id,
+                    group,
+                    target.tag,
+                    err
+                );
+                return;
+            }
+        };
+        match crate::project_remote::recheck_webhook_targets(
+            &state.work_root,
+            &state.dict,
+            vec![target.clone()],
+        ) {
+            Ok(current) if current.is_empty() => {
+                info_ctrl!(
+                    "project remote webhook superseded request_id={} group={} tag={}",
+                    reload_id,
+                    group,
+                    target.tag
+                );
+                continue;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                warn_ctrl!(
+                    "project remote webhook failed request_id={} group={} tag={} error={}",
+                    reload_id,
+                    group,
+                    target.tag,
+                    err
+                );
+                return;
+            }
+        }
+        let outcome = update_and_reload(
+            &state.work_root,
+            &state.dict,
+            &state.control_handle,
+            lock_guard,
+            &reload_id,
+            WEBHOOK_ORIGIN,
+            target.group.clone(),
+            Some(&target.version),
+            WEBHOOK_RELOAD_TIMEOUT,
+        )
+        .await;
+        match outcome {
+            Ok(UnattendedReload::Reloaded(result)) => info_ctrl!(
+                "project remote webhook updated request_id={} group={} current_version={} resolved_tag={}",
+                reload_id,
+                group,
+                result.current_version,
+                result.resolved_tag
+            ),
+            Ok(UnattendedReload::Unchanged(result)) => info_ctrl!(
+                "project remote webhook up to date request_id={} group={} current_version={}",
+                reload_id,
+                group,
+                result.current_version
+            ),
+            Ok(UnattendedReload::Running(result)) => {
+                info_ctrl!(
+                    "project remote webhook reload still running request_id={} group={} resolved_tag={}",
+                    reload_id,
+                    group,
+                    result.resolved_tag
+                );
+            }
+            Ok(UnattendedReload::Blocked(result)) => {
+                warn_ctrl!(
+                    "project remote webhook blocked by validation request_id={} group={} resolved_tag={} failures={}",
+                    reload_id,
+                    group,
+                    result.resolved_tag,
+                    result
+                        .validation
+                        .as_ref()
+                        .map(crate::project_remote::ProjectRemoteValidation::summary)
+                        .unwrap_or_default()
+                );
+                return;
+            }
+            Err(err) => {
+                warn_ctrl!(
+                    "project remote webhook failed request_id={} group={} tag={} error={}",
+                    reload_id,
+                    group,
+                    target.tag,
+                    err
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Waits until the runtime accepts commands and no reload is running, such
+/// as one a background monitor still owns.
+async fn wait_runtime_idle(
+    control_handle: &RuntimeControlHandle,
+    limit: Duration,
+) -> Result<(), &'static str> {
+    let deadline = tokio::time::Instant::now() + limit;
+    loop {
+        let status = control_handle.status_snapshot();
+        if status.accepting_commands && !status.reloading {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(if status.accepting_commands {
+                "reload in progress"
+            } else {
+                "runtime not ready"
+            });
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Outcome of [`update_and_reload`].
+pub(crate) enum UnattendedReload {
+    /// The release was applied and the runtime reloaded it.
+    Reloaded(crate::project_remote::ProjectRemoteUpdateResult),
+    /// The managed dirs already matched the release, so no reload was sent.
+    Unchanged(crate::project_remote::ProjectRemoteUpdateResult),
+    /// The reload outlived the wait; a background monitor owns the rollback.
+    Running(crate::project_remote::ProjectRemoteUpdateResult),
+    /// The validation gate rejected the release and nothing was applied.
+    Blocked(crate::project_remote::ProjectRemoteUpdateResult),
+}
+
+/// The `update=true` path of `POST /admin/v1/reloads/model` for callers
+/// without an HTTP request: snapshot, sync behind the validation gate,
+/// reload, and roll back when the runtime rejects the release. The caller
+/// holds the reload gate; `lock_guard` is released once the reload settles.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn update_and_reload(
+    work_root: &Path,
+    dict: &EnvDict,
+    control_handle: &RuntimeControlHandle,
+    lock_guard: crate::project_remote::ProjectRemoteLockGuard,
+    request_id: &str,
+    origin: &'static str,
+    group: Option<crate::project_remote::RemoteGroup>,
+    requested_version: Option<&str>,
+    wait_timeout: Duration,
+) -> RunResult<UnattendedReload> {
+    let snapshot = crate::project_remote::capture_project_remote_snapshot_with_group(
+        work_root,
+        group.clone(),
+    )?;
+    let runtime_snapshot = crate::project_remote::capture_runtime_artifact_snapshot(work_root)?;
+    let result = sync_remote_blocking(
+        work_root.to_path_buf(),
+        dict.clone(),
+        group.clone(),
+        requested_version.map(str::to_string),
+        crate::project_remote::SyncOptions::default(),
+    )
+    .await?;
+    if result.validation_failed() {
+        return Ok(UnattendedReload::Blocked(result));
+    }
+    if !result.changed {
+        return Ok(UnattendedReload::Unchanged(result));
+    }
+    let reload_ctx = ProjectRemoteReloadContext {
+        _lock_guard: lock_guard,
+        snapshot: Some(snapshot),
+        runtime_snapshot: Some(runtime_snapshot),
+        update_result: Some(result.clone()),
+        set_result: None,
+        group,
+    };
+
+    let mut reply_rx = match control_handle
+        .request_load_model(request_id.to_string())
+        .await
+    {
+        Ok(reply_rx) => reply_rx,
+        Err(err) => {
+            let warning = rollback_updated_project(
+                work_root,
+                Some(&reload_ctx),
+                request_id,
+                origin,
+                "send_error",
+            );
+            return Err(unattended_reload_err(send_error_detail(&err), warning));
+        }
+    };
+    match timeout(wait_timeout, &mut reply_rx).await {
+        Ok(Ok(resp)) => match resp.result {
+            RuntimeCommandResult::ReloadFailed { reason } => {
+                let warning = rollback_updated_project(
+                    work_root,
+                    Some(&reload_ctx),
+                    request_id,
+                    origin,
+                    "reload_failed",
+                );
+                Err(unattended_reload_err(
+                    format!("reload failed: {}", reason),
+                    warning,
+                ))
+            }
+            RuntimeCommandResult::ReloadDone | RuntimeCommandResult::ReloadDoneWithForceReplace => {
+                Ok(UnattendedReload::Reloaded(result))
+            }
+        },
+        Ok(Err(_)) => {
+            let warning = rollback_updated_project(
+                work_root,
+                Some(&reload_ctx),
+                request_id,
+                origin,
+                "response_channel_closed",
+            );
+            Err(unattended_reload_err(
+                "runtime response channel closed",
+                warning,
+            ))
+        }
+        Err(_) => {
+            tokio::spawn(monitor_reload_result(
+                reply_rx,
+                work_root.to_path_buf(),
+                reload_ctx,
+                origin,
+                String::new(),
+            ));
+            Ok(UnattendedReload::Running(result))
+        }
+    }
+}
+
+fn send_error_detail(err: &RuntimeCommandSendError) -> &'static str {
+    match err {
+        RuntimeCommandSendError::ReloadBusy => "reload already in progress",
+        RuntimeCommandSendError::RuntimeNotReady => "runtime command receiver not ready",
+        RuntimeCommandSendError::ChannelClosed => "runtime command channel closed",
+    }
+}
+
+fn sync_options(reload_req: &ReloadRequest) -> crate::project_remote::SyncOptions {
+    crate::project_remote::SyncOptions {
+        skip_validate: reload_req.skip_validate,
+        discard_local: reload_req.discard_local,
+        ..Default::default()
+    }
+}
+
+/// Syncs one group, or the single remote, on the blocking pool.
+async fn sync_remote_blocking(
+    work_root: PathBuf,
+    dict: EnvDict,
+    group: Option<crate::project_remote::RemoteGroup>,
+    requested_version: Option<String>,
+    opts: crate::project_remote::SyncOptions,
+) -> RunResult<crate::project_remote::ProjectRemoteUpdateResult> {
+    run_sync_blocking(move || match group {
+        Some(group) => crate::project_remote::sync_project_remote_group_with_dict(
+            &work_root,
+            group,
+            requested_version.as_deref(),
+            &dict,
+            &opts,
+        ),
+        None => crate::project_remote::sync_project_remote_with_dict(
+            &work_root,
+            requested_version.as_deref(),
+            &dict,
+            &opts,
+        ),
+    })
+    .await
+}
+
+/// A sync fetches over git and runs the project check in-process;
+/// neither may hold a runtime worker thread.
+async fn run_sync_blocking<T, F>(sync: F) -> RunResult<T>
+where
+    F: FnOnce() -> RunResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(sync).await.map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("project remote sync task failed: {}", e))
+    })?
+}
+
+fn unattended_reload_err(
+    detail: impl Into<String>,
+    rollback_warning: Option<String>,
+) -> wp_error::RunError {
+    let detail = detail.into();
+    let detail = match rollback_warning {
+        Some(warning) => format!("{}; {}", detail, warning),
+        None => detail,
+    };
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+fn read_project_version(work_root: &Path) -> RunResult<Option<serde_json::Value>> {
+    match crate::project_remote::current_project_group_versions(work_root)? {
+        Some(group_versions) => Ok(Some(group_versions)),
+        None => Ok(crate::project_remote::current_project_version(work_root)?
+            .map(serde_json::Value::String)),
+    }
+}
+
+fn invalid_reload_request(request_id: &str, error: &str) -> Response<Full<Bytes>> {
+    json_response(
+        StatusCode::BAD_REQUEST,
+        &ErrorResponse {
+            request_id: request_id.to_string(),
+            accepted: false,
+            result: "invalid_request",
+            error: error.to_string(),
+        },
+    )
+}
+
+fn map_send_error(
+    request_id: &str,
+    remote_addr: SocketAddr,
+    reason: &str,
+    err: RuntimeCommandSendError,
+) -> Response<Full<Bytes>> {
+    match err {
+        RuntimeCommandSendError::ReloadBusy => {
+            warn_ctrl!(
+                "admin api reload busy request_id={} remote={} reason={}",
+                request_id,
+                remote_addr,
+                reason
+            );
+            json_response(
+                StatusCode::CONFLICT,
+                &ReloadResponse {
+                    request_id: request_id.to_string(),
+                    accepted: false,
+                    result: "reload_in_progress",
+                    update: None,
+                    requested_version: None,
+                    current_version: None,
+                    resolved_tag: None,
+                    group: None,
+                    force_replaced: None,
+                    warning: None,
+                    error: None,
+                    validation: None,
+                    release_notes: None,
+                    release_set: None,
+                },
+            )
+        }
+        RuntimeCommandSendError::RuntimeNotReady => json_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &ErrorResponse {
+                request_id: request_id.to_string(),
+                accepted: false,
+                result: "runtime_not_ready",
+                error: "runtime command receiver not ready".to_string(),
             },
         ),
         RuntimeCommandSendError::ChannelClosed => json_response(
@@ -1314,10 +2359,16 @@ enum ReadBodyError {
     Read(String),
 }
 
-async fn read_json_body<T>(mut body: Incoming, max_body_bytes: usize) -> Result<T, ReadBodyError>
+async fn read_json_body<T>(body: Incoming, max_body_bytes: usize) -> Result<T, ReadBodyError>
 where
     T: for<'de> Deserialize<'de>,
 {
+    let bytes = read_body(body, max_body_bytes).await?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| ReadBodyError::InvalidJson(format!("invalid JSON body: {}", e)))
+}
+
+async fn read_body(mut body: Incoming, max_body_bytes: usize) -> Result<Vec<u8>, ReadBodyError> {
     let mut bytes = Vec::new();
     while let Some(frame) = body.frame().await {
         let frame =
@@ -1329,9 +2380,7 @@ where
             bytes.extend_from_slice(&data);
         }
     }
-
-    serde_json::from_slice(&bytes)
-        .map_err(|e| ReadBodyError::InvalidJson(format!("invalid JSON body: {}", e)))
+    Ok(bytes)
 }
 
 fn authorized(headers: &hyper::HeaderMap<HeaderValue>, token: &str) -> bool {
@@ -2068,4 +3117,133 @@ init_version = "0.1.7"
 
         runtime.shutdown().await;
     }
+
+    #[tokio::test]
+    async fn admin_api_webhook_verifies_signature_without_bearer_token() {
+        let temp = tempdir().expect("tempdir");
+        write_test_work_root(temp.path(), "127.0.0.1:0", "runtime/admin_api.token");
+        write_token(temp.path(), "runtime/admin_api.token", 0o600);
+        write_token(temp.path(), "runtime/webhook.secret", 0o600);
+        let conf_path = temp.path().join("conf/wparse.toml");
+        let mut conf = fs::read_to_string(&conf_path).expect("read config");
+        conf.push_str(
+            r#"
+[project_remote]
+enabled = true
+repo = "https://git.example.com/wp-labs/wp-rule.git"
+init_version = "1.4.2"
+
+[project_remote.webhook]
+enabled = true
+secret_file = "runtime/webhook.secret"
+"#,
+        );
+        fs::write(&conf_path, conf).expect("write config");
+
+        let dict = EnvDict::default();
+        let runtime = start_if_enabled(temp.path(), &dict, shared_control_handle())
+            .await
+            .expect("start admin api")
+            .expect("enabled");
+        let client = Client::builder()
+            .no_proxy()
+            .build()
+            .expect("build reqwest client without proxy");
+        let url = format!("http://{}{}", runtime.local_addr(), WEBHOOK_ROUTE);
+        let payload = serde_json::json!({
+            "ref": "refs/tags/v1.4.3",
+            "deleted": false,
+            "repository": {"clone_url": "https://git.example.com/wp-labs/other.git"}
+        })
+        .to_string();
+
+        let response = client
+            .post(&url)
+            .header("X-GitHub-Event", "push")
+            .header("X-Hub-Signature-256", "sha256=00")
+            .body(payload.clone())
+            .send()
+            .await
+            .expect("send unsigned webhook");
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body: serde_json::Value = response.json().await.expect("parse json");
+        assert_eq!(body["result"], "unauthorized");
+
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"test-token");
+        let signature: String = ring::hmac::sign(&key, payload.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let response = client
+            .post(&url)
+            .header("X-GitHub-Event", "push")
+            .header("X-Hub-Signature-256", format!("sha256={}", signature))
+            .body(payload)
+            .send()
+            .await
+            .expect("send signed webhook");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.expect("parse json");
+        assert_eq!(body["result"], "ignored");
+        assert_eq!(body["provider"], "github");
+        assert_eq!(
+            body["reason"],
+            "repository is not a configured project_remote source"
+        );
+
+        runtime.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn admin_api_restart_requires_hot_restart_and_the_gate() {
+        let temp = tempdir().expect("tempdir");
+        write_test_work_root(temp.path(), "127.0.0.1:0", "runtime/admin_api.token");
+        write_token(temp.path(), "runtime/admin_api.token", 0o600);
+        let dict = EnvDict::default();
+        let client = Client::builder()
+            .no_proxy()
+            .build()
+            .expect("build reqwest client without proxy");
+
+        let runtime = start_if_enabled(temp.path(), &dict, shared_control_handle())
+            .await
+            .expect("start admin api")
+            .expect("enabled");
+        let response = client
+            .post(format!("http://{}{}", runtime.local_addr(), RESTART_ROUTE))
+            .bearer_auth("test-token")
+            .send()
+            .await
+            .expect("send restart");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json().await.expect("parse json");
+        assert_eq!(body["error"], "hot restart is not available");
+        runtime.shutdown().await;
+
+        let coordinator = ReloadCoordinator::default().with_hot_restart(
+            crate::hot_restart::HotRestart::current().expect("current executable"),
+        );
+        let runtime = start_with_coordinator(
+            temp.path(),
+            &dict,
+            shared_control_handle(),
+            coordinator.clone(),
+        )
+        .await
+        .expect("start admin api")
+        .expect("enabled");
+        let _gate = coordinator.try_gate_owned().expect("take gate");
+        let response = client
+            .post(format!("http://{}{}", runtime.local_addr(), RESTART_ROUTE))
+            .bearer_auth("test-token")
+            .json(&serde_json::json!({"reason": "upgrade"}))
+            .send()
+            .await
+            .expect("send restart");
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = response.json().await.expect("parse json");
+        assert_eq!(body["result"], "reload_in_progress");
+        runtime.shutdown().await;
+    }
 }
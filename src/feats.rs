@@ -61,3 +61,15 @@ pub fn register_optional_connectors() {
         register_sink_factory(wp_connectors::http::HttpSinkFactory);
     }
 }
+
+/// Connector types (`type = "..."` in source and sink configs) this build can
+/// instantiate, after [`register_for_runtime`].
+pub fn runtime_connector_kinds() -> std::collections::BTreeSet<String> {
+    use wp_engine::connectors::registry::{list_sink_kinds, list_source_kinds};
+
+    register_for_runtime();
+    list_source_kinds()
+        .into_iter()
+        .chain(list_sink_kinds())
+        .collect()
+}
@@ -0,0 +1,723 @@
+use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use crate::compat::UvsFrom;
+use orion_error::conversion::ToStructError;
+use serde::Serialize;
+use tokio::sync::OwnedMutexGuard;
+use tokio::task::JoinHandle;
+use wp_error::run_error::{RunReason, RunResult};
+use wp_log::{info_ctrl, warn_ctrl};
+
+use crate::admin_api::ReloadCoordinator;
+
+/// Old process id; the new process only trusts the inherited descriptors
+/// when it is that process's child.
+const HANDOFF_PID_ENV: &str = "WPARSE_HANDOFF_PID";
+const HANDOFF_READY_FD_ENV: &str = "WPARSE_HANDOFF_READY_FD";
+const LISTEN_FDS_ENV: &str = "LISTEN_FDS";
+const LISTEN_FDNAMES_ENV: &str = "LISTEN_FDNAMES";
+const LISTEN_PID_ENV: &str = "LISTEN_PID";
+#[cfg(target_os = "linux")]
+const WATCHDOG_PID_ENV: &str = "WATCHDOG_PID";
+/// First inherited descriptor, as in systemd socket activation.
+#[cfg(target_os = "linux")]
+const LISTEN_FDS_START: i32 = 3;
+#[cfg(target_os = "linux")]
+const READY_MESSAGE: &[u8] = b"READY\n";
+/// How long the old process waits for the new one to accept commands.
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(120);
+/// Lets the admin API answer the restart request before the drain starts.
+const DRAIN_DELAY: Duration = Duration::from_millis(500);
+
+/// Listeners received from the old process, claimed by address.
+static INHERITED: OnceLock<Mutex<Vec<InheritedListener>>> = OnceLock::new();
+static HANDED_OFF: AtomicBool = AtomicBool::new(false);
+
+/// A listening socket passed to the new process.
+#[derive(Debug, Clone, Serialize)]
+pub struct HandoffListener {
+    /// `tcp-<port>` or `udp-<port>`, as listed in `LISTEN_FDNAMES`.
+    pub name: String,
+    pub addr: SocketAddr,
+    #[serde(skip)]
+    fd: i32,
+}
+
+/// A new process that reported ready with the listeners it inherited.
+#[derive(Debug, Clone, Serialize)]
+pub struct HandoffOutcome {
+    pub pid: u32,
+    pub listeners: Vec<HandoffListener>,
+}
+
+#[derive(Debug)]
+struct InheritedListener {
+    name: String,
+    fd: i32,
+}
+
+/// Re-executes the daemon binary with the running process's listening
+/// sockets. Captured at startup, so the path still names the binary after
+/// `wproj self update` replaces the file.
+#[derive(Debug, Clone)]
+pub struct HotRestart {
+    exe: PathBuf,
+    args: Vec<OsString>,
+    /// Set once the admin API is bound; its socket is the only one a
+    /// successor adopts.
+    admin_addr: Arc<OnceLock<SocketAddr>>,
+}
+
+impl HotRestart {
+    pub fn current() -> RunResult<Self> {
+        let exe = std::env::current_exe()
+            .map_err(|e| conf_err_source("resolve current executable failed", e))?;
+        Ok(Self {
+            exe,
+            args: std::env::args_os().skip(1).collect(),
+            admin_addr: Arc::default(),
+        })
+    }
+
+    /// Records the admin API listener handed to a successor.
+    pub(crate) fn set_admin_listener(&self, addr: SocketAddr) {
+        let _ = self.admin_addr.set(addr);
+    }
+
+    /// Starts the new process and waits until it reports ready. On failure
+    /// the new process is killed and this one keeps serving. The caller
+    /// holds the reload gate so no update runs during the handoff.
+    pub async fn handoff(&self, ready_timeout: Duration) -> RunResult<HandoffOutcome> {
+        let restart = self.clone();
+        tokio::task::spawn_blocking(move || restart.run_handoff(ready_timeout))
+            .await
+            .map_err(|e| restart_err(format!("hot restart task failed: {}", e)))?
+    }
+
+    /// Ends this process once its successor is ready: keeps `gate` so no
+    /// further update or reload starts here, then raises `SIGTERM` so the
+    /// engine drains through its normal shutdown.
+    pub fn drain_after_handoff(&self, gate: OwnedMutexGuard<()>, successor_pid: u32) {
+        HANDED_OFF.store(true, Ordering::SeqCst);
+        tokio::spawn(async move {
+            let _gate = gate;
+            tokio::time::sleep(DRAIN_DELAY).await;
+            info_ctrl!(
+                "hot restart draining pid={} successor_pid={}",
+                std::process::id(),
+                successor_pid
+            );
+            raise_terminate();
+            std::future::pending::<()>().await;
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    fn run_handoff(&self, ready_timeout: Duration) -> RunResult<HandoffOutcome> {
+        let listeners = handoff_listeners(collect_listeners()?, self.admin_addr.get().copied())
+            .map_err(restart_err)?;
+        let (ready_rx, ready_tx) = ready_pipe()?;
+        let mut child = self.spawn_successor(&listeners, ready_tx)?;
+        let pid = child.id();
+        info_ctrl!(
+            "hot restart started successor pid={} exe={} listeners={}",
+            pid,
+            self.exe.display(),
+            listener_names(&listeners)
+        );
+        match wait_ready(&ready_rx, ready_timeout) {
+            Ok(()) => Ok(HandoffOutcome { pid, listeners }),
+            Err(detail) => {
+                let _ = child.kill();
+                let status = child
+                    .wait()
+                    .map(|status| format!(" ({})", status))
+                    .unwrap_or_default();
+                Err(restart_err(format!(
+                    "successor pid {} {}{}",
+                    pid, detail, status
+                )))
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn run_handoff(&self, _ready_timeout: Duration) -> RunResult<HandoffOutcome> {
+        Err(restart_err("hot restart is only supported on Linux"))
+    }
+
+    /// Inherited descriptors start at 3 in `listeners` order, followed by
+    /// the write end of the ready pipe.
+    #[cfg(target_os = "linux")]
+    fn spawn_successor(
+        &self,
+        listeners: &[HandoffListener],
+        ready_tx: std::os::fd::OwnedFd,
+    ) -> RunResult<std::process::Child> {
+        use std::os::fd::AsRawFd;
+        use std::os::unix::process::CommandExt;
+
+        let names: Vec<&str> = listeners.iter().map(|l| l.name.as_str()).collect();
+        let mut sources: Vec<i32> = listeners.iter().map(|l| l.fd).collect();
+        sources.push(ready_tx.as_raw_fd());
+        let ready_fd = LISTEN_FDS_START + listeners.len() as i32;
+        let mut staged = vec![-1; sources.len()];
+
+        let mut cmd = std::process::Command::new(&self.exe);
+        cmd.args(&self.args)
+            .env(LISTEN_FDS_ENV, listeners.len().to_string())
+            .env(LISTEN_FDNAMES_ENV, names.join(":"))
+            .env_remove(LISTEN_PID_ENV)
+            // The successor becomes the service's main process.
+            .env_remove(WATCHDOG_PID_ENV)
+            .env(HANDOFF_PID_ENV, std::process::id().to_string())
+            .env(HANDOFF_READY_FD_ENV, ready_fd.to_string());
+        // SAFETY: remap_fds only calls fcntl and dup2 and does not allocate.
+        unsafe {
+            cmd.pre_exec(move || remap_fds(&sources, &mut staged));
+        }
+        let child = cmd
+            .spawn()
+            .map_err(|e| conf_err_source(format!("start {} failed", self.exe.display()), e))?;
+        drop(ready_tx);
+        Ok(child)
+    }
+}
+
+/// Set in a process started by [`HotRestart::handoff`].
+#[derive(Debug)]
+pub struct HandoffSuccessor {
+    parent_pid: u32,
+    #[cfg(target_os = "linux")]
+    ready: std::os::fd::OwnedFd,
+}
+
+impl HandoffSuccessor {
+    /// Tells the old process to drain once `ready` returns true, i.e. the
+    /// runtime has started and accepts commands. If this process exits
+    /// first, the old one sees the pipe close and keeps serving.
+    pub async fn report_ready_when<F>(self, ready: F)
+    where
+        F: Fn() -> bool,
+    {
+        while !ready() {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        let parent_pid = self.parent_pid;
+        match self.write_ready() {
+            Ok(()) => info_ctrl!("hot restart ready reported parent_pid={}", parent_pid),
+            Err(err) => warn_ctrl!(
+                "hot restart ready report failed parent_pid={} error={}",
+                parent_pid,
+                err
+            ),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn write_ready(self) -> std::io::Result<()> {
+        use std::io::Write;
+
+        std::fs::File::from(self.ready).write_all(READY_MESSAGE)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn write_ready(self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Picks up a handoff from the environment and clears its variables. Must
+/// run before the async runtime is built, since it edits the environment: it
+/// points `LISTEN_PID` at this process and registers the inherited admin API
+/// listener for [`take_inherited_tcp`].
+#[cfg(target_os = "linux")]
+pub fn take_successor() -> Option<HandoffSuccessor> {
+    use std::os::fd::FromRawFd;
+
+    let parent_pid = std::env::var(HANDOFF_PID_ENV).ok()?;
+    let ready_fd = std::env::var(HANDOFF_READY_FD_ENV).ok();
+    std::env::remove_var(HANDOFF_PID_ENV);
+    std::env::remove_var(HANDOFF_READY_FD_ENV);
+    let parent_pid: u32 = parent_pid.parse().ok()?;
+    // SAFETY: getppid has no preconditions.
+    if unsafe { libc::getppid() } as u32 != parent_pid {
+        warn_ctrl!(
+            "hot restart environment ignored: parent pid {} is not {}",
+            // SAFETY: as above.
+            unsafe { libc::getppid() },
+            parent_pid
+        );
+        std::env::remove_var(LISTEN_FDS_ENV);
+        std::env::remove_var(LISTEN_FDNAMES_ENV);
+        return None;
+    }
+    let ready_fd: i32 = ready_fd?.parse().ok()?;
+    std::env::set_var(LISTEN_PID_ENV, std::process::id().to_string());
+    let names = register_inherited()?;
+    // SAFETY: the ready pipe was passed open by the old process.
+    unsafe { libc::fcntl(ready_fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    info_ctrl!(
+        "hot restart successor pid={} parent_pid={} listeners={}",
+        std::process::id(),
+        parent_pid,
+        names
+    );
+    Some(HandoffSuccessor {
+        parent_pid,
+        // SAFETY: the ready pipe is owned by this process from here on.
+        ready: unsafe { std::os::fd::OwnedFd::from_raw_fd(ready_fd) },
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn take_successor() -> Option<HandoffSuccessor> {
+    None
+}
+
+/// Registers the `LISTEN_FDS` descriptors addressed to this process, from a
+/// handoff or from systemd socket activation, and returns their names.
+/// Later calls find the table filled and return `None`.
+#[cfg(target_os = "linux")]
+pub(crate) fn register_inherited() -> Option<String> {
+    if INHERITED.get().is_some() {
+        return None;
+    }
+    let listen_pid: u32 = std::env::var(LISTEN_PID_ENV).ok()?.parse().ok()?;
+    if listen_pid != std::process::id() {
+        return None;
+    }
+    let count: i32 = std::env::var(LISTEN_FDS_ENV).ok()?.parse().ok()?;
+    let names = std::env::var(LISTEN_FDNAMES_ENV).unwrap_or_default();
+    let mut names = names.split(':');
+    let inherited: Vec<InheritedListener> = (LISTEN_FDS_START..LISTEN_FDS_START + count)
+        .map(|fd| {
+            // Keep the sockets out of anything this process spawns.
+            // SAFETY: fd was passed open to this process.
+            unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+            InheritedListener {
+                name: names.next().unwrap_or_default().to_string(),
+                fd,
+            }
+        })
+        .collect();
+    let listed = inherited
+        .iter()
+        .map(|l| l.name.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    INHERITED.set(Mutex::new(inherited)).ok()?;
+    Some(listed)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn register_inherited() -> Option<String> {
+    None
+}
+
+/// True once this process handed its sockets to a successor and drains.
+pub fn handed_off() -> bool {
+    HANDED_OFF.load(Ordering::SeqCst)
+}
+
+/// Claims the inherited TCP listener bound to `addr`, if the old process or
+/// systemd passed one; the admin API adopts its socket this way.
+#[cfg(target_os = "linux")]
+pub fn take_inherited_tcp(addr: SocketAddr) -> Option<std::net::TcpListener> {
+    use std::os::fd::FromRawFd;
+
+    let mut inherited = INHERITED
+        .get()?
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let index = inherited.iter().position(|listener| {
+        inspect_listener(listener.fd)
+            .is_some_and(|found| found.name.starts_with("tcp-") && found.addr == addr)
+    })?;
+    let listener = inherited.remove(index);
+    // SAFETY: the descriptor is removed from the table, so it has one owner.
+    let socket = unsafe { std::net::TcpListener::from_raw_fd(listener.fd) };
+    if let Err(err) = socket.set_nonblocking(true) {
+        warn_ctrl!("hot restart adopt {} failed: {}", listener.name, err);
+        return None;
+    }
+    Some(socket)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn take_inherited_tcp(_addr: SocketAddr) -> Option<std::net::TcpListener> {
+    None
+}
+
+/// Runs a hot restart on each `SIGUSR2`, like
+/// `POST /admin/v1/runtime/restart`.
+#[cfg(unix)]
+pub fn listen_for_signal(
+    restart: HotRestart,
+    coordinator: ReloadCoordinator,
+) -> RunResult<JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut signals = signal(SignalKind::user_defined2())
+        .map_err(|e| conf_err_source("install SIGUSR2 handler failed", e))?;
+    Ok(tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            info_ctrl!("hot restart requested by SIGUSR2");
+            let gate = coordinator.wait_gate_owned().await;
+            match restart.handoff(DEFAULT_READY_TIMEOUT).await {
+                Ok(outcome) => {
+                    info_ctrl!(
+                        "hot restart handed off successor_pid={} listeners={}",
+                        outcome.pid,
+                        listener_names(&outcome.listeners)
+                    );
+                    restart.drain_after_handoff(gate, outcome.pid);
+                    return;
+                }
+                Err(err) => warn_ctrl!("hot restart failed, keep serving: {}", err),
+            }
+        }
+    }))
+}
+
+#[cfg(not(unix))]
+pub fn listen_for_signal(
+    _restart: HotRestart,
+    _coordinator: ReloadCoordinator,
+) -> RunResult<JoinHandle<()>> {
+    Ok(tokio::spawn(async {}))
+}
+
+pub(crate) fn listener_names(listeners: &[HandoffListener]) -> String {
+    if listeners.is_empty() {
+        return "-".to_string();
+    }
+    listeners
+        .iter()
+        .map(|l| format!("{}={}", l.name, l.addr))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Only the admin API adopts an inherited socket; the engine's sources bind
+/// their own when they start. A process with a listening source is
+/// therefore refused before a successor is started, since the successor
+/// could neither bind the port nor release the inherited copy.
+#[cfg(target_os = "linux")]
+fn handoff_listeners(
+    listeners: Vec<HandoffListener>,
+    admin_addr: Option<SocketAddr>,
+) -> Result<Vec<HandoffListener>, String> {
+    let (admin, sources): (Vec<_>, Vec<_>) = listeners
+        .into_iter()
+        .partition(|l| l.name.starts_with("tcp-") && Some(l.addr) == admin_addr);
+    if !sources.is_empty() {
+        return Err(format!(
+            "hot restart cannot hand over source listeners {}; restart the service instead",
+            listener_names(&sources)
+        ));
+    }
+    Ok(admin)
+}
+
+/// Listening TCP sockets and bound, unconnected UDP sockets among the
+/// process's open descriptors: the admin API and every network source.
+#[cfg(target_os = "linux")]
+fn collect_listeners() -> RunResult<Vec<HandoffListener>> {
+    let entries = std::fs::read_dir("/proc/self/fd")
+        .map_err(|e| conf_err_source("read /proc/self/fd failed", e))?;
+    let mut fds: Vec<i32> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    fds.sort_unstable();
+    Ok(fds.into_iter().filter_map(inspect_listener).collect())
+}
+
+#[cfg(target_os = "linux")]
+fn inspect_listener(fd: i32) -> Option<HandoffListener> {
+    use std::mem::ManuallyDrop;
+    use std::os::fd::FromRawFd;
+
+    let (proto, addr) = match socket_option(fd, libc::SO_TYPE)? {
+        libc::SOCK_STREAM => {
+            if socket_option(fd, libc::SO_ACCEPTCONN)? == 0 {
+                return None;
+            }
+            // SAFETY: borrowed only to query the address, never closed.
+            let socket = ManuallyDrop::new(unsafe { std::net::TcpListener::from_raw_fd(fd) });
+            ("tcp", socket.local_addr().ok()?)
+        }
+        libc::SOCK_DGRAM => {
+            // SAFETY: as above.
+            let socket = ManuallyDrop::new(unsafe { std::net::UdpSocket::from_raw_fd(fd) });
+            if socket.peer_addr().is_ok() {
+                return None;
+            }
+            ("udp", socket.local_addr().ok()?)
+        }
+        _ => return None,
+    };
+    if addr.port() == 0 {
+        return None;
+    }
+    Some(HandoffListener {
+        name: format!("{}-{}", proto, addr.port()),
+        addr,
+        fd,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn socket_option(fd: i32, name: libc::c_int) -> Option<libc::c_int> {
+    let mut value: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    // SAFETY: value and len describe a valid c_int buffer.
+    let rc = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            name,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    (rc == 0).then_some(value)
+}
+
+#[cfg(target_os = "linux")]
+fn ready_pipe() -> RunResult<(std::os::fd::OwnedFd, std::os::fd::OwnedFd)> {
+    use std::os::fd::FromRawFd;
+
+    let mut fds = [-1; 2];
+    // SAFETY: fds has room for both ends.
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(conf_err_source(
+            "create hot restart pipe failed",
+            std::io::Error::last_os_error(),
+        ));
+    }
+    // SAFETY: pipe2 returned two fresh descriptors.
+    Ok(unsafe {
+        (
+            std::os::fd::OwnedFd::from_raw_fd(fds[0]),
+            std::os::fd::OwnedFd::from_raw_fd(fds[1]),
+        )
+    })
+}
+
+/// Runs between fork and exec, so it only uses async-signal-safe calls:
+/// every descriptor is first copied above the target range, then `dup2`ed
+/// into place, which clears close-on-exec on the copy the new binary sees.
+#[cfg(target_os = "linux")]
+fn remap_fds(sources: &[i32], staged: &mut [i32]) -> std::io::Result<()> {
+    let floor = LISTEN_FDS_START + sources.len() as i32;
+    for (slot, fd) in staged.iter_mut().zip(sources) {
+        // SAFETY: fcntl on a descriptor inherited across fork.
+        let copy = unsafe { libc::fcntl(*fd, libc::F_DUPFD_CLOEXEC, floor) };
+        if copy < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        *slot = copy;
+    }
+    for (target, fd) in (LISTEN_FDS_START..).zip(staged.iter()) {
+        // SAFETY: both descriptors are open in the child.
+        if unsafe { libc::dup2(*fd, target) } < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn wait_ready(ready_rx: &std::os::fd::OwnedFd, limit: Duration) -> Result<(), String> {
+    use std::os::fd::AsRawFd;
+
+    let deadline = std::time::Instant::now() + limit;
+    let mut received = Vec::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Err(format!("not ready within {}s", limit.as_secs()));
+        }
+        let mut poll_fd = libc::pollfd {
+            fd: ready_rx.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let wait_ms = remaining.as_millis().min(i32::MAX as u128) as libc::c_int;
+        // SAFETY: poll_fd is a single valid pollfd.
+        let rc = unsafe { libc::poll(&mut poll_fd, 1, wait_ms) };
+        if rc < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(format!("wait failed: {}", err));
+        }
+        if rc == 0 {
+            continue;
+        }
+        let mut buf = [0u8; 64];
+        // SAFETY: buf is valid for buf.len() bytes.
+        let read = unsafe {
+            libc::read(
+                ready_rx.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+            )
+        };
+        if read < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(format!("wait failed: {}", err));
+        }
+        if read == 0 {
+            return Err("exited before reporting ready".to_string());
+        }
+        received.extend_from_slice(&buf[..read as usize]);
+        if received.starts_with(READY_MESSAGE) {
+            return Ok(());
+        }
+        if received.len() >= READY_MESSAGE.len() {
+            return Err("sent an unexpected ready message".to_string());
+        }
+    }
+}
+
+#[cfg(unix)]
+fn raise_terminate() {
+    // SAFETY: signalling this process has no preconditions.
+    unsafe {
+        libc::kill(libc::getpid(), libc::SIGTERM);
+    }
+}
+
+#[cfg(not(unix))]
+fn raise_terminate() {}
+
+fn restart_err(detail: impl Into<String>) -> wp_error::RunError {
+    RunReason::from_biz().to_err().with_detail(detail.into())
+}
+
+fn conf_err_source<E>(detail: impl Into<String>, source: E) -> wp_error::RunError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    RunReason::from_conf()
+        .to_err()
+        .with_detail(detail.into())
+        .with_source(source)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+
+    #[test]
+    fn collect_listeners_finds_tcp_and_udp_listeners() {
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0").expect("bind tcp");
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").expect("bind udp");
+        let client = std::net::UdpSocket::bind("127.0.0.1:0").expect("bind client");
+        client
+            .connect(udp.local_addr().expect("udp addr"))
+            .expect("connect client");
+        let stream = std::net::TcpStream::connect(tcp.local_addr().expect("tcp addr"))
+            .expect("connect stream");
+
+        let listeners = collect_listeners().expect("collect listeners");
+        let tcp_addr = tcp.local_addr().expect("tcp addr");
+        let udp_addr = udp.local_addr().expect("udp addr");
+        let found = |fd: i32| listeners.iter().find(|l| l.fd == fd);
+
+        let tcp_found = found(tcp.as_raw_fd()).expect("tcp listener");
+        assert_eq!(tcp_found.name, format!("tcp-{}", tcp_addr.port()));
+        assert_eq!(tcp_found.addr, tcp_addr);
+        let udp_found = found(udp.as_raw_fd()).expect("udp listener");
+        assert_eq!(udp_found.name, format!("udp-{}", udp_addr.port()));
+        assert!(found(client.as_raw_fd()).is_none());
+        assert!(found(stream.as_raw_fd()).is_none());
+    }
+
+    #[test]
+    fn handoff_refuses_listening_sources() {
+        let listener = |name: &str, addr: &str| HandoffListener {
+            name: name.to_string(),
+            addr: addr.parse().expect("addr"),
+            fd: -1,
+        };
+        let admin: SocketAddr = "127.0.0.1:9090".parse().expect("addr");
+
+        let only_admin =
+            handoff_listeners(vec![listener("tcp-9090", "127.0.0.1:9090")], Some(admin))
+                .expect("admin only");
+        assert_eq!(only_admin.len(), 1);
+
+        let err = handoff_listeners(
+            vec![
+                listener("tcp-9090", "127.0.0.1:9090"),
+                listener("udp-514", "0.0.0.0:514"),
+                listener("tcp-601", "0.0.0.0:601"),
+            ],
+            Some(admin),
+        )
+        .expect_err("sources listening");
+        assert_eq!(
+            err,
+            "hot restart cannot hand over source listeners udp-514=0.0.0.0:514,tcp-601=0.0.0.0:601; restart the service instead"
+        );
+        assert!(handoff_listeners(vec![listener("tcp-9090", "127.0.0.1:9090")], None).is_err());
+        assert!(handoff_listeners(Vec::new(), None)
+            .expect("nothing")
+            .is_empty());
+    }
+
+    #[test]
+    fn wait_ready_accepts_ready_message() {
+        let (rx, tx) = ready_pipe().expect("pipe");
+        std::fs::File::from(tx)
+            .write_all(READY_MESSAGE)
+            .expect("write ready");
+        wait_ready(&rx, Duration::from_secs(5)).expect("ready");
+    }
+
+    #[test]
+    fn wait_ready_fails_when_successor_exits_or_stalls() {
+        let (rx, tx) = ready_pipe().expect("pipe");
+        drop(tx);
+        let err = wait_ready(&rx, Duration::from_secs(5)).expect_err("closed pipe");
+        assert_eq!(err, "exited before reporting ready");
+
+        let (rx, _tx) = ready_pipe().expect("pipe");
+        let err = wait_ready(&rx, Duration::from_millis(100)).expect_err("silent pipe");
+        assert!(err.starts_with("not ready within"), "{err}");
+    }
+
+    #[test]
+    fn remap_fds_places_descriptors_from_three() {
+        let (rx, tx) = ready_pipe().expect("pipe");
+        let child = unsafe {
+            use std::os::unix::process::CommandExt;
+
+            let sources = vec![tx.as_raw_fd()];
+            let mut staged = vec![-1; 1];
+            std::process::Command::new("sh")
+                .arg("-c")
+                .arg("printf 'READY\\n' >&3")
+                .pre_exec(move || remap_fds(&sources, &mut staged))
+                .spawn()
+                .expect("spawn sh")
+        };
+        drop(tx);
+        wait_ready(&rx, Duration::from_secs(5)).expect("ready from fd 3");
+        let _ = child.wait_with_output();
+    }
+}
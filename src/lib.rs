@@ -11,7 +11,10 @@ shadow_rs::shadow!(build);
 pub mod admin_api;
 pub mod compat;
 pub mod feats;
+pub mod hot_restart;
 pub mod project_remote;
+pub mod remote_poll;
+pub mod systemd;
 pub const SEK_KEY_FILE: &str = "sec_key.toml";
 pub const WP_DOT_DIR: &str = ".warp_parse";
 
@@ -0,0 +1,399 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use git2::Repository;
+use orion_variate::EnvDict;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use wp_error::run_error::RunResult;
+use wp_log::{info_ctrl, warn_ctrl};
+
+use super::groups::load_project_remote_ext;
+use super::managed::remove_path;
+use super::repo::{fetch_remote_tags, prepare_remote_repo};
+use super::{
+    conf_err_source, configured_sources, remote_cache_path_for, ENGINE_CONF_PATH, REMOTE_CACHE_DIR,
+};
+
+const MIB: u64 = 1024 * 1024;
+const REBUILD_SUFFIX: &str = ".rebuild";
+
+/// `[project_remote.cache]` as written in `conf/wparse.toml`.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct RawCacheConf {
+    #[serde(default)]
+    fetch_depth: u32,
+    #[serde(default)]
+    max_size_mb: u64,
+}
+
+/// How the remote caches are fetched and when they are rebuilt.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct CachePolicy {
+    /// History depth of each fetched ref; 0 keeps the full history. A
+    /// non-zero depth also limits explicit-version syncs to the target tag.
+    pub(super) fetch_depth: u32,
+    /// Cache size above which the cache is rebuilt before the next fetch; 0
+    /// never rebuilds.
+    pub(super) max_size_bytes: u64,
+}
+
+impl CachePolicy {
+    /// libgit2's local transport cannot fetch shallow, so path and `file://`
+    /// remotes always get the full history.
+    pub(super) fn shallow_depth(&self, repo_url: &str) -> Option<i32> {
+        if self.fetch_depth == 0 || is_local_url(repo_url) {
+            return None;
+        }
+        Some(self.fetch_depth.min(i32::MAX as u32) as i32)
+    }
+
+    /// Why an existing cache must be rebuilt rather than fetched into, if at
+    /// all. libgit2 has no gc, so a rebuild is how the cache is compacted.
+    pub(super) fn rebuild_reason(&self, repo: &Repository, root: &Path) -> Option<String> {
+        if self.fetch_depth == 0 && repo.is_shallow() {
+            return Some("cache is shallow but fetch_depth is 0".to_string());
+        }
+        if self.max_size_bytes > 0 {
+            let size = dir_size(root);
+            if size > self.max_size_bytes {
+                return Some(format!("cache size {} bytes exceeds max_size_mb", size));
+            }
+        }
+        None
+    }
+}
+
+/// Cache policy of `work_root`; the defaults when there is no engine conf yet
+/// (bootstrap from a repo URL).
+pub(super) fn load_cache_policy(work_root: &Path, dict: &EnvDict) -> RunResult<CachePolicy> {
+    if !work_root.join(ENGINE_CONF_PATH).exists() {
+        return Ok(CachePolicy::default());
+    }
+    let raw = load_project_remote_ext(work_root, dict)?
+        .cache
+        .unwrap_or_default();
+    Ok(CachePolicy {
+        fetch_depth: raw.fetch_depth,
+        max_size_bytes: raw.max_size_mb.saturating_mul(MIB),
+    })
+}
+
+/// One remote cache directory under `.run/project_remote/`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteCacheInfo {
+    /// Work-root relative path of the cache.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Configured remote; `None` for a cache no source uses any more.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    pub exists: bool,
+    pub size_bytes: u64,
+    pub objects: usize,
+    pub shallow: bool,
+}
+
+/// Reports every configured cache, plus leftover caches of sources that are
+/// no longer configured.
+pub fn project_remote_cache_info<P: AsRef<Path>>(
+    work_root: P,
+    dict: &EnvDict,
+) -> RunResult<Vec<RemoteCacheInfo>> {
+    let work_root = work_root.as_ref();
+    let mut infos = Vec::new();
+    let mut known = Vec::new();
+    for (group, source) in configured_sources(work_root, dict)? {
+        let rel = remote_cache_path_for(group.as_ref());
+        infos.push(cache_info(
+            work_root,
+            &rel,
+            group.as_ref().map(|g| g.name().to_string()),
+            Some(source.repo),
+        )?);
+        known.push(rel);
+    }
+    for rel in orphan_caches(work_root, &known)? {
+        infos.push(cache_info(work_root, &rel, None, None)?);
+    }
+    Ok(infos)
+}
+
+/// Rebuilds every configured cache from scratch and removes the leftover
+/// ones. Each cache is fetched next to the old one and only swapped in once
+/// the fetch succeeded, so a failure leaves the old cache in place. Callers
+/// hold `acquire_project_remote_lock`.
+pub fn rebuild_project_remote_cache<P: AsRef<Path>>(
+    work_root: P,
+    dict: &EnvDict,
+) -> RunResult<Vec<RemoteCacheInfo>> {
+    let work_root = work_root.as_ref();
+    let policy = load_cache_policy(work_root, dict)?;
+    let sources = configured_sources(work_root, dict)?;
+    let known: Vec<String> = sources
+        .iter()
+        .map(|(group, _)| remote_cache_path_for(group.as_ref()))
+        .collect();
+    for rel in orphan_caches(work_root, &known)? {
+        info_ctrl!(
+            "project remote cache remove unused work_root={} path={}",
+            work_root.display(),
+            rel
+        );
+        remove_path(&work_root.join(rel))?;
+    }
+    for ((_, source), rel) in sources.iter().zip(&known) {
+        let root = work_root.join(rel);
+        let staging = PathBuf::from(format!("{}{}", root.display(), REBUILD_SUFFIX));
+        remove_path(&staging)?;
+        let fetched = prepare_remote_repo(&staging, &source.repo, &policy)
+            .and_then(|repo| fetch_remote_tags(&repo, &source.repo, &policy, None));
+        if let Err(err) = fetched {
+            warn_ctrl!(
+                "project remote cache rebuild failed work_root={} path={} error={}",
+                work_root.display(),
+                rel,
+                err
+            );
+            remove_path(&staging)?;
+            return Err(err);
+        }
+        remove_path(&root)?;
+        fs::rename(&staging, &root).map_err(|e| {
+            conf_err_source(
+                format!("move {} to {} failed", staging.display(), root.display()),
+                e,
+            )
+        })?;
+        info_ctrl!(
+            "project remote cache rebuilt work_root={} path={} repo={}",
+            work_root.display(),
+            rel,
+            source.repo
+        );
+    }
+    project_remote_cache_info(work_root, dict)
+}
+
+fn cache_info(
+    work_root: &Path,
+    rel: &str,
+    group: Option<String>,
+    repo: Option<String>,
+) -> RunResult<RemoteCacheInfo> {
+    let root = work_root.join(rel);
+    let mut info = RemoteCacheInfo {
+        path: rel.to_string(),
+        group,
+        repo,
+        exists: root.exists(),
+        size_bytes: 0,
+        objects: 0,
+        shallow: false,
+    };
+    if !info.exists {
+        return Ok(info);
+    }
+    info.size_bytes = dir_size(&root);
+    // A directory that is not a repository is rebuilt on the next sync.
+    if let Ok(cache) = Repository::open(&root) {
+        info.shallow = cache.is_shallow();
+        let odb = cache
+            .odb()
+            .map_err(|e| conf_err_source(format!("open objects of {} failed", rel), e))?;
+        odb.foreach(|_| {
+            info.objects += 1;
+            true
+        })
+        .map_err(|e| conf_err_source(format!("count objects of {} failed", rel), e))?;
+    }
+    Ok(info)
+}
+
+/// Cache directories (`remote`, `remote-*`, half-built `*.rebuild`) that no
+/// configured source maps to.
+fn orphan_caches(work_root: &Path, known: &[String]) -> RunResult<Vec<String>> {
+    let dir = work_root.join(REMOTE_CACHE_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(conf_err_source(
+                format!("read {} failed", dir.display()),
+                err,
+            ))
+        }
+    };
+    let mut orphans = Vec::new();
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| conf_err_source(format!("read {} failed", dir.display()), e))?;
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if !name.starts_with("remote") {
+            continue;
+        }
+        let rel = format!("{}/{}", REMOTE_CACHE_DIR, name);
+        if !known.contains(&rel) {
+            orphans.push(rel);
+        }
+    }
+    orphans.sort();
+    Ok(orphans)
+}
+
+fn dir_size(root: &Path) -> u64 {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+fn is_local_url(url: &str) -> bool {
+    url.starts_with("file://") || Path::new(url).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{
+        append_engine_conf, create_remote_fixture, create_work_root, skip_check, write_engine_conf,
+    };
+    use super::super::{sync_project_remote, REMOTE_CACHE_PATH};
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn shallow_depth_applies_only_to_network_remotes() {
+        let local = tempdir().expect("tempdir");
+        let local_url = local.path().to_str().expect("utf8 path");
+        let shallow = CachePolicy {
+            fetch_depth: 5,
+            max_size_bytes: 0,
+        };
+
+        assert_eq!(
+            shallow.shallow_depth("https://git.example.com/p.git"),
+            Some(5)
+        );
+        assert_eq!(shallow.shallow_depth(local_url), None);
+        assert_eq!(shallow.shallow_depth("file:///srv/git/p.git"), None);
+        assert_eq!(
+            CachePolicy::default().shallow_depth("https://git.example.com/p.git"),
+            None
+        );
+    }
+
+    #[test]
+    fn load_cache_policy_reads_depth_and_size() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let policy =
+            load_cache_policy(work_root.path(), &EnvDict::default()).expect("default policy");
+        assert_eq!(policy.fetch_depth, 0);
+        assert_eq!(policy.max_size_bytes, 0);
+
+        append_engine_conf(
+            work_root.path(),
+            "\n[project_remote.cache]\nfetch_depth = 1\nmax_size_mb = 2\n",
+        );
+        let policy = load_cache_policy(work_root.path(), &EnvDict::default()).expect("policy");
+        assert_eq!(policy.fetch_depth, 1);
+        assert_eq!(policy.max_size_bytes, 2 * MIB);
+
+        // Bootstrap from a repo URL runs before any engine conf exists.
+        let empty = tempdir().expect("tempdir");
+        assert_eq!(
+            load_cache_policy(empty.path(), &EnvDict::default())
+                .expect("bootstrap policy")
+                .fetch_depth,
+            0
+        );
+    }
+
+    #[test]
+    fn oversized_cache_is_rebuilt() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+        let root = work_root.path().join(REMOTE_CACHE_PATH);
+        let repo = Repository::open(&root).expect("open cache");
+
+        assert_eq!(CachePolicy::default().rebuild_reason(&repo, &root), None);
+        let tiny = CachePolicy {
+            fetch_depth: 0,
+            max_size_bytes: 1,
+        };
+        let reason = tiny.rebuild_reason(&repo, &root).expect("rebuild reason");
+        assert!(reason.contains("exceeds max_size_mb"));
+    }
+
+    #[test]
+    fn cache_info_lists_configured_and_orphaned_caches() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let dict = EnvDict::default();
+        let infos = project_remote_cache_info(work_root.path(), &dict).expect("info");
+        assert_eq!(infos.len(), 1);
+        assert!(!infos[0].exists);
+
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+        fs::create_dir_all(work_root.path().join(".run/project_remote/remote-old"))
+            .expect("orphan cache");
+        let infos = project_remote_cache_info(work_root.path(), &dict).expect("info");
+
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].path, REMOTE_CACHE_PATH);
+        assert_eq!(infos[0].repo.as_deref(), Some(fixture.repo_url()));
+        assert!(infos[0].exists);
+        assert!(infos[0].objects > 0);
+        assert!(infos[0].size_bytes > 0);
+        assert!(!infos[0].shallow);
+        assert_eq!(infos[1].path, ".run/project_remote/remote-old");
+        assert_eq!(infos[1].repo, None);
+    }
+
+    #[test]
+    fn rebuild_replaces_caches_and_drops_orphans() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let dict = EnvDict::default();
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync");
+        fs::create_dir_all(work_root.path().join(".run/project_remote/remote-old"))
+            .expect("orphan cache");
+
+        let infos = rebuild_project_remote_cache(work_root.path(), &dict).expect("rebuild");
+
+        assert_eq!(infos.len(), 1);
+        assert!(infos[0].exists);
+        assert!(infos[0].objects > 0);
+        assert!(!work_root
+            .path()
+            .join(".run/project_remote/remote-old")
+            .exists());
+        // The rebuilt cache serves the next sync.
+        let result =
+            sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+        assert!(result.changed);
+    }
+
+    #[test]
+    fn failed_rebuild_keeps_the_old_cache() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+        let missing = work_root.path().join("no-such-remote");
+        write_engine_conf(work_root.path(), missing.to_str().expect("utf8 path"));
+
+        rebuild_project_remote_cache(work_root.path(), &EnvDict::default())
+            .expect_err("unreachable remote");
+
+        let root = work_root.path().join(REMOTE_CACHE_PATH);
+        assert!(Repository::open(&root).is_ok());
+        assert!(!PathBuf::from(format!("{}{}", root.display(), REBUILD_SUFFIX)).exists());
+    }
+}
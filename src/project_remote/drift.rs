@@ -0,0 +1,621 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use git2::{Oid, Repository, Tree};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use wp_error::run_error::RunResult;
+
+use super::managed::{managed_tree, ManagedSet};
+use super::state::{atomic_write_file, load_state};
+use super::store::{entry_kind, link_matches, object_key, EntryKind, ObjectStore};
+use super::{conf_err_source, ProjectRemoteState, RemoteGroup, MANIFEST_PATH};
+
+/// Manifests kept per sync target: the active release and the one before
+/// it, so a rollback to the previous release is still checked.
+const KEPT_RECORDS: usize = 2;
+const SINGLE_TARGET_KEY: &str = "-";
+
+/// Content of the managed dirs as recorded at each sync, keyed by target
+/// (group name, or `-` in single-repo mode).
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ManifestFile {
+    #[serde(default)]
+    targets: BTreeMap<String, Vec<ManifestRecord>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestRecord {
+    revision: String,
+    dirs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude: Vec<String>,
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ManifestEntry {
+    File {
+        oid: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        executable: bool,
+    },
+    Link {
+        target: String,
+    },
+}
+
+/// Work-root paths under the managed dirs that no longer match the recorded
+/// release.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProjectRemoteDrift {
+    pub modified: Vec<String>,
+    pub added: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+impl ProjectRemoteDrift {
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty() && self.added.is_empty() && self.deleted.is_empty()
+    }
+
+    /// `modified: a, b; added: c` listing for logs and error details.
+    pub fn summary(&self) -> String {
+        [
+            ("modified", &self.modified),
+            ("added", &self.added),
+            ("deleted", &self.deleted),
+        ]
+        .iter()
+        .filter(|(_, paths)| !paths.is_empty())
+        .map(|(label, paths)| format!("{}: {}", label, paths.join(", ")))
+        .collect::<Vec<_>>()
+        .join("; ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftStatus {
+    Clean,
+    Drifted,
+    /// No manifest was recorded for the active release, e.g. it was synced
+    /// by an older warp-parse.
+    Unknown,
+}
+
+impl DriftStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DriftStatus::Clean => "clean",
+            DriftStatus::Drifted => "drifted",
+            DriftStatus::Unknown => "unknown",
+        }
+    }
+}
+
+/// Drift of one sync target against its active release.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagedDriftStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub version: String,
+    pub revision: String,
+    pub status: DriftStatus,
+    #[serde(flatten)]
+    pub drift: ProjectRemoteDrift,
+}
+
+/// Reports local drift for every target recorded in the state file. Empty
+/// when nothing has been synced yet.
+pub fn project_remote_drift_status<P: AsRef<Path>>(
+    work_root: P,
+) -> RunResult<Vec<ManagedDriftStatus>> {
+    let work_root = work_root.as_ref();
+    let targets = match load_state(work_root)? {
+        None => Vec::new(),
+        Some(ProjectRemoteState::Single {
+            current_version,
+            revision,
+            ..
+        }) => vec![(None, current_version, revision)],
+        Some(ProjectRemoteState::Groups(groups)) => groups
+            .into_iter()
+            .map(|(name, g)| (Some(name), g.current_version, g.revision))
+            .collect(),
+    };
+    let manifest = load_manifest(work_root)?;
+    let store = ObjectStore::open(work_root)?;
+    let mut statuses = Vec::new();
+    for (group, version, revision) in targets {
+        let key = group.as_deref().unwrap_or(SINGLE_TARGET_KEY);
+        let (status, drift) = match find_record(&manifest, key, &revision) {
+            Some(record) => {
+                let drift = detect_drift(work_root, &store, record)?;
+                let status = if drift.is_clean() {
+                    DriftStatus::Clean
+                } else {
+                    DriftStatus::Drifted
+                };
+                (status, drift)
+            }
+            None => (DriftStatus::Unknown, ProjectRemoteDrift::default()),
+        };
+        statuses.push(ManagedDriftStatus {
+            group,
+            version,
+            revision,
+            status,
+            drift,
+        });
+    }
+    Ok(statuses)
+}
+
+/// Drift of the release a target is currently on, `None` when that release
+/// has no recorded manifest.
+pub(super) fn current_drift(
+    work_root: &Path,
+    group: Option<&RemoteGroup>,
+    state: Option<&ProjectRemoteState>,
+) -> RunResult<Option<ProjectRemoteDrift>> {
+    let revision = match (state, group) {
+        (Some(ProjectRemoteState::Single { revision, .. }), None) => revision.as_str(),
+        (Some(state), Some(g)) => match state.group(g) {
+            Some(g) => g.revision.as_str(),
+            None => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
+    let manifest = load_manifest(work_root)?;
+    let Some(record) = find_record(&manifest, target_key(group), revision) else {
+        return Ok(None);
+    };
+    let store = ObjectStore::open(work_root)?;
+    detect_drift(work_root, &store, record).map(Some)
+}
+
+/// Records what `tree` puts into `dirs` as the manifest of `revision`.
+pub(super) fn record_manifest(
+    work_root: &Path,
+    group: Option<&RemoteGroup>,
+    revision: &str,
+    repo: &Repository,
+    tree: &Tree,
+    dirs: &[String],
+    set: &ManagedSet,
+) -> RunResult<()> {
+    let mut files = BTreeMap::new();
+    for dir in dirs {
+        if let Some(dir_tree) = managed_tree(repo, tree, dir)? {
+            collect_tree_files(repo, &dir_tree, dir, set, &mut files)?;
+        }
+    }
+    let record = ManifestRecord {
+        revision: revision.to_string(),
+        dirs: dirs.to_vec(),
+        exclude: set.exclude().to_vec(),
+        files,
+    };
+    let mut manifest = load_manifest(work_root)?;
+    let records = manifest
+        .targets
+        .entry(target_key(group).to_string())
+        .or_default();
+    records.retain(|r| r.revision != revision);
+    records.insert(0, record);
+    records.truncate(KEPT_RECORDS);
+    let body = serde_json::to_vec(&manifest)
+        .map_err(|e| conf_err_source("encode project remote manifest failed", e))?;
+    atomic_write_file(&work_root.join(MANIFEST_PATH), &body)
+}
+
+/// Drops store objects that no recorded release uses. The manifest keeps the
+/// active and the previous release of every target, which covers the work
+/// root and every backup a rollback may still restore.
+pub(super) fn prune_object_store(work_root: &Path) -> RunResult<usize> {
+    let manifest = load_manifest(work_root)?;
+    let keep = manifest
+        .targets
+        .values()
+        .flatten()
+        .flat_map(|record| record.files.values())
+        .filter_map(|entry| match entry {
+            ManifestEntry::File { oid, executable } => Some(object_key(oid, *executable)),
+            ManifestEntry::Link { .. } => None,
+        })
+        .collect::<BTreeSet<_>>();
+    ObjectStore::open(work_root)?.gc(&keep)
+}
+
+fn target_key(group: Option<&RemoteGroup>) -> &str {
+    group.map(RemoteGroup::name).unwrap_or(SINGLE_TARGET_KEY)
+}
+
+fn find_record<'m>(
+    manifest: &'m ManifestFile,
+    key: &str,
+    revision: &str,
+) -> Option<&'m ManifestRecord> {
+    manifest
+        .targets
+        .get(key)?
+        .iter()
+        .find(|record| record.revision == revision)
+}
+
+fn load_manifest(work_root: &Path) -> RunResult<ManifestFile> {
+    let path = work_root.join(MANIFEST_PATH);
+    match fs::read(&path) {
+        Ok(body) => serde_json::from_slice(&body)
+            .map_err(|e| conf_err_source(format!("parse {} failed", path.display()), e)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(ManifestFile::default()),
+        Err(err) => Err(conf_err_source(
+            format!("read {} failed", path.display()),
+            err,
+        )),
+    }
+}
+
+fn collect_tree_files(
+    repo: &Repository,
+    tree: &Tree,
+    rel: &str,
+    set: &ManagedSet,
+    out: &mut BTreeMap<String, ManifestEntry>,
+) -> RunResult<()> {
+    for entry in tree.iter() {
+        // Non-utf8 names cannot be reported either way.
+        let Some(name) = entry.name() else {
+            continue;
+        };
+        let child_rel = format!("{}/{}", rel, name);
+        if set.is_excluded(&child_rel) {
+            continue;
+        }
+        match entry_kind(&entry) {
+            EntryKind::Tree => {
+                let subtree = repo
+                    .find_tree(entry.id())
+                    .map_err(|e| conf_err_source(format!("load tree {} failed", entry.id()), e))?;
+                collect_tree_files(repo, &subtree, &child_rel, set, out)?;
+            }
+            EntryKind::Blob { executable } => {
+                out.insert(
+                    child_rel,
+                    ManifestEntry::File {
+                        oid: entry.id().to_string(),
+                        executable,
+                    },
+                );
+            }
+            EntryKind::Link => {
+                let blob = repo
+                    .find_blob(entry.id())
+                    .map_err(|e| conf_err_source(format!("load blob {} failed", entry.id()), e))?;
+                out.insert(
+                    child_rel,
+                    ManifestEntry::Link {
+                        target: String::from_utf8_lossy(blob.content()).into_owned(),
+                    },
+                );
+            }
+            EntryKind::Submodule => {}
+        }
+    }
+    Ok(())
+}
+
+fn detect_drift(
+    work_root: &Path,
+    store: &ObjectStore,
+    record: &ManifestRecord,
+) -> RunResult<ProjectRemoteDrift> {
+    let set = ManagedSet::new(record.dirs.clone(), record.exclude.clone());
+    let mut drift = ProjectRemoteDrift::default();
+    let mut seen = BTreeSet::new();
+    for dir in &record.dirs {
+        let root = work_root.join(dir);
+        if fs::symlink_metadata(&root).is_err() {
+            continue;
+        }
+        let kept = |path: &Path| match relative_path(dir, &root, path) {
+            Some(rel) => !set.is_excluded(&rel),
+            None => true,
+        };
+        let walker = WalkDir::new(&root)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| kept(entry.path()));
+        for entry in walker {
+            let entry =
+                entry.map_err(|e| conf_err_source(format!("walk {} failed", root.display()), e))?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let Some(rel) = relative_path(dir, &root, entry.path()) else {
+                continue;
+            };
+            let matches = match record.files.get(&rel) {
+                Some(expected) => entry_matches(store, expected, entry.path())?,
+                None => {
+                    drift.added.push(rel);
+                    continue;
+                }
+            };
+            if !matches {
+                drift.modified.push(rel.clone());
+            }
+            seen.insert(rel);
+        }
+    }
+    drift.deleted = record
+        .files
+        .keys()
+        .filter(|rel| !seen.contains(*rel))
+        .cloned()
+        .collect();
+    Ok(drift)
+}
+
+fn entry_matches(store: &ObjectStore, expected: &ManifestEntry, path: &Path) -> RunResult<bool> {
+    match expected {
+        ManifestEntry::File { oid, executable } => {
+            let oid = Oid::from_str(oid)
+                .map_err(|e| conf_err_source(format!("invalid manifest oid {}", oid), e))?;
+            store.blob_matches(oid, *executable, path)
+        }
+        ManifestEntry::Link { target } => link_matches(target.as_bytes(), path),
+    }
+}
+
+fn relative_path(dir: &str, root: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?;
+    let parts: Option<Vec<&str>> = rel.components().map(|c| c.as_os_str().to_str()).collect();
+    Some(format!("{}/{}", dir, parts?.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::ObjectType;
+    use tempfile::tempdir;
+
+    fn file_entry(body: &str) -> ManifestEntry {
+        ManifestEntry::File {
+            oid: Oid::hash_object(ObjectType::Blob, body.as_bytes())
+                .expect("hash blob")
+                .to_string(),
+            executable: false,
+        }
+    }
+
+    /// Writes `files` into the work root and returns them as a record of
+    /// the managed `dirs`.
+    fn released(
+        work_root: &Path,
+        dirs: &[&str],
+        exclude: &[&str],
+        files: &[(&str, &str)],
+    ) -> ManifestRecord {
+        let mut entries = BTreeMap::new();
+        for (rel, body) in files {
+            let path = work_root.join(rel);
+            fs::create_dir_all(path.parent().expect("parent")).expect("create parent");
+            fs::write(&path, body).expect("write release file");
+            entries.insert(rel.to_string(), file_entry(body));
+        }
+        ManifestRecord {
+            revision: "rev".to_string(),
+            dirs: dirs.iter().map(|d| d.to_string()).collect(),
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+            files: entries,
+        }
+    }
+
+    fn drift_of(work_root: &Path, record: &ManifestRecord) -> ProjectRemoteDrift {
+        let store = ObjectStore::open(work_root).expect("open store");
+        detect_drift(work_root, &store, record).expect("detect drift")
+    }
+
+    #[test]
+    fn untouched_release_is_clean() {
+        let work_root = tempdir().expect("tempdir");
+        let record = released(
+            work_root.path(),
+            &["models", "conf"],
+            &[],
+            &[
+                ("models/wpl/nginx/parse.wpl", "rule\n"),
+                ("conf/wparse.toml", "version = \"1.0\"\n"),
+            ],
+        );
+
+        let drift = drift_of(work_root.path(), &record);
+
+        assert!(drift.is_clean());
+        assert_eq!(drift.summary(), "");
+    }
+
+    #[test]
+    fn modified_files_are_reported() {
+        let work_root = tempdir().expect("tempdir");
+        let record = released(
+            work_root.path(),
+            &["models"],
+            &[],
+            &[
+                ("models/wpl/nginx/parse.wpl", "rule\n"),
+                ("models/oml/nginx.oml", "model\n"),
+            ],
+        );
+        fs::write(work_root.path().join("models/oml/nginx.oml"), "hotfix\n").expect("edit");
+
+        let drift = drift_of(work_root.path(), &record);
+
+        assert_eq!(drift.modified, vec!["models/oml/nginx.oml"]);
+        assert!(drift.added.is_empty());
+        assert!(drift.deleted.is_empty());
+        assert_eq!(drift.summary(), "modified: models/oml/nginx.oml");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mode_and_link_changes_are_modifications() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let work_root = tempdir().expect("tempdir");
+        let mut record = released(
+            work_root.path(),
+            &["models"],
+            &[],
+            &[("models/run.sh", "#!/bin/sh\n")],
+        );
+        symlink("run.sh", work_root.path().join("models/current")).expect("symlink");
+        record.files.insert(
+            "models/current".to_string(),
+            ManifestEntry::Link {
+                target: "run.sh".to_string(),
+            },
+        );
+        assert!(drift_of(work_root.path(), &record).is_clean());
+
+        fs::set_permissions(
+            work_root.path().join("models/run.sh"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .expect("chmod");
+        fs::remove_file(work_root.path().join("models/current")).expect("unlink");
+        symlink("other.sh", work_root.path().join("models/current")).expect("relink");
+
+        let drift = drift_of(work_root.path(), &record);
+
+        assert_eq!(drift.modified, vec!["models/current", "models/run.sh"]);
+    }
+
+    #[test]
+    fn added_files_are_reported_in_new_subdirs_too() {
+        let work_root = tempdir().expect("tempdir");
+        let record = released(
+            work_root.path(),
+            &["models"],
+            &[],
+            &[("models/wpl/nginx/parse.wpl", "rule\n")],
+        );
+        fs::write(work_root.path().join("models/wpl/nginx/extra.wpl"), "x\n").expect("add");
+        fs::create_dir_all(work_root.path().join("models/wpl/local")).expect("mkdir");
+        fs::write(work_root.path().join("models/wpl/local/mine.wpl"), "y\n").expect("add");
+
+        let drift = drift_of(work_root.path(), &record);
+
+        assert_eq!(
+            drift.added,
+            vec!["models/wpl/local/mine.wpl", "models/wpl/nginx/extra.wpl"]
+        );
+        assert!(drift.modified.is_empty());
+        assert!(drift.deleted.is_empty());
+    }
+
+    #[test]
+    fn removed_files_and_dirs_are_reported_as_deleted() {
+        let work_root = tempdir().expect("tempdir");
+        let record = released(
+            work_root.path(),
+            &["models", "topology"],
+            &[],
+            &[
+                ("models/wpl/nginx/parse.wpl", "rule\n"),
+                ("models/oml/nginx.oml", "model\n"),
+                ("topology/sinks/default.toml", "sink\n"),
+            ],
+        );
+        fs::remove_file(work_root.path().join("models/oml/nginx.oml")).expect("delete");
+        fs::remove_dir_all(work_root.path().join("topology")).expect("delete dir");
+
+        let drift = drift_of(work_root.path(), &record);
+
+        assert_eq!(
+            drift.deleted,
+            vec!["models/oml/nginx.oml", "topology/sinks/default.toml"]
+        );
+        assert!(drift.modified.is_empty());
+        assert!(drift.added.is_empty());
+        assert_eq!(
+            drift.summary(),
+            "deleted: models/oml/nginx.oml, topology/sinks/default.toml"
+        );
+    }
+
+    #[test]
+    fn runtime_and_excluded_paths_are_ignored() {
+        let work_root = tempdir().expect("tempdir");
+        let record = released(
+            work_root.path(),
+            &["models"],
+            &["models/team_*"],
+            &[("models/wpl/nginx/parse.wpl", "rule\n")],
+        );
+        // Runtime data next to the managed dirs is never part of a release.
+        for rel in [
+            "runtime/admin_api.token",
+            "data/local.dat",
+            ".run/project_remote_state.json",
+        ] {
+            let path = work_root.path().join(rel);
+            fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+            fs::write(path, "local\n").expect("write runtime file");
+        }
+        // Excluded paths inside a managed dir belong to another owner.
+        fs::create_dir_all(work_root.path().join("models/team_a")).expect("mkdir");
+        fs::write(work_root.path().join("models/team_a/rule.wpl"), "team\n").expect("write");
+
+        assert!(drift_of(work_root.path(), &record).is_clean());
+    }
+
+    #[test]
+    fn manifest_keeps_the_active_and_previous_release() {
+        let work_root = tempdir().expect("tempdir");
+        let repo = Repository::init(work_root.path().join("repo")).expect("init repo");
+        let set = ManagedSet::new(vec!["models".to_string()], Vec::new());
+        let dirs = vec!["models".to_string()];
+        for (revision, body) in [("rev-1", "one\n"), ("rev-2", "two\n"), ("rev-3", "three\n")] {
+            let blob = repo.blob(body.as_bytes()).expect("blob");
+            let mut models = repo.treebuilder(None).expect("builder");
+            models
+                .insert("version.txt", blob, 0o100644)
+                .expect("insert blob");
+            let models = models.write().expect("write models tree");
+            let mut root = repo.treebuilder(None).expect("builder");
+            root.insert("models", models, 0o040000)
+                .expect("insert tree");
+            let tree = repo
+                .find_tree(root.write().expect("write root tree"))
+                .expect("find tree");
+            record_manifest(work_root.path(), None, revision, &repo, &tree, &dirs, &set)
+                .expect("record manifest");
+        }
+
+        let manifest = load_manifest(work_root.path()).expect("load manifest");
+        assert!(find_record(&manifest, SINGLE_TARGET_KEY, "rev-3").is_some());
+        assert!(find_record(&manifest, SINGLE_TARGET_KEY, "rev-2").is_some());
+        assert!(find_record(&manifest, SINGLE_TARGET_KEY, "rev-1").is_none());
+        assert_eq!(
+            find_record(&manifest, SINGLE_TARGET_KEY, "rev-3")
+                .expect("rev-3")
+                .files
+                .get("models/version.txt"),
+            Some(&file_entry("three\n"))
+        );
+    }
+
+    #[test]
+    fn current_drift_is_unknown_without_a_recorded_release() {
+        let work_root = tempdir().expect("tempdir");
+        assert!(current_drift(work_root.path(), None, None)
+            .expect("drift")
+            .is_none());
+    }
+}
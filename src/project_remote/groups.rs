@@ -0,0 +1,370 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::compat::UvsFrom;
+use orion_error::conversion::ToStructError;
+use orion_variate::{EnvDict, EnvEvaluable};
+use serde::Deserialize;
+use wp_config::engine::{ProjectRemoteConf, RepoGroupConf};
+use wp_error::run_error::RunResult;
+use wp_error::RunReason;
+
+use super::cache::RawCacheConf;
+use super::managed::{
+    is_path_within, validate_managed_pattern, ManagedSet, DIRS_INFRA, DIRS_MODELS,
+};
+use super::poll::RawPollConf;
+use super::webhook::RawWebhookConf;
+use super::{
+    conf_err_source, project_remote_ambiguous_mode_err, project_remote_dual_partial_err,
+    project_remote_repo_required_err_for, ProjectRemoteMode, RemoteGroup, RemoteSource,
+    ENGINE_CONF_PATH,
+};
+
+/// One configured repo group: where it comes from and which work-root paths
+/// it owns.
+#[derive(Debug, Clone)]
+pub(crate) struct GroupConf {
+    pub(crate) group: RemoteGroup,
+    pub(crate) source: RemoteSource,
+    pub(crate) managed: ManagedSet,
+}
+
+// `ProjectRemoteConf` comes from wp-config and only knows the fixed
+// models/infra pair. Named groups, per-group path overrides and the
+// subpath / track_branch / poll / cache / webhook options live in the same
+// `[project_remote]` table and are typed here, loaded from the engine conf
+// with `${VAR}` resolved from the same dict as the engine config.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct ProjectRemoteExt {
+    #[serde(default)]
+    subpath: Option<String>,
+    #[serde(default)]
+    track_branch: Option<String>,
+    #[serde(default)]
+    models: Option<RawPathOverrides>,
+    #[serde(default)]
+    infra: Option<RawPathOverrides>,
+    #[serde(default)]
+    groups: BTreeMap<String, RawGroupConf>,
+    #[serde(default)]
+    pub(super) poll: Option<RawPollConf>,
+    #[serde(default)]
+    pub(super) cache: Option<RawCacheConf>,
+    #[serde(default)]
+    pub(super) webhook: Option<RawWebhookConf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawPathOverrides {
+    #[serde(default)]
+    paths: Option<Vec<String>>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    subpath: Option<String>,
+    #[serde(default)]
+    track_branch: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawGroupConf {
+    #[serde(default)]
+    repo: String,
+    #[serde(default)]
+    init_version: String,
+    #[serde(default)]
+    paths: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    subpath: Option<String>,
+    #[serde(default)]
+    track_branch: Option<String>,
+}
+
+pub(super) fn resolve_mode(
+    work_root: &Path,
+    conf: &ProjectRemoteConf,
+    dict: &EnvDict,
+) -> RunResult<ProjectRemoteMode> {
+    let raw = load_project_remote_ext(work_root, dict)?;
+    let has_single = !conf.repo.trim().is_empty();
+    let has_models = conf.models.is_some();
+    let has_infra = conf.infra.is_some();
+    let has_named = !raw.groups.is_empty();
+
+    if has_single {
+        if has_models || has_infra || has_named {
+            return Err(project_remote_ambiguous_mode_err());
+        }
+        return Ok(ProjectRemoteMode::Single(remote_source(
+            "",
+            conf.repo.clone(),
+            conf.init_version.clone(),
+            raw.subpath.as_deref(),
+            raw.track_branch.as_deref(),
+        )?));
+    }
+    // Without named groups the legacy pair keeps its all-or-nothing rule.
+    if !has_named {
+        match (has_models, has_infra) {
+            (true, false) => return Err(project_remote_dual_partial_err("infra")),
+            (false, true) => return Err(project_remote_dual_partial_err("models")),
+            (false, false) => return Err(project_remote_ambiguous_mode_err()),
+            (true, true) => {}
+        }
+    }
+
+    let mut groups = Vec::new();
+    if let Some(models) = conf.models.as_ref() {
+        groups.push(legacy_group(
+            RemoteGroup::Models,
+            models,
+            DIRS_MODELS,
+            raw.models.as_ref(),
+        )?);
+    }
+    if let Some(infra) = conf.infra.as_ref() {
+        groups.push(legacy_group(
+            RemoteGroup::Infra,
+            infra,
+            DIRS_INFRA,
+            raw.infra.as_ref(),
+        )?);
+    }
+    for (name, raw_group) in raw.groups {
+        let group = name
+            .parse::<RemoteGroup>()
+            .map_err(|e| RunReason::from_conf().to_err().with_detail(e))?;
+        if !matches!(group, RemoteGroup::Named(_)) {
+            return Err(reserved_group_name_err(&name));
+        }
+        if raw_group.paths.is_empty() {
+            return Err(group_paths_required_err(&name));
+        }
+        let source = remote_source(
+            &conf_key(&group),
+            raw_group.repo,
+            raw_group.init_version,
+            raw_group.subpath.as_deref(),
+            raw_group.track_branch.as_deref(),
+        )?;
+        groups.push(GroupConf {
+            group,
+            source,
+            managed: ManagedSet::new(raw_group.paths, raw_group.exclude),
+        });
+    }
+    validate_groups(&groups)?;
+    Ok(ProjectRemoteMode::Groups(groups))
+}
+
+fn legacy_group(
+    group: RemoteGroup,
+    conf: &RepoGroupConf,
+    default_paths: &[&str],
+    overrides: Option<&RawPathOverrides>,
+) -> RunResult<GroupConf> {
+    let paths = overrides
+        .and_then(|o| o.paths.clone())
+        .unwrap_or_else(|| default_paths.iter().map(|p| p.to_string()).collect());
+    let exclude = overrides.map(|o| o.exclude.clone()).unwrap_or_default();
+    let source = remote_source(
+        group.name(),
+        conf.repo.clone(),
+        conf.init_version.clone(),
+        overrides.and_then(|o| o.subpath.as_deref()),
+        overrides.and_then(|o| o.track_branch.as_deref()),
+    )?;
+    Ok(GroupConf {
+        group,
+        source,
+        managed: ManagedSet::new(paths, exclude),
+    })
+}
+
+/// `key` is the table below `project_remote` the options came from, empty for
+/// the single-repo table itself.
+fn remote_source(
+    key: &str,
+    repo: String,
+    init_version: String,
+    subpath: Option<&str>,
+    track_branch: Option<&str>,
+) -> RunResult<RemoteSource> {
+    let subpath = match subpath.map(|raw| raw.trim().trim_end_matches('/')) {
+        None | Some("") => None,
+        Some(raw) => {
+            let raw = raw.strip_prefix("./").unwrap_or(raw);
+            validate_subpath(raw)
+                .map_err(|reason| invalid_option_err(key, "subpath", raw, &reason))?;
+            Some(raw.to_string())
+        }
+    };
+    let track_branch = match track_branch.map(str::trim) {
+        None | Some("") => None,
+        Some(branch) => {
+            if !git2::Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+                return Err(invalid_option_err(
+                    key,
+                    "track_branch",
+                    branch,
+                    "not a valid branch name",
+                ));
+            }
+            Some(branch.to_string())
+        }
+    };
+    Ok(RemoteSource {
+        repo,
+        init_version,
+        subpath,
+        track_branch,
+    })
+}
+
+fn validate_subpath(subpath: &str) -> Result<(), String> {
+    if subpath.starts_with('/') || subpath.contains('\\') || subpath.contains(':') {
+        return Err("must be relative to the repository root and use '/' separators".to_string());
+    }
+    if subpath
+        .split('/')
+        .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return Err("must not contain empty, '.' or '..' components".to_string());
+    }
+    if subpath.contains(['*', '?', '[']) {
+        return Err("must be a literal directory, not a glob".to_string());
+    }
+    Ok(())
+}
+
+fn validate_groups(groups: &[GroupConf]) -> RunResult<()> {
+    for conf in groups {
+        if conf.source.repo.trim().is_empty() {
+            return Err(project_remote_repo_required_err_for(&conf_key(&conf.group)));
+        }
+        for pattern in conf.managed.paths().iter().chain(conf.managed.exclude()) {
+            validate_managed_pattern(pattern).map_err(|reason| {
+                invalid_managed_path_err(&conf_key(&conf.group), pattern, &reason)
+            })?;
+        }
+    }
+    // A path may only sit inside another group's path when that group
+    // excludes it, otherwise two repos would fight over the same files.
+    for (i, left) in groups.iter().enumerate() {
+        for right in &groups[i + 1..] {
+            for l in left.managed.paths() {
+                for r in right.managed.paths() {
+                    let conflict = l == r
+                        || (is_path_within(l, r) && !left.managed.is_excluded(r))
+                        || (is_path_within(r, l) && !right.managed.is_excluded(l));
+                    if conflict {
+                        return Err(overlapping_groups_err(
+                            left.group.name(),
+                            l,
+                            right.group.name(),
+                            r,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the `[project_remote]` options wp-config does not type, resolving
+/// `${VAR}` in every string value the way the engine config is resolved.
+pub(super) fn load_project_remote_ext(
+    work_root: &Path,
+    dict: &EnvDict,
+) -> RunResult<ProjectRemoteExt> {
+    let path = work_root.join(ENGINE_CONF_PATH);
+    let body = fs::read_to_string(&path)
+        .map_err(|e| conf_err_source(format!("read {} failed", path.display()), e))?;
+    let mut table: toml::Table = toml::from_str(&body)
+        .map_err(|e| conf_err_source(format!("parse {} failed", path.display()), e))?;
+    let Some(value) = table.remove("project_remote") else {
+        return Ok(ProjectRemoteExt::default());
+    };
+    ProjectRemoteExt::deserialize(env_eval_strings(value, dict))
+        .map_err(|e| conf_err_source(format!("parse {} failed", path.display()), e))
+}
+
+fn env_eval_strings(value: toml::Value, dict: &EnvDict) -> toml::Value {
+    match value {
+        toml::Value::String(s) => toml::Value::String(s.env_eval(dict)),
+        toml::Value::Array(items) => toml::Value::Array(
+            items
+                .into_iter()
+                .map(|item| env_eval_strings(item, dict))
+                .collect(),
+        ),
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .into_iter()
+                .map(|(key, item)| (key, env_eval_strings(item, dict)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn conf_key(group: &RemoteGroup) -> String {
+    match group {
+        RemoteGroup::Named(name) => format!("groups.{}", name),
+        other => other.name().to_string(),
+    }
+}
+
+pub(super) fn invalid_option_err(
+    key: &str,
+    option: &str,
+    value: &str,
+    reason: &str,
+) -> wp_error::RunError {
+    let table = if key.is_empty() {
+        "project_remote".to_string()
+    } else {
+        format!("project_remote.{}", key)
+    };
+    RunReason::from_conf().to_err().with_detail(format!(
+        "invalid {} '{}' in {}: {}",
+        option, value, table, reason
+    ))
+}
+
+fn reserved_group_name_err(name: &str) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "project_remote.groups.{name} uses a reserved name; configure it as [project_remote.{name}] instead"
+    ))
+}
+
+fn group_paths_required_err(name: &str) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "project_remote.groups.{}.paths must list at least one managed path",
+        name
+    ))
+}
+
+fn invalid_managed_path_err(key: &str, pattern: &str, reason: &str) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "invalid managed path '{}' in project_remote.{}: {}",
+        pattern, key, reason
+    ))
+}
+
+fn overlapping_groups_err(
+    left: &str,
+    left_path: &str,
+    right: &str,
+    right_path: &str,
+) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "project_remote groups '{}' ({}) and '{}' ({}) manage overlapping paths; exclude the inner path from the outer group",
+        left, left_path, right, right_path
+    ))
+}
@@ -1,95 +1,358 @@
 use std::collections::BTreeSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::compat::UvsFrom;
+use git2::{Repository, Tree};
+use glob::{MatchOptions, Pattern};
 use orion_error::conversion::ToStructError;
 use walkdir::WalkDir;
 use wp_error::run_error::RunResult;
 use wp_error::RunReason;
 
-use super::{conf_err_source, BackupManifest, RemoteGroup, BACKUP_MANIFEST_PATH, BACKUP_PATH};
+use super::store::ObjectStore;
+use super::{
+    conf_err_source, remote_cache_path_for, BackupManifest, RemoteGroup, BACKUP_MANIFEST_PATH,
+    BACKUP_PATH, STAGING_PATH,
+};
 
-const DIRS_MODELS: &[&str] = &["models"];
-const DIRS_INFRA: &[&str] = &["conf", "topology", "connectors"];
+pub(super) const DIRS_MODELS: &[&str] = &["models"];
+pub(super) const DIRS_INFRA: &[&str] = &["conf", "topology", "connectors"];
 const DIRS_ALL: &[&str] = &["conf", "models", "topology", "connectors"];
 
-pub(super) fn managed_dirs_for(group: Option<RemoteGroup>) -> &'static [&'static str] {
-    match group {
-        Some(RemoteGroup::Models) => DIRS_MODELS,
-        Some(RemoteGroup::Infra) => DIRS_INFRA,
-        None => DIRS_ALL,
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Work-root paths owned by one sync target. `paths` are directory globs
+/// relative to the work root; anything matching `exclude` inside them stays
+/// local and is carried across every swap untouched.
+#[derive(Debug, Clone)]
+pub(crate) struct ManagedSet {
+    paths: Vec<String>,
+    exclude: Vec<String>,
+    exclude_patterns: Vec<Pattern>,
+}
+
+impl ManagedSet {
+    pub(super) fn new(paths: Vec<String>, exclude: Vec<String>) -> Self {
+        // Invalid patterns are reported by config validation; skip them here.
+        let exclude_patterns = exclude
+            .iter()
+            .filter_map(|p| Pattern::new(p).ok())
+            .collect();
+        Self {
+            paths,
+            exclude,
+            exclude_patterns,
+        }
+    }
+
+    /// The fixed set used by single-repo mode and the models/infra pair
+    /// before group paths became configurable.
+    pub(super) fn legacy(group: Option<&RemoteGroup>) -> Option<Self> {
+        let dirs = match group {
+            Some(RemoteGroup::Models) => DIRS_MODELS,
+            Some(RemoteGroup::Infra) => DIRS_INFRA,
+            Some(RemoteGroup::Named(_)) => return None,
+            None => DIRS_ALL,
+        };
+        Some(Self::new(
+            dirs.iter().map(|d| d.to_string()).collect(),
+            Vec::new(),
+        ))
+    }
+
+    pub(super) fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    pub(super) fn exclude(&self) -> &[String] {
+        &self.exclude
+    }
+
+    /// True when `rel` or one of its ancestors matches an exclude pattern.
+    pub(super) fn is_excluded(&self, rel: &str) -> bool {
+        !self.exclude_patterns.is_empty()
+            && ancestors_and_self(rel).any(|candidate| {
+                self.exclude_patterns
+                    .iter()
+                    .any(|p| p.matches_with(candidate, MATCH_OPTIONS))
+            })
+    }
+}
+
+/// True when `path` equals, or sits below, a directory matched by `pattern`.
+pub(super) fn is_path_within(pattern: &str, path: &str) -> bool {
+    match Pattern::new(pattern) {
+        Ok(compiled) => ancestors_and_self(path)
+            .any(|candidate| compiled.matches_with(candidate, MATCH_OPTIONS)),
+        Err(_) => ancestors_and_self(path).any(|candidate| candidate == pattern),
+    }
+}
+
+pub(super) fn validate_managed_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.is_empty() {
+        return Err("path must not be empty".to_string());
+    }
+    if pattern.starts_with('/') || pattern.contains('\\') || pattern.contains(':') {
+        return Err("path must be relative to the work root and use '/' separators".to_string());
+    }
+    if pattern
+        .split('/')
+        .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return Err("path must not contain empty, '.' or '..' components".to_string());
+    }
+    if pattern.split('/').next() == Some(".run") {
+        return Err("'.run' holds runtime state and cannot be managed".to_string());
+    }
+    Pattern::new(pattern).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Resolves the group's path globs to concrete directories, looking at both
+/// the target tree and the work root so that dirs dropped by the target
+/// release are still swapped out.
+pub(super) fn expand_managed_paths(
+    repo: &Repository,
+    target: &Tree,
+    work_root: &Path,
+    set: &ManagedSet,
+) -> RunResult<Vec<String>> {
+    let mut expanded = BTreeSet::new();
+    for pattern in set.paths() {
+        if !has_glob_meta(pattern) {
+            expanded.insert(pattern.clone());
+            continue;
+        }
+        let compiled = Pattern::new(pattern)
+            .map_err(|e| conf_err_source(format!("invalid managed path '{}'", pattern), e))?;
+        let depth = pattern.split('/').count();
+        let mut candidates = BTreeSet::new();
+        collect_tree_dirs(repo, target, "", depth, &mut candidates)?;
+        collect_work_dirs(work_root, depth, &mut candidates)?;
+        expanded.extend(
+            candidates
+                .into_iter()
+                .filter(|candidate| compiled.matches_with(candidate, MATCH_OPTIONS)),
+        );
     }
+    let expanded: Vec<String> = expanded
+        .into_iter()
+        .filter(|dir| !set.is_excluded(dir))
+        .collect();
+    // A dir nested in another managed dir of the same set moves with it.
+    Ok(expanded
+        .iter()
+        .filter(|dir| {
+            !expanded
+                .iter()
+                .any(|outer| outer != *dir && dir.starts_with(&format!("{}/", outer)))
+        })
+        .cloned()
+        .collect())
 }
 
 pub(super) fn managed_dirs_differ(
-    remote_root: &Path,
-    current_root: &Path,
-    dirs: &[&str],
+    store: &ObjectStore,
+    repo: &Repository,
+    target: &Tree,
+    work_root: &Path,
+    dirs: &[String],
+    set: &ManagedSet,
 ) -> RunResult<bool> {
     for dir in dirs {
-        if !paths_equal(&remote_root.join(dir), &current_root.join(dir))? {
+        let current = work_root.join(dir);
+        let same = match managed_tree(repo, target, dir)? {
+            Some(tree) => store.tree_matches(repo, &tree, &current, dir, set)?,
+            None => !has_unexcluded_content(&current, dir, set)?,
+        };
+        if !same {
             return Ok(true);
         }
     }
     Ok(false)
 }
 
-pub(super) fn backup_managed_dirs(work_root: &Path, dirs: &[&str]) -> RunResult<()> {
-    let backup_root = work_root.join(BACKUP_PATH);
-    remove_path(&backup_root)?;
-    fs::create_dir_all(&backup_root)
-        .map_err(|e| conf_err_source(format!("create {} failed", backup_root.display()), e))?;
-
-    let mut existing_dirs = Vec::new();
+/// Builds the target version of every managed dir under the staging area,
+/// hard-linked from the object store, without touching the work root.
+pub(super) fn stage_managed_dirs(
+    store: &mut ObjectStore,
+    repo: &Repository,
+    target: &Tree,
+    work_root: &Path,
+    dirs: &[String],
+    set: &ManagedSet,
+) -> RunResult<()> {
+    let staging_root = work_root.join(STAGING_PATH);
+    remove_path(&staging_root)?;
+    fs::create_dir_all(&staging_root)
+        .map_err(|e| conf_err_source(format!("create {} failed", staging_root.display()), e))?;
     for dir in dirs {
-        let src = work_root.join(dir);
-        if !src.exists() {
-            continue;
+        if let Some(tree) = managed_tree(repo, target, dir)? {
+            store.stage_tree(repo, &tree, &staging_root.join(dir), dir, set)?;
         }
-        existing_dirs.push((*dir).to_string());
-        copy_path(&src, &backup_root.join(dir))?;
     }
+    store.save()
+}
+
+/// Activates the staged dirs. Each current dir is swapped with its staged
+/// replacement in one step where the platform allows it, and the previous
+/// version lands in the backup area so a rollback is the same swap reversed.
+pub(super) fn swap_managed_dirs(
+    work_root: &Path,
+    group: Option<&RemoteGroup>,
+    dirs: &[String],
+    set: &ManagedSet,
+) -> RunResult<()> {
+    let staging_root = work_root.join(STAGING_PATH);
+    let (backup_root, manifest_path) = backup_paths(work_root, group);
+    remove_path(&backup_root)?;
+    // The new backup supersedes a legacy one left for this group.
+    if legacy_backup(work_root, group)?.is_some() {
+        remove_path(&work_root.join(BACKUP_PATH))?;
+    }
+    fs::create_dir_all(&backup_root)
+        .map_err(|e| conf_err_source(format!("create {} failed", backup_root.display()), e))?;
 
-    let manifest = BackupManifest { existing_dirs };
-    let manifest_path = work_root.join(BACKUP_MANIFEST_PATH);
+    let existing_dirs = dirs
+        .iter()
+        .filter(|dir| path_exists(&work_root.join(dir)))
+        .cloned()
+        .collect();
+    let manifest = BackupManifest {
+        existing_dirs,
+        group: group.map(|g| g.name().to_string()),
+        managed_dirs: dirs.to_vec(),
+        exclude: set.exclude().to_vec(),
+    };
     let body = serde_json::to_vec_pretty(&manifest)
         .map_err(|e| conf_err_source("encode backup manifest failed", e))?;
     fs::write(&manifest_path, body)
         .map_err(|e| conf_err_source(format!("write {} failed", manifest_path.display()), e))?;
-    Ok(())
-}
 
-pub(super) fn sync_managed_dirs(
-    remote_root: &Path,
-    work_root: &Path,
-    dirs: &[&str],
-) -> RunResult<()> {
     for dir in dirs {
-        let src = remote_root.join(dir);
-        let dst = work_root.join(dir);
-        remove_path(&dst)?;
-        if src.exists() {
-            copy_path(&src, &dst)?;
+        let staged = staging_root.join(dir);
+        let current = work_root.join(dir);
+        let backup = backup_root.join(dir);
+        carry_excluded(&current, &staged, dir, set)?;
+        match (path_exists(&staged), path_exists(&current)) {
+            (true, true) => {
+                if exchange_paths(&staged, &current)? {
+                    if let Err(err) = move_path(&staged, &backup) {
+                        let _ = exchange_paths(&staged, &current);
+                        return Err(err);
+                    }
+                } else {
+                    move_path(&current, &backup)?;
+                    move_path(&staged, &current)?;
+                }
+            }
+            (true, false) => move_path(&staged, &current)?,
+            (false, true) => move_path(&current, &backup)?,
+            (false, false) => {}
+        }
+    }
+    remove_path(&staging_root)
+}
+
+/// Backup area of one sync target and its manifest. Each group keeps its
+/// own, so the backups of every group a release set touched survive until
+/// the set is rolled back.
+fn backup_paths(work_root: &Path, group: Option<&RemoteGroup>) -> (PathBuf, PathBuf) {
+    match group {
+        None => (
+            work_root.join(BACKUP_PATH),
+            work_root.join(BACKUP_MANIFEST_PATH),
+        ),
+        Some(group) => {
+            let root = work_root.join(format!("{}-{}", BACKUP_PATH, group.name()));
+            let manifest = root.join("manifest.json");
+            (root, manifest)
         }
     }
-    Ok(())
 }
 
-pub(super) fn restore_managed_dirs(work_root: &Path, dirs: &[&str]) -> RunResult<()> {
-    let manifest_path = work_root.join(BACKUP_MANIFEST_PATH);
-    let body = match fs::read(&manifest_path) {
+fn read_backup_manifest(path: &Path) -> RunResult<Option<BackupManifest>> {
+    let body = match fs::read(path) {
         Ok(body) => body,
-        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
         Err(err) => {
             return Err(conf_err_source(
-                format!("read {} failed", manifest_path.display()),
+                format!("read {} failed", path.display()),
                 err,
             ))
         }
     };
-    let manifest: BackupManifest = serde_json::from_slice(&body)
-        .map_err(|e| conf_err_source(format!("parse {} failed", manifest_path.display()), e))?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| conf_err_source(format!("parse {} failed", path.display()), e))
+}
+
+/// Before backups moved per group, the models and infra groups shared the
+/// single-repo area with a manifest that names no group. Such a backup
+/// still belongs to `group` when every dir it recorded is one of the
+/// group's, so an upgraded node keeps its rollback target.
+fn legacy_backup(
+    work_root: &Path,
+    group: Option<&RemoteGroup>,
+) -> RunResult<Option<BackupManifest>> {
+    let Some(legacy_set) = group.and_then(|g| ManagedSet::legacy(Some(g))) else {
+        return Ok(None);
+    };
+    Ok(
+        read_backup_manifest(&work_root.join(BACKUP_MANIFEST_PATH))?.filter(|manifest| {
+            manifest.group.is_none()
+                && manifest.managed_dirs.is_empty()
+                && !manifest.existing_dirs.is_empty()
+                && manifest
+                    .existing_dirs
+                    .iter()
+                    .all(|dir| legacy_set.paths().contains(dir))
+        }),
+    )
+}
+
+/// Backup a rollback of `group` restores: its own, else a legacy one.
+fn locate_backup(
+    work_root: &Path,
+    group: Option<&RemoteGroup>,
+) -> RunResult<Option<(PathBuf, PathBuf, BackupManifest)>> {
+    let (backup_root, manifest_path) = backup_paths(work_root, group);
+    if let Some(manifest) = read_backup_manifest(&manifest_path)? {
+        return Ok(Some((backup_root, manifest_path, manifest)));
+    }
+    Ok(legacy_backup(work_root, group)?.map(|manifest| {
+        (
+            work_root.join(BACKUP_PATH),
+            work_root.join(BACKUP_MANIFEST_PATH),
+            manifest,
+        )
+    }))
+}
+
+pub(super) fn restore_managed_dirs(work_root: &Path, group: Option<&RemoteGroup>) -> RunResult<()> {
+    let Some((backup_root, manifest_path, manifest)) = locate_backup(work_root, group)? else {
+        return Ok(());
+    };
+    let expected_group = group.map(RemoteGroup::name);
+    // Manifests written before managed paths became configurable carry no
+    // scope of their own and fall back to the fixed dirs of the group.
+    let set = if manifest.managed_dirs.is_empty() {
+        ManagedSet::legacy(group).ok_or_else(|| backup_manifest_scope_err(expected_group))?
+    } else {
+        if manifest.group.as_deref() != expected_group {
+            return Err(RunReason::from_conf().to_err().with_detail(format!(
+                "backup manifest belongs to group '{}', expected '{}'",
+                manifest.group.as_deref().unwrap_or("-"),
+                expected_group.unwrap_or("-")
+            )));
+        }
+        ManagedSet::new(manifest.managed_dirs.clone(), manifest.exclude.clone())
+    };
+    let dirs = set.paths();
     // Validate that the backup manifest matches the expected dirs
     for d in &manifest.existing_dirs {
         if !dirs.iter().any(|expected| expected == d) {
@@ -99,92 +362,253 @@ pub(super) fn restore_managed_dirs(work_root: &Path, dirs: &[&str]) -> RunResult
             )));
         }
     }
-    let backup_root = work_root.join(BACKUP_PATH);
+    heal_backup(work_root, group, &backup_root)?;
+    let parking_root = work_root.join(STAGING_PATH);
 
-    // Remove all managed dirs in scope first, then restore backed-up ones.
-    // This ensures dirs created during a failed update are cleaned up, not
-    // just dirs that happened to exist before the update.
+    // Dirs absent from the manifest did not exist before the update, so any
+    // copy in the work root was created by it and is removed. A backed-up dir
+    // whose backup is missing was never moved aside and is left untouched.
     for dir in dirs {
-        remove_path(&work_root.join(dir))?;
+        let current = work_root.join(dir);
+        let backup = backup_root.join(dir);
+        if !manifest.existing_dirs.iter().any(|d| d == dir) {
+            let parked = parking_root.join(dir);
+            carry_excluded(&current, &parked, dir, &set)?;
+            remove_path(&current)?;
+            carry_excluded(&parked, &current, dir, &set)?;
+            continue;
+        }
+        if !path_exists(&backup) {
+            continue;
+        }
+        carry_excluded(&current, &backup, dir, &set)?;
+        if path_exists(&current) && exchange_paths(&backup, &current)? {
+            remove_path(&backup)?;
+            continue;
+        }
+        remove_path(&current)?;
+        move_path(&backup, &current)?;
     }
-    for dir in &manifest.existing_dirs {
-        copy_path(&backup_root.join(dir), &work_root.join(dir))?;
+    remove_path(&parking_root)?;
+    // The backup has been consumed; a second restore must be a no-op.
+    remove_path(&manifest_path)
+}
+
+/// Files the update left unchanged are hard links shared by the backup and
+/// the live tree, so a hot fix edited in place after the swap shows up in
+/// the backup too. Those files are re-linked to pristine objects read from
+/// the group's cache before the backup is swapped back.
+fn heal_backup(work_root: &Path, group: Option<&RemoteGroup>, backup_root: &Path) -> RunResult<()> {
+    if !is_real_dir(backup_root) {
+        return Ok(());
     }
+    let cache_root = work_root.join(remote_cache_path_for(group));
+    let mut store = ObjectStore::open(work_root)?;
+    store.heal_tree(backup_root, || {
+        Repository::open(&cache_root)
+            .map_err(|e| conf_err_source(format!("open {} failed", cache_root.display()), e))
+    })?;
     Ok(())
 }
 
-fn paths_equal(left: &Path, right: &Path) -> RunResult<bool> {
-    let left_exists = left.exists();
-    let right_exists = right.exists();
-    if !left_exists && !right_exists {
-        return Ok(true);
+/// Moves every excluded entry of `from` to the same place under `to`, so
+/// local-only content survives the swap of the surrounding managed dir.
+fn carry_excluded(from: &Path, to: &Path, rel: &str, set: &ManagedSet) -> RunResult<()> {
+    if set.exclude().is_empty() || !is_real_dir(from) {
+        return Ok(());
     }
-    if left_exists != right_exists {
-        return Ok(false);
+    for entry in fs::read_dir(from)
+        .map_err(|e| conf_err_source(format!("read dir {} failed", from.display()), e))?
+    {
+        let entry = entry.map_err(|e| conf_err_source("read dir entry failed", e))?;
+        let name = entry.file_name();
+        let child_rel = format!("{}/{}", rel, name.to_string_lossy());
+        let src = from.join(&name);
+        let dst = to.join(&name);
+        if set.is_excluded(&child_rel) {
+            remove_path(&dst)?;
+            move_path(&src, &dst)?;
+        } else if is_real_dir(&src) {
+            carry_excluded(&src, &dst, &child_rel, set)?;
+        }
     }
+    Ok(())
+}
 
-    let left_meta = fs::symlink_metadata(left)
-        .map_err(|e| conf_err_source(format!("stat {} failed", left.display()), e))?;
-    let right_meta = fs::symlink_metadata(right)
-        .map_err(|e| conf_err_source(format!("stat {} failed", right.display()), e))?;
-    if left_meta.file_type().is_dir() != right_meta.file_type().is_dir() {
+fn has_unexcluded_content(path: &Path, rel: &str, set: &ManagedSet) -> RunResult<bool> {
+    if !path_exists(path) {
         return Ok(false);
     }
-    if left_meta.file_type().is_file() != right_meta.file_type().is_file() {
-        return Ok(false);
+    if set.exclude().is_empty() || !is_real_dir(path) {
+        return Ok(true);
     }
-    if left_meta.file_type().is_symlink() != right_meta.file_type().is_symlink() {
-        return Ok(false);
+    for entry in fs::read_dir(path)
+        .map_err(|e| conf_err_source(format!("read dir {} failed", path.display()), e))?
+    {
+        let entry = entry.map_err(|e| conf_err_source("read dir entry failed", e))?;
+        let name = entry.file_name();
+        let child_rel = format!("{}/{}", rel, name.to_string_lossy());
+        if !set.is_excluded(&child_rel)
+            && (!is_real_dir(&entry.path())
+                || has_unexcluded_content(&entry.path(), &child_rel, set)?)
+        {
+            return Ok(true);
+        }
     }
+    Ok(false)
+}
 
-    if left_meta.file_type().is_file() {
-        return file_bytes_equal(left, right);
-    }
-    if left_meta.file_type().is_symlink() {
-        let left_target = fs::read_link(left)
-            .map_err(|e| conf_err_source(format!("read link {} failed", left.display()), e))?;
-        let right_target = fs::read_link(right)
-            .map_err(|e| conf_err_source(format!("read link {} failed", right.display()), e))?;
-        return Ok(left_target == right_target);
+pub(super) fn managed_tree<'r>(
+    repo: &'r Repository,
+    target: &Tree,
+    dir: &str,
+) -> RunResult<Option<Tree<'r>>> {
+    let entry = match target.get_path(Path::new(dir)) {
+        Ok(entry) => entry,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(conf_err_source(
+                format!("lookup {} in remote tree failed", dir),
+                err,
+            ))
+        }
+    };
+    if entry.kind() != Some(git2::ObjectType::Tree) {
+        return Err(managed_path_not_dir_err(dir));
     }
+    repo.find_tree(entry.id())
+        .map(Some)
+        .map_err(|e| conf_err_source(format!("load tree {} failed", entry.id()), e))
+}
 
-    let left_entries = read_dir_names(left)?;
-    let right_entries = read_dir_names(right)?;
-    if left_entries != right_entries {
-        return Ok(false);
+fn collect_tree_dirs(
+    repo: &Repository,
+    tree: &Tree,
+    prefix: &str,
+    depth: usize,
+    out: &mut BTreeSet<String>,
+) -> RunResult<()> {
+    for entry in tree.iter() {
+        if entry.kind() != Some(git2::ObjectType::Tree) {
+            continue;
+        }
+        let Some(name) = entry.name() else {
+            continue;
+        };
+        let rel = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if depth == 1 {
+            out.insert(rel);
+            continue;
+        }
+        let subtree = repo
+            .find_tree(entry.id())
+            .map_err(|e| conf_err_source(format!("load tree {} failed", entry.id()), e))?;
+        collect_tree_dirs(repo, &subtree, &rel, depth - 1, out)?;
     }
-    for entry in left_entries {
-        if !paths_equal(&left.join(&entry), &right.join(&entry))? {
-            return Ok(false);
+    Ok(())
+}
+
+fn collect_work_dirs(work_root: &Path, depth: usize, out: &mut BTreeSet<String>) -> RunResult<()> {
+    let walker = WalkDir::new(work_root)
+        .min_depth(depth)
+        .max_depth(depth)
+        .into_iter()
+        .filter_entry(|entry| !(entry.depth() == 1 && entry.file_name() == ".run"));
+    for entry in walker {
+        let entry = entry
+            .map_err(|e| conf_err_source(format!("walk {} failed", work_root.display()), e))?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let rel = entry
+            .path()
+            .strip_prefix(work_root)
+            .map_err(|e| conf_err_source("strip prefix failed", e))?;
+        let parts: Option<Vec<&str>> = rel.components().map(|c| c.as_os_str().to_str()).collect();
+        if let Some(parts) = parts {
+            out.insert(parts.join("/"));
         }
     }
-    Ok(true)
+    Ok(())
 }
 
-fn read_dir_names(path: &Path) -> RunResult<BTreeSet<String>> {
-    let mut names = BTreeSet::new();
-    for entry in fs::read_dir(path)
-        .map_err(|e| conf_err_source(format!("read dir {} failed", path.display()), e))?
-    {
-        let entry = entry.map_err(|e| conf_err_source("read dir entry failed", e))?;
-        let name = entry
-            .file_name()
-            .into_string()
-            .map_err(|_| non_utf8_path_err(path.display().to_string()))?;
-        names.insert(name);
+fn ancestors_and_self(rel: &str) -> impl Iterator<Item = &str> {
+    rel.match_indices('/')
+        .map(move |(idx, _)| &rel[..idx])
+        .chain(std::iter::once(rel))
+}
+
+fn has_glob_meta(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+fn path_exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+fn is_real_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_dir())
+}
+
+fn move_path(src: &Path, dst: &Path) -> RunResult<()> {
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| conf_err_source(format!("create {} failed", parent.display()), e))?;
     }
-    Ok(names)
+    if fs::rename(src, dst).is_ok() {
+        return Ok(());
+    }
+    // rename(2) cannot cross filesystems, e.g. when `.run` is a mount point.
+    copy_path(src, dst)?;
+    remove_path(src)
 }
 
-fn file_bytes_equal(left: &Path, right: &Path) -> RunResult<bool> {
-    let left_bytes = fs::read(left)
-        .map_err(|e| conf_err_source(format!("read {} failed", left.display()), e))?;
-    let right_bytes = fs::read(right)
-        .map_err(|e| conf_err_source(format!("read {} failed", right.display()), e))?;
-    Ok(left_bytes == right_bytes)
+/// Atomically exchanges two paths. Returns `Ok(false)` when the platform or
+/// filesystem has no exchange primitive and the caller must fall back to a
+/// pair of renames.
+#[cfg(target_os = "linux")]
+fn exchange_paths(left: &Path, right: &Path) -> RunResult<bool> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let to_cstring = |path: &Path| {
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| conf_err_source(format!("invalid path {}", path.display()), e))
+    };
+    let left_c = to_cstring(left)?;
+    let right_c = to_cstring(right)?;
+    let rc = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            left_c.as_ptr(),
+            libc::AT_FDCWD,
+            right_c.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    if rc == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EINVAL) | Some(libc::ENOSYS) | Some(libc::EXDEV) => Ok(false),
+        _ => Err(conf_err_source(
+            format!("exchange {} <-> {} failed", left.display(), right.display()),
+            err,
+        )),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exchange_paths(_left: &Path, _right: &Path) -> RunResult<bool> {
+    Ok(false)
 }
 
-fn copy_path(src: &Path, dst: &Path) -> RunResult<()> {
+pub(super) fn copy_path(src: &Path, dst: &Path) -> RunResult<()> {
     let meta = fs::symlink_metadata(src)
         .map_err(|e| conf_err_source(format!("stat {} failed", src.display()), e))?;
     if meta.file_type().is_dir() {
@@ -338,12 +762,6 @@ pub(super) fn remove_path(path: &Path) -> RunResult<()> {
     Ok(())
 }
 
-fn non_utf8_path_err(path: impl Into<String>) -> wp_error::RunError {
-    RunReason::from_logic()
-        .to_err()
-        .with_detail(format!("non-utf8 path under {}", path.into()))
-}
-
 fn unsupported_file_type_under_err(path: impl Into<String>) -> wp_error::RunError {
     RunReason::from_logic()
         .to_err()
@@ -362,3 +780,131 @@ fn symlink_copy_unsupported_err(path: impl Into<String>) -> wp_error::RunError {
         path.into()
     ))
 }
+
+fn managed_path_not_dir_err(dir: &str) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "managed path '{}' in remote repository is not a directory",
+        dir
+    ))
+}
+
+fn backup_manifest_scope_err(group: Option<&str>) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "backup manifest has no managed dirs recorded for group '{}'",
+        group.unwrap_or("-")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_remote::REMOTE_CACHE_PATH;
+    use git2::Oid;
+    use tempfile::tempdir;
+
+    fn models_tree(repo: &Repository, files: &[(&str, &str)]) -> Oid {
+        let mut models = repo.treebuilder(None).expect("models builder");
+        for (name, body) in files {
+            let blob = repo.blob(body.as_bytes()).expect("write blob");
+            models.insert(name, blob, 0o100644).expect("insert blob");
+        }
+        let models = models.write().expect("write models tree");
+        let mut root = repo.treebuilder(None).expect("root builder");
+        root.insert("models", models, 0o040000)
+            .expect("insert models");
+        root.write().expect("write root tree")
+    }
+
+    fn apply(work_root: &Path, repo: &Repository, tree: Oid, dirs: &[String], set: &ManagedSet) {
+        let tree = repo.find_tree(tree).expect("find tree");
+        let mut store = ObjectStore::open(work_root).expect("open store");
+        stage_managed_dirs(&mut store, repo, &tree, work_root, dirs, set).expect("stage");
+        swap_managed_dirs(work_root, None, dirs, set).expect("swap");
+    }
+
+    fn read(work_root: &Path, rel: &str) -> String {
+        fs::read_to_string(work_root.join(rel)).expect("read managed file")
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn swap_links_unchanged_files_and_restore_swaps_back() {
+        let work_root = tempdir().expect("tempdir");
+        let repo = Repository::init(work_root.path().join(REMOTE_CACHE_PATH)).expect("init cache");
+        let v1 = models_tree(&repo, &[("same.txt", "same\n"), ("version.txt", "1\n")]);
+        let v2 = models_tree(&repo, &[("same.txt", "same\n"), ("version.txt", "2\n")]);
+        let dirs = vec!["models".to_string()];
+        let set = ManagedSet::new(dirs.clone(), Vec::new());
+
+        apply(work_root.path(), &repo, v1, &dirs, &set);
+        apply(work_root.path(), &repo, v2, &dirs, &set);
+
+        assert_eq!(read(work_root.path(), "models/version.txt"), "2\n");
+        let backup = work_root.path().join(BACKUP_PATH).join("models/same.txt");
+        let live = work_root.path().join("models/same.txt");
+        assert!(store_shares_inode(&backup, &live));
+
+        restore_managed_dirs(work_root.path(), None).expect("restore");
+        assert_eq!(read(work_root.path(), "models/version.txt"), "1\n");
+        assert!(!work_root.path().join(BACKUP_MANIFEST_PATH).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restore_after_in_place_edit_brings_back_the_released_bytes() {
+        let work_root = tempdir().expect("tempdir");
+        let repo = Repository::init(work_root.path().join(REMOTE_CACHE_PATH)).expect("init cache");
+        let v1 = models_tree(&repo, &[("same.txt", "same\n"), ("version.txt", "1\n")]);
+        let v2 = models_tree(&repo, &[("same.txt", "same\n"), ("version.txt", "2\n")]);
+        let dirs = vec!["models".to_string()];
+        let set = ManagedSet::new(dirs.clone(), Vec::new());
+        apply(work_root.path(), &repo, v1, &dirs, &set);
+        apply(work_root.path(), &repo, v2, &dirs, &set);
+
+        // The file did not change between v1 and v2, so the live copy and the
+        // backup are the same inode; the hot fix lands in both.
+        fs::write(work_root.path().join("models/same.txt"), "hot fix\n").expect("edit in place");
+        assert_eq!(
+            read(&work_root.path().join(BACKUP_PATH), "models/same.txt"),
+            "hot fix\n"
+        );
+
+        restore_managed_dirs(work_root.path(), None).expect("restore");
+
+        assert_eq!(read(work_root.path(), "models/same.txt"), "same\n");
+        assert_eq!(read(work_root.path(), "models/version.txt"), "1\n");
+        // The healed object is reused by the next stage instead of the edit.
+        apply(work_root.path(), &repo, v2, &dirs, &set);
+        assert_eq!(read(work_root.path(), "models/same.txt"), "same\n");
+    }
+
+    #[cfg(unix)]
+    fn store_shares_inode(left: &Path, right: &Path) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        let left = fs::metadata(left).expect("stat left");
+        let right = fs::metadata(right).expect("stat right");
+        left.ino() == right.ino() && left.dev() == right.dev()
+    }
+
+    #[test]
+    fn group_restore_picks_up_a_legacy_shared_backup() {
+        let work_root = tempdir().expect("tempdir");
+        let backup_root = work_root.path().join(BACKUP_PATH);
+        fs::create_dir_all(backup_root.join("models")).expect("create backup models");
+        fs::write(backup_root.join("models/version.txt"), "1\n").expect("write backup");
+        fs::create_dir_all(work_root.path().join("models")).expect("create models");
+        fs::write(work_root.path().join("models/version.txt"), "2\n").expect("write live");
+        // Written before backups moved per group: no group, no managed dirs.
+        let legacy = r#"{ "existing_dirs": ["models"] }"#;
+        fs::write(work_root.path().join(BACKUP_MANIFEST_PATH), legacy).expect("write manifest");
+
+        // The infra group does not own `models`, so the legacy backup is left alone.
+        restore_managed_dirs(work_root.path(), Some(&RemoteGroup::Infra)).expect("restore infra");
+        assert_eq!(read(work_root.path(), "models/version.txt"), "2\n");
+
+        restore_managed_dirs(work_root.path(), Some(&RemoteGroup::Models)).expect("restore");
+        assert_eq!(read(work_root.path(), "models/version.txt"), "1\n");
+        assert!(!work_root.path().join(BACKUP_MANIFEST_PATH).exists());
+    }
+}
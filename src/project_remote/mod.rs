@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -6,21 +7,55 @@ use git2::Oid;
 use orion_error::conversion::ToStructError;
 use orion_variate::EnvDict;
 use serde::{Deserialize, Serialize};
-use wp_config::engine::{ProjectRemoteConf, RepoGroupConf};
+use wp_config::engine::ProjectRemoteConf;
 use wp_error::run_error::{RunReason, RunResult};
 use wp_log::{info_ctrl, warn_ctrl};
 
+mod cache;
+mod drift;
+mod groups;
 mod managed;
+mod notes;
+mod plan;
+mod poll;
+mod release_set;
 mod repo;
+mod requirements;
 mod state;
-
+mod store;
+mod validate;
+mod webhook;
+
+use self::cache::load_cache_policy;
+pub use self::cache::{project_remote_cache_info, rebuild_project_remote_cache, RemoteCacheInfo};
+use self::drift::{current_drift, prune_object_store, record_manifest};
+pub use self::drift::{
+    project_remote_drift_status, DriftStatus, ManagedDriftStatus, ProjectRemoteDrift,
+};
+pub(crate) use self::groups::GroupConf;
 use self::managed::{
-    backup_managed_dirs, managed_dirs_differ, managed_dirs_for, restore_managed_dirs,
-    sync_managed_dirs,
+    expand_managed_paths, managed_dirs_differ, restore_managed_dirs, stage_managed_dirs,
+    swap_managed_dirs, ManagedSet,
+};
+use self::notes::collect_release_notes;
+pub use self::notes::{ReleaseCommit, ReleaseNotes};
+pub use self::plan::{
+    plan_project_remote_update, FileChange, ItemChanges, PlanSummary, PlannedFile,
+    ProjectRemotePlan,
+};
+pub use self::poll::{
+    check_project_remote_updates, load_poll_conf, PendingUpdate, PollPolicy, ProjectRemotePollConf,
+};
+pub use self::release_set::{
+    parse_release_set, release_set_from_map, restore_project_remote_release_set,
+    sync_project_remote_release_set_with_dict, ProjectRemoteSetResult,
 };
 use self::repo::{
     checkout_commit, fetch_remote_tags, prepare_remote_repo, resolve_default_target,
-    resolve_tag_for_version,
+    resolve_requested_target, source_tree,
+};
+use self::requirements::{
+    check_release_requirements, load_release_requirements, EngineCapabilities,
 };
 pub use self::state::{
     acquire_project_remote_lock, capture_project_remote_snapshot,
@@ -32,14 +67,27 @@ use self::state::{
     load_engine_config, load_state, persist_group_state, persist_state,
     restore_project_remote_state,
 };
+use self::store::ObjectStore;
+use self::validate::validate_synced_tree;
+pub use self::validate::{ProjectRemoteValidation, ValidationFailure, ValidationStatus};
+pub use self::webhook::{
+    load_webhook_conf, plan_webhook_action, recheck_webhook_targets, verify_webhook,
+    ProjectRemoteWebhookConf, WebhookAction, WebhookError, WebhookEvent, WebhookProvider,
+    WebhookQueue, WebhookTarget,
+};
 
 const ENGINE_CONF_PATH: &str = "conf/wparse.toml";
 const STATE_PATH: &str = ".run/project_remote_state.json";
+const REMOTE_CACHE_DIR: &str = ".run/project_remote";
 const REMOTE_CACHE_PATH: &str = ".run/project_remote/remote";
 const REMOTE_CACHE_PATH_MODELS: &str = ".run/project_remote/remote-models";
 const REMOTE_CACHE_PATH_INFRA: &str = ".run/project_remote/remote-infra";
 const BACKUP_PATH: &str = ".run/project_remote/backup";
 const BACKUP_MANIFEST_PATH: &str = ".run/project_remote/backup/manifest.json";
+const STAGING_PATH: &str = ".run/project_remote/staging";
+const STORE_OBJECTS_PATH: &str = ".run/project_remote/store/objects";
+const STORE_INDEX_PATH: &str = ".run/project_remote/store/index.json";
+const MANIFEST_PATH: &str = ".run/project_remote/manifest.json";
 const LOCK_PATH: &str = ".run/project_remote.lock";
 const RULE_MAPPING_PATH: &str = ".run/rule_mapping.dat";
 const AUTHORITY_DB_PATH: &str = ".run/authority.sqlite";
@@ -54,6 +102,30 @@ pub struct ProjectRemoteUpdateResult {
     pub changed: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<ProjectRemoteValidation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_notes: Option<ReleaseNotes>,
+}
+
+impl ProjectRemoteUpdateResult {
+    /// True when the post-sync project check rejected the release and the
+    /// managed dirs were restored.
+    pub fn validation_failed(&self) -> bool {
+        self.validation
+            .as_ref()
+            .is_some_and(ProjectRemoteValidation::is_failed)
+    }
+}
+
+/// How a sync treats the post-sync project check and local changes in the
+/// managed dirs.
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Apply the release without running the project check.
+    pub skip_validate: bool,
+    /// Overwrite local drift in the managed dirs instead of refusing.
+    pub discard_local: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -73,10 +145,28 @@ pub struct ProjectRemoteLockGuard {
     file: fs::File,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum RemoteGroup {
     Models,
     Infra,
+    /// A group declared under `[project_remote.groups.<name>]`.
+    Named(String),
+}
+
+impl RemoteGroup {
+    pub fn name(&self) -> &str {
+        match self {
+            RemoteGroup::Models => "models",
+            RemoteGroup::Infra => "infra",
+            RemoteGroup::Named(name) => name,
+        }
+    }
+}
+
+impl std::fmt::Display for RemoteGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 impl std::str::FromStr for RemoteGroup {
@@ -86,8 +176,16 @@ impl std::str::FromStr for RemoteGroup {
         match s {
             "models" => Ok(RemoteGroup::Models),
             "infra" => Ok(RemoteGroup::Infra),
+            other
+                if !other.is_empty()
+                    && other
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+            {
+                Ok(RemoteGroup::Named(other.to_string()))
+            }
             other => Err(format!(
-                "invalid group '{}': expected 'models' or 'infra'",
+                "invalid group '{}': expected a name made of [A-Za-z0-9_-]",
                 other
             )),
         }
@@ -103,6 +201,8 @@ struct GroupState {
     revision: String,
 }
 
+/// Persisted sync state. Multi-group state is a map keyed by group name, so
+/// the dual-repo file `{"models": {...}, "infra": {...}}` is read unchanged.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 enum ProjectRemoteState {
@@ -111,10 +211,7 @@ enum ProjectRemoteState {
         resolved_tag: String,
         revision: String,
     },
-    Dual {
-        models: Option<GroupState>,
-        infra: Option<GroupState>,
-    },
+    Groups(#[serde(deserialize_with = "deserialize_group_states")] BTreeMap<String, GroupState>),
 }
 
 impl ProjectRemoteState {
@@ -123,25 +220,67 @@ impl ProjectRemoteState {
             ProjectRemoteState::Single {
                 current_version, ..
             } => Some(current_version.as_str()),
-            ProjectRemoteState::Dual { .. } => None,
+            ProjectRemoteState::Groups(_) => None,
+        }
+    }
+
+    fn group(&self, group: &RemoteGroup) -> Option<&GroupState> {
+        match self {
+            ProjectRemoteState::Groups(groups) => groups.get(group.name()),
+            ProjectRemoteState::Single { .. } => None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Dual-repo state files written by older releases store a group that was
+// never synced as `null`.
+fn deserialize_group_states<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, GroupState>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = BTreeMap::<String, Option<GroupState>>::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .filter_map(|(name, state)| state.map(|state| (name, state)))
+        .collect())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct BackupManifest {
     existing_dirs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    managed_dirs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude: Vec<String>,
 }
 
 pub(crate) enum ProjectRemoteMode {
-    Single {
-        repo: String,
-        init_version: String,
-    },
-    Dual {
-        models: RepoGroupConf,
-        infra: RepoGroupConf,
-    },
+    Single(RemoteSource),
+    Groups(Vec<GroupConf>),
+}
+
+/// Where a sync reads from. `subpath` roots the managed paths inside the
+/// repository and `track_branch` follows a branch tip instead of release
+/// tags.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RemoteSource {
+    pub(crate) repo: String,
+    pub(crate) init_version: String,
+    pub(crate) subpath: Option<String>,
+    pub(crate) track_branch: Option<String>,
+}
+
+impl ProjectRemoteMode {
+    pub(crate) fn group_names(&self) -> Vec<&str> {
+        match self {
+            ProjectRemoteMode::Single(_) => Vec::new(),
+            ProjectRemoteMode::Groups(groups) => groups.iter().map(|g| g.group.name()).collect(),
+        }
+    }
 }
 
 struct ResolvedTag {
@@ -153,15 +292,17 @@ struct ResolvedTag {
 pub fn sync_project_remote<P: AsRef<Path>>(
     work_root: P,
     requested_version: Option<&str>,
+    opts: &SyncOptions,
 ) -> RunResult<ProjectRemoteUpdateResult> {
     let dict = crate::load_sec_dict()?;
-    sync_project_remote_with_dict(work_root, requested_version, &dict)
+    sync_project_remote_with_dict(work_root, requested_version, &dict, opts)
 }
 
 pub fn sync_project_remote_with_dict<P: AsRef<Path>>(
     work_root: P,
     requested_version: Option<&str>,
     dict: &EnvDict,
+    opts: &SyncOptions,
 ) -> RunResult<ProjectRemoteUpdateResult> {
     let work_root = work_root.as_ref();
     let conf = load_engine_config(work_root, dict)?;
@@ -171,16 +312,20 @@ pub fn sync_project_remote_with_dict<P: AsRef<Path>>(
             work_root.join(ENGINE_CONF_PATH).display().to_string(),
         ));
     }
-    let mode = resolve_project_remote_mode(remote_conf)?;
+    let mode = resolve_project_remote_mode(work_root, remote_conf, dict)?;
     match mode {
-        ProjectRemoteMode::Single { repo, init_version } => sync_project_remote_with_repo_inner(
+        ProjectRemoteMode::Single(source) => sync_project_remote_with_repo_inner(
             work_root,
-            &repo,
+            &source,
             requested_version,
-            Some(init_version.as_str()),
             None,
+            &single_managed_set(),
+            dict,
+            opts,
         ),
-        ProjectRemoteMode::Dual { .. } => Err(project_remote_dual_requires_group_err()),
+        ProjectRemoteMode::Groups(_) => {
+            Err(project_remote_dual_requires_group_err(&mode.group_names()))
+        }
     }
 }
 
@@ -189,6 +334,7 @@ pub fn sync_project_remote_group_with_dict<P: AsRef<Path>>(
     group: RemoteGroup,
     requested_version: Option<&str>,
     dict: &EnvDict,
+    opts: &SyncOptions,
 ) -> RunResult<ProjectRemoteUpdateResult> {
     let work_root = work_root.as_ref();
     let conf = load_engine_config(work_root, dict)?;
@@ -198,22 +344,24 @@ pub fn sync_project_remote_group_with_dict<P: AsRef<Path>>(
             work_root.join(ENGINE_CONF_PATH).display().to_string(),
         ));
     }
-    let mode = resolve_project_remote_mode(remote_conf)?;
-    match mode {
-        ProjectRemoteMode::Dual { models, infra } => {
-            let group_conf = match group {
-                RemoteGroup::Models => &models,
-                RemoteGroup::Infra => &infra,
-            };
+    let mode = resolve_project_remote_mode(work_root, remote_conf, dict)?;
+    match &mode {
+        ProjectRemoteMode::Groups(groups) => {
+            let group_conf = groups
+                .iter()
+                .find(|g| g.group == group)
+                .ok_or_else(|| project_remote_unknown_group_err(&group, &mode.group_names()))?;
             sync_project_remote_with_repo_inner(
                 work_root,
-                &group_conf.repo,
+                &group_conf.source,
                 requested_version,
-                Some(group_conf.init_version.as_str()),
-                Some(group),
+                Some(&group),
+                &group_conf.managed,
+                dict,
+                opts,
             )
         }
-        ProjectRemoteMode::Single { .. } => Err(project_remote_single_no_group_err()),
+        ProjectRemoteMode::Single(_) => Err(project_remote_single_no_group_err()),
     }
 }
 
@@ -221,12 +369,26 @@ pub fn sync_project_remote_from_repo<P: AsRef<Path>>(
     work_root: P,
     repo_url: &str,
     requested_version: Option<&str>,
+    dict: &EnvDict,
+    opts: &SyncOptions,
 ) -> RunResult<ProjectRemoteUpdateResult> {
     let work_root = work_root.as_ref();
     if repo_url.trim().is_empty() {
         return Err(project_remote_repo_required_err());
     }
-    sync_project_remote_with_repo_inner(work_root, repo_url, requested_version, None, None)
+    let source = RemoteSource {
+        repo: repo_url.to_string(),
+        ..RemoteSource::default()
+    };
+    sync_project_remote_with_repo_inner(
+        work_root,
+        &source,
+        requested_version,
+        None,
+        &single_managed_set(),
+        dict,
+        opts,
+    )
 }
 
 pub fn current_project_version<P: AsRef<Path>>(work_root: P) -> RunResult<Option<String>> {
@@ -241,26 +403,19 @@ pub fn current_project_group_versions<P: AsRef<Path>>(
 ) -> RunResult<Option<serde_json::Value>> {
     let state = load_state(work_root.as_ref())?;
     match state {
-        Some(ProjectRemoteState::Dual { models, infra }) => {
-            let mut map = serde_json::Map::new();
-            if let Some(m) = models {
-                map.insert(
-                    "models".to_string(),
-                    serde_json::json!({
-                        "version": m.current_version,
-                        "tag": m.resolved_tag,
-                    }),
-                );
-            }
-            if let Some(i) = infra {
-                map.insert(
-                    "infra".to_string(),
-                    serde_json::json!({
-                        "version": i.current_version,
-                        "tag": i.resolved_tag,
-                    }),
-                );
-            }
+        Some(ProjectRemoteState::Groups(groups)) => {
+            let map = groups
+                .into_iter()
+                .map(|(name, g)| {
+                    (
+                        name,
+                        serde_json::json!({
+                            "version": g.current_version,
+                            "tag": g.resolved_tag,
+                        }),
+                    )
+                })
+                .collect();
             Ok(Some(serde_json::Value::Object(map)))
         }
         _ => Ok(None),
@@ -268,70 +423,92 @@ pub fn current_project_group_versions<P: AsRef<Path>>(
 }
 
 pub(crate) fn resolve_project_remote_mode(
+    work_root: &Path,
     conf: &ProjectRemoteConf,
+    dict: &EnvDict,
 ) -> RunResult<ProjectRemoteMode> {
-    let has_single = !conf.repo.trim().is_empty();
-    let has_models = conf.models.is_some();
-    let has_infra = conf.infra.is_some();
-
-    match (has_single, has_models, has_infra) {
-        (true, false, false) => Ok(ProjectRemoteMode::Single {
-            repo: conf.repo.clone(),
-            init_version: conf.init_version.clone(),
-        }),
-        (false, true, true) => {
-            let models = conf.models.as_ref().unwrap();
-            let infra = conf.infra.as_ref().unwrap();
-            if models.repo.trim().is_empty() {
-                return Err(project_remote_repo_required_err_for("models"));
-            }
-            if infra.repo.trim().is_empty() {
-                return Err(project_remote_repo_required_err_for("infra"));
-            }
-            Ok(ProjectRemoteMode::Dual {
-                models: models.clone(),
-                infra: infra.clone(),
-            })
-        }
-        (false, true, false) => Err(project_remote_dual_partial_err("infra")),
-        (false, false, true) => Err(project_remote_dual_partial_err("models")),
-        _ => Err(project_remote_ambiguous_mode_err()),
+    groups::resolve_mode(work_root, conf, dict)
+}
+
+/// Every configured source with its group (`None` in single-repo mode);
+/// empty when project_remote is disabled.
+fn configured_sources(
+    work_root: &Path,
+    dict: &EnvDict,
+) -> RunResult<Vec<(Option<RemoteGroup>, RemoteSource)>> {
+    let conf = load_engine_config(work_root, dict)?;
+    let remote_conf = conf.project_remote();
+    if !remote_conf.enabled {
+        return Ok(Vec::new());
     }
+    Ok(
+        match resolve_project_remote_mode(work_root, remote_conf, dict)? {
+            ProjectRemoteMode::Single(source) => vec![(None, source)],
+            ProjectRemoteMode::Groups(groups) => groups
+                .into_iter()
+                .map(|conf| (Some(conf.group), conf.source))
+                .collect(),
+        },
+    )
+}
+
+fn single_managed_set() -> ManagedSet {
+    ManagedSet::legacy(None).expect("single-repo mode has a fixed managed set")
 }
 
-fn remote_cache_path_for(group: Option<RemoteGroup>) -> &'static str {
+fn remote_cache_path_for(group: Option<&RemoteGroup>) -> String {
     match group {
-        Some(RemoteGroup::Models) => REMOTE_CACHE_PATH_MODELS,
-        Some(RemoteGroup::Infra) => REMOTE_CACHE_PATH_INFRA,
-        None => REMOTE_CACHE_PATH,
+        Some(RemoteGroup::Models) => REMOTE_CACHE_PATH_MODELS.to_string(),
+        Some(RemoteGroup::Infra) => REMOTE_CACHE_PATH_INFRA.to_string(),
+        Some(RemoteGroup::Named(name)) => format!("{}-{}", REMOTE_CACHE_PATH, name),
+        None => REMOTE_CACHE_PATH.to_string(),
     }
 }
 
 fn sync_project_remote_with_repo_inner(
     work_root: &Path,
-    repo_url: &str,
+    source: &RemoteSource,
     requested_version: Option<&str>,
-    init_version: Option<&str>,
-    group: Option<RemoteGroup>,
+    group: Option<&RemoteGroup>,
+    managed: &ManagedSet,
+    dict: &EnvDict,
+    opts: &SyncOptions,
 ) -> RunResult<ProjectRemoteUpdateResult> {
-    let dirs = managed_dirs_for(group);
-    let group_label = group.map(|g| match g {
-        RemoteGroup::Models => "models",
-        RemoteGroup::Infra => "infra",
-    });
+    let group_label = group.map(RemoteGroup::name);
+    let repo_url = source.repo.as_str();
+    let init_version = Some(source.init_version.trim()).filter(|v| !v.is_empty());
+    let track_branch = source.track_branch.as_deref();
     info_ctrl!(
-        "project remote sync start work_root={} requested_version={} repo={} group={}",
+        "project remote sync start work_root={} requested_version={} repo={} subpath={} track_branch={} group={}",
         work_root.display(),
         requested_version.unwrap_or("(auto)"),
         repo_url,
+        source.subpath.as_deref().unwrap_or("-"),
+        track_branch.unwrap_or("-"),
         group_label.unwrap_or("-")
     );
 
+    let previous_state = load_state(work_root)?;
+    if let Some(drift) = current_drift(work_root, group, previous_state.as_ref())? {
+        if !drift.is_clean() {
+            if !opts.discard_local {
+                return Err(local_drift_err(group, &drift));
+            }
+            warn_ctrl!(
+                "project remote sync discarding local drift work_root={} group={} {}",
+                work_root.display(),
+                group_label.unwrap_or("-"),
+                drift.summary()
+            );
+        }
+    }
+
+    let cache = load_cache_policy(work_root, dict)?;
     let remote_root = work_root.join(remote_cache_path_for(group));
-    let repo = prepare_remote_repo(&remote_root, repo_url)?;
-    fetch_remote_tags(&repo, repo_url)?;
+    let repo = prepare_remote_repo(&remote_root, repo_url, &cache)?;
+    let explicit_version = requested_version.map(str::trim).filter(|v| !v.is_empty());
+    fetch_remote_tags(&repo, repo_url, &cache, explicit_version)?;
 
-    let previous_state = load_state(work_root)?;
     let resolved = match requested_version {
         Some(version) if !version.trim().is_empty() => {
             let target_version = version.trim().to_string();
@@ -343,12 +520,12 @@ fn sync_project_remote_with_repo_inner(
                 init_version.unwrap_or("-"),
                 previous_state.is_some()
             );
-            resolve_tag_for_version(&repo, &target_version)?
+            resolve_requested_target(&repo, &target_version, track_branch)?
                 .ok_or_else(|| requested_version_not_found_err(&target_version))?
         }
         _ => {
             let resolved =
-                resolve_default_target(work_root, &repo, init_version.map(str::trim), group)?;
+                resolve_default_target(work_root, &repo, init_version, group, track_branch)?;
             info_ctrl!(
                 "project remote sync target resolved work_root={} requested_version={} target_version={} init_version={} state_exists={}",
                 work_root.display(),
@@ -371,14 +548,25 @@ fn sync_project_remote_with_repo_inner(
 
     checkout_commit(&repo, resolved.commit_id, &resolved.tag)?;
 
-    let changed = managed_dirs_differ(&remote_root, work_root, dirs)?;
-    let from_revision = previous_state.as_ref().and_then(|ps| match ps {
-        ProjectRemoteState::Single { revision, .. } => Some(revision.as_str()),
-        ProjectRemoteState::Dual { models, infra } => match group {
-            Some(RemoteGroup::Models) => models.as_ref().map(|m| m.revision.as_str()),
-            Some(RemoteGroup::Infra) => infra.as_ref().map(|i| i.revision.as_str()),
-            None => None,
-        },
+    let target_tree = source_tree(&repo, resolved.commit_id, source.subpath.as_deref())?;
+    if let Some(requirements) = load_release_requirements(&repo, &target_tree, &resolved.tag)? {
+        let engine = EngineCapabilities::current()?;
+        info_ctrl!(
+            "project remote sync check requirements work_root={} resolved_tag={} engine_version={} connectors={}",
+            work_root.display(),
+            resolved.tag,
+            engine.version,
+            engine.connectors.len()
+        );
+        check_release_requirements(&requirements, &engine, &resolved.tag)?;
+    }
+    let dirs = expand_managed_paths(&repo, &target_tree, work_root, managed)?;
+    let mut store = ObjectStore::open(work_root)?;
+    let changed = managed_dirs_differ(&store, &repo, &target_tree, work_root, &dirs, managed)?;
+    let from_revision = previous_state.as_ref().and_then(|ps| match (ps, group) {
+        (ProjectRemoteState::Single { revision, .. }, _) => Some(revision.as_str()),
+        (ProjectRemoteState::Groups(_), Some(g)) => ps.group(g).map(|g| g.revision.as_str()),
+        (ProjectRemoteState::Groups(_), None) => None,
     });
     info_ctrl!(
         "project remote sync diff work_root={} requested_version={} changed={} from_revision={} to_revision={}",
@@ -388,38 +576,86 @@ fn sync_project_remote_with_repo_inner(
         from_revision.unwrap_or("-"),
         resolved.commit_id
     );
+    let to_revision = oid_to_string(resolved.commit_id);
+    let release_notes = if from_revision == Some(to_revision.as_str()) {
+        None
+    } else {
+        collect_release_notes(
+            &repo,
+            &target_tree,
+            &resolved.tag,
+            &resolved.version,
+            from_revision,
+            resolved.commit_id,
+            source.subpath.as_deref(),
+        )
+        .unwrap_or_else(|err| {
+            warn_ctrl!(
+                "project remote sync release notes skipped work_root={} resolved_tag={} error={}",
+                work_root.display(),
+                resolved.tag,
+                err
+            );
+            None
+        })
+    };
     if changed {
         info_ctrl!(
-            "project remote sync backup managed dirs work_root={} dirs={}",
+            "project remote sync stage managed dirs work_root={} dirs={}",
             work_root.display(),
             dirs.join(",")
         );
-        backup_managed_dirs(work_root, dirs)?;
+        stage_managed_dirs(&mut store, &repo, &target_tree, work_root, &dirs, managed)?;
     }
 
-    let result = ProjectRemoteUpdateResult {
+    let mut result = ProjectRemoteUpdateResult {
         requested_version: requested_version.map(str::to_string),
         current_version: resolved.version,
         resolved_tag: resolved.tag,
         from_revision: from_revision.map(str::to_string),
-        to_revision: oid_to_string(resolved.commit_id),
+        to_revision,
         changed,
         group: group_label.map(str::to_string),
+        validation: None,
+        release_notes,
     };
     let apply_result = (|| {
         if changed {
             info_ctrl!(
-                "project remote sync apply managed dirs work_root={} remote_cache={}",
+                "project remote sync swap managed dirs work_root={} dirs={}",
                 work_root.display(),
-                remote_root.display()
+                dirs.join(",")
             );
-            sync_managed_dirs(&remote_root, work_root, dirs)?;
+            swap_managed_dirs(work_root, group, &dirs, managed)?;
+        }
+        // An unchanged tree is checked too: re-syncing a release that is
+        // already broken on disk must not be reported as a clean sync.
+        let validation = validate_synced_tree(work_root, dict, opts);
+        info_ctrl!(
+            "project remote sync validate work_root={} current_version={} status={} failures={}",
+            work_root.display(),
+            result.current_version,
+            validation.status.as_str(),
+            validation.failures.len()
+        );
+        let failed = validation.is_failed();
+        result.validation = Some(validation);
+        if failed {
+            return Ok(());
         }
         match group {
             Some(g) => persist_group_state(work_root, g, &result)?,
             None => persist_state(work_root, &result)?,
         }
-        Ok(())
+        record_manifest(
+            work_root,
+            group,
+            &result.to_revision,
+            &repo,
+            &target_tree,
+            &dirs,
+            managed,
+        )
     })();
     if let Err(err) = apply_result {
         warn_ctrl!(
@@ -431,7 +667,7 @@ fn sync_project_remote_with_repo_inner(
             result.changed,
             err
         );
-        rollback_partial_update(work_root, previous_state.as_ref(), changed, dirs).map_err(
+        rollback_partial_update(work_root, previous_state.as_ref(), changed, group).map_err(
             |rollback_err| {
                 RunReason::from_conf()
                     .to_err()
@@ -449,6 +685,37 @@ fn sync_project_remote_with_repo_inner(
         );
         return Err(err);
     }
+    if result.validation_failed() {
+        warn_ctrl!(
+            "project remote sync blocked by validation work_root={} requested_version={} current_version={} resolved_tag={} failures={}",
+            work_root.display(),
+            requested_version.unwrap_or("(auto)"),
+            result.current_version,
+            result.resolved_tag,
+            result
+                .validation
+                .as_ref()
+                .map(ProjectRemoteValidation::summary)
+                .unwrap_or_default()
+        );
+        rollback_partial_update(work_root, previous_state.as_ref(), changed, group)?;
+        result.changed = false;
+        return Ok(result);
+    }
+    if changed {
+        match prune_object_store(work_root) {
+            Ok(removed) => info_ctrl!(
+                "project remote sync pruned object store work_root={} removed={}",
+                work_root.display(),
+                removed
+            ),
+            Err(err) => warn_ctrl!(
+                "project remote sync object store prune skipped work_root={} error={}",
+                work_root.display(),
+                err
+            ),
+        }
+    }
     info_ctrl!(
         "project remote sync done work_root={} requested_version={} current_version={} resolved_tag={} from_revision={} to_revision={} changed={}",
         work_root.display(),
@@ -466,10 +733,10 @@ fn rollback_partial_update(
     work_root: &Path,
     previous_state: Option<&ProjectRemoteState>,
     changed: bool,
-    dirs: &[&str],
+    group: Option<&RemoteGroup>,
 ) -> RunResult<()> {
     if changed {
-        restore_managed_dirs(work_root, dirs)?;
+        restore_managed_dirs(work_root, group)?;
     }
     restore_project_remote_state(work_root, previous_state)
 }
@@ -490,13 +757,13 @@ fn project_remote_repo_required_err() -> wp_error::RunError {
         .with_detail("project_remote.repo must not be empty")
 }
 
-fn project_remote_repo_required_err_for(group: &str) -> wp_error::RunError {
+pub(super) fn project_remote_repo_required_err_for(group: &str) -> wp_error::RunError {
     RunReason::from_conf()
         .to_err()
         .with_detail(format!("project_remote.{}.repo must not be empty", group))
 }
 
-fn project_remote_dual_partial_err(missing: &str) -> wp_error::RunError {
+pub(super) fn project_remote_dual_partial_err(missing: &str) -> wp_error::RunError {
     RunReason::from_conf()
         .to_err()
         .with_detail(format!(
@@ -505,18 +772,27 @@ fn project_remote_dual_partial_err(missing: &str) -> wp_error::RunError {
         ))
 }
 
-fn project_remote_ambiguous_mode_err() -> wp_error::RunError {
+pub(super) fn project_remote_ambiguous_mode_err() -> wp_error::RunError {
     RunReason::from_conf()
         .to_err()
         .with_detail(
-            "ambiguous project_remote config: use either 'repo' (single-repo) or both 'models' + 'infra' (dual-repo), not a mix",
+            "ambiguous project_remote config: use either 'repo' (single-repo) or repo groups ('models' + 'infra' and/or [project_remote.groups.*]), not a mix",
         )
 }
 
-fn project_remote_dual_requires_group_err() -> wp_error::RunError {
-    RunReason::from_conf().to_err().with_detail(
-        "dual-repo mode requires --group (models|infra); use sync_project_remote_group_with_dict",
-    )
+fn project_remote_dual_requires_group_err(groups: &[&str]) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "multi-repo mode requires --group ({}); use sync_project_remote_group_with_dict",
+        groups.join("|")
+    ))
+}
+
+fn project_remote_unknown_group_err(group: &RemoteGroup, groups: &[&str]) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "project_remote group '{}' is not configured; expected one of: {}",
+        group,
+        groups.join(", ")
+    ))
 }
 
 fn project_remote_single_no_group_err() -> wp_error::RunError {
@@ -525,6 +801,17 @@ fn project_remote_single_no_group_err() -> wp_error::RunError {
         .with_detail("single-repo mode does not support --group; use sync_project_remote_with_dict")
 }
 
+fn local_drift_err(group: Option<&RemoteGroup>, drift: &ProjectRemoteDrift) -> wp_error::RunError {
+    let scope = group
+        .map(|g| format!(" of group '{}'", g))
+        .unwrap_or_default();
+    RunReason::from_conf().to_err().with_detail(format!(
+        "managed dirs{} have local changes ({}); run 'wproj conf status' to inspect or pass --discard-local to overwrite them",
+        scope,
+        drift.summary()
+    ))
+}
+
 fn requested_version_not_found_err(version: &str) -> wp_error::RunError {
     RunReason::from_conf()
         .to_err()
@@ -547,10 +834,12 @@ mod test_support;
 #[cfg(test)]
 mod tests {
     use super::test_support::{
-        create_dual_work_root, create_empty_managed_dirs, create_infra_remote_fixture,
-        create_models_remote_fixture, create_remote_fixture, create_remote_fixture_without_tags,
-        create_work_root, write_engine_conf_with_init_version, write_model_version,
-        write_runtime_local_dirs,
+        append_engine_conf, commit_on_branch, create_dual_work_root, create_empty_managed_dirs,
+        create_group_remote_fixture, create_infra_remote_fixture, create_models_remote_fixture,
+        create_monorepo_remote_fixture, create_remote_fixture, create_remote_fixture_without_tags,
+        create_work_root, discard_local, skip_check, tag_annotated_release, tag_release,
+        tag_release_with_manifest, write_engine_conf, write_engine_conf_with_init_version,
+        write_model_version, write_runtime_local_dirs,
     };
     use super::*;
     use std::fs;
@@ -563,7 +852,8 @@ mod tests {
         write_model_version(work_root.path(), "1.4.2");
         write_runtime_local_dirs(work_root.path());
 
-        let result = sync_project_remote(work_root.path(), Some("1.4.3")).expect("sync remote");
+        let result = sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check())
+            .expect("sync remote");
 
         assert_eq!(result.requested_version.as_deref(), Some("1.4.3"));
         assert_eq!(result.current_version, "1.4.3");
@@ -594,7 +884,8 @@ mod tests {
         let work_root = create_work_root(&fixture);
         create_empty_managed_dirs(work_root.path());
 
-        let result = sync_project_remote(work_root.path(), None).expect("sync remote");
+        let result =
+            sync_project_remote(work_root.path(), None, &skip_check()).expect("sync remote");
 
         assert_eq!(result.requested_version, None);
         assert_eq!(result.current_version, "1.4.2");
@@ -616,11 +907,14 @@ mod tests {
                 to_revision: "old-revision".to_string(),
                 changed: false,
                 group: None,
+                validation: None,
+                release_notes: None,
             },
         )
         .expect("persist prior state");
 
-        let result = sync_project_remote(work_root.path(), None).expect("sync remote");
+        let result =
+            sync_project_remote(work_root.path(), None, &skip_check()).expect("sync remote");
 
         assert_eq!(result.requested_version, None);
         assert_eq!(result.current_version, "1.4.3");
@@ -634,7 +928,8 @@ mod tests {
         write_engine_conf_with_init_version(work_root.path(), fixture.repo_url(), "");
         create_empty_managed_dirs(work_root.path());
 
-        let result = sync_project_remote(work_root.path(), None).expect("sync remote");
+        let result =
+            sync_project_remote(work_root.path(), None, &skip_check()).expect("sync remote");
 
         assert_eq!(result.requested_version, None);
         assert!(result.resolved_tag.starts_with("HEAD@"));
@@ -655,7 +950,7 @@ mod tests {
         write_model_version(work_root.path(), "1.4.2");
         write_runtime_local_dirs(work_root.path());
 
-        sync_project_remote(work_root.path(), Some("1.4.3")).expect("sync remote");
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync remote");
 
         assert_eq!(
             fs::read_to_string(work_root.path().join("runtime/admin_api.token"))
@@ -674,8 +969,8 @@ mod tests {
         let work_root = create_work_root(&fixture);
         write_runtime_local_dirs(work_root.path());
 
-        let result =
-            sync_project_remote(work_root.path(), Some("1.4.2")).expect("sync should initialize");
+        let result = sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check())
+            .expect("sync should initialize");
 
         assert_eq!(result.current_version, "1.4.2");
         assert_eq!(result.resolved_tag, "v1.4.2");
@@ -699,7 +994,7 @@ mod tests {
         write_runtime_local_dirs(work_root.path());
 
         let snapshot = capture_project_remote_snapshot(work_root.path()).expect("capture snapshot");
-        sync_project_remote(work_root.path(), Some("1.4.3")).expect("sync remote");
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync remote");
         restore_project_remote_snapshot(work_root.path(), &snapshot).expect("restore snapshot");
 
         assert_eq!(
@@ -720,7 +1015,8 @@ mod tests {
         write_model_version(work_root.path(), "1.4.2");
 
         let snapshot = capture_project_remote_snapshot(work_root.path()).expect("capture snapshot");
-        let result = sync_project_remote(work_root.path(), Some("1.4.2")).expect("sync remote");
+        let result = sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check())
+            .expect("sync remote");
         assert!(!result.changed);
         assert!(work_root.path().join(STATE_PATH).exists());
 
@@ -739,14 +1035,16 @@ mod tests {
         let work_root = create_work_root(&fixture);
         write_model_version(work_root.path(), "1.4.2");
 
-        sync_project_remote(work_root.path(), Some("1.4.3")).expect("sync to latest");
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check())
+            .expect("sync to latest");
         assert_eq!(
             fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
             "1.4.3\n"
         );
 
         let snapshot = capture_project_remote_snapshot(work_root.path()).expect("capture snapshot");
-        let result = sync_project_remote(work_root.path(), Some("1.4.3")).expect("sync unchanged");
+        let result = sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check())
+            .expect("sync unchanged");
         assert!(!result.changed);
 
         restore_project_remote_update(work_root.path(), &snapshot, result.changed)
@@ -773,11 +1071,11 @@ mod tests {
         )
         .expect("write dual state");
 
-        let err =
-            sync_project_remote(work_root.path(), Some("1.4.3")).expect_err("sync should fail");
+        let err = sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check())
+            .expect_err("sync should fail");
         assert!(
             err.to_string()
-                .contains("cannot persist single-repo state over dual-repo state"),
+                .contains("cannot persist single-repo state over group state"),
             "unexpected error: {}",
             err
         );
@@ -821,6 +1119,7 @@ mod tests {
             RemoteGroup::Models,
             Some("1.4.3"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models");
 
@@ -848,6 +1147,7 @@ mod tests {
             RemoteGroup::Infra,
             Some("1.1.0"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync infra");
 
@@ -875,6 +1175,7 @@ mod tests {
             RemoteGroup::Models,
             None,
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models with init_version");
 
@@ -895,13 +1196,14 @@ mod tests {
             RemoteGroup::Models,
             Some("1.4.2"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models v1.4.2");
 
         // Manually inject infra state to simulate a previously-synced infra
         persist_group_state(
             work_root.path(),
-            RemoteGroup::Infra,
+            &RemoteGroup::Infra,
             &ProjectRemoteUpdateResult {
                 requested_version: Some("1.0.0".to_string()),
                 current_version: "1.0.0".to_string(),
@@ -910,6 +1212,8 @@ mod tests {
                 to_revision: "infra-rev".to_string(),
                 changed: false,
                 group: Some("infra".to_string()),
+                validation: None,
+                release_notes: None,
             },
         )
         .expect("inject infra state");
@@ -920,6 +1224,7 @@ mod tests {
             RemoteGroup::Models,
             Some("1.4.3"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models v1.4.3");
 
@@ -928,13 +1233,13 @@ mod tests {
             .expect("load state")
             .expect("state exists");
         match state {
-            ProjectRemoteState::Dual { models, infra } => {
-                let models = models.expect("models synced");
-                let infra = infra.expect("infra synced");
+            ProjectRemoteState::Groups(groups) => {
+                let models = groups.get("models").expect("models synced");
+                let infra = groups.get("infra").expect("infra synced");
                 assert_eq!(models.current_version, "1.4.3");
                 assert_eq!(infra.current_version, "1.0.0");
             }
-            _ => panic!("expected Dual state"),
+            _ => panic!("expected Groups state"),
         }
     }
 
@@ -950,6 +1255,7 @@ mod tests {
             RemoteGroup::Models,
             Some("1.4.2"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models");
         sync_project_remote_group_with_dict(
@@ -957,6 +1263,7 @@ mod tests {
             RemoteGroup::Infra,
             Some("1.0.0"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync infra");
 
@@ -980,6 +1287,7 @@ mod tests {
             RemoteGroup::Models,
             None,
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect_err("should reject group on single repo");
 
@@ -996,8 +1304,13 @@ mod tests {
         let infra_remote = create_infra_remote_fixture();
         let work_root = create_dual_work_root(&models_remote, &infra_remote);
 
-        let err = sync_project_remote_with_dict(work_root.path(), None, &EnvDict::default())
-            .expect_err("should require group on dual repo");
+        let err = sync_project_remote_with_dict(
+            work_root.path(),
+            None,
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect_err("should require group on dual repo");
 
         assert!(
             err.to_string().contains("--group"),
@@ -1030,7 +1343,7 @@ mod tests {
                 assert_eq!(resolved_tag, "v1.4.2");
                 assert_eq!(revision, "abc123");
             }
-            _ => panic!("expected Single state, got Dual"),
+            _ => panic!("expected Single state, got Groups"),
         }
 
         let version = current_project_version(work_root.path()).expect("read version");
@@ -1049,6 +1362,7 @@ mod tests {
             RemoteGroup::Models,
             Some("1.4.3"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models");
         sync_project_remote_group_with_dict(
@@ -1056,6 +1370,7 @@ mod tests {
             RemoteGroup::Infra,
             Some("1.1.0"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync infra");
 
@@ -1064,9 +1379,9 @@ mod tests {
             .expect("load state")
             .expect("state exists");
         match state {
-            ProjectRemoteState::Dual { models, infra } => {
-                let models = models.expect("models synced");
-                let infra = infra.expect("infra synced");
+            ProjectRemoteState::Groups(groups) => {
+                let models = groups.get("models").expect("models synced");
+                let infra = groups.get("infra").expect("infra synced");
                 assert_eq!(models.current_version, "1.4.3");
                 assert_eq!(models.resolved_tag, "v1.4.3");
                 assert!(!models.revision.is_empty());
@@ -1074,7 +1389,7 @@ mod tests {
                 assert_eq!(infra.resolved_tag, "v1.1.0");
                 assert!(!infra.revision.is_empty());
             }
-            _ => panic!("expected Dual state"),
+            _ => panic!("expected Groups state"),
         }
     }
 
@@ -1091,6 +1406,7 @@ mod tests {
             RemoteGroup::Models,
             Some("1.4.3"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models");
 
@@ -1126,6 +1442,7 @@ mod tests {
             RemoteGroup::Models,
             Some("1.4.3"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models v1.4.3");
         assert_eq!(
@@ -1162,6 +1479,7 @@ mod tests {
             RemoteGroup::Models,
             Some("1.4.2"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models");
         sync_project_remote_group_with_dict(
@@ -1169,6 +1487,7 @@ mod tests {
             RemoteGroup::Infra,
             Some("1.0.0"),
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync infra");
 
@@ -1199,6 +1518,7 @@ mod tests {
             RemoteGroup::Models,
             None,
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync models first");
 
@@ -1209,6 +1529,7 @@ mod tests {
             RemoteGroup::Infra,
             None,
             &EnvDict::default(),
+            &skip_check(),
         )
         .expect("sync infra second");
 
@@ -1234,6 +1555,8 @@ mod tests {
         fs::write(backup_root.join("models/version.txt"), "1.4.2\n").expect("write backup version");
         let manifest = BackupManifest {
             existing_dirs: vec!["models".to_string()],
+            managed_dirs: dirs.iter().map(|d| d.to_string()).collect(),
+            ..Default::default()
         };
         let body = serde_json::to_vec_pretty(&manifest).expect("encode manifest");
         fs::write(&manifest_path, body).expect("write manifest");
@@ -1243,7 +1566,7 @@ mod tests {
         fs::write(work_root.path().join("conf/new.toml"), "[new]\n").expect("write new conf");
 
         // Restore: should remove both models/ and conf/, then restore models/ from backup
-        restore_managed_dirs(work_root.path(), dirs).expect("restore");
+        restore_managed_dirs(work_root.path(), None).expect("restore");
 
         // conf/ (created during failed update, not in backup) must be cleaned up
         assert!(
@@ -1260,13 +1583,13 @@ mod tests {
     #[test]
     fn restore_managed_dirs_no_manifest_is_noop() {
         let work_root = tempdir().expect("tempdir");
-        let dirs: &[&str] = &["models"];
 
         fs::create_dir_all(work_root.path().join("models")).expect("create models");
         fs::write(work_root.path().join("models/version.txt"), "data\n").expect("write data");
 
         // No backup manifest at all — should be a no-op
-        restore_managed_dirs(work_root.path(), dirs).expect("restore without manifest");
+        restore_managed_dirs(work_root.path(), Some(&RemoteGroup::Models))
+            .expect("restore without manifest");
 
         // models/ should be untouched
         assert_eq!(
@@ -1274,4 +1597,1047 @@ mod tests {
             "data\n"
         );
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn sync_project_remote_links_managed_files_into_object_store() {
+        use std::os::unix::fs::MetadataExt;
+
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        write_model_version(work_root.path(), "1.4.2");
+
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync remote");
+
+        let meta = fs::metadata(work_root.path().join("models/version.txt")).expect("stat version");
+        assert!(
+            meta.nlink() >= 2,
+            "managed file should be a store hard link"
+        );
+        assert!(!work_root.path().join(STAGING_PATH).exists());
+        assert!(work_root.path().join(STORE_INDEX_PATH).exists());
+    }
+
+    #[test]
+    fn sync_project_remote_prunes_objects_no_release_references() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync 1.4.2");
+        let stray = work_root
+            .path()
+            .join(STORE_OBJECTS_PATH)
+            .join("00/00000000000000000000000000000000000000");
+        fs::create_dir_all(stray.parent().expect("shard")).expect("create shard");
+        fs::write(&stray, "stray").expect("write stray object");
+
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync 1.4.3");
+
+        assert!(!stray.exists());
+        let objects = walkdir::WalkDir::new(work_root.path().join(STORE_OBJECTS_PATH))
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .count();
+        // conf/wparse.toml plus both recorded versions of models/version.txt.
+        assert_eq!(objects, 3);
+    }
+
+    #[test]
+    fn restore_managed_dirs_swaps_backup_back_once() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        write_model_version(work_root.path(), "1.4.2");
+
+        let snapshot = capture_project_remote_snapshot(work_root.path()).expect("capture snapshot");
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync remote");
+        restore_project_remote_snapshot(work_root.path(), &snapshot).expect("restore snapshot");
+        assert!(!work_root.path().join(BACKUP_MANIFEST_PATH).exists());
+
+        // The backup was consumed by the first restore; repeating it must not
+        // remove the restored dirs.
+        restore_project_remote_snapshot(work_root.path(), &snapshot).expect("restore again");
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.2\n"
+        );
+    }
+
+    #[test]
+    fn sync_project_remote_detects_in_place_edit_of_linked_file() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        write_model_version(work_root.path(), "1.4.2");
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync remote");
+
+        // Writing through the hard link also rewrites the store object.
+        fs::write(work_root.path().join("models/version.txt"), "edit!\n").expect("edit in place");
+
+        let err = sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check())
+            .expect_err("local edit should block the sync");
+        assert!(err.to_string().contains("modified: models/version.txt"));
+
+        let result = sync_project_remote(work_root.path(), Some("1.4.3"), &discard_local())
+            .expect("sync again");
+        assert!(result.changed);
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.3\n"
+        );
+    }
+
+    #[test]
+    fn project_remote_drift_status_reports_local_changes() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        write_model_version(work_root.path(), "1.4.2");
+        assert!(project_remote_drift_status(work_root.path())
+            .expect("status before sync")
+            .is_empty());
+
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync remote");
+        let status = project_remote_drift_status(work_root.path()).expect("clean status");
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].version, "1.4.3");
+        assert_eq!(status[0].status, DriftStatus::Clean);
+
+        fs::write(work_root.path().join("models/version.txt"), "hotfix\n").expect("edit");
+        fs::write(work_root.path().join("models/extra.txt"), "extra\n").expect("add");
+        fs::remove_file(work_root.path().join("conf/wparse.toml")).expect("delete");
+        let status = project_remote_drift_status(work_root.path()).expect("drift status");
+        assert_eq!(status[0].status, DriftStatus::Drifted);
+        assert_eq!(status[0].drift.modified, vec!["models/version.txt"]);
+        assert_eq!(status[0].drift.added, vec!["models/extra.txt"]);
+        assert_eq!(status[0].drift.deleted, vec!["conf/wparse.toml"]);
+    }
+
+    #[test]
+    fn sync_project_remote_restores_dirs_when_validation_fails() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        write_model_version(work_root.path(), "1.4.2");
+        write_runtime_local_dirs(work_root.path());
+
+        // The fixture has no conf/wpsrc.toml, so the project check rejects it.
+        let result = sync_project_remote(work_root.path(), Some("1.4.3"), &SyncOptions::default())
+            .expect("blocked sync still reports a result");
+
+        assert!(result.validation_failed());
+        assert!(!result.changed);
+        let validation = result.validation.as_ref().expect("validation result");
+        assert!(!validation.failures.is_empty());
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.2\n"
+        );
+        assert!(!work_root.path().join(STATE_PATH).exists());
+        assert!(!work_root.path().join(BACKUP_MANIFEST_PATH).exists());
+    }
+
+    #[test]
+    fn sync_project_remote_validates_an_unchanged_resync() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        write_model_version(work_root.path(), "1.4.2");
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+        let state_before = fs::read(work_root.path().join(STATE_PATH)).expect("read state");
+
+        let result = sync_project_remote(work_root.path(), Some("1.4.3"), &SyncOptions::default())
+            .expect("blocked re-sync still reports a result");
+
+        assert!(!result.changed);
+        assert!(result.validation_failed());
+        assert_eq!(
+            fs::read(work_root.path().join(STATE_PATH)).expect("read state"),
+            state_before
+        );
+    }
+
+    #[test]
+    fn sync_project_remote_skip_validate_marks_result_skipped() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        write_model_version(work_root.path(), "1.4.2");
+
+        let result =
+            sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+
+        assert!(result.changed);
+        assert_eq!(
+            result.validation.as_ref().map(|v| v.status),
+            Some(ValidationStatus::Skipped)
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.3\n"
+        );
+    }
+
+    #[test]
+    fn named_group_syncs_only_its_paths_next_to_dual_groups() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let knowledge_remote = create_group_remote_fixture("knowledge");
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+        append_engine_conf(
+            work_root.path(),
+            &format!(
+                "\n[project_remote.groups.knowledge]\nrepo = \"{}\"\npaths = [\"knowledge\"]\n",
+                knowledge_remote.repo_url()
+            ),
+        );
+        write_model_version(work_root.path(), "local");
+
+        let group: RemoteGroup = "knowledge".parse().expect("parse group");
+        let result = sync_project_remote_group_with_dict(
+            work_root.path(),
+            group,
+            Some("1.1.0"),
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect("sync knowledge");
+
+        assert_eq!(result.group.as_deref(), Some("knowledge"));
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("knowledge/version.txt"))
+                .expect("read knowledge"),
+            "1.1.0\n"
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read models"),
+            "local\n"
+        );
+        assert!(work_root
+            .path()
+            .join(".run/project_remote/remote-knowledge")
+            .exists());
+        let versions = current_project_group_versions(work_root.path())
+            .expect("read versions")
+            .expect("group versions");
+        assert_eq!(versions["knowledge"]["version"], "1.1.0");
+    }
+
+    #[test]
+    fn group_exclude_keeps_nested_group_across_outer_swap() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let team_remote = create_group_remote_fixture("models/team_a");
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+        fs::write(
+            work_root.path().join("conf/wparse.toml"),
+            format!(
+                r#"version = "1.0"
+
+[project_remote]
+enabled = true
+
+[project_remote.models]
+repo = "{}"
+init_version = "1.4.2"
+exclude = ["models/team_*"]
+
+[project_remote.infra]
+repo = "{}"
+
+[project_remote.groups.team_a]
+repo = "{}"
+paths = ["models/team_a"]
+"#,
+                models_remote.repo_url(),
+                infra_remote.repo_url(),
+                team_remote.repo_url()
+            ),
+        )
+        .expect("write conf");
+
+        let team: RemoteGroup = "team_a".parse().expect("parse group");
+        sync_project_remote_group_with_dict(
+            work_root.path(),
+            team,
+            Some("1.0.0"),
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect("sync team_a");
+        sync_project_remote_group_with_dict(
+            work_root.path(),
+            RemoteGroup::Models,
+            Some("1.4.3"),
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect("sync models");
+
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read models"),
+            "1.4.3\n"
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/team_a/version.txt"))
+                .expect("read team_a"),
+            "1.0.0\n"
+        );
+
+        // Rolling the outer group back must not take the nested group with it.
+        let snapshot =
+            capture_project_remote_snapshot_with_group(work_root.path(), Some(RemoteGroup::Models))
+                .expect("capture snapshot");
+        let result = sync_project_remote_group_with_dict(
+            work_root.path(),
+            RemoteGroup::Models,
+            Some("1.4.2"),
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect("sync models back");
+        restore_project_remote_update(work_root.path(), &snapshot, result.changed)
+            .expect("restore models");
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read models"),
+            "1.4.3\n"
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/team_a/version.txt"))
+                .expect("read team_a"),
+            "1.0.0\n"
+        );
+    }
+
+    #[test]
+    fn overlapping_groups_without_exclude_are_rejected() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+        append_engine_conf(
+            work_root.path(),
+            "\n[project_remote.groups.team_a]\nrepo = \"https://example.com/team_a.git\"\npaths = [\"models/team_a\"]\n",
+        );
+
+        let err = sync_project_remote_group_with_dict(
+            work_root.path(),
+            RemoteGroup::Models,
+            None,
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect_err("overlap should be rejected");
+        assert!(
+            err.to_string().contains("overlapping"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn unknown_group_is_rejected() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+
+        let group: RemoteGroup = "knowledge".parse().expect("parse group");
+        let err = sync_project_remote_group_with_dict(
+            work_root.path(),
+            group,
+            None,
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect_err("unknown group");
+        assert!(
+            err.to_string().contains("is not configured"),
+            "unexpected error: {}",
+            err
+        );
+        assert!("../x".parse::<RemoteGroup>().is_err());
+    }
+
+    #[test]
+    fn sync_project_remote_reads_managed_dirs_from_subpath() {
+        let fixture = create_monorepo_remote_fixture("projects/site-a");
+        let work_root = create_work_root(&fixture);
+        append_engine_conf(work_root.path(), "subpath = \"projects/site-a\"\n");
+        write_model_version(work_root.path(), "1.4.2");
+
+        let result =
+            sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+
+        assert!(result.changed);
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.3\n"
+        );
+        assert!(!work_root.path().join("projects").exists());
+        let conf = fs::read_to_string(work_root.path().join(ENGINE_CONF_PATH)).expect("read conf");
+        assert!(conf.contains("subpath = \"projects/site-a\""));
+    }
+
+    #[test]
+    fn sync_project_remote_rejects_missing_or_invalid_subpath() {
+        let fixture = create_monorepo_remote_fixture("projects/site-a");
+        let work_root = create_work_root(&fixture);
+        append_engine_conf(work_root.path(), "subpath = \"projects/site-b\"\n");
+        write_model_version(work_root.path(), "1.4.2");
+
+        let err = sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check())
+            .expect_err("missing subpath should fail");
+        assert!(
+            err.to_string().contains("subpath 'projects/site-b'"),
+            "unexpected error: {}",
+            err
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.2\n"
+        );
+
+        write_engine_conf(work_root.path(), fixture.repo_url());
+        append_engine_conf(work_root.path(), "subpath = \"../outside\"\n");
+        let err = sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check())
+            .expect_err("escaping subpath should fail");
+        assert!(
+            err.to_string().contains("invalid subpath"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn track_branch_follows_branch_tip_and_records_commit() {
+        let fixture = create_remote_fixture();
+        let first = commit_on_branch(&fixture, "staging", "staging-1");
+        let work_root = tempdir().expect("tempdir");
+        write_engine_conf_with_init_version(work_root.path(), fixture.repo_url(), "");
+        append_engine_conf(work_root.path(), "track_branch = \"staging\"\n");
+        create_empty_managed_dirs(work_root.path());
+
+        let result =
+            sync_project_remote(work_root.path(), None, &skip_check()).expect("sync branch tip");
+        assert_eq!(result.current_version, first.to_string());
+        assert_eq!(
+            result.resolved_tag,
+            format!("staging@{}", &first.to_string()[..12])
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "staging-1\n"
+        );
+
+        let second = commit_on_branch(&fixture, "staging", "staging-2");
+        let result =
+            sync_project_remote(work_root.path(), None, &skip_check()).expect("follow new tip");
+        assert_eq!(result.current_version, second.to_string());
+        assert_eq!(result.from_revision, Some(first.to_string()));
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "staging-2\n"
+        );
+        let state: serde_json::Value = serde_json::from_slice(
+            &fs::read(work_root.path().join(STATE_PATH)).expect("read state file"),
+        )
+        .expect("parse state json");
+        assert_eq!(state["current_version"], second.to_string());
+
+        // Pinning a commit from the branch history works like a version.
+        let result = sync_project_remote(
+            work_root.path(),
+            Some(&first.to_string()[..12]),
+            &skip_check(),
+        )
+        .expect("pin earlier commit");
+        assert_eq!(result.current_version, first.to_string());
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "staging-1\n"
+        );
+    }
+
+    #[test]
+    fn sync_project_remote_reports_release_notes() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        create_empty_managed_dirs(work_root.path());
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync 1.4.2");
+        tag_release(&fixture, "1.4.4");
+        tag_annotated_release(
+            &fixture,
+            "1.5.0",
+            "Minor release\n\nNew nginx rules.",
+            "- nginx",
+        );
+
+        let result = sync_project_remote(work_root.path(), Some("1.5.0"), &skip_check())
+            .expect("sync 1.5.0");
+        let notes = result.release_notes.expect("release notes");
+        assert_eq!(
+            notes.tag_message.as_deref(),
+            Some("Minor release\n\nNew nginx rules.")
+        );
+        assert_eq!(notes.changelog.as_deref(), Some("- nginx"));
+        let summaries: Vec<&str> = notes.commits.iter().map(|c| c.summary.as_str()).collect();
+        assert_eq!(
+            summaries,
+            ["release 1.5.0", "release 1.4.4", "release 1.4.3"]
+        );
+        assert_eq!(notes.commits[0].id, result.to_revision);
+        assert!(!notes.truncated);
+
+        // Re-syncing the active release has nothing new to report.
+        let result =
+            sync_project_remote(work_root.path(), Some("1.5.0"), &skip_check()).expect("resync");
+        assert!(result.release_notes.is_none());
+    }
+
+    #[test]
+    fn fetch_depth_limits_explicit_sync_to_target_tag() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        append_engine_conf(
+            work_root.path(),
+            "\n[project_remote.cache]\nfetch_depth = 1\n",
+        );
+        create_empty_managed_dirs(work_root.path());
+        let cache_tags = || {
+            let repo = git2::Repository::open(work_root.path().join(REMOTE_CACHE_PATH))
+                .expect("open cache");
+            let names = repo.tag_names(None).expect("tag names");
+            names
+                .iter()
+                .flatten()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync 1.4.2");
+        assert_eq!(cache_tags(), ["v1.4.2"]);
+
+        let result = sync_project_remote(work_root.path(), None, &skip_check()).expect("sync auto");
+        assert_eq!(result.current_version, "1.4.3");
+        assert_eq!(cache_tags(), ["v1.4.2", "v1.4.3"]);
+    }
+
+    #[test]
+    fn oversized_cache_is_rebuilt_before_fetch() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        create_empty_managed_dirs(work_root.path());
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync 1.4.2");
+        let cache_root = work_root.path().join(REMOTE_CACHE_PATH);
+        fs::write(cache_root.join("stale.bin"), "stale").expect("write stale file");
+
+        let policy = cache::CachePolicy {
+            fetch_depth: 0,
+            max_size_bytes: 1,
+        };
+        let repo =
+            prepare_remote_repo(&cache_root, fixture.repo_url(), &policy).expect("prepare cache");
+        assert!(!cache_root.join("stale.bin").exists());
+        fetch_remote_tags(&repo, fixture.repo_url(), &policy, None).expect("fetch");
+        assert!(resolve_requested_target(&repo, "1.4.3", None)
+            .expect("resolve")
+            .is_some());
+    }
+
+    #[test]
+    fn cache_info_reports_and_rebuild_replaces_caches() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        create_empty_managed_dirs(work_root.path());
+        let dict = EnvDict::default();
+        let infos = project_remote_cache_info(work_root.path(), &dict).expect("info before sync");
+        assert_eq!(infos.len(), 1);
+        assert!(!infos[0].exists);
+
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync 1.4.2");
+        let orphan = work_root
+            .path()
+            .join(format!("{}-retired", REMOTE_CACHE_PATH));
+        fs::create_dir_all(&orphan).expect("create orphan cache");
+        let infos = project_remote_cache_info(work_root.path(), &dict).expect("info");
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].path, REMOTE_CACHE_PATH);
+        assert_eq!(infos[0].repo.as_deref(), Some(fixture.repo_url()));
+        assert!(infos[0].exists && infos[0].objects > 0 && infos[0].size_bytes > 0);
+        assert!(!infos[0].shallow);
+        assert_eq!(infos[1].repo, None);
+
+        let infos = rebuild_project_remote_cache(work_root.path(), &dict).expect("rebuild");
+        assert_eq!(infos.len(), 1);
+        assert!(infos[0].exists && infos[0].objects > 0);
+        assert!(!orphan.exists());
+        let result = sync_project_remote(work_root.path(), None, &skip_check()).expect("sync auto");
+        assert_eq!(result.current_version, "1.4.3");
+    }
+
+    #[test]
+    fn poll_check_picks_newest_release_allowed_by_policy() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        create_empty_managed_dirs(work_root.path());
+        let dict = EnvDict::default();
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync 1.4.2");
+        tag_release(&fixture, "1.5.0");
+        tag_release(&fixture, "2.0.0");
+
+        for (policy, expected) in [
+            (PollPolicy::Patch, "1.4.3"),
+            (PollPolicy::Minor, "1.5.0"),
+            (PollPolicy::Latest, "2.0.0"),
+        ] {
+            let pending = check_project_remote_updates(work_root.path(), &dict, policy)
+                .expect("check updates");
+            assert_eq!(pending.len(), 1, "policy {}", policy.as_str());
+            assert_eq!(pending[0].current_version.as_deref(), Some("1.4.2"));
+            assert_eq!(pending[0].target_version, expected);
+            assert_eq!(pending[0].requested_version(), Some(expected));
+        }
+        // Checking only fetches; the work root stays on 1.4.2.
+        assert_eq!(
+            current_project_version(work_root.path()).expect("read version"),
+            Some("1.4.2".to_string())
+        );
+
+        sync_project_remote(work_root.path(), Some("2.0.0"), &skip_check()).expect("sync 2.0.0");
+        let pending = check_project_remote_updates(work_root.path(), &dict, PollPolicy::Latest)
+            .expect("check updates");
+        assert!(pending.is_empty(), "unexpected pending: {:?}", pending);
+    }
+
+    #[test]
+    fn poll_check_reports_new_branch_tip() {
+        let fixture = create_remote_fixture();
+        commit_on_branch(&fixture, "staging", "staging-1");
+        let work_root = tempdir().expect("tempdir");
+        write_engine_conf_with_init_version(work_root.path(), fixture.repo_url(), "");
+        append_engine_conf(work_root.path(), "track_branch = \"staging\"\n");
+        create_empty_managed_dirs(work_root.path());
+        let dict = EnvDict::default();
+
+        // Never synced: the default target is reported and resolved again.
+        let pending = check_project_remote_updates(work_root.path(), &dict, PollPolicy::Patch)
+            .expect("check updates");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].requested_version(), None);
+
+        sync_project_remote(work_root.path(), None, &skip_check()).expect("sync branch tip");
+        let pending = check_project_remote_updates(work_root.path(), &dict, PollPolicy::Patch)
+            .expect("check updates");
+        assert!(pending.is_empty(), "unexpected pending: {:?}", pending);
+
+        let second = commit_on_branch(&fixture, "staging", "staging-2");
+        let pending = check_project_remote_updates(work_root.path(), &dict, PollPolicy::Patch)
+            .expect("check updates");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].target_version, second.to_string());
+    }
+
+    #[test]
+    fn load_poll_conf_reads_poll_table_and_backs_off() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let dict = EnvDict::default();
+        assert!(load_poll_conf(work_root.path(), &dict)
+            .expect("load poll conf")
+            .is_none());
+
+        append_engine_conf(
+            work_root.path(),
+            "\n[project_remote.poll]\ninterval_secs = 60\nmax_backoff_secs = 300\npolicy = \"minor\"\n",
+        );
+        let conf = load_poll_conf(work_root.path(), &dict)
+            .expect("load poll conf")
+            .expect("poll enabled");
+        assert_eq!(conf.policy, PollPolicy::Minor);
+        assert_eq!(conf.next_delay(0), std::time::Duration::from_secs(60));
+        assert_eq!(conf.next_delay(1), std::time::Duration::from_secs(120));
+        assert_eq!(conf.next_delay(2), std::time::Duration::from_secs(240));
+        assert_eq!(conf.next_delay(5), std::time::Duration::from_secs(300));
+
+        let jittered = ProjectRemotePollConf {
+            jitter: std::time::Duration::from_secs(10),
+            ..conf
+        };
+        for _ in 0..16 {
+            let delay = jittered.next_delay(0);
+            assert!(delay >= std::time::Duration::from_secs(60));
+            assert!(delay <= std::time::Duration::from_secs(70));
+        }
+
+        write_engine_conf(work_root.path(), fixture.repo_url());
+        append_engine_conf(
+            work_root.path(),
+            "\n[project_remote.poll]\ninterval_secs = 60\npolicy = \"newest\"\n",
+        );
+        let err = load_poll_conf(work_root.path(), &dict).expect_err("bad policy");
+        assert!(
+            err.to_string().contains("invalid policy 'newest'"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    // Only models is synced up front: syncing infra replaces conf/wparse.toml
+    // with the fixture's copy, which points at unreachable remotes.
+    fn sync_models_baseline(work_root: &Path) {
+        sync_project_remote_group_with_dict(
+            work_root,
+            RemoteGroup::Models,
+            Some("1.4.2"),
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect("sync models v1.4.2");
+    }
+
+    #[test]
+    fn release_set_moves_groups_together_and_restores_them_together() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+        create_empty_managed_dirs(work_root.path());
+        sync_models_baseline(work_root.path());
+        let before = fs::read(work_root.path().join(STATE_PATH)).expect("read state");
+
+        let set = parse_release_set(["models=1.4.3", "infra=1.1.0"]).expect("parse set");
+        let snapshot = capture_project_remote_snapshot(work_root.path()).expect("capture");
+        let result = sync_project_remote_release_set_with_dict(
+            work_root.path(),
+            &set,
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect("sync release set");
+
+        assert!(result.changed());
+        assert_eq!(result.summary(), "models=1.4.3,infra=1.1.0");
+        assert_eq!(
+            result.validation.as_ref().map(|v| v.status),
+            Some(ValidationStatus::Skipped)
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.3\n"
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("conf/infra.toml")).expect("read infra"),
+            "[infra]\nversion = \"1.1.0\"\n"
+        );
+        let versions = current_project_group_versions(work_root.path())
+            .expect("group versions")
+            .expect("group state");
+        assert_eq!(versions["models"]["version"], "1.4.3");
+        assert_eq!(versions["infra"]["version"], "1.1.0");
+
+        // A failed reload undoes the whole set.
+        restore_project_remote_release_set(work_root.path(), &snapshot, &result)
+            .expect("restore set");
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.2\n"
+        );
+        assert!(!work_root.path().join("conf/infra.toml").exists());
+        assert_eq!(
+            fs::read(work_root.path().join(STATE_PATH)).expect("read state"),
+            before
+        );
+    }
+
+    #[test]
+    fn release_set_restores_synced_groups_when_a_later_group_fails() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+        create_empty_managed_dirs(work_root.path());
+        sync_models_baseline(work_root.path());
+        let before = fs::read(work_root.path().join(STATE_PATH)).expect("read state");
+
+        let set = parse_release_set(["models=1.4.3", "infra=9.9.9"]).expect("parse set");
+        let err = sync_project_remote_release_set_with_dict(
+            work_root.path(),
+            &set,
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect_err("unknown infra version");
+        assert!(
+            err.to_string().contains("9.9.9"),
+            "unexpected error: {}",
+            err
+        );
+
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.2\n"
+        );
+        assert!(!work_root.path().join("conf/infra.toml").exists());
+        assert_eq!(
+            fs::read(work_root.path().join(STATE_PATH)).expect("read state"),
+            before
+        );
+    }
+
+    #[test]
+    fn release_set_validates_once_and_restores_every_group_on_failure() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+        create_empty_managed_dirs(work_root.path());
+        sync_models_baseline(work_root.path());
+
+        // The fixtures carry no conf/wpsrc.toml, so the combined tree fails
+        // the project check.
+        let set = parse_release_set(["models=1.4.3", "infra=1.1.0"]).expect("parse set");
+        let result = sync_project_remote_release_set_with_dict(
+            work_root.path(),
+            &set,
+            &EnvDict::default(),
+            &SyncOptions::default(),
+        )
+        .expect("blocked set still reports a result");
+
+        assert!(result.validation_failed());
+        assert!(!result.changed());
+        assert!(result.groups.iter().all(|g| g.validation.is_none()));
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.2\n"
+        );
+        assert!(!work_root.path().join("conf/infra.toml").exists());
+        let versions = current_project_group_versions(work_root.path())
+            .expect("group versions")
+            .expect("group state");
+        assert_eq!(versions["models"]["version"], "1.4.2");
+        assert!(versions.get("infra").is_none());
+    }
+
+    #[test]
+    fn parse_release_set_rejects_malformed_entries() {
+        assert!(parse_release_set(["models"]).is_err());
+        assert!(parse_release_set(["models="]).is_err());
+        assert!(parse_release_set(["models=1.4.3", "models=1.4.2"]).is_err());
+        assert!(parse_release_set(["bad group=1.0.0"]).is_err());
+    }
+
+    #[test]
+    fn sync_project_remote_rejects_release_requiring_newer_engine() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync v1.4.2");
+        let before = fs::read(work_root.path().join(STATE_PATH)).expect("read state");
+
+        tag_release_with_manifest(
+            &fixture,
+            "1.5.0",
+            "requires_engine = \">=99.0\"\nrequires_connectors = [\"file\", \"kafka\"]\n",
+        );
+        let err = sync_project_remote(work_root.path(), Some("1.5.0"), &skip_check())
+            .expect_err("incompatible release");
+        let msg = err.to_string();
+        assert!(msg.contains("release v1.5.0 is not compatible"), "{}", msg);
+        assert!(msg.contains("connectors kafka"), "{}", msg);
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.2\n"
+        );
+        assert_eq!(
+            fs::read(work_root.path().join(STATE_PATH)).expect("read state"),
+            before
+        );
+
+        tag_release_with_manifest(
+            &fixture,
+            "1.6.0",
+            "requires_engine = \">=0.1\"\nrequires_connectors = [\"file\"]\n",
+        );
+        let result = sync_project_remote(work_root.path(), Some("1.6.0"), &skip_check())
+            .expect("compatible release");
+        assert_eq!(result.current_version, "1.6.0");
+    }
+
+    #[test]
+    fn plan_project_remote_update_diffs_target_without_applying_it() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        write_model_version(work_root.path(), "1.4.2");
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("sync 1.4.2");
+        fs::create_dir_all(work_root.path().join("models/wpl")).expect("create wpl dir");
+        fs::write(
+            work_root.path().join("models/wpl/local.wpl"),
+            "package /local/ {\n   rule extra {\n   }\n}\n",
+        )
+        .expect("write local rule");
+        let state_before = fs::read(work_root.path().join(STATE_PATH)).expect("read state");
+        let dict = EnvDict::default();
+
+        let plan = plan_project_remote_update(work_root.path(), Some("1.4.3"), None, &dict)
+            .expect("plan update");
+
+        assert_eq!(plan.current_version.as_deref(), Some("1.4.2"));
+        assert_eq!(plan.target_version, "1.4.3");
+        assert_eq!(plan.resolved_tag, "v1.4.3");
+        assert!(plan.changed);
+        assert_eq!(plan.incompatible, None);
+        let version = plan
+            .files
+            .iter()
+            .find(|f| f.path == "models/version.txt")
+            .expect("version.txt planned");
+        assert_eq!(version.change, FileChange::Modified);
+        let diff = version.diff.as_deref().expect("text diff");
+        assert!(
+            diff.contains("-1.4.2") && diff.contains("+1.4.3"),
+            "{}",
+            diff
+        );
+        let local = plan
+            .files
+            .iter()
+            .find(|f| f.path == "models/wpl/local.wpl")
+            .expect("local rule planned");
+        assert_eq!(local.change, FileChange::Deleted);
+        assert_eq!(plan.summary.wpl_rules.removed, vec!["/local/extra"]);
+        assert!(plan.summary.wpl_rules.added.is_empty());
+
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.2\n"
+        );
+        assert!(work_root.path().join("models/wpl/local.wpl").exists());
+        assert_eq!(
+            fs::read(work_root.path().join(STATE_PATH)).expect("read state"),
+            state_before
+        );
+        assert!(!work_root.path().join(LOCK_PATH).exists());
+    }
+
+    #[test]
+    fn webhook_tag_push_maps_to_configured_source() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let repo_url = fixture.repo_url();
+        let dict = EnvDict::default();
+        let event = |tag: &str, url: &str| WebhookEvent {
+            provider: WebhookProvider::Github,
+            event: "push".to_string(),
+            tag: Some(tag.to_string()),
+            repo_urls: vec![url.to_string()],
+            delivery: None,
+        };
+
+        let action = plan_webhook_action(
+            work_root.path(),
+            &dict,
+            &event("v1.4.3", &format!("{}/", repo_url)),
+        )
+        .expect("plan webhook");
+        assert_eq!(
+            action,
+            WebhookAction::Update(vec![WebhookTarget {
+                group: None,
+                version: "1.4.3".to_string(),
+                tag: "v1.4.3".to_string(),
+                pinned: false,
+            }])
+        );
+
+        let other = plan_webhook_action(
+            work_root.path(),
+            &dict,
+            &event("v1.4.3", "https://example.com/other/repo.git"),
+        )
+        .expect("plan webhook");
+        assert!(matches!(other, WebhookAction::Ignored(_)));
+        let nightly = plan_webhook_action(work_root.path(), &dict, &event("nightly", repo_url))
+            .expect("plan webhook");
+        assert_eq!(
+            nightly,
+            WebhookAction::Ignored("tag nightly is not a release version".to_string())
+        );
+    }
+
+    #[test]
+    fn webhook_rejects_tags_the_poll_policy_does_not_allow() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let repo_url = fixture.repo_url();
+        let dict = EnvDict::default();
+        let event = |tag: &str| WebhookEvent {
+            provider: WebhookProvider::Github,
+            event: "push".to_string(),
+            tag: Some(tag.to_string()),
+            repo_urls: vec![repo_url.to_string()],
+            delivery: Some(format!("delivery-{}", tag)),
+        };
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync remote");
+
+        // A replayed delivery of the previous release must not roll back.
+        for tag in ["v1.4.2", "v1.4.3"] {
+            let action =
+                plan_webhook_action(work_root.path(), &dict, &event(tag)).expect("plan webhook");
+            assert_eq!(
+                action,
+                WebhookAction::Ignored(format!(
+                    "tag {} is not a release the latest poll policy allows (1.4.3 is deployed)",
+                    tag
+                ))
+            );
+        }
+        let action =
+            plan_webhook_action(work_root.path(), &dict, &event("v2.0.0")).expect("plan webhook");
+        assert!(matches!(action, WebhookAction::Update(_)));
+
+        append_engine_conf(
+            work_root.path(),
+            "\n[project_remote.poll]\ninterval_secs = 0\npolicy = \"patch\"\n",
+        );
+        let action =
+            plan_webhook_action(work_root.path(), &dict, &event("v2.0.0")).expect("plan webhook");
+        assert!(matches!(action, WebhookAction::Ignored(_)));
+        let action =
+            plan_webhook_action(work_root.path(), &dict, &event("v1.4.4")).expect("plan webhook");
+        assert!(matches!(action, WebhookAction::Update(_)));
+    }
+
+    #[test]
+    fn pinned_webhook_may_roll_back_and_survives_the_recheck() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let repo_url = fixture.repo_url();
+        let dict = EnvDict::default();
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync remote");
+        let rollback = WebhookTarget {
+            group: None,
+            version: "1.4.2".to_string(),
+            tag: "v1.4.2".to_string(),
+            pinned: false,
+        };
+        assert!(
+            recheck_webhook_targets(work_root.path(), &dict, vec![rollback.clone()])
+                .expect("recheck")
+                .is_empty()
+        );
+
+        append_engine_conf(work_root.path(), "\n[project_remote.webhook]\npin = true\n");
+        let action = plan_webhook_action(
+            work_root.path(),
+            &dict,
+            &WebhookEvent {
+                provider: WebhookProvider::Github,
+                event: "push".to_string(),
+                tag: Some("v1.4.2".to_string()),
+                repo_urls: vec![repo_url.to_string()],
+                delivery: None,
+            },
+        )
+        .expect("plan webhook");
+        let pinned = WebhookTarget {
+            pinned: true,
+            ..rollback
+        };
+        assert_eq!(action, WebhookAction::Update(vec![pinned.clone()]));
+        assert_eq!(
+            recheck_webhook_targets(work_root.path(), &dict, vec![pinned.clone()])
+                .expect("recheck"),
+            vec![pinned]
+        );
+    }
 }
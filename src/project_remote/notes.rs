@@ -0,0 +1,246 @@
+use std::path::Path;
+
+use git2::{Commit, ObjectType, Oid, Repository, Sort, Tree};
+use serde::Serialize;
+use wp_error::run_error::RunResult;
+
+use super::conf_err_source;
+
+const CHANGELOG_FILE: &str = "CHANGELOG.md";
+const MAX_TAG_MESSAGE_BYTES: usize = 4 * 1024;
+const MAX_CHANGELOG_BYTES: usize = 8 * 1024;
+const MAX_SUMMARY_BYTES: usize = 200;
+const MAX_COMMITS: usize = 50;
+
+/// What changed between the previous and the newly synced release, as far as
+/// the remote repository tells. Every part is size-bounded; `truncated` is set
+/// when anything was cut.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReleaseNotes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<ReleaseCommit>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl ReleaseNotes {
+    pub fn is_empty(&self) -> bool {
+        self.tag_message.is_none() && self.changelog.is_none() && self.commits.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseCommit {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    /// Commit time, seconds since the Unix epoch.
+    pub time: i64,
+}
+
+/// Collects the notes of the release at `to`: the annotated message of `tag`,
+/// the `version` section of `CHANGELOG.md` in `tree`, and the commits from
+/// `from` (exclusive) to `to` that touch `subpath`. `None` when the remote
+/// has nothing to say.
+pub(super) fn collect_release_notes(
+    repo: &Repository,
+    tree: &Tree,
+    tag: &str,
+    version: &str,
+    from: Option<&str>,
+    to: Oid,
+    subpath: Option<&str>,
+) -> RunResult<Option<ReleaseNotes>> {
+    let mut notes = ReleaseNotes::default();
+    if let Some(message) = tag_message(repo, tag)? {
+        notes.tag_message = Some(bounded(
+            message,
+            MAX_TAG_MESSAGE_BYTES,
+            &mut notes.truncated,
+        ));
+    }
+    if let Some(section) = changelog_section(repo, tree, version)? {
+        notes.changelog = Some(bounded(section, MAX_CHANGELOG_BYTES, &mut notes.truncated));
+    }
+    if let Some(from) = from {
+        collect_commits(repo, from, to, subpath, &mut notes)?;
+    }
+    Ok((!notes.is_empty()).then_some(notes))
+}
+
+/// Message of an annotated tag; lightweight tags and branch targets have none.
+fn tag_message(repo: &Repository, tag: &str) -> RunResult<Option<String>> {
+    let Ok(reference) = repo.find_reference(&format!("refs/tags/{}", tag)) else {
+        return Ok(None);
+    };
+    let Some(target) = reference.target() else {
+        return Ok(None);
+    };
+    let annotated = match repo.find_object(target, None) {
+        Ok(obj) if obj.kind() == Some(ObjectType::Tag) => obj.into_tag().ok(),
+        Ok(_) => None,
+        Err(err) => return Err(conf_err_source(format!("load tag {} failed", tag), err)),
+    };
+    Ok(annotated
+        .and_then(|t| t.message().map(|m| m.trim().to_string()))
+        .filter(|m| !m.is_empty()))
+}
+
+fn changelog_section(repo: &Repository, tree: &Tree, version: &str) -> RunResult<Option<String>> {
+    let Ok(entry) = tree.get_path(Path::new(CHANGELOG_FILE)) else {
+        return Ok(None);
+    };
+    if entry.kind() != Some(ObjectType::Blob) {
+        return Ok(None);
+    }
+    let blob = repo
+        .find_blob(entry.id())
+        .map_err(|e| conf_err_source(format!("load {} failed", CHANGELOG_FILE), e))?;
+    let text = String::from_utf8_lossy(blob.content());
+    Ok(find_section(&text, version))
+}
+
+/// Section under the first heading that names `version` (`## [1.4.3]`,
+/// `## v1.4.3 - 2025-01-01`, ...), up to the next heading of the same or a
+/// higher level.
+fn find_section(text: &str, version: &str) -> Option<String> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let mut lines = text.lines();
+    let level = loop {
+        let line = lines.next()?;
+        let Some((level, title)) = heading(line) else {
+            continue;
+        };
+        let names_version = title
+            .split(|c: char| c.is_whitespace() || "[](),".contains(c))
+            .any(|token| token.strip_prefix('v').unwrap_or(token) == version);
+        if names_version {
+            break level;
+        }
+    };
+    let body: Vec<&str> = lines
+        .take_while(|line| !matches!(heading(line), Some((next, _)) if next <= level))
+        .collect();
+    let body = body.join("\n").trim().to_string();
+    (!body.is_empty()).then_some(body)
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|b| *b == b'#').count();
+    if level == 0 {
+        return None;
+    }
+    let title = &line[level..];
+    title
+        .starts_with(char::is_whitespace)
+        .then(|| (level, title.trim()))
+}
+
+/// Walks `from..to`, newest first, keeping at most `MAX_COMMITS`. A `from`
+/// unknown to the cache (e.g. the repo URL changed) yields no commits rather
+/// than the whole history.
+fn collect_commits(
+    repo: &Repository,
+    from: &str,
+    to: Oid,
+    subpath: Option<&str>,
+    notes: &mut ReleaseNotes,
+) -> RunResult<()> {
+    let Ok(from) = Oid::from_str(from) else {
+        return Ok(());
+    };
+    if from == to || repo.find_commit(from).is_err() {
+        return Ok(());
+    }
+    let mut walk = repo
+        .revwalk()
+        .map_err(|e| conf_err_source("start commit walk failed", e))?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+        .map_err(|e| conf_err_source("sort commit walk failed", e))?;
+    walk.push(to)
+        .and_then(|_| walk.hide(from))
+        .map_err(|e| conf_err_source(format!("walk {}..{} failed", from, to), e))?;
+    for oid in walk {
+        let oid = oid.map_err(|e| conf_err_source("walk commits failed", e))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| conf_err_source(format!("load commit {} failed", oid), e))?;
+        if let Some(subpath) = subpath {
+            if !touches_subpath(&commit, subpath)? {
+                continue;
+            }
+        }
+        if notes.commits.len() == MAX_COMMITS {
+            notes.truncated = true;
+            break;
+        }
+        let summary = commit.summary().unwrap_or_default().to_string();
+        let summary = bounded(summary, MAX_SUMMARY_BYTES, &mut notes.truncated);
+        notes.commits.push(ReleaseCommit {
+            id: oid.to_string(),
+            summary,
+            author: commit.author().name().unwrap_or_default().to_string(),
+            time: commit.time().seconds(),
+        });
+    }
+    Ok(())
+}
+
+/// Whether `commit` changed anything under `subpath` compared with its first
+/// parent.
+fn touches_subpath(commit: &Commit, subpath: &str) -> RunResult<bool> {
+    let entry_id = |commit: &Commit| -> RunResult<Option<Oid>> {
+        let tree = commit.tree().map_err(|e| {
+            conf_err_source(format!("load tree of commit {} failed", commit.id()), e)
+        })?;
+        Ok(tree
+            .get_path(Path::new(subpath))
+            .ok()
+            .map(|entry| entry.id()))
+    };
+    let parent = match commit.parent(0) {
+        Ok(parent) => entry_id(&parent)?,
+        Err(_) => None,
+    };
+    Ok(entry_id(commit)? != parent)
+}
+
+/// Cuts `text` to at most `max` bytes on a char boundary.
+fn bounded(mut text: String, max: usize, truncated: &mut bool) -> String {
+    if text.len() > max {
+        let mut end = max;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        *truncated = true;
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_section_matches_bracketed_and_prefixed_versions() {
+        let text = "# Changelog\n\n## [Unreleased]\n- wip\n\n## [1.4.3] - 2025-01-02\n### Fixed\n- parser\n\n## v1.4.2\n- first\n";
+        assert_eq!(
+            find_section(text, "1.4.3").as_deref(),
+            Some("### Fixed\n- parser")
+        );
+        assert_eq!(find_section(text, "v1.4.2").as_deref(), Some("- first"));
+        assert_eq!(find_section(text, "1.4"), None);
+    }
+
+    #[test]
+    fn bounded_cuts_on_char_boundary() {
+        let mut truncated = false;
+        assert_eq!(bounded("日志".to_string(), 4, &mut truncated), "日");
+        assert!(truncated);
+    }
+}
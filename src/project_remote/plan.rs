@@ -0,0 +1,666 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use git2::{DiffOptions, ObjectType, Patch, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use orion_variate::EnvDict;
+use serde::Serialize;
+use walkdir::WalkDir;
+use wp_error::run_error::RunResult;
+use wp_log::info_ctrl;
+
+use super::cache::load_cache_policy;
+use super::managed::{expand_managed_paths, managed_tree, ManagedSet};
+use super::repo::{
+    fetch_remote_tags, prepare_remote_repo, resolve_default_target, resolve_requested_target,
+    source_tree,
+};
+use super::requirements::{
+    check_release_requirements, load_release_requirements, EngineCapabilities,
+};
+use super::state::{load_engine_config, load_state};
+use super::{
+    conf_err_source, oid_to_string, project_remote_disabled_err,
+    project_remote_dual_requires_group_err, project_remote_single_no_group_err,
+    project_remote_unknown_group_err, remote_cache_path_for, requested_version_not_found_err,
+    resolve_project_remote_mode, single_managed_set, ProjectRemoteMode, ProjectRemoteState,
+    RemoteGroup, RemoteSource, ENGINE_CONF_PATH,
+};
+
+const MAX_DIFF_BYTES: usize = 64 * 1024;
+const DIFF_CONTEXT_LINES: u32 = 3;
+
+/// What an update would change, computed without touching the managed dirs,
+/// the state file or the lock.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRemotePlan {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub requested_version: Option<String>,
+    pub current_version: Option<String>,
+    pub target_version: String,
+    pub resolved_tag: String,
+    pub from_revision: Option<String>,
+    pub to_revision: String,
+    pub changed: bool,
+    /// Why the update would be rejected by the release's `wp-project.toml`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incompatible: Option<String>,
+    pub summary: PlanSummary,
+    pub files: Vec<PlannedFile>,
+}
+
+/// Named items the update adds or removes, across all managed files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlanSummary {
+    pub wpl_rules: ItemChanges,
+    pub oml_models: ItemChanges,
+    pub sources: ItemChanges,
+    pub sinks: ItemChanges,
+}
+
+impl PlanSummary {
+    pub fn categories(&self) -> [(&'static str, &ItemChanges); 4] {
+        [
+            ("wpl_rules", &self.wpl_rules),
+            ("oml_models", &self.oml_models),
+            ("sources", &self.sources),
+            ("sinks", &self.sinks),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ItemChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    /// Work-root relative path.
+    pub path: String,
+    pub change: FileChange,
+    /// Unified diff from the current to the target content; `None` for
+    /// binary files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub binary: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl FileChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileChange::Added => "added",
+            FileChange::Modified => "modified",
+            FileChange::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ItemKind {
+    WplRule,
+    OmlModel,
+    Source,
+    Sink,
+}
+
+/// Previews `wproj conf update`: fetches and resolves the target release
+/// like a sync does, then diffs its managed dirs against the work root. Only
+/// the remote cache under `.run/project_remote/` is refreshed.
+pub fn plan_project_remote_update<P: AsRef<Path>>(
+    work_root: P,
+    requested_version: Option<&str>,
+    group: Option<RemoteGroup>,
+    dict: &EnvDict,
+) -> RunResult<ProjectRemotePlan> {
+    let work_root = work_root.as_ref();
+    let conf = load_engine_config(work_root, dict)?;
+    let remote_conf = conf.project_remote();
+    if !remote_conf.enabled {
+        return Err(project_remote_disabled_err(
+            work_root.join(ENGINE_CONF_PATH).display().to_string(),
+        ));
+    }
+    let mode = resolve_project_remote_mode(work_root, remote_conf, dict)?;
+    let (source, managed) = plan_target(&mode, group.as_ref())?;
+    info_ctrl!(
+        "project remote plan start work_root={} requested_version={} repo={} group={}",
+        work_root.display(),
+        requested_version.unwrap_or("(auto)"),
+        source.repo,
+        group.as_ref().map(RemoteGroup::name).unwrap_or("-")
+    );
+
+    let cache = load_cache_policy(work_root, dict)?;
+    let repo = prepare_remote_repo(
+        &work_root.join(remote_cache_path_for(group.as_ref())),
+        &source.repo,
+        &cache,
+    )?;
+    let explicit_version = requested_version.map(str::trim).filter(|v| !v.is_empty());
+    fetch_remote_tags(&repo, &source.repo, &cache, explicit_version)?;
+    let track_branch = source.track_branch.as_deref();
+    let resolved = match explicit_version {
+        Some(version) => resolve_requested_target(&repo, version, track_branch)?
+            .ok_or_else(|| requested_version_not_found_err(version))?,
+        None => {
+            let init_version = Some(source.init_version.trim()).filter(|v| !v.is_empty());
+            resolve_default_target(work_root, &repo, init_version, group.as_ref(), track_branch)?
+        }
+    };
+    let target_tree = source_tree(&repo, resolved.commit_id, source.subpath.as_deref())?;
+    let incompatible = match load_release_requirements(&repo, &target_tree, &resolved.tag)? {
+        Some(requirements) => check_release_requirements(
+            &requirements,
+            &EngineCapabilities::current()?,
+            &resolved.tag,
+        )
+        .err()
+        .map(|err| err.to_string()),
+        None => None,
+    };
+
+    let state = load_state(work_root)?;
+    let (current_version, from_revision) = match (state.as_ref(), group.as_ref()) {
+        (
+            Some(ProjectRemoteState::Single {
+                current_version,
+                revision,
+                ..
+            }),
+            None,
+        ) => (Some(current_version.clone()), Some(revision.clone())),
+        (Some(state), Some(g)) => state
+            .group(g)
+            .map(|g| (Some(g.current_version.clone()), Some(g.revision.clone())))
+            .unwrap_or_default(),
+        _ => (None, None),
+    };
+
+    let dirs = expand_managed_paths(&repo, &target_tree, work_root, &managed)?;
+    let mut current = BTreeMap::new();
+    let mut target = BTreeMap::new();
+    for dir in &dirs {
+        collect_work_files(work_root, dir, &managed, &mut current)?;
+        if let Some(dir_tree) = managed_tree(&repo, &target_tree, dir)? {
+            collect_tree_files(&repo, &dir_tree, dir, &managed, &mut target)?;
+        }
+    }
+    let files = diff_files(&current, &target)?;
+    let summary = summarize_items(&current, &target);
+    let plan = ProjectRemotePlan {
+        group: group.as_ref().map(|g| g.name().to_string()),
+        requested_version: requested_version.map(str::to_string),
+        current_version,
+        target_version: resolved.version,
+        resolved_tag: resolved.tag,
+        from_revision,
+        to_revision: oid_to_string(resolved.commit_id),
+        changed: !files.is_empty(),
+        incompatible,
+        summary,
+        files,
+    };
+    info_ctrl!(
+        "project remote plan done work_root={} resolved_tag={} changed={} files={}",
+        work_root.display(),
+        plan.resolved_tag,
+        plan.changed,
+        plan.files.len()
+    );
+    Ok(plan)
+}
+
+fn plan_target(
+    mode: &ProjectRemoteMode,
+    group: Option<&RemoteGroup>,
+) -> RunResult<(RemoteSource, ManagedSet)> {
+    match (mode, group) {
+        (ProjectRemoteMode::Single(source), None) => Ok((source.clone(), single_managed_set())),
+        (ProjectRemoteMode::Single(_), Some(_)) => Err(project_remote_single_no_group_err()),
+        (ProjectRemoteMode::Groups(_), None) => {
+            Err(project_remote_dual_requires_group_err(&mode.group_names()))
+        }
+        (ProjectRemoteMode::Groups(groups), Some(group)) => groups
+            .iter()
+            .find(|g| &g.group == group)
+            .map(|g| (g.source.clone(), g.managed.clone()))
+            .ok_or_else(|| project_remote_unknown_group_err(group, &mode.group_names())),
+    }
+}
+
+/// Content of every file (or link target) under `dir` in the work root.
+fn collect_work_files(
+    work_root: &Path,
+    dir: &str,
+    set: &ManagedSet,
+    out: &mut BTreeMap<String, Vec<u8>>,
+) -> RunResult<()> {
+    let root = work_root.join(dir);
+    if fs::symlink_metadata(&root).is_err() {
+        return Ok(());
+    }
+    for entry in WalkDir::new(&root).sort_by_file_name() {
+        let entry =
+            entry.map_err(|e| conf_err_source(format!("walk {} failed", root.display()), e))?;
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            continue;
+        }
+        let Ok(rel) = entry.path().strip_prefix(work_root) else {
+            continue;
+        };
+        let Some(rel) = rel.to_str().map(|r| r.replace('\\', "/")) else {
+            continue;
+        };
+        if set.is_excluded(&rel) {
+            continue;
+        }
+        let body = if file_type.is_symlink() {
+            fs::read_link(entry.path())
+                .map(|target| target.to_string_lossy().into_owned().into_bytes())
+        } else {
+            fs::read(entry.path())
+        }
+        .map_err(|e| conf_err_source(format!("read {} failed", entry.path().display()), e))?;
+        out.insert(rel, body);
+    }
+    Ok(())
+}
+
+/// Content of every blob under `tree`, keyed by work-root relative path.
+fn collect_tree_files(
+    repo: &Repository,
+    tree: &Tree,
+    dir: &str,
+    set: &ManagedSet,
+    out: &mut BTreeMap<String, Vec<u8>>,
+) -> RunResult<()> {
+    let mut blobs = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |parent, entry| {
+        let Some(name) = entry.name() else {
+            return TreeWalkResult::Skip;
+        };
+        let rel = format!("{}/{}{}", dir, parent, name);
+        if set.is_excluded(&rel) {
+            return TreeWalkResult::Skip;
+        }
+        if entry.kind() == Some(ObjectType::Blob) {
+            blobs.push((rel, entry.id()));
+        }
+        TreeWalkResult::Ok
+    })
+    .map_err(|e| conf_err_source(format!("walk remote tree {} failed", dir), e))?;
+    for (rel, id) in blobs {
+        let blob = repo
+            .find_blob(id)
+            .map_err(|e| conf_err_source(format!("load blob {} failed", id), e))?;
+        out.insert(rel, blob.content().to_vec());
+    }
+    Ok(())
+}
+
+fn diff_files(
+    current: &BTreeMap<String, Vec<u8>>,
+    target: &BTreeMap<String, Vec<u8>>,
+) -> RunResult<Vec<PlannedFile>> {
+    let paths: BTreeSet<&String> = current.keys().chain(target.keys()).collect();
+    let mut files = Vec::new();
+    for path in paths {
+        let (old, new) = (current.get(path), target.get(path));
+        let change = match (old, new) {
+            (Some(old), Some(new)) if old == new => continue,
+            (Some(_), Some(_)) => FileChange::Modified,
+            (None, Some(_)) => FileChange::Added,
+            (Some(_), None) => FileChange::Deleted,
+            (None, None) => continue,
+        };
+        let old = old.map(Vec::as_slice).unwrap_or_default();
+        let new = new.map(Vec::as_slice).unwrap_or_default();
+        files.push(unified_diff(path, change, old, new)?);
+    }
+    Ok(files)
+}
+
+fn unified_diff(path: &str, change: FileChange, old: &[u8], new: &[u8]) -> RunResult<PlannedFile> {
+    let mut opts = DiffOptions::new();
+    opts.context_lines(DIFF_CONTEXT_LINES);
+    let old_path = (change != FileChange::Added).then(|| Path::new(path));
+    let new_path = (change != FileChange::Deleted).then(|| Path::new(path));
+    let mut patch = Patch::from_buffers(old, old_path, new, new_path, Some(&mut opts))
+        .map_err(|e| conf_err_source(format!("diff {} failed", path), e))?;
+    let binary = patch.delta().flags().is_binary();
+    let mut planned = PlannedFile {
+        path: path.to_string(),
+        change,
+        diff: None,
+        binary,
+        truncated: false,
+    };
+    if binary {
+        return Ok(planned);
+    }
+    let buf = patch
+        .to_buf()
+        .map_err(|e| conf_err_source(format!("format diff of {} failed", path), e))?;
+    let mut text = String::from_utf8_lossy(&buf).into_owned();
+    if text.len() > MAX_DIFF_BYTES {
+        let mut end = MAX_DIFF_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        planned.truncated = true;
+    }
+    planned.diff = Some(text);
+    Ok(planned)
+}
+
+fn summarize_items(
+    current: &BTreeMap<String, Vec<u8>>,
+    target: &BTreeMap<String, Vec<u8>>,
+) -> PlanSummary {
+    let before = collect_items(current);
+    let after = collect_items(target);
+    let changes = |kind: ItemKind| {
+        let empty = BTreeSet::new();
+        let before = before.get(&kind).unwrap_or(&empty);
+        let after = after.get(&kind).unwrap_or(&empty);
+        ItemChanges {
+            added: after.difference(before).cloned().collect(),
+            removed: before.difference(after).cloned().collect(),
+        }
+    };
+    PlanSummary {
+        wpl_rules: changes(ItemKind::WplRule),
+        oml_models: changes(ItemKind::OmlModel),
+        sources: changes(ItemKind::Source),
+        sinks: changes(ItemKind::Sink),
+    }
+}
+
+fn collect_items(files: &BTreeMap<String, Vec<u8>>) -> BTreeMap<ItemKind, BTreeSet<String>> {
+    let mut items: BTreeMap<ItemKind, BTreeSet<String>> = BTreeMap::new();
+    for (path, body) in files {
+        let Ok(text) = std::str::from_utf8(body) else {
+            continue;
+        };
+        let found = if path.ends_with(".wpl") {
+            wpl_rules(text)
+                .into_iter()
+                .map(|rule| (ItemKind::WplRule, rule))
+                .collect()
+        } else if path.ends_with(".oml") {
+            oml_model(text)
+                .map(|name| vec![(ItemKind::OmlModel, name)])
+                .unwrap_or_default()
+        } else if path.ends_with(".toml") {
+            topology_items(text)
+        } else {
+            Vec::new()
+        };
+        for (kind, name) in found {
+            items.entry(kind).or_default().insert(name);
+        }
+    }
+    items
+}
+
+/// `package /nginx/ { rule example { ... } }` yields `/nginx/example`.
+fn wpl_rules(text: &str) -> Vec<String> {
+    let mut package = String::new();
+    let mut rules = Vec::new();
+    for line in text.lines() {
+        let mut tokens = line
+            .split(|c: char| c.is_whitespace() || c == '{')
+            .filter(|t| !t.is_empty());
+        match (tokens.next(), tokens.next()) {
+            (Some("package"), Some(name)) => package = name.to_string(),
+            (Some("rule"), Some(name)) if package.is_empty() => rules.push(name.to_string()),
+            (Some("rule"), Some(name)) => {
+                let sep = if package.ends_with('/') { "" } else { "/" };
+                rules.push(format!("{}{}{}", package, sep, name));
+            }
+            _ => {}
+        }
+    }
+    rules
+}
+
+/// `name : nginx` in the header of an OML model.
+fn oml_model(text: &str) -> Option<String> {
+    text.lines()
+        .take_while(|line| line.trim() != "---")
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "name")
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Source keys of `[[sources]]` and sink group names of `[sink_group]`.
+fn topology_items(text: &str) -> Vec<(ItemKind, String)> {
+    let Ok(doc) = toml::from_str::<toml::Table>(text) else {
+        return Vec::new();
+    };
+    let mut items = Vec::new();
+    if let Some(sources) = doc.get("sources").and_then(toml::Value::as_array) {
+        items.extend(
+            sources
+                .iter()
+                .filter_map(|s| s.get("key").and_then(toml::Value::as_str))
+                .map(|key| (ItemKind::Source, key.to_string())),
+        );
+    }
+    if let Some(name) = doc
+        .get("sink_group")
+        .and_then(|g| g.get("name"))
+        .and_then(toml::Value::as_str)
+    {
+        items.push((ItemKind::Sink, name.to_string()));
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{
+        create_remote_fixture, create_work_root, skip_check, tag_release_with_files,
+        tag_release_with_manifest,
+    };
+    use super::super::{sync_project_remote, STATE_PATH};
+    use super::*;
+
+    fn planned(plan: &ProjectRemotePlan) -> Vec<(&str, FileChange)> {
+        plan.files
+            .iter()
+            .map(|f| (f.path.as_str(), f.change))
+            .collect()
+    }
+
+    #[test]
+    fn plan_for_the_current_release_is_a_no_op() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+
+        let plan = plan_project_remote_update(work_root.path(), None, None, &EnvDict::default())
+            .expect("plan");
+
+        assert!(!plan.changed);
+        assert!(plan.files.is_empty());
+        assert_eq!(plan.current_version.as_deref(), Some("1.4.3"));
+        assert_eq!(plan.target_version, "1.4.3");
+        assert_eq!(
+            plan.from_revision.as_deref(),
+            Some(plan.to_revision.as_str())
+        );
+        assert!(plan
+            .summary
+            .categories()
+            .iter()
+            .all(|(_, c)| c.added.is_empty() && c.removed.is_empty()));
+    }
+
+    #[test]
+    fn upgrade_plan_lists_files_and_new_items() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+        tag_release_with_files(
+            &fixture,
+            "1.5.0",
+            &[
+                (
+                    "models/wpl/nginx/parse.wpl",
+                    "package /nginx/ {\n   rule access {\n   }\n}\n",
+                ),
+                (
+                    "models/oml/nginx.oml",
+                    "name : nginx\nrule : /nginx/*\n---\n",
+                ),
+                (
+                    "topology/sources/wpsrc.toml",
+                    "[[sources]]\nkey = \"tcp_1\"\n",
+                ),
+            ],
+        );
+
+        let plan =
+            plan_project_remote_update(work_root.path(), Some("1.5.0"), None, &EnvDict::default())
+                .expect("plan");
+
+        assert!(plan.changed);
+        assert_eq!(plan.resolved_tag, "v1.5.0");
+        assert_eq!(
+            planned(&plan),
+            vec![
+                ("models/oml/nginx.oml", FileChange::Added),
+                ("models/version.txt", FileChange::Modified),
+                ("models/wpl/nginx/parse.wpl", FileChange::Added),
+                ("topology/sources/wpsrc.toml", FileChange::Added),
+            ]
+        );
+        assert_eq!(plan.summary.wpl_rules.added, vec!["/nginx/access"]);
+        assert_eq!(plan.summary.oml_models.added, vec!["nginx"]);
+        assert_eq!(plan.summary.sources.added, vec!["tcp_1"]);
+        let version = plan.files[1].diff.as_deref().expect("text diff");
+        assert!(version.contains("-1.4.3"));
+        assert!(version.contains("+1.5.0"));
+    }
+
+    #[test]
+    fn rollback_plan_removes_what_the_newer_release_added() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        tag_release_with_files(
+            &fixture,
+            "1.5.0",
+            &[(
+                "models/wpl/nginx/parse.wpl",
+                "package /nginx/ {\n   rule access {\n   }\n}\n",
+            )],
+        );
+        sync_project_remote(work_root.path(), Some("1.5.0"), &skip_check()).expect("sync");
+        let state_before = fs::read(work_root.path().join(STATE_PATH)).expect("read state");
+
+        let plan =
+            plan_project_remote_update(work_root.path(), Some("1.4.3"), None, &EnvDict::default())
+                .expect("plan");
+
+        assert!(plan.changed);
+        assert_eq!(plan.current_version.as_deref(), Some("1.5.0"));
+        assert_eq!(plan.target_version, "1.4.3");
+        assert_eq!(
+            planned(&plan),
+            vec![
+                ("models/version.txt", FileChange::Modified),
+                ("models/wpl/nginx/parse.wpl", FileChange::Deleted),
+            ]
+        );
+        assert_eq!(plan.summary.wpl_rules.removed, vec!["/nginx/access"]);
+        // The preview leaves the work root on the newer release.
+        assert_eq!(
+            fs::read(work_root.path().join(STATE_PATH)).expect("read state"),
+            state_before
+        );
+        assert!(work_root.path().join("models/wpl/nginx/parse.wpl").exists());
+    }
+
+    #[test]
+    fn plan_reports_a_release_the_engine_would_reject() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("sync");
+        tag_release_with_manifest(&fixture, "1.5.0", "requires_engine = \">=99.0\"\n");
+
+        let plan =
+            plan_project_remote_update(work_root.path(), Some("1.5.0"), None, &EnvDict::default())
+                .expect("an incompatible release is still planned");
+
+        let reason = plan.incompatible.as_deref().expect("incompatible");
+        assert!(reason.contains("requires engine >=99.0"));
+        assert!(plan.changed);
+        assert_eq!(current_version(work_root.path()), Some("1.4.3".to_string()));
+    }
+
+    #[test]
+    fn plan_rejects_a_group_in_single_repo_mode_and_unknown_versions() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let dict = EnvDict::default();
+
+        plan_project_remote_update(work_root.path(), None, Some(RemoteGroup::Models), &dict)
+            .expect_err("single-repo mode has no groups");
+        plan_project_remote_update(work_root.path(), Some("9.9.9"), None, &dict)
+            .expect_err("no such release");
+    }
+
+    #[test]
+    fn binary_and_oversized_diffs_are_marked() {
+        let binary = unified_diff("models/blob.bin", FileChange::Added, b"", b"\0\x01\x02")
+            .expect("binary diff");
+        assert!(binary.binary);
+        assert_eq!(binary.diff, None);
+
+        let large = "line\n".repeat(MAX_DIFF_BYTES);
+        let text = unified_diff("models/big.txt", FileChange::Added, b"", large.as_bytes())
+            .expect("text diff");
+        assert!(text.truncated);
+        assert!(text.diff.expect("diff").len() <= MAX_DIFF_BYTES);
+    }
+
+    fn current_version(work_root: &Path) -> Option<String> {
+        super::super::current_project_version(work_root).expect("current version")
+    }
+
+    #[test]
+    fn item_parsers_read_rules_models_sources_and_sinks() {
+        assert_eq!(
+            wpl_rules("package /nginx/ {\n   rule example {\n   }\n   rule other{\n}\n}\n"),
+            vec!["/nginx/example", "/nginx/other"]
+        );
+        assert_eq!(
+            oml_model("name : nginx\nrule : /nginx/*\n---\n* : auto = take() ;\n").as_deref(),
+            Some("nginx")
+        );
+        assert_eq!(oml_model("---\nname : body\n"), None);
+        let sources =
+            topology_items("[[sources]]\nkey = \"tcp_1\"\n[[sources]]\nkey = \"file_1\"\n");
+        assert_eq!(sources.len(), 2);
+        let sinks = topology_items("version = \"2.0\"\n[sink_group]\nname = \"demo\"\n");
+        assert!(matches!(sinks.as_slice(), [(ItemKind::Sink, name)] if name == "demo"));
+    }
+}
@@ -0,0 +1,377 @@
+use std::path::Path;
+use std::time::Duration;
+
+use orion_variate::EnvDict;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use wp_error::run_error::RunResult;
+
+use super::cache::{load_cache_policy, CachePolicy};
+use super::groups::{invalid_option_err, load_project_remote_ext};
+use super::repo::{
+    fetch_remote_tags, prepare_remote_repo, resolve_default_target, resolve_newer_released_target,
+};
+use super::state::{load_engine_config, load_state};
+use super::{
+    configured_sources, remote_cache_path_for, ProjectRemoteState, RemoteGroup, RemoteSource,
+};
+
+const DEFAULT_MAX_BACKOFF_SECS: u64 = 3600;
+
+/// `[project_remote.poll]` as written in `conf/wparse.toml`.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct RawPollConf {
+    #[serde(default)]
+    interval_secs: u64,
+    #[serde(default)]
+    jitter_secs: u64,
+    #[serde(default)]
+    max_backoff_secs: Option<u64>,
+    #[serde(default)]
+    policy: Option<String>,
+}
+
+/// Which newer release tags the daemon poller may move to on its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PollPolicy {
+    /// Any newer release.
+    #[default]
+    Latest,
+    /// Newer releases with the same major version.
+    Minor,
+    /// Newer releases with the same major and minor version.
+    Patch,
+}
+
+impl PollPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PollPolicy::Latest => "latest",
+            PollPolicy::Minor => "minor",
+            PollPolicy::Patch => "patch",
+        }
+    }
+
+    pub(super) fn allows(&self, current: &semver::Version, candidate: &semver::Version) -> bool {
+        if candidate <= current {
+            return false;
+        }
+        match self {
+            PollPolicy::Latest => true,
+            PollPolicy::Minor => candidate.major == current.major,
+            PollPolicy::Patch => {
+                candidate.major == current.major && candidate.minor == current.minor
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for PollPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(PollPolicy::Latest),
+            "minor" => Ok(PollPolicy::Minor),
+            "patch" => Ok(PollPolicy::Patch),
+            _ => Err("expected one of latest|minor|patch".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectRemotePollConf {
+    pub interval: Duration,
+    pub jitter: Duration,
+    pub max_backoff: Duration,
+    pub policy: PollPolicy,
+}
+
+impl ProjectRemotePollConf {
+    /// Wait before the next poll: the interval doubled per consecutive
+    /// failure up to `max_backoff`, plus a random share of `jitter` so a
+    /// fleet does not hit the remote in lockstep.
+    pub fn next_delay(&self, consecutive_failures: u32) -> Duration {
+        self.backoff(consecutive_failures) + random_jitter(self.jitter)
+    }
+
+    fn backoff(&self, consecutive_failures: u32) -> Duration {
+        if consecutive_failures == 0 {
+            return self.interval;
+        }
+        let factor = 1u32
+            .checked_shl(consecutive_failures.min(16))
+            .unwrap_or(u32::MAX);
+        self.interval
+            .saturating_mul(factor)
+            .min(self.max_backoff.max(self.interval))
+    }
+}
+
+fn random_jitter(jitter: Duration) -> Duration {
+    if jitter.is_zero() {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(rand::rng().random_range(0..=jitter.as_millis() as u64))
+}
+
+/// A release the poller found for one source.
+#[derive(Debug, Clone)]
+pub struct PendingUpdate {
+    pub group: Option<RemoteGroup>,
+    pub current_version: Option<String>,
+    pub target_version: String,
+    pub target_tag: String,
+}
+
+impl PendingUpdate {
+    /// Version to pass to the sync. A source that was never synced resolves
+    /// its default target again, so `init_version` applies as it does for
+    /// `wproj conf update`.
+    pub fn requested_version(&self) -> Option<&str> {
+        self.current_version
+            .as_ref()
+            .map(|_| self.target_version.as_str())
+    }
+}
+
+/// Reads `[project_remote.poll]`. `None` when project_remote is disabled or
+/// `interval_secs` is unset or zero.
+pub fn load_poll_conf<P: AsRef<Path>>(
+    work_root: P,
+    dict: &EnvDict,
+) -> RunResult<Option<ProjectRemotePollConf>> {
+    let work_root = work_root.as_ref();
+    let conf = load_engine_config(work_root, dict)?;
+    if !conf.project_remote().enabled {
+        return Ok(None);
+    }
+    let Some(raw) = load_project_remote_ext(work_root, dict)?.poll else {
+        return Ok(None);
+    };
+    if raw.interval_secs == 0 {
+        return Ok(None);
+    }
+    let policy = poll_policy(Some(&raw))?;
+    Ok(Some(ProjectRemotePollConf {
+        interval: Duration::from_secs(raw.interval_secs),
+        jitter: Duration::from_secs(raw.jitter_secs),
+        max_backoff: Duration::from_secs(raw.max_backoff_secs.unwrap_or(DEFAULT_MAX_BACKOFF_SECS)),
+        policy,
+    }))
+}
+
+/// `policy` of `[project_remote.poll]`, which also bounds the releases a
+/// webhook may move to; `latest` when unset.
+pub(super) fn poll_policy(raw: Option<&RawPollConf>) -> RunResult<PollPolicy> {
+    match raw.and_then(|raw| raw.policy.as_deref()).map(str::trim) {
+        None | Some("") => Ok(PollPolicy::default()),
+        Some(value) => value
+            .parse::<PollPolicy>()
+            .map_err(|reason| invalid_option_err("poll", "policy", value, &reason)),
+    }
+}
+
+/// Fetches every configured source and reports the ones with a release the
+/// policy allows. Only the remote caches are touched; callers hold
+/// `acquire_project_remote_lock`.
+pub fn check_project_remote_updates<P: AsRef<Path>>(
+    work_root: P,
+    dict: &EnvDict,
+    policy: PollPolicy,
+) -> RunResult<Vec<PendingUpdate>> {
+    let work_root = work_root.as_ref();
+    let sources = configured_sources(work_root, dict)?;
+    let cache = load_cache_policy(work_root, dict)?;
+    let state = load_state(work_root)?;
+    let mut pending = Vec::new();
+    for (group, source) in sources {
+        if let Some(update) = check_source(
+            work_root,
+            &source,
+            group.as_ref(),
+            state.as_ref(),
+            &cache,
+            policy,
+        )? {
+            pending.push(update);
+        }
+    }
+    Ok(pending)
+}
+
+fn check_source(
+    work_root: &Path,
+    source: &RemoteSource,
+    group: Option<&RemoteGroup>,
+    state: Option<&ProjectRemoteState>,
+    cache: &CachePolicy,
+    policy: PollPolicy,
+) -> RunResult<Option<PendingUpdate>> {
+    let repo = prepare_remote_repo(
+        &work_root.join(remote_cache_path_for(group)),
+        source.repo.as_str(),
+        cache,
+    )?;
+    fetch_remote_tags(&repo, source.repo.as_str(), cache, None)?;
+
+    let current = match (state, group) {
+        (
+            Some(ProjectRemoteState::Single {
+                current_version,
+                revision,
+                ..
+            }),
+            None,
+        ) => Some((current_version.as_str(), revision.as_str())),
+        (Some(state), Some(g)) => state
+            .group(g)
+            .map(|g| (g.current_version.as_str(), g.revision.as_str())),
+        _ => None,
+    };
+    let track_branch = source.track_branch.as_deref();
+    let target = match current {
+        None => {
+            let init_version = Some(source.init_version.trim()).filter(|v| !v.is_empty());
+            Some(resolve_default_target(
+                work_root,
+                &repo,
+                init_version,
+                group,
+                track_branch,
+            )?)
+        }
+        // A tracked branch has no ordering; any other tip is news.
+        Some((_, revision)) if track_branch.is_some() => {
+            let tip = resolve_default_target(work_root, &repo, None, group, track_branch)?;
+            (tip.commit_id.to_string() != revision).then_some(tip)
+        }
+        Some((version, _)) => resolve_newer_released_target(&repo, version, policy)?,
+    };
+    Ok(target.map(|target| PendingUpdate {
+        group: group.cloned(),
+        current_version: current.map(|(version, _)| version.to_string()),
+        target_version: target.version,
+        target_tag: target.tag,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{
+        append_engine_conf, create_remote_fixture, create_work_root, skip_check, tag_release,
+    };
+    use super::super::{current_project_version, sync_project_remote};
+    use super::*;
+
+    fn poll_conf(interval: u64, jitter: u64, max_backoff: u64) -> ProjectRemotePollConf {
+        ProjectRemotePollConf {
+            interval: Duration::from_secs(interval),
+            jitter: Duration::from_secs(jitter),
+            max_backoff: Duration::from_secs(max_backoff),
+            policy: PollPolicy::Latest,
+        }
+    }
+
+    #[test]
+    fn next_delay_doubles_per_failure_up_to_max_backoff() {
+        let conf = poll_conf(60, 0, 300);
+        let delays: Vec<u64> = [0, 1, 2, 3, 40]
+            .iter()
+            .map(|failures| conf.next_delay(*failures).as_secs())
+            .collect();
+        assert_eq!(delays, vec![60, 120, 240, 300, 300]);
+
+        // A backoff cap below the interval never shortens the interval.
+        assert_eq!(poll_conf(60, 0, 10).next_delay(3), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn next_delay_adds_at_most_the_jitter() {
+        let conf = poll_conf(60, 5, 300);
+        for _ in 0..32 {
+            let delay = conf.next_delay(0);
+            assert!(delay >= Duration::from_secs(60));
+            assert!(delay <= Duration::from_secs(65));
+        }
+    }
+
+    #[test]
+    fn load_poll_conf_reads_interval_and_policy() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        assert_eq!(
+            load_poll_conf(work_root.path(), &EnvDict::default()).expect("no poll"),
+            None
+        );
+
+        append_engine_conf(
+            work_root.path(),
+            "\n[project_remote.poll]\ninterval_secs = 60\npolicy = \"patch\"\n",
+        );
+        let conf = load_poll_conf(work_root.path(), &EnvDict::default())
+            .expect("poll conf")
+            .expect("poll enabled");
+        assert_eq!(conf.interval, Duration::from_secs(60));
+        assert_eq!(
+            conf.max_backoff,
+            Duration::from_secs(DEFAULT_MAX_BACKOFF_SECS)
+        );
+        assert_eq!(conf.policy, PollPolicy::Patch);
+    }
+
+    #[test]
+    fn found_update_is_applied_by_a_sync_and_then_cleared() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.2"), &skip_check()).expect("baseline");
+
+        let pending =
+            check_project_remote_updates(work_root.path(), &EnvDict::default(), PollPolicy::Latest)
+                .expect("check updates");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].current_version.as_deref(), Some("1.4.2"));
+        assert_eq!(pending[0].target_version, "1.4.3");
+        assert_eq!(pending[0].target_tag, "v1.4.3");
+
+        let result = sync_project_remote(
+            work_root.path(),
+            pending[0].requested_version(),
+            &skip_check(),
+        )
+        .expect("apply update");
+        assert!(result.changed);
+        assert_eq!(
+            current_project_version(work_root.path()).expect("version"),
+            Some("1.4.3".to_string())
+        );
+        assert!(check_project_remote_updates(
+            work_root.path(),
+            &EnvDict::default(),
+            PollPolicy::Latest
+        )
+        .expect("check again")
+        .is_empty());
+    }
+
+    #[test]
+    fn policy_limits_which_releases_are_found() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        sync_project_remote(work_root.path(), Some("1.4.3"), &skip_check()).expect("baseline");
+        tag_release(&fixture, "1.5.0");
+        tag_release(&fixture, "2.0.0");
+
+        let targets = |policy| {
+            check_project_remote_updates(work_root.path(), &EnvDict::default(), policy)
+                .expect("check updates")
+                .into_iter()
+                .map(|update| update.target_version)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(targets(PollPolicy::Latest), vec!["2.0.0"]);
+        assert_eq!(targets(PollPolicy::Minor), vec!["1.5.0"]);
+        assert!(targets(PollPolicy::Patch).is_empty());
+    }
+}
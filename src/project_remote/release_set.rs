@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::compat::UvsFrom;
+use orion_error::conversion::ToStructError;
+use orion_variate::EnvDict;
+use serde::Serialize;
+use wp_error::run_error::RunResult;
+use wp_error::RunReason;
+use wp_log::{info_ctrl, warn_ctrl};
+
+use super::managed::restore_managed_dirs;
+use super::state::{capture_project_remote_snapshot, load_engine_config, restore_state_file_bytes};
+use super::validate::validate_synced_tree;
+use super::{
+    project_remote_disabled_err, project_remote_single_no_group_err,
+    project_remote_unknown_group_err, resolve_project_remote_mode,
+    sync_project_remote_with_repo_inner, ProjectRemoteMode, ProjectRemoteSnapshot,
+    ProjectRemoteUpdateResult, ProjectRemoteValidation, RemoteGroup, SyncOptions, ENGINE_CONF_PATH,
+};
+
+/// Outcome of a release set: one result per group, in group order, and the
+/// project check that ran once against the combined tree.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRemoteSetResult {
+    pub groups: Vec<ProjectRemoteUpdateResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<ProjectRemoteValidation>,
+}
+
+impl ProjectRemoteSetResult {
+    pub fn changed(&self) -> bool {
+        self.groups.iter().any(|g| g.changed)
+    }
+
+    /// True when the check rejected the combined release and every group
+    /// was restored.
+    pub fn validation_failed(&self) -> bool {
+        self.validation
+            .as_ref()
+            .is_some_and(ProjectRemoteValidation::is_failed)
+    }
+
+    /// `models=1.4.3,infra=1.1.0` listing for logs.
+    pub fn summary(&self) -> String {
+        self.groups
+            .iter()
+            .map(|g| {
+                format!(
+                    "{}={}",
+                    g.group.as_deref().unwrap_or("-"),
+                    g.current_version
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Parses `group=version` pairs, as given on the command line.
+pub fn parse_release_set<I, S>(pairs: I) -> Result<BTreeMap<RemoteGroup, String>, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut set = BTreeMap::new();
+    for pair in pairs {
+        let pair = pair.as_ref().trim();
+        let Some((group, version)) = pair.split_once('=') else {
+            return Err(format!("release set entry '{}' is not group=version", pair));
+        };
+        insert_release(&mut set, group, version)?;
+    }
+    Ok(set)
+}
+
+/// Builds a release set from a `{group: version}` map, as sent to the admin
+/// API.
+pub fn release_set_from_map(
+    map: &BTreeMap<String, String>,
+) -> Result<BTreeMap<RemoteGroup, String>, String> {
+    let mut set = BTreeMap::new();
+    for (group, version) in map {
+        insert_release(&mut set, group, version)?;
+    }
+    Ok(set)
+}
+
+fn insert_release(
+    set: &mut BTreeMap<RemoteGroup, String>,
+    group: &str,
+    version: &str,
+) -> Result<(), String> {
+    let group = group.trim().parse::<RemoteGroup>()?;
+    let version = version.trim();
+    if version.is_empty() {
+        return Err(format!(
+            "release set entry '{}' has no version",
+            group.name()
+        ));
+    }
+    if set.insert(group.clone(), version.to_string()).is_some() {
+        return Err(format!(
+            "group '{}' appears twice in the release set",
+            group.name()
+        ));
+    }
+    Ok(())
+}
+
+/// Moves several groups to the given versions as one release: each group is
+/// synced in turn, the project check runs once against the combined tree,
+/// and any failure restores every group the set already touched together
+/// with the state file. Callers hold `acquire_project_remote_lock`; after a
+/// failed reload they undo the set with [`restore_project_remote_release_set`].
+pub fn sync_project_remote_release_set_with_dict<P: AsRef<Path>>(
+    work_root: P,
+    release_set: &BTreeMap<RemoteGroup, String>,
+    dict: &EnvDict,
+    opts: &SyncOptions,
+) -> RunResult<ProjectRemoteSetResult> {
+    let work_root = work_root.as_ref();
+    if release_set.is_empty() {
+        return Err(empty_release_set_err());
+    }
+    let conf = load_engine_config(work_root, dict)?;
+    let remote_conf = conf.project_remote();
+    if !remote_conf.enabled {
+        return Err(project_remote_disabled_err(
+            work_root.join(ENGINE_CONF_PATH).display().to_string(),
+        ));
+    }
+    let mode = resolve_project_remote_mode(work_root, remote_conf, dict)?;
+    let ProjectRemoteMode::Groups(groups) = &mode else {
+        return Err(project_remote_single_no_group_err());
+    };
+    let mut targets = Vec::new();
+    for (group, version) in release_set {
+        let group_conf = groups
+            .iter()
+            .find(|g| &g.group == group)
+            .ok_or_else(|| project_remote_unknown_group_err(group, &mode.group_names()))?;
+        targets.push((group_conf, version.as_str()));
+    }
+    info_ctrl!(
+        "project remote release set start work_root={} groups={}",
+        work_root.display(),
+        release_set
+            .iter()
+            .map(|(g, v)| format!("{}={}", g.name(), v))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+
+    let snapshot = capture_project_remote_snapshot(work_root)?;
+    let mut result = ProjectRemoteSetResult {
+        groups: Vec::new(),
+        validation: None,
+    };
+    // The gate runs once below; a half-applied set is not expected to pass
+    // it on its own.
+    let group_opts = SyncOptions {
+        skip_validate: true,
+        ..opts.clone()
+    };
+    for (group_conf, version) in targets {
+        let synced = sync_project_remote_with_repo_inner(
+            work_root,
+            &group_conf.source,
+            Some(version),
+            Some(&group_conf.group),
+            &group_conf.managed,
+            dict,
+            &group_opts,
+        );
+        match synced {
+            Ok(mut group_result) => {
+                group_result.validation = None;
+                result.groups.push(group_result);
+            }
+            Err(err) => {
+                warn_ctrl!(
+                    "project remote release set group failed work_root={} group={} version={} error={}",
+                    work_root.display(),
+                    group_conf.group.name(),
+                    version,
+                    err
+                );
+                restore_project_remote_release_set(work_root, &snapshot, &result).map_err(
+                    |rollback_err| {
+                        RunReason::from_conf()
+                            .to_err()
+                            .with_detail(format!("{}; rollback failed", err))
+                            .with_source(rollback_err)
+                    },
+                )?;
+                return Err(err);
+            }
+        }
+    }
+
+    let validation = validate_synced_tree(work_root, dict, opts);
+    info_ctrl!(
+        "project remote release set validate work_root={} groups={} status={} failures={}",
+        work_root.display(),
+        result.summary(),
+        validation.status.as_str(),
+        validation.failures.len()
+    );
+    let failed = validation.is_failed();
+    result.validation = Some(validation);
+    if failed {
+        warn_ctrl!(
+            "project remote release set blocked by validation work_root={} groups={} failures={}",
+            work_root.display(),
+            result.summary(),
+            result
+                .validation
+                .as_ref()
+                .map(ProjectRemoteValidation::summary)
+                .unwrap_or_default()
+        );
+        restore_project_remote_release_set(work_root, &snapshot, &result)?;
+        for group in &mut result.groups {
+            group.changed = false;
+        }
+        return Ok(result);
+    }
+    info_ctrl!(
+        "project remote release set done work_root={} groups={} changed={}",
+        work_root.display(),
+        result.summary(),
+        result.changed()
+    );
+    Ok(result)
+}
+
+/// Undoes a release set: the managed dirs of every changed group, newest
+/// first, then the state file captured in `snapshot` before the set began.
+pub fn restore_project_remote_release_set<P: AsRef<Path>>(
+    work_root: P,
+    snapshot: &ProjectRemoteSnapshot,
+    result: &ProjectRemoteSetResult,
+) -> RunResult<()> {
+    let work_root = work_root.as_ref();
+    for group_result in result.groups.iter().rev() {
+        if !group_result.changed {
+            continue;
+        }
+        let Some(name) = group_result.group.as_deref() else {
+            continue;
+        };
+        let group = name
+            .parse::<RemoteGroup>()
+            .map_err(|e| RunReason::from_conf().to_err().with_detail(e))?;
+        restore_managed_dirs(work_root, Some(&group))?;
+    }
+    restore_state_file_bytes(work_root, snapshot.state_file.as_deref())
+}
+
+fn empty_release_set_err() -> wp_error::RunError {
+    RunReason::from_conf()
+        .to_err()
+        .with_detail("release set names no group")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::{
+        create_dual_work_root, create_empty_managed_dirs, create_infra_remote_fixture,
+        create_models_remote_fixture, create_remote_fixture, create_work_root, skip_check,
+    };
+    use super::super::STATE_PATH;
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn parse_release_set_reads_trimmed_pairs() {
+        let set = parse_release_set([" models = 1.4.3 ", "infra=1.1.0", "team_a=2.0.0"])
+            .expect("parse set");
+
+        assert_eq!(set.len(), 3);
+        assert_eq!(set[&RemoteGroup::Models], "1.4.3");
+        assert_eq!(set[&RemoteGroup::Infra], "1.1.0");
+        assert_eq!(
+            set[&"team_a".parse::<RemoteGroup>().expect("group")],
+            "2.0.0"
+        );
+    }
+
+    #[test]
+    fn release_set_from_map_trims_group_names_before_dedup() {
+        let map = BTreeMap::from([
+            ("models".to_string(), "1.4.3".to_string()),
+            ("models ".to_string(), "1.4.2".to_string()),
+        ]);
+        let err = release_set_from_map(&map).expect_err("duplicate after trim");
+        assert!(err.contains("appears twice"));
+    }
+
+    #[test]
+    fn empty_set_and_single_repo_mode_are_rejected() {
+        let fixture = create_remote_fixture();
+        let work_root = create_work_root(&fixture);
+        let dict = EnvDict::default();
+
+        let err = sync_project_remote_release_set_with_dict(
+            work_root.path(),
+            &BTreeMap::new(),
+            &dict,
+            &skip_check(),
+        )
+        .expect_err("empty set");
+        assert!(err.to_string().contains("names no group"));
+
+        let set = parse_release_set(["models=1.4.3"]).expect("parse set");
+        sync_project_remote_release_set_with_dict(work_root.path(), &set, &dict, &skip_check())
+            .expect_err("single-repo mode has no groups");
+        assert!(!work_root.path().join(STATE_PATH).exists());
+    }
+
+    #[test]
+    fn unknown_group_fails_before_anything_is_synced() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+        create_empty_managed_dirs(work_root.path());
+
+        let set = parse_release_set(["models=1.4.3", "team_a=1.0.0"]).expect("parse set");
+        sync_project_remote_release_set_with_dict(
+            work_root.path(),
+            &set,
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect_err("team_a is not configured");
+
+        assert!(!work_root.path().join("models/version.txt").exists());
+        assert!(!work_root.path().join(STATE_PATH).exists());
+    }
+
+    #[test]
+    fn unchanged_set_is_still_validated() {
+        let models_remote = create_models_remote_fixture();
+        let infra_remote = create_infra_remote_fixture();
+        let work_root = create_dual_work_root(&models_remote, &infra_remote);
+        create_empty_managed_dirs(work_root.path());
+        let set = parse_release_set(["models=1.4.3"]).expect("parse set");
+        sync_project_remote_release_set_with_dict(
+            work_root.path(),
+            &set,
+            &EnvDict::default(),
+            &skip_check(),
+        )
+        .expect("apply set");
+        let state = fs::read(work_root.path().join(STATE_PATH)).expect("read state");
+
+        // The tree has no conf/wpsrc.toml, so the check rejects it even
+        // though no group moves.
+        let result = sync_project_remote_release_set_with_dict(
+            work_root.path(),
+            &set,
+            &EnvDict::default(),
+            &SyncOptions::default(),
+        )
+        .expect("blocked set still reports a result");
+
+        assert!(!result.changed());
+        assert!(result.validation_failed());
+        assert_eq!(result.summary(), "models=1.4.3");
+        assert_eq!(
+            fs::read_to_string(work_root.path().join("models/version.txt")).expect("read version"),
+            "1.4.3\n"
+        );
+        assert_eq!(
+            fs::read(work_root.path().join(STATE_PATH)).expect("read state"),
+            state
+        );
+    }
+}
@@ -2,52 +2,54 @@ use std::fs;
 use std::path::Path;
 
 use crate::compat::UvsFrom;
-use git2::{build::CheckoutBuilder, ErrorCode, FetchOptions, Oid, Remote, Repository};
+use git2::{
+    build::{CheckoutBuilder, RepoBuilder},
+    AutotagOption, ErrorCode, FetchOptions, ObjectType, Oid, Remote, Repository, Tree,
+};
 use orion_error::conversion::ToStructError;
 use semver::Version;
 use wp_error::run_error::RunResult;
 use wp_error::RunReason;
+use wp_log::info_ctrl;
 
+use super::cache::CachePolicy;
 use super::managed::remove_path;
+use super::poll::PollPolicy;
 use super::{
     conf_err_source, requested_version_not_found_err, RemoteGroup, ResolvedTag, STATE_PATH,
 };
 
-pub(super) fn prepare_remote_repo(remote_root: &Path, repo_url: &str) -> RunResult<Repository> {
+pub(super) fn prepare_remote_repo(
+    remote_root: &Path,
+    repo_url: &str,
+    cache: &CachePolicy,
+) -> RunResult<Repository> {
     if !remote_root.exists() {
         if let Some(parent) = remote_root.parent() {
             fs::create_dir_all(parent)
                 .map_err(|e| conf_err_source(format!("create {} failed", parent.display()), e))?;
         }
-        return Repository::clone(repo_url, remote_root).map_err(|e| {
-            conf_err_source(
-                format!(
-                    "clone remote repository {} into {} failed",
-                    repo_url,
-                    remote_root.display()
-                ),
-                e,
-            )
-        });
+        return clone_remote_repo(remote_root, repo_url, cache);
     }
 
     match Repository::open(remote_root) {
         Ok(repo) => {
+            if let Some(reason) = cache.rebuild_reason(&repo, remote_root) {
+                info_ctrl!(
+                    "project remote cache rebuild path={} reason={}",
+                    remote_root.display(),
+                    reason
+                );
+                drop(repo);
+                remove_path(remote_root)?;
+                return clone_remote_repo(remote_root, repo_url, cache);
+            }
             ensure_remote(&repo, repo_url)?;
             Ok(repo)
         }
         Err(err) if err.code() == ErrorCode::NotFound => {
             remove_path(remote_root)?;
-            Repository::clone(repo_url, remote_root).map_err(|e| {
-                conf_err_source(
-                    format!(
-                        "clone remote repository {} into {} failed",
-                        repo_url,
-                        remote_root.display()
-                    ),
-                    e,
-                )
-            })
+            clone_remote_repo(remote_root, repo_url, cache)
         }
         Err(err) => Err(conf_err_source(
             format!(
@@ -59,21 +61,61 @@ pub(super) fn prepare_remote_repo(remote_root: &Path, repo_url: &str) -> RunResu
     }
 }
 
-pub(super) fn fetch_remote_tags(repo: &Repository, repo_url: &str) -> RunResult<()> {
+fn clone_remote_repo(
+    remote_root: &Path,
+    repo_url: &str,
+    cache: &CachePolicy,
+) -> RunResult<Repository> {
+    let mut fetch_options = FetchOptions::new();
+    if let Some(depth) = cache.shallow_depth(repo_url) {
+        fetch_options.depth(depth);
+    }
+    RepoBuilder::new()
+        .fetch_options(fetch_options)
+        .clone(repo_url, remote_root)
+        .map_err(|e| {
+            conf_err_source(
+                format!(
+                    "clone remote repository {} into {} failed",
+                    repo_url,
+                    remote_root.display()
+                ),
+                e,
+            )
+        })
+}
+
+/// Fetches branches and release tags. With a `fetch_depth` and an explicit
+/// `version`, only the tags that can resolve it are fetched.
+pub(super) fn fetch_remote_tags(
+    repo: &Repository,
+    repo_url: &str,
+    cache: &CachePolicy,
+    version: Option<&str>,
+) -> RunResult<()> {
     clear_local_release_tags(repo)?;
     let mut remote = ensure_remote(repo, repo_url)?;
     let mut fetch_options = FetchOptions::new();
     fetch_options.prune(git2::FetchPrune::On);
+    if let Some(depth) = cache.shallow_depth(repo_url) {
+        fetch_options.depth(depth);
+    }
+    let mut refspecs = vec![
+        "+HEAD:refs/remotes/origin/HEAD".to_string(),
+        "+refs/heads/*:refs/remotes/origin/*".to_string(),
+    ];
+    match version.filter(|_| cache.fetch_depth > 0) {
+        Some(version) => {
+            let version = version.strip_prefix('v').unwrap_or(version);
+            for tag in [format!("v{}", version), version.to_string()] {
+                refspecs.push(format!("+refs/tags/{0}:refs/tags/{0}", tag));
+            }
+            fetch_options.download_tags(AutotagOption::None);
+        }
+        None => refspecs.push("+refs/tags/*:refs/tags/*".to_string()),
+    }
     remote
-        .fetch(
-            &[
-                "+HEAD:refs/remotes/origin/HEAD",
-                "+refs/heads/*:refs/remotes/origin/*",
-                "+refs/tags/*:refs/tags/*",
-            ],
-            Some(&mut fetch_options),
-            None,
-        )
+        .fetch(&refspecs, Some(&mut fetch_options), None)
         .map_err(|e| conf_err_source("fetch remote tags failed", e))?;
     Ok(())
 }
@@ -120,34 +162,31 @@ pub(super) fn resolve_default_target(
     work_root: &Path,
     repo: &Repository,
     init_version: Option<&str>,
-    group: Option<RemoteGroup>,
+    group: Option<&RemoteGroup>,
+    track_branch: Option<&str>,
 ) -> RunResult<ResolvedTag> {
     if is_first_initialization(work_root, group)? {
         if let Some(init_version) = init_version {
             if !init_version.trim().is_empty() {
-                return resolve_tag_for_version(repo, init_version.trim())?
+                return resolve_requested_target(repo, init_version.trim(), track_branch)?
                     .ok_or_else(|| requested_version_not_found_err(init_version.trim()));
             }
         }
     }
+    if let Some(branch) = track_branch {
+        return resolve_branch_target(repo, branch);
+    }
     match resolve_latest_released_target(repo)? {
         Some(resolved) => Ok(resolved),
         None => resolve_remote_head_target(repo),
     }
 }
 
-fn is_first_initialization(work_root: &Path, group: Option<RemoteGroup>) -> RunResult<bool> {
+fn is_first_initialization(work_root: &Path, group: Option<&RemoteGroup>) -> RunResult<bool> {
     match group {
         Some(g) => {
             let state = super::state::load_state(work_root)?;
-            let has_group = match state {
-                Some(super::ProjectRemoteState::Dual { models, infra }) => match g {
-                    RemoteGroup::Models => models.is_some(),
-                    RemoteGroup::Infra => infra.is_some(),
-                },
-                _ => false,
-            };
-            Ok(!has_group)
+            Ok(state.and_then(|state| state.group(g).cloned()).is_none())
         }
         None => Ok(!work_root.join(STATE_PATH).exists()),
     }
@@ -169,6 +208,32 @@ fn resolve_latest_released_target(repo: &Repository) -> RunResult<Option<Resolve
     }
 }
 
+/// Highest release tag above `current` that `policy` allows. A current
+/// version that is not a release (a branch name) has nothing to compare with.
+pub(super) fn resolve_newer_released_target(
+    repo: &Repository,
+    current: &str,
+    policy: PollPolicy,
+) -> RunResult<Option<ResolvedTag>> {
+    let Some((_, current)) = parse_tag_version(current) else {
+        return Ok(None);
+    };
+    let names = repo
+        .tag_names(None)
+        .map_err(|e| conf_err_source("list tags failed", e))?;
+    let newest = names
+        .iter()
+        .flatten()
+        .filter_map(parse_tag_version)
+        .filter(|(_, candidate)| policy.allows(&current, candidate))
+        .max_by(|a, b| a.1.cmp(&b.1))
+        .map(|(version, _)| version);
+    match newest {
+        Some(version) => resolve_tag_for_version(repo, &version),
+        None => Ok(None),
+    }
+}
+
 fn resolve_remote_head_target(repo: &Repository) -> RunResult<ResolvedTag> {
     let head = repo
         .find_reference("refs/remotes/origin/HEAD")
@@ -196,6 +261,67 @@ fn resolve_remote_head_target(repo: &Repository) -> RunResult<ResolvedTag> {
     })
 }
 
+/// Release tags win; a branch-tracking source also accepts a commit id from
+/// the branch history, which is what it records as its version.
+pub(super) fn resolve_requested_target(
+    repo: &Repository,
+    version: &str,
+    track_branch: Option<&str>,
+) -> RunResult<Option<ResolvedTag>> {
+    if let Some(resolved) = resolve_tag_for_version(repo, version)? {
+        return Ok(Some(resolved));
+    }
+    match track_branch {
+        Some(branch) => resolve_branch_commit(repo, branch, version),
+        None => Ok(None),
+    }
+}
+
+fn resolve_branch_target(repo: &Repository, branch: &str) -> RunResult<ResolvedTag> {
+    let tip = branch_tip(repo, branch)?;
+    Ok(branch_resolved(branch, tip))
+}
+
+fn resolve_branch_commit(
+    repo: &Repository,
+    branch: &str,
+    version: &str,
+) -> RunResult<Option<ResolvedTag>> {
+    if version.len() < 7 || !version.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+    let commit_id = match repo
+        .revparse_single(version)
+        .and_then(|obj| obj.peel_to_commit())
+    {
+        Ok(commit) => commit.id(),
+        Err(_) => return Ok(None),
+    };
+    let tip = branch_tip(repo, branch)?;
+    let on_branch = commit_id == tip
+        || repo
+            .graph_descendant_of(tip, commit_id)
+            .map_err(|e| conf_err_source(format!("walk branch {} failed", branch), e))?;
+    Ok(on_branch.then(|| branch_resolved(branch, commit_id)))
+}
+
+fn branch_tip(repo: &Repository, branch: &str) -> RunResult<Oid> {
+    let refname = format!("refs/remotes/origin/{}", branch);
+    repo.find_reference(&refname)
+        .and_then(|reference| reference.peel_to_commit())
+        .map(|commit| commit.id())
+        .map_err(|e| conf_err_source(format!("resolve track_branch {} failed", branch), e))
+}
+
+fn branch_resolved(branch: &str, commit_id: Oid) -> ResolvedTag {
+    let version = commit_id.to_string();
+    ResolvedTag {
+        tag: format!("{}@{}", branch, &version[..12]),
+        version,
+        commit_id,
+    }
+}
+
 pub(super) fn resolve_tag_for_version(
     repo: &Repository,
     version: &str,
@@ -238,6 +364,44 @@ fn origin_head_not_symbolic_err() -> wp_error::RunError {
         .with_detail("origin HEAD is not a symbolic ref")
 }
 
+/// Tree the managed paths are resolved against: the commit root, or
+/// `subpath` inside it for a monorepo source.
+pub(super) fn source_tree<'r>(
+    repo: &'r Repository,
+    commit_id: Oid,
+    subpath: Option<&str>,
+) -> RunResult<Tree<'r>> {
+    let root = repo
+        .find_commit(commit_id)
+        .and_then(|commit| commit.tree())
+        .map_err(|e| conf_err_source(format!("load tree of commit {} failed", commit_id), e))?;
+    let Some(subpath) = subpath else {
+        return Ok(root);
+    };
+    let entry = match root.get_path(Path::new(subpath)) {
+        Ok(entry) if entry.kind() == Some(ObjectType::Tree) => entry,
+        Ok(_) => return Err(subpath_not_found_err(subpath, commit_id)),
+        Err(err) if err.code() == ErrorCode::NotFound => {
+            return Err(subpath_not_found_err(subpath, commit_id))
+        }
+        Err(err) => {
+            return Err(conf_err_source(
+                format!("lookup subpath {} failed", subpath),
+                err,
+            ))
+        }
+    };
+    repo.find_tree(entry.id())
+        .map_err(|e| conf_err_source(format!("load tree {} failed", entry.id()), e))
+}
+
+fn subpath_not_found_err(subpath: &str, commit_id: Oid) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(format!(
+        "project_remote subpath '{}' is not a directory in commit {}",
+        subpath, commit_id
+    ))
+}
+
 pub(super) fn checkout_commit(repo: &Repository, commit_id: Oid, tag: &str) -> RunResult<()> {
     let commit = repo
         .find_commit(commit_id)
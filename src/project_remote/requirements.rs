@@ -0,0 +1,177 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::compat::UvsFrom;
+use git2::{ObjectType, Repository, Tree};
+use orion_error::conversion::ToStructError;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use wp_error::run_error::RunResult;
+use wp_error::RunReason;
+
+use super::conf_err_source;
+
+/// Optional manifest at the root of a release (or of its `subpath`).
+const PROJECT_MANIFEST_FILE: &str = "wp-project.toml";
+
+/// What a release needs from the engine that loads it.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct ReleaseRequirements {
+    /// Semver requirement on the engine version, e.g. `">=0.24"`.
+    #[serde(default)]
+    requires_engine: Option<String>,
+    /// Connector types the release's sources and sinks use.
+    #[serde(default)]
+    requires_connectors: Vec<String>,
+}
+
+/// The engine a release is checked against: its version and the connector
+/// types its runtime registers.
+#[derive(Debug, Clone)]
+pub(super) struct EngineCapabilities {
+    pub(super) version: Version,
+    pub(super) connectors: BTreeSet<String>,
+}
+
+impl EngineCapabilities {
+    /// This build: `build::PKG_VERSION` and the factories registered by
+    /// `feats::register_for_runtime`.
+    pub(super) fn current() -> RunResult<Self> {
+        let version = Version::parse(crate::build::PKG_VERSION).map_err(|e| {
+            conf_err_source(
+                format!("parse engine version {} failed", crate::build::PKG_VERSION),
+                e,
+            )
+        })?;
+        Ok(Self {
+            version,
+            connectors: crate::feats::runtime_connector_kinds(),
+        })
+    }
+}
+
+/// Reads the manifest of the release in `tree`; `None` when it has none.
+pub(super) fn load_release_requirements(
+    repo: &Repository,
+    tree: &Tree,
+    tag: &str,
+) -> RunResult<Option<ReleaseRequirements>> {
+    let Ok(entry) = tree.get_path(Path::new(PROJECT_MANIFEST_FILE)) else {
+        return Ok(None);
+    };
+    if entry.kind() != Some(ObjectType::Blob) {
+        return Ok(None);
+    }
+    let blob = repo.find_blob(entry.id()).map_err(|e| {
+        conf_err_source(
+            format!("load {} of {} failed", PROJECT_MANIFEST_FILE, tag),
+            e,
+        )
+    })?;
+    let text = std::str::from_utf8(blob.content()).map_err(|e| {
+        conf_err_source(
+            format!("read {} of {} failed", PROJECT_MANIFEST_FILE, tag),
+            e,
+        )
+    })?;
+    toml::from_str(text).map(Some).map_err(|e| {
+        conf_err_source(
+            format!("parse {} of {} failed", PROJECT_MANIFEST_FILE, tag),
+            e,
+        )
+    })
+}
+
+/// Rejects a release whose manifest asks for a newer engine or for connectors
+/// this engine does not register, naming every unmet requirement at once.
+pub(super) fn check_release_requirements(
+    requirements: &ReleaseRequirements,
+    engine: &EngineCapabilities,
+    tag: &str,
+) -> RunResult<()> {
+    let mut unmet = Vec::new();
+    if let Some(raw) = requirements.requires_engine.as_deref() {
+        let req = VersionReq::parse(raw).map_err(|e| {
+            conf_err_source(
+                format!(
+                    "invalid requires_engine '{}' in {} of {}",
+                    raw, PROJECT_MANIFEST_FILE, tag
+                ),
+                e,
+            )
+        })?;
+        if !req.matches(&engine.version) {
+            unmet.push(format!(
+                "engine {} (this engine is {})",
+                raw, engine.version
+            ));
+        }
+    }
+    let missing: Vec<&str> = requirements
+        .requires_connectors
+        .iter()
+        .map(String::as_str)
+        .filter(|kind| !engine.connectors.contains(*kind))
+        .collect();
+    if !missing.is_empty() {
+        unmet.push(format!("connectors {}", missing.join(", ")));
+    }
+    if unmet.is_empty() {
+        return Ok(());
+    }
+    Err(RunReason::from_conf().to_err().with_detail(format!(
+        "release {} is not compatible with this engine: requires {}",
+        tag,
+        unmet.join("; ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(version: &str, connectors: &[&str]) -> EngineCapabilities {
+        EngineCapabilities {
+            version: Version::parse(version).expect("version"),
+            connectors: connectors.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    fn requirements(text: &str) -> ReleaseRequirements {
+        toml::from_str(text).expect("parse manifest")
+    }
+
+    #[test]
+    fn check_accepts_satisfied_requirements() {
+        let req = requirements("requires_engine = \">=0.24\"\nrequires_connectors = [\"kafka\"]\n");
+        check_release_requirements(&req, &engine("0.24.12", &["file", "kafka"]), "v1.5.0")
+            .expect("compatible");
+        check_release_requirements(&requirements(""), &engine("0.1.0", &[]), "v1.5.0")
+            .expect("empty manifest");
+    }
+
+    #[test]
+    fn check_names_every_unmet_requirement() {
+        let req = requirements(
+            "requires_engine = \">=0.25\"\nrequires_connectors = [\"file\", \"kafka\", \"doris\"]\n",
+        );
+        let err = check_release_requirements(&req, &engine("0.24.12", &["file"]), "v1.5.0")
+            .expect_err("incompatible");
+        let msg = err.to_string();
+        assert!(msg.contains("release v1.5.0 is not compatible"), "{}", msg);
+        assert!(
+            msg.contains("engine >=0.25 (this engine is 0.24.12)"),
+            "{}",
+            msg
+        );
+        assert!(msg.contains("connectors kafka, doris"), "{}", msg);
+    }
+
+    #[test]
+    fn check_rejects_malformed_engine_requirement() {
+        let req = requirements("requires_engine = \"newest\"\n");
+        let err = check_release_requirements(&req, &engine("0.24.12", &[]), "v1.5.0")
+            .expect_err("bad requirement");
+        assert!(err.to_string().contains("invalid requires_engine 'newest'"));
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -9,7 +10,7 @@ use wp_error::run_error::RunResult;
 use wp_error::RunReason;
 use wp_log::info_ctrl;
 
-use super::managed::{managed_dirs_for, restore_managed_dirs};
+use super::managed::restore_managed_dirs;
 use super::{
     conf_err_source, GroupState, ProjectRemoteLockGuard, ProjectRemoteSnapshot, ProjectRemoteState,
     ProjectRemoteUpdateResult, ProjectRuntimeArtifactSnapshot, RemoteGroup, AUTHORITY_DB_PATH,
@@ -80,8 +81,7 @@ pub fn restore_project_remote_update<P: AsRef<Path>>(
 ) -> RunResult<()> {
     let work_root = work_root.as_ref();
     if changed {
-        let dirs = managed_dirs_for(snapshot.group);
-        restore_managed_dirs(work_root, dirs)?;
+        restore_managed_dirs(work_root, snapshot.group.as_ref())?;
     }
     restore_state_file_bytes(work_root, snapshot.state_file.as_deref())?;
     Ok(())
@@ -153,12 +153,12 @@ pub(super) fn restore_project_remote_state(
     }
 }
 
-fn restore_state_file_bytes(work_root: &Path, bytes: Option<&[u8]>) -> RunResult<()> {
+pub(super) fn restore_state_file_bytes(work_root: &Path, bytes: Option<&[u8]>) -> RunResult<()> {
     let state_path = work_root.join(STATE_PATH);
     restore_optional_file(&state_path, bytes)
 }
 
-fn atomic_write_file(path: &Path, bytes: &[u8]) -> RunResult<()> {
+pub(super) fn atomic_write_file(path: &Path, bytes: &[u8]) -> RunResult<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .map_err(|e| conf_err_source(format!("create {} failed", parent.display()), e))?;
@@ -249,10 +249,10 @@ fn project_remote_lock_busy_err(lock_path: impl Into<String>) -> wp_error::RunEr
 }
 
 pub(super) fn persist_state(work_root: &Path, result: &ProjectRemoteUpdateResult) -> RunResult<()> {
-    // Prevent overwriting dual-repo state with single-repo state
-    if let Some(ProjectRemoteState::Dual { .. }) = load_state(work_root)? {
+    // Prevent overwriting group state with single-repo state
+    if let Some(ProjectRemoteState::Groups(_)) = load_state(work_root)? {
         return Err(RunReason::from_conf().to_err().with_detail(
-            "cannot persist single-repo state over dual-repo state; use persist_group_state",
+            "cannot persist single-repo state over group state; use persist_group_state",
         ));
     }
     let state = ProjectRemoteState::Single {
@@ -268,7 +268,7 @@ pub(super) fn persist_state(work_root: &Path, result: &ProjectRemoteUpdateResult
 
 pub(super) fn persist_group_state(
     work_root: &Path,
-    group: RemoteGroup,
+    group: &RemoteGroup,
     result: &ProjectRemoteUpdateResult,
 ) -> RunResult<()> {
     let new_group = GroupState {
@@ -276,28 +276,12 @@ pub(super) fn persist_group_state(
         resolved_tag: result.resolved_tag.clone(),
         revision: result.to_revision.clone(),
     };
-    let state = match load_state(work_root)? {
-        Some(ProjectRemoteState::Dual { models, infra }) => match group {
-            RemoteGroup::Models => ProjectRemoteState::Dual {
-                models: Some(new_group),
-                infra,
-            },
-            RemoteGroup::Infra => ProjectRemoteState::Dual {
-                models,
-                infra: Some(new_group),
-            },
-        },
-        _ => match group {
-            RemoteGroup::Models => ProjectRemoteState::Dual {
-                models: Some(new_group),
-                infra: None,
-            },
-            RemoteGroup::Infra => ProjectRemoteState::Dual {
-                models: None,
-                infra: Some(new_group),
-            },
-        },
+    let mut groups = match load_state(work_root)? {
+        Some(ProjectRemoteState::Groups(groups)) => groups,
+        _ => BTreeMap::new(),
     };
+    groups.insert(group.name().to_string(), new_group);
+    let state = ProjectRemoteState::Groups(groups);
     let path = work_root.join(STATE_PATH);
     let body = serde_json::to_vec_pretty(&state)
         .map_err(|e| conf_err_source("encode project remote state failed", e))?;
@@ -0,0 +1,635 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use git2::{ObjectType, Oid, Repository, Tree};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use wp_error::run_error::RunResult;
+
+use super::managed::{copy_path, remove_path, ManagedSet};
+use super::state::atomic_write_file;
+use super::{conf_err_source, STORE_INDEX_PATH, STORE_OBJECTS_PATH};
+
+const FILEMODE_BLOB_EXECUTABLE: i32 = 0o100755;
+const FILEMODE_LINK: i32 = 0o120000;
+const FILEMODE_COMMIT: i32 = 0o160000;
+
+/// Content-addressed blob store shared by every managed-dir version.
+///
+/// Objects are keyed by git blob id (plus an executable marker) and are only
+/// ever replaced via rename, so hard links held by the work root, the backup
+/// or a staged tree never observe a partial write. The index records the
+/// size and mtime of each object at insertion time; an object whose metadata
+/// no longer matches was edited in place through one of its links and is
+/// rewritten before reuse.
+///
+/// Such an edit also reaches every other link to the object, including the
+/// backup of the previous release when the file did not change between the
+/// two. [`ObjectStore::heal_tree`] puts the pristine content back before a
+/// backup is restored, and [`ObjectStore::gc`] drops objects that no
+/// recorded release references any more.
+pub(super) struct ObjectStore {
+    root: PathBuf,
+    index_path: PathBuf,
+    index: BTreeMap<String, ObjectStamp>,
+    dirty: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct ObjectStamp {
+    len: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+pub(super) enum EntryKind {
+    Tree,
+    Blob { executable: bool },
+    Link,
+    Submodule,
+}
+
+impl ObjectStore {
+    pub(super) fn open(work_root: &Path) -> RunResult<Self> {
+        let root = work_root.join(STORE_OBJECTS_PATH);
+        let index_path = work_root.join(STORE_INDEX_PATH);
+        let index = match fs::read(&index_path) {
+            // A corrupt index only costs re-verification, never correctness.
+            Ok(body) => serde_json::from_slice(&body).unwrap_or_default(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(conf_err_source(
+                    format!("read {} failed", index_path.display()),
+                    err,
+                ))
+            }
+        };
+        Ok(Self {
+            root,
+            index_path,
+            index,
+            dirty: false,
+        })
+    }
+
+    pub(super) fn save(&mut self) -> RunResult<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let body = serde_json::to_vec(&self.index)
+            .map_err(|e| conf_err_source("encode object store index failed", e))?;
+        atomic_write_file(&self.index_path, &body)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Materialises `tree` at `dst` as hard links into the store. Blobs that
+    /// are already stored cost one `link(2)` and no data I/O. Entries whose
+    /// work-root path `rel/...` is excluded by `set` are skipped.
+    pub(super) fn stage_tree(
+        &mut self,
+        repo: &Repository,
+        tree: &Tree,
+        dst: &Path,
+        rel: &str,
+        set: &ManagedSet,
+    ) -> RunResult<()> {
+        fs::create_dir_all(dst)
+            .map_err(|e| conf_err_source(format!("create {} failed", dst.display()), e))?;
+        for entry in tree.iter() {
+            let name = entry_name(&entry, dst)?;
+            let child_rel = format!("{}/{}", rel, name);
+            if set.is_excluded(&child_rel) {
+                continue;
+            }
+            let target = dst.join(name);
+            match entry_kind(&entry) {
+                EntryKind::Tree => {
+                    let subtree = repo.find_tree(entry.id()).map_err(|e| {
+                        conf_err_source(format!("load tree {} failed", entry.id()), e)
+                    })?;
+                    self.stage_tree(repo, &subtree, &target, &child_rel, set)?;
+                }
+                EntryKind::Blob { executable } => {
+                    let object = self.ensure_blob(repo, entry.id(), executable)?;
+                    link_or_copy(&object, &target)?;
+                }
+                EntryKind::Link => {
+                    let blob = repo.find_blob(entry.id()).map_err(|e| {
+                        conf_err_source(format!("load blob {} failed", entry.id()), e)
+                    })?;
+                    create_symlink(&String::from_utf8_lossy(blob.content()), &target)?;
+                }
+                EntryKind::Submodule => {
+                    fs::create_dir_all(&target).map_err(|e| {
+                        conf_err_source(format!("create {} failed", target.display()), e)
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true when `path` holds exactly the content of `tree`. Files
+    /// that are still hard links to a verified object are accepted from
+    /// metadata alone; anything else is hashed.
+    pub(super) fn tree_matches(
+        &self,
+        repo: &Repository,
+        tree: &Tree,
+        path: &Path,
+        rel: &str,
+        set: &ManagedSet,
+    ) -> RunResult<bool> {
+        let meta = match fs::symlink_metadata(path) {
+            Ok(meta) => meta,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => {
+                return Err(conf_err_source(
+                    format!("stat {} failed", path.display()),
+                    err,
+                ))
+            }
+        };
+        if !meta.file_type().is_dir() {
+            return Ok(false);
+        }
+
+        let is_local = |name: &str| set.is_excluded(&format!("{}/{}", rel, name));
+        let mut expected = BTreeSet::new();
+        for entry in tree.iter() {
+            let name = entry_name(&entry, path)?;
+            if !is_local(name) {
+                expected.insert(name.to_string());
+            }
+        }
+        let mut actual = read_dir_names(path)?;
+        actual.retain(|name| !is_local(name));
+        if actual != expected {
+            return Ok(false);
+        }
+
+        for entry in tree.iter() {
+            let name = entry_name(&entry, path)?;
+            if is_local(name) {
+                continue;
+            }
+            let child_rel = format!("{}/{}", rel, name);
+            let target = path.join(name);
+            let matches = match entry_kind(&entry) {
+                EntryKind::Tree => {
+                    let subtree = repo.find_tree(entry.id()).map_err(|e| {
+                        conf_err_source(format!("load tree {} failed", entry.id()), e)
+                    })?;
+                    self.tree_matches(repo, &subtree, &target, &child_rel, set)?
+                }
+                EntryKind::Blob { executable } => {
+                    self.blob_matches(entry.id(), executable, &target)?
+                }
+                EntryKind::Link => {
+                    let blob = repo.find_blob(entry.id()).map_err(|e| {
+                        conf_err_source(format!("load blob {} failed", entry.id()), e)
+                    })?;
+                    link_matches(blob.content(), &target)?
+                }
+                EntryKind::Submodule => target.is_dir(),
+            };
+            if !matches {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    pub(super) fn blob_matches(&self, oid: Oid, executable: bool, path: &Path) -> RunResult<bool> {
+        let meta = fs::symlink_metadata(path)
+            .map_err(|e| conf_err_source(format!("stat {} failed", path.display()), e))?;
+        if !meta.file_type().is_file() || is_executable(&meta).is_some_and(|x| x != executable) {
+            return Ok(false);
+        }
+        if let Some(object_meta) = self.verified_object_meta(&object_key(oid, executable)) {
+            if same_inode(&meta, &object_meta) {
+                return Ok(true);
+            }
+        }
+        let actual = Oid::hash_file(ObjectType::Blob, path)
+            .map_err(|e| conf_err_source(format!("hash {} failed", path.display()), e))?;
+        Ok(actual == oid)
+    }
+
+    /// Removes every object whose key is not in `keep`, plus temp files left
+    /// by an interrupted write. Files linked to a removed object keep their
+    /// content; only the store's own link goes away.
+    pub(super) fn gc(&mut self, keep: &BTreeSet<String>) -> RunResult<usize> {
+        let mut removed = 0;
+        let shards = match fs::read_dir(&self.root) {
+            Ok(shards) => shards,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => {
+                return Err(conf_err_source(
+                    format!("read dir {} failed", self.root.display()),
+                    err,
+                ))
+            }
+        };
+        for shard in shards {
+            let shard = shard.map_err(|e| conf_err_source("read dir entry failed", e))?;
+            let shard_path = shard.path();
+            if !shard_path.is_dir() {
+                continue;
+            }
+            let prefix = shard.file_name().to_string_lossy().into_owned();
+            for entry in fs::read_dir(&shard_path).map_err(|e| {
+                conf_err_source(format!("read dir {} failed", shard_path.display()), e)
+            })? {
+                let entry = entry.map_err(|e| conf_err_source("read dir entry failed", e))?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let key = format!("{}{}", prefix, name);
+                if name.ends_with(".tmp") || !keep.contains(&key) {
+                    remove_path(&entry.path())?;
+                    removed += 1;
+                }
+            }
+            // Fails while the shard still holds objects, which is fine.
+            let _ = fs::remove_dir(&shard_path);
+        }
+        let indexed = self.index.len();
+        self.index.retain(|key, _| keep.contains(key));
+        self.dirty |= self.index.len() != indexed;
+        self.save()?;
+        Ok(removed)
+    }
+
+    /// Re-links every file under `path` that still shares its inode with an
+    /// object edited in place, reading the pristine blob from the repository
+    /// returned by `open_repo`. Returns the number of files replaced.
+    pub(super) fn heal_tree<F>(&mut self, path: &Path, open_repo: F) -> RunResult<usize>
+    where
+        F: Fn() -> RunResult<Repository>,
+    {
+        let mut edited = BTreeMap::new();
+        for (key, stamp) in &self.index {
+            let Ok(meta) = fs::symlink_metadata(self.object_path(key)) else {
+                continue;
+            };
+            if let Some(inode) = inode_of(&meta) {
+                if meta.file_type().is_file() && stamp_of(&meta) != *stamp {
+                    edited.insert(inode, key.clone());
+                }
+            }
+        }
+        if edited.is_empty() {
+            return Ok(0);
+        }
+
+        let mut repo = None;
+        let mut healed = 0;
+        for entry in WalkDir::new(path).min_depth(1) {
+            let entry =
+                entry.map_err(|e| conf_err_source(format!("walk {} failed", path.display()), e))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let meta = entry.metadata().map_err(|e| {
+                conf_err_source(format!("stat {} failed", entry.path().display()), e)
+            })?;
+            let Some(key) = inode_of(&meta).and_then(|inode| edited.get(&inode)) else {
+                continue;
+            };
+            let (oid, executable) = match key.strip_suffix(".x") {
+                Some(oid) => (oid, true),
+                None => (key.as_str(), false),
+            };
+            let oid = Oid::from_str(oid)
+                .map_err(|e| conf_err_source(format!("invalid object key {}", key), e))?;
+            if repo.is_none() {
+                repo = Some(open_repo()?);
+            }
+            let repo = repo.as_ref().expect("repository opened above");
+            let object = self.ensure_blob(repo, oid, executable)?;
+            remove_path(entry.path())?;
+            link_or_copy(&object, entry.path())?;
+            healed += 1;
+        }
+        self.save()?;
+        Ok(healed)
+    }
+
+    fn ensure_blob(&mut self, repo: &Repository, oid: Oid, executable: bool) -> RunResult<PathBuf> {
+        let key = object_key(oid, executable);
+        let path = self.object_path(&key);
+        if self.verified_object_meta(&key).is_some() {
+            return Ok(path);
+        }
+
+        let blob = repo
+            .find_blob(oid)
+            .map_err(|e| conf_err_source(format!("load blob {} failed", oid), e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| conf_err_source(format!("create {} failed", parent.display()), e))?;
+        }
+        // Never write an object in place: other links may still point at it.
+        let tmp_path = self.root.join(&key[..2]).join(format!("{}.tmp", &key[2..]));
+        fs::write(&tmp_path, blob.content())
+            .map_err(|e| conf_err_source(format!("write {} failed", tmp_path.display()), e))?;
+        set_executable(&tmp_path, executable)?;
+        fs::rename(&tmp_path, &path).map_err(|e| {
+            let _ = fs::remove_file(&tmp_path);
+            conf_err_source(
+                format!("rename {} -> {} failed", tmp_path.display(), path.display()),
+                e,
+            )
+        })?;
+        let meta = fs::metadata(&path)
+            .map_err(|e| conf_err_source(format!("stat {} failed", path.display()), e))?;
+        self.index.insert(key, stamp_of(&meta));
+        self.dirty = true;
+        Ok(path)
+    }
+
+    fn verified_object_meta(&self, key: &str) -> Option<fs::Metadata> {
+        let stamp = self.index.get(key)?;
+        let meta = fs::symlink_metadata(self.object_path(key)).ok()?;
+        (meta.file_type().is_file() && stamp_of(&meta) == *stamp).then_some(meta)
+    }
+
+    fn object_path(&self, key: &str) -> PathBuf {
+        self.root.join(&key[..2]).join(&key[2..])
+    }
+}
+
+/// Store key of a blob: its git id, with `.x` appended for executables.
+pub(super) fn object_key(oid: impl std::fmt::Display, executable: bool) -> String {
+    if executable {
+        format!("{}.x", oid)
+    } else {
+        oid.to_string()
+    }
+}
+
+pub(super) fn entry_kind(entry: &git2::TreeEntry<'_>) -> EntryKind {
+    match entry.kind() {
+        Some(ObjectType::Tree) => EntryKind::Tree,
+        Some(ObjectType::Commit) => EntryKind::Submodule,
+        _ => match entry.filemode() {
+            FILEMODE_LINK => EntryKind::Link,
+            FILEMODE_COMMIT => EntryKind::Submodule,
+            mode => EntryKind::Blob {
+                executable: mode == FILEMODE_BLOB_EXECUTABLE,
+            },
+        },
+    }
+}
+
+fn entry_name<'a>(entry: &'a git2::TreeEntry<'_>, parent: &Path) -> RunResult<&'a str> {
+    entry.name().ok_or_else(|| {
+        conf_err_source(
+            format!("non-utf8 tree entry under {}", parent.display()),
+            std::io::Error::from(std::io::ErrorKind::InvalidData),
+        )
+    })
+}
+
+fn read_dir_names(path: &Path) -> RunResult<BTreeSet<String>> {
+    let mut names = BTreeSet::new();
+    for entry in fs::read_dir(path)
+        .map_err(|e| conf_err_source(format!("read dir {} failed", path.display()), e))?
+    {
+        let entry = entry.map_err(|e| conf_err_source("read dir entry failed", e))?;
+        // A non-utf8 name can never match a tree entry.
+        names.insert(entry.file_name().to_string_lossy().into_owned());
+    }
+    Ok(names)
+}
+
+pub(super) fn link_matches(expected: &[u8], path: &Path) -> RunResult<bool> {
+    let meta = fs::symlink_metadata(path)
+        .map_err(|e| conf_err_source(format!("stat {} failed", path.display()), e))?;
+    if !meta.file_type().is_symlink() {
+        return Ok(false);
+    }
+    let target = fs::read_link(path)
+        .map_err(|e| conf_err_source(format!("read link {} failed", path.display()), e))?;
+    Ok(target.as_os_str().to_string_lossy().as_bytes() == expected)
+}
+
+fn link_or_copy(object: &Path, target: &Path) -> RunResult<()> {
+    if fs::hard_link(object, target).is_ok() {
+        return Ok(());
+    }
+    // Hard links are unavailable (foreign filesystem, link limit, ...):
+    // fall back to a private copy, which is correct but costs the I/O.
+    copy_path(object, target)
+}
+
+fn create_symlink(link_target: &str, dst: &Path) -> RunResult<()> {
+    remove_path(dst)?;
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(link_target, dst).map_err(|e| {
+            conf_err_source(
+                format!("create symlink {} -> {} failed", dst.display(), link_target),
+                e,
+            )
+        })
+    }
+    #[cfg(windows)]
+    {
+        std::os::windows::fs::symlink_file(link_target, dst).map_err(|e| {
+            conf_err_source(
+                format!("create symlink {} -> {} failed", dst.display(), link_target),
+                e,
+            )
+        })
+    }
+}
+
+fn stamp_of(meta: &fs::Metadata) -> ObjectStamp {
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    ObjectStamp {
+        len: meta.len(),
+        mtime_secs: mtime.as_secs(),
+        mtime_nanos: mtime.subsec_nanos(),
+    }
+}
+
+#[cfg(unix)]
+fn same_inode(left: &fs::Metadata, right: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    left.dev() == right.dev() && left.ino() == right.ino()
+}
+
+#[cfg(not(unix))]
+fn same_inode(_left: &fs::Metadata, _right: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn inode_of(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode_of(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &fs::Metadata) -> Option<bool> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &fs::Metadata) -> Option<bool> {
+    None
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path, executable: bool) -> RunResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = if executable { 0o755 } else { 0o644 };
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|e| conf_err_source(format!("set permissions on {} failed", path.display()), e))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path, _executable: bool) -> RunResult<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn flat_tree(repo: &Repository, files: &[(&str, &str)]) -> Oid {
+        let mut builder = repo.treebuilder(None).expect("tree builder");
+        for (name, body) in files {
+            let blob = repo.blob(body.as_bytes()).expect("write blob");
+            builder.insert(name, blob, 0o100644).expect("insert blob");
+        }
+        builder.write().expect("write tree")
+    }
+
+    fn stage(store: &mut ObjectStore, repo: &Repository, tree: Oid, dst: &Path) {
+        let tree = repo.find_tree(tree).expect("find tree");
+        let set = ManagedSet::new(Vec::new(), Vec::new());
+        store
+            .stage_tree(repo, &tree, dst, "models", &set)
+            .expect("stage tree");
+    }
+
+    fn object_count(work_root: &Path) -> usize {
+        WalkDir::new(work_root.join(STORE_OBJECTS_PATH))
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .count()
+    }
+
+    #[test]
+    fn stage_tree_stores_shared_blobs_once() {
+        let temp = tempdir().expect("tempdir");
+        let repo = Repository::init(temp.path().join("repo")).expect("init repo");
+        let v1 = flat_tree(&repo, &[("same.txt", "same\n"), ("version.txt", "1\n")]);
+        let v2 = flat_tree(&repo, &[("same.txt", "same\n"), ("version.txt", "2\n")]);
+        let work_root = temp.path().join("work");
+        let mut store = ObjectStore::open(&work_root).expect("open store");
+
+        stage(&mut store, &repo, v1, &work_root.join("v1"));
+        stage(&mut store, &repo, v2, &work_root.join("v2"));
+        store.save().expect("save index");
+
+        assert_eq!(object_count(&work_root), 3);
+        let left = fs::metadata(work_root.join("v1/same.txt")).expect("stat v1");
+        let right = fs::metadata(work_root.join("v2/same.txt")).expect("stat v2");
+        assert!(same_inode(&left, &right));
+        let reopened = ObjectStore::open(&work_root).expect("reopen store");
+        assert_eq!(reopened.index.len(), 3);
+    }
+
+    #[test]
+    fn gc_drops_unreferenced_objects_but_keeps_linked_content() {
+        let temp = tempdir().expect("tempdir");
+        let repo = Repository::init(temp.path().join("repo")).expect("init repo");
+        let v1 = flat_tree(&repo, &[("same.txt", "same\n"), ("version.txt", "1\n")]);
+        let v2 = flat_tree(&repo, &[("same.txt", "same\n"), ("version.txt", "2\n")]);
+        let work_root = temp.path().join("work");
+        let mut store = ObjectStore::open(&work_root).expect("open store");
+        stage(&mut store, &repo, v1, &work_root.join("v1"));
+        stage(&mut store, &repo, v2, &work_root.join("v2"));
+        let stale_tmp = work_root.join(STORE_OBJECTS_PATH).join("ab/cdef.tmp");
+        fs::create_dir_all(stale_tmp.parent().expect("shard")).expect("create shard");
+        fs::write(&stale_tmp, "partial").expect("write tmp");
+
+        let keep: BTreeSet<String> = repo
+            .find_tree(v2)
+            .expect("find tree")
+            .iter()
+            .map(|entry| object_key(entry.id(), false))
+            .collect();
+        let removed = store.gc(&keep).expect("gc");
+
+        assert_eq!(removed, 2);
+        assert_eq!(object_count(&work_root), 2);
+        assert!(!stale_tmp.exists());
+        assert_eq!(store.index.len(), 2);
+        assert_eq!(
+            fs::read_to_string(work_root.join("v1/version.txt")).expect("read v1"),
+            "1\n"
+        );
+    }
+
+    #[test]
+    fn heal_tree_restores_copy_edited_through_shared_link() {
+        let temp = tempdir().expect("tempdir");
+        let repo_path = temp.path().join("repo");
+        let repo = Repository::init(&repo_path).expect("init repo");
+        let tree = flat_tree(&repo, &[("same.txt", "same\n"), ("other.txt", "other\n")]);
+        let work_root = temp.path().join("work");
+        let mut store = ObjectStore::open(&work_root).expect("open store");
+        stage(&mut store, &repo, tree, &work_root.join("backup"));
+        stage(&mut store, &repo, tree, &work_root.join("live"));
+
+        fs::write(work_root.join("live/same.txt"), "hot fix\n").expect("edit in place");
+        assert_eq!(
+            fs::read_to_string(work_root.join("backup/same.txt")).expect("read backup"),
+            "hot fix\n"
+        );
+
+        let healed = store
+            .heal_tree(&work_root.join("backup"), || {
+                Repository::open(&repo_path).map_err(|e| conf_err_source("open repo failed", e))
+            })
+            .expect("heal backup");
+
+        assert_eq!(healed, 1);
+        assert_eq!(
+            fs::read_to_string(work_root.join("backup/same.txt")).expect("read backup"),
+            "same\n"
+        );
+        assert_eq!(
+            fs::read_to_string(work_root.join("live/same.txt")).expect("read live"),
+            "hot fix\n"
+        );
+        let unedited = store
+            .heal_tree(&work_root.join("backup"), || {
+                unreachable!("nothing to heal")
+            })
+            .expect("heal again");
+        assert_eq!(unedited, 0);
+    }
+}
@@ -4,6 +4,8 @@ use std::path::{Path, PathBuf};
 use git2::{Oid, Repository, Signature};
 use tempfile::{tempdir, TempDir};
 
+use super::SyncOptions;
+
 pub(super) struct RemoteFixture {
     _temp: TempDir,
     remote_path: PathBuf,
@@ -235,3 +237,178 @@ pub(super) fn create_dual_work_root(
     );
     work_root
 }
+
+/// Remote that publishes `<managed_path>/version.txt` at v1.0.0 and v1.1.0.
+pub(super) fn create_group_remote_fixture(managed_path: &str) -> RemoteFixture {
+    let temp = tempdir().expect("tempdir");
+    let repo = Repository::init(temp.path()).expect("init group remote repo");
+    let dir = temp.path().join(managed_path);
+    fs::create_dir_all(&dir).expect("create managed dir");
+    fs::write(dir.join("version.txt"), "1.0.0\n").expect("write v1.0.0");
+    commit_all(&repo, "group release 1.0.0");
+    tag_head(&repo, "v1.0.0");
+
+    fs::write(dir.join("version.txt"), "1.1.0\n").expect("write v1.1.0");
+    commit_all(&repo, "group release 1.1.0");
+    tag_head(&repo, "v1.1.0");
+
+    RemoteFixture {
+        remote_path: temp.path().to_path_buf(),
+        _temp: temp,
+    }
+}
+
+pub(super) fn append_engine_conf(work_root: &Path, extra: &str) {
+    let path = work_root.join("conf/wparse.toml");
+    let mut body = fs::read_to_string(&path).expect("read wparse.toml");
+    body.push_str(extra);
+    fs::write(&path, body).expect("write wparse.toml");
+}
+
+/// Monorepo remote that keeps the project under `subpath`, next to a root
+/// level `models/` that must never be synced. Tags v1.4.2 and v1.4.3.
+pub(super) fn create_monorepo_remote_fixture(subpath: &str) -> RemoteFixture {
+    let temp = tempdir().expect("tempdir");
+    let repo = Repository::init(temp.path()).expect("init monorepo remote");
+    let project = temp.path().join(subpath);
+    write_engine_conf(&project, temp.path().to_str().expect("repo path utf8"));
+    append_engine_conf(&project, &format!("subpath = \"{subpath}\"\n"));
+    fs::create_dir_all(project.join("models")).expect("create models dir");
+    fs::create_dir_all(project.join("topology")).expect("create topology dir");
+    fs::create_dir_all(project.join("connectors")).expect("create connectors dir");
+    fs::create_dir_all(temp.path().join("models")).expect("create root models dir");
+    fs::write(temp.path().join("models/version.txt"), "root\n").expect("write root marker");
+    fs::write(project.join("models/version.txt"), "1.4.2\n").expect("write v1.4.2");
+    commit_all(&repo, "release 1.4.2");
+    tag_head(&repo, "v1.4.2");
+
+    fs::write(project.join("models/version.txt"), "1.4.3\n").expect("write v1.4.3");
+    commit_all(&repo, "release 1.4.3");
+    tag_head(&repo, "v1.4.3");
+
+    RemoteFixture {
+        remote_path: temp.path().to_path_buf(),
+        _temp: temp,
+    }
+}
+
+/// Commits `models/version.txt = <marker>` onto `branch`, whose engine conf
+/// tracks that branch, without moving the fixture's HEAD.
+pub(super) fn commit_on_branch(fixture: &RemoteFixture, branch: &str, marker: &str) -> Oid {
+    let repo = Repository::open(&fixture.remote_path).expect("open remote repo");
+    let refname = format!("refs/heads/{branch}");
+    let parent = repo
+        .find_reference(&refname)
+        .or_else(|_| repo.head())
+        .and_then(|reference| reference.peel_to_commit())
+        .expect("resolve branch parent");
+
+    let conf_path = fixture.remote_path.join("conf/wparse.toml");
+    let conf = fs::read_to_string(&conf_path).expect("read remote conf");
+    let track_line = format!("track_branch = \"{branch}\"\n");
+    if !conf.contains(&track_line) {
+        fs::write(&conf_path, format!("{conf}{track_line}")).expect("write remote conf");
+    }
+    fs::write(
+        fixture.remote_path.join("models/version.txt"),
+        format!("{marker}\n"),
+    )
+    .expect("write branch marker");
+
+    let mut index = repo.index().expect("open index");
+    index
+        .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+        .expect("add all");
+    index.write().expect("write index");
+    let tree = repo
+        .find_tree(index.write_tree().expect("write tree"))
+        .expect("find tree");
+    let sig = Signature::now("warp-parse-test", "warp-parse@test.local").expect("signature");
+    repo.commit(
+        Some(&refname),
+        &sig,
+        &sig,
+        &format!("{branch} {marker}"),
+        &tree,
+        &[&parent],
+    )
+    .expect("commit on branch")
+}
+
+/// Commits `models/version.txt = <version>` on the fixture's HEAD and tags
+/// it `v<version>`.
+pub(super) fn tag_release(fixture: &RemoteFixture, version: &str) {
+    let repo = Repository::open(&fixture.remote_path).expect("open remote repo");
+    fs::write(
+        fixture.remote_path.join("models/version.txt"),
+        format!("{version}\n"),
+    )
+    .expect("write release marker");
+    commit_all(&repo, &format!("release {version}"));
+    tag_head(&repo, &format!("v{version}"));
+}
+
+/// Commits `models/version.txt = <version>` with a `CHANGELOG.md` holding
+/// `changelog` under a `## [<version>]` heading, and tags it `v<version>`
+/// as an annotated tag carrying `message`.
+pub(super) fn tag_annotated_release(
+    fixture: &RemoteFixture,
+    version: &str,
+    message: &str,
+    changelog: &str,
+) {
+    let repo = Repository::open(&fixture.remote_path).expect("open remote repo");
+    fs::write(
+        fixture.remote_path.join("models/version.txt"),
+        format!("{version}\n"),
+    )
+    .expect("write release marker");
+    fs::write(
+        fixture.remote_path.join("CHANGELOG.md"),
+        format!("# Changelog\n\n## [{version}]\n{changelog}\n\n## [1.4.3]\n- older\n"),
+    )
+    .expect("write changelog");
+    let commit_id = commit_all(&repo, &format!("release {version}"));
+    let target = repo.find_object(commit_id, None).expect("find commit");
+    let sig = Signature::now("warp-parse-test", "warp-parse@test.local").expect("signature");
+    repo.tag(&format!("v{version}"), &target, &sig, message, false)
+        .expect("create annotated tag");
+}
+
+/// Commits `models/version.txt = <version>` with `manifest` as
+/// `wp-project.toml` and tags it `v<version>`.
+pub(super) fn tag_release_with_manifest(fixture: &RemoteFixture, version: &str, manifest: &str) {
+    fs::write(fixture.remote_path.join("wp-project.toml"), manifest)
+        .expect("write project manifest");
+    tag_release(fixture, version);
+}
+
+/// Writes `files` (remote-relative path, content) next to the release
+/// marker, commits them and tags `v<version>`.
+pub(super) fn tag_release_with_files(
+    fixture: &RemoteFixture,
+    version: &str,
+    files: &[(&str, &str)],
+) {
+    for (rel, body) in files {
+        let path = fixture.remote_path.join(rel);
+        fs::create_dir_all(path.parent().expect("parent")).expect("create release dir");
+        fs::write(path, body).expect("write release file");
+    }
+    tag_release(fixture, version);
+}
+
+/// Options for tests about sync mechanics rather than the project check.
+pub(super) fn skip_check() -> SyncOptions {
+    SyncOptions {
+        skip_validate: true,
+        ..SyncOptions::default()
+    }
+}
+
+pub(super) fn discard_local() -> SyncOptions {
+    SyncOptions {
+        discard_local: true,
+        ..skip_check()
+    }
+}
@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use orion_variate::EnvDict;
+use serde::Serialize;
+use wp_proj::project::{checker, init::PrjScope, WarpProject};
+
+use super::SyncOptions;
+
+/// Outcome of the project check that gates a sync before its state is
+/// persisted.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRemoteValidation {
+    pub status: ValidationStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<ValidationFailure>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl ValidationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationStatus::Passed => "passed",
+            ValidationStatus::Failed => "failed",
+            ValidationStatus::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationFailure {
+    pub component: String,
+    pub error: String,
+}
+
+impl ProjectRemoteValidation {
+    pub fn is_failed(&self) -> bool {
+        self.status == ValidationStatus::Failed
+    }
+
+    /// One-line `component: error` listing for logs and error details.
+    pub fn summary(&self) -> String {
+        self.failures
+            .iter()
+            .map(|f| format!("{}: {}", f.component, f.error))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    fn skipped() -> Self {
+        Self {
+            status: ValidationStatus::Skipped,
+            failures: Vec::new(),
+        }
+    }
+
+    fn from_failures(failures: Vec<ValidationFailure>) -> Self {
+        let status = if failures.is_empty() {
+            ValidationStatus::Passed
+        } else {
+            ValidationStatus::Failed
+        };
+        Self { status, failures }
+    }
+}
+
+/// Runs the `wproj check` components that a release can break against the
+/// freshly swapped tree, in-process through `checker::check_with`.
+pub(super) fn validate_synced_tree(
+    work_root: &Path,
+    dict: &EnvDict,
+    opts: &SyncOptions,
+) -> ProjectRemoteValidation {
+    if opts.skip_validate {
+        return ProjectRemoteValidation::skipped();
+    }
+    // The engine check switches the process cwd to the work root; hold the
+    // lock so concurrent syncs cannot interleave, and restore the cwd after.
+    let _lock = CHECK_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _cwd_guard = CwdGuard::capture();
+    let root = match absolute_root(work_root) {
+        Ok(root) => root.display().to_string(),
+        Err(err) => {
+            return ProjectRemoteValidation::from_failures(vec![ValidationFailure {
+                component: "project".to_string(),
+                error: format!("resolve {} failed: {}", work_root.display(), err),
+            }]);
+        }
+    };
+    let project = match WarpProject::load(root.as_str(), PrjScope::Normal, dict) {
+        Ok(project) => project,
+        Err(err) => {
+            return ProjectRemoteValidation::from_failures(vec![ValidationFailure {
+                component: "project".to_string(),
+                error: err.to_string(),
+            }]);
+        }
+    };
+    let mut check_opts = checker::CheckOptions::new(root.as_str());
+    check_opts.console = false;
+    check_opts.fail_fast = true;
+    check_opts.json = false;
+    check_opts.only_fail = true;
+
+    let mut failures = Vec::new();
+    for (component, name) in gate_components() {
+        let comps = checker::CheckComponents::default().with_only(vec![component]);
+        if let Err(err) = checker::check_with(&project, &check_opts, &comps, dict) {
+            failures.push(ValidationFailure {
+                component: name.to_string(),
+                error: err.to_string(),
+            });
+            // Every later component reads the engine config.
+            if name == "conf" {
+                break;
+            }
+        }
+    }
+    ProjectRemoteValidation::from_failures(failures)
+}
+
+static CHECK_LOCK: Mutex<()> = Mutex::new(());
+
+fn gate_components() -> Vec<(checker::CheckComponent, &'static str)> {
+    vec![
+        (checker::CheckComponent::Engine, "conf"),
+        (checker::CheckComponent::Sources, "sources"),
+        (checker::CheckComponent::Sinks, "sinks"),
+        (checker::CheckComponent::Wpl, "wpl"),
+        (checker::CheckComponent::Oml, "oml"),
+    ]
+}
+
+/// The checker resolves the project against the cwd it switches to, so a
+/// relative work root must be anchored first.
+fn absolute_root(work_root: &Path) -> std::io::Result<PathBuf> {
+    if work_root.is_absolute() {
+        Ok(work_root.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(work_root))
+    }
+}
+
+struct CwdGuard {
+    original: Option<PathBuf>,
+}
+
+impl CwdGuard {
+    fn capture() -> Self {
+        Self {
+            original: std::env::current_dir().ok(),
+        }
+    }
+}
+
+impl Drop for CwdGuard {
+    fn drop(&mut self) {
+        if let Some(original) = self.original.as_ref() {
+            let _ = std::env::set_current_dir(original);
+        }
+    }
+}
@@ -0,0 +1,822 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use orion_variate::EnvDict;
+use ring::hmac;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use wp_error::run_error::RunResult;
+
+use super::groups::{invalid_option_err, load_project_remote_ext};
+use super::poll::poll_policy;
+use super::state::{load_engine_config, load_state};
+use super::{conf_err_source, configured_sources, ProjectRemoteState, RemoteGroup};
+
+const TAG_REF_PREFIX: &str = "refs/tags/";
+/// Delivery IDs remembered for replay detection.
+const SEEN_DELIVERIES: usize = 256;
+
+/// `[project_remote.webhook]` as written in `conf/wparse.toml`.
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct RawWebhookConf {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    secret_file: String,
+    /// Accept tags that are not newer than the deployed release, so a tag
+    /// push can pin or roll back a source.
+    #[serde(default)]
+    pin: bool,
+}
+
+/// Shared secret that deliveries to `POST /admin/v1/project/webhook` are
+/// verified with.
+pub struct ProjectRemoteWebhookConf {
+    key: hmac::Key,
+    /// HMAC of the secret itself, so a GitLab token can be compared in
+    /// constant time without keeping the secret around.
+    secret_tag: hmac::Tag,
+}
+
+impl std::fmt::Debug for ProjectRemoteWebhookConf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProjectRemoteWebhookConf")
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProjectRemoteWebhookConf {
+    pub fn from_secret(secret: &[u8]) -> Self {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+        let secret_tag = hmac::sign(&key, secret);
+        Self { key, secret_tag }
+    }
+
+    fn verify_signature(&self, body: &[u8], hex_signature: &str) -> bool {
+        decode_hex(hex_signature.trim())
+            .is_some_and(|signature| hmac::verify(&self.key, body, &signature).is_ok())
+    }
+
+    fn verify_token(&self, token: &str) -> bool {
+        hmac::verify(&self.key, token.as_bytes(), self.secret_tag.as_ref()).is_ok()
+    }
+}
+
+/// Git server that sent a delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookProvider {
+    Github,
+    Gitlab,
+    Gitea,
+}
+
+impl WebhookProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookProvider::Github => "github",
+            WebhookProvider::Gitlab => "gitlab",
+            WebhookProvider::Gitea => "gitea",
+        }
+    }
+}
+
+/// A delivery whose signature checked out.
+#[derive(Debug, Clone)]
+pub struct WebhookEvent {
+    pub provider: WebhookProvider,
+    /// Event name from the provider's event header.
+    pub event: String,
+    /// Tag the delivery created; `None` for branch pushes, tag deletions and
+    /// other events.
+    pub tag: Option<String>,
+    /// Every URL the payload gives for the pushed repository.
+    pub repo_urls: Vec<String>,
+    /// Provider delivery ID (`X-GitHub-Delivery`, `X-Gitea-Delivery`,
+    /// `X-Gitlab-Event-UUID`); redeliveries of one event share it.
+    pub delivery: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookError {
+    /// No provider headers, or a missing or wrong signature.
+    Unauthorized(String),
+    /// A verified body that is not a push payload.
+    Invalid(String),
+}
+
+impl std::fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookError::Unauthorized(detail) | WebhookError::Invalid(detail) => {
+                f.write_str(detail)
+            }
+        }
+    }
+}
+
+/// A release a verified tag push points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookTarget {
+    pub group: Option<RemoteGroup>,
+    pub version: String,
+    pub tag: String,
+    /// Planned under `[project_remote.webhook].pin`, so it may move the
+    /// source to an older release.
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookAction {
+    /// Sources to move, in configuration order. A monorepo serving several
+    /// groups yields one target per group.
+    Update(Vec<WebhookTarget>),
+    /// Nothing to do, and why.
+    Ignored(String),
+}
+
+/// Reads `[project_remote.webhook]`. `None` when project_remote or the
+/// webhook is disabled. A relative `secret_file` is resolved against the
+/// work root and, like the admin API token, must be readable by its owner
+/// only.
+pub fn load_webhook_conf<P: AsRef<Path>>(
+    work_root: P,
+    dict: &EnvDict,
+) -> RunResult<Option<ProjectRemoteWebhookConf>> {
+    let work_root = work_root.as_ref();
+    let conf = load_engine_config(work_root, dict)?;
+    if !conf.project_remote().enabled {
+        return Ok(None);
+    }
+    let Some(raw) = load_project_remote_ext(work_root, dict)?.webhook else {
+        return Ok(None);
+    };
+    if !raw.enabled {
+        return Ok(None);
+    }
+    let secret_file = raw.secret_file.trim();
+    if secret_file.is_empty() {
+        return Err(invalid_option_err(
+            "webhook",
+            "secret_file",
+            secret_file,
+            "must be set when the webhook is enabled",
+        ));
+    }
+    let path = work_root.join(secret_file);
+    let meta = fs::metadata(&path)
+        .map_err(|e| conf_err_source(format!("stat {} failed", path.display()), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = meta.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Err(invalid_option_err(
+                "webhook",
+                "secret_file",
+                secret_file,
+                &format!(
+                    "permissions {:o} are too permissive; require owner-only access",
+                    mode
+                ),
+            ));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = meta;
+    let secret = fs::read_to_string(&path)
+        .map_err(|e| conf_err_source(format!("read {} failed", path.display()), e))?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(invalid_option_err(
+            "webhook",
+            "secret_file",
+            secret_file,
+            "file is empty",
+        ));
+    }
+    Ok(Some(ProjectRemoteWebhookConf::from_secret(
+        secret.as_bytes(),
+    )))
+}
+
+/// Identifies the provider from its headers, checks the delivery against
+/// the shared secret and reads the push out of `body`. GitHub and Gitea
+/// sign the body with HMAC-SHA256 (`X-Hub-Signature-256`,
+/// `X-Gitea-Signature`); GitLab sends the secret as `X-Gitlab-Token`.
+/// `header` looks up a request header by its lowercase name.
+pub fn verify_webhook<'h, F>(
+    conf: &ProjectRemoteWebhookConf,
+    header: F,
+    body: &[u8],
+) -> Result<WebhookEvent, WebhookError>
+where
+    F: Fn(&str) -> Option<&'h str>,
+{
+    // Gitea also sends the GitHub headers, so it is recognised first.
+    let (provider, event) = if let Some(event) = header("x-gitea-event") {
+        let signature =
+            header("x-gitea-signature").ok_or_else(|| unauthorized("missing X-Gitea-Signature"))?;
+        if !conf.verify_signature(body, signature) {
+            return Err(unauthorized("X-Gitea-Signature does not match"));
+        }
+        (WebhookProvider::Gitea, event)
+    } else if let Some(event) = header("x-gitlab-event") {
+        let token =
+            header("x-gitlab-token").ok_or_else(|| unauthorized("missing X-Gitlab-Token"))?;
+        if !conf.verify_token(token) {
+            return Err(unauthorized("X-Gitlab-Token does not match"));
+        }
+        (WebhookProvider::Gitlab, event)
+    } else if let Some(event) = header("x-github-event") {
+        let signature = header("x-hub-signature-256")
+            .and_then(|value| value.strip_prefix("sha256="))
+            .ok_or_else(|| unauthorized("missing X-Hub-Signature-256"))?;
+        if !conf.verify_signature(body, signature) {
+            return Err(unauthorized("X-Hub-Signature-256 does not match"));
+        }
+        (WebhookProvider::Github, event)
+    } else {
+        return Err(unauthorized(
+            "no X-GitHub-Event, X-Gitlab-Event or X-Gitea-Event header",
+        ));
+    };
+
+    let payload: PushPayload = serde_json::from_slice(body)
+        .map_err(|e| WebhookError::Invalid(format!("invalid webhook payload: {}", e)))?;
+    let is_tag_push = match provider {
+        WebhookProvider::Github | WebhookProvider::Gitea => event == "push",
+        WebhookProvider::Gitlab => event == "Tag Push Hook" || payload.object_kind == "tag_push",
+    };
+    let deleted = payload.deleted || (!payload.after.is_empty() && is_zero_oid(&payload.after));
+    let tag = payload
+        .git_ref
+        .strip_prefix(TAG_REF_PREFIX)
+        .filter(|tag| is_tag_push && !deleted && !tag.is_empty())
+        .map(str::to_string);
+    let repo_urls = payload
+        .repository
+        .iter()
+        .chain(payload.project.iter())
+        .flat_map(RepoPayload::urls)
+        .collect();
+    let delivery = match provider {
+        WebhookProvider::Github => header("x-github-delivery"),
+        WebhookProvider::Gitea => {
+            header("x-gitea-delivery").or_else(|| header("x-github-delivery"))
+        }
+        WebhookProvider::Gitlab => header("x-gitlab-event-uuid"),
+    }
+    .map(str::trim)
+    .filter(|id| !id.is_empty())
+    .map(str::to_string);
+    Ok(WebhookEvent {
+        provider,
+        event: event.to_string(),
+        tag,
+        repo_urls,
+        delivery,
+    })
+}
+
+/// Maps a verified delivery to the configured sources it releases: the tag
+/// must be a release version and the repository one of the sources'. Sources
+/// that follow a `track_branch` do not take tags and are skipped. The
+/// signature only covers the body, so a replayed delivery of an old tag
+/// still verifies; unless `[project_remote.webhook].pin` is set, a source
+/// only moves to a release that `[project_remote.poll].policy` allows over
+/// the deployed one.
+pub fn plan_webhook_action<P: AsRef<Path>>(
+    work_root: P,
+    dict: &EnvDict,
+    event: &WebhookEvent,
+) -> RunResult<WebhookAction> {
+    let work_root = work_root.as_ref();
+    let Some(tag) = event.tag.as_deref() else {
+        return Ok(WebhookAction::Ignored(format!(
+            "{} event is not a tag push",
+            event.event
+        )));
+    };
+    let version = tag.strip_prefix('v').unwrap_or(tag);
+    let Ok(candidate) = Version::parse(version) else {
+        return Ok(WebhookAction::Ignored(format!(
+            "tag {} is not a release version",
+            tag
+        )));
+    };
+    let raw = load_project_remote_ext(work_root, dict)?;
+    let pin = raw.webhook.as_ref().is_some_and(|webhook| webhook.pin);
+    let policy = poll_policy(raw.poll.as_ref())?;
+    let state = load_state(work_root)?;
+    let pushed: BTreeSet<String> = event
+        .repo_urls
+        .iter()
+        .map(|url| normalize_repo_url(url))
+        .collect();
+    let mut targets = Vec::new();
+    let mut tracking = Vec::new();
+    let mut stale = Vec::new();
+    for (group, source) in configured_sources(work_root, dict)? {
+        if !pushed.contains(&normalize_repo_url(&source.repo)) {
+            continue;
+        }
+        if source.track_branch.is_some() {
+            tracking.push(
+                group
+                    .as_ref()
+                    .map(RemoteGroup::name)
+                    .unwrap_or("-")
+                    .to_string(),
+            );
+            continue;
+        }
+        let deployed = deployed_version(state.as_ref(), group.as_ref());
+        if let Some(current) = deployed.and_then(|v| Version::parse(v).ok()) {
+            if !pin && !policy.allows(&current, &candidate) {
+                stale.push(format!(
+                    "{}{} is deployed",
+                    group
+                        .as_ref()
+                        .map(|g| format!("{} ", g.name()))
+                        .unwrap_or_default(),
+                    current
+                ));
+                continue;
+            }
+        }
+        targets.push(WebhookTarget {
+            group,
+            version: version.to_string(),
+            tag: tag.to_string(),
+            pinned: pin,
+        });
+    }
+    if !targets.is_empty() {
+        return Ok(WebhookAction::Update(targets));
+    }
+    Ok(WebhookAction::Ignored(if !stale.is_empty() {
+        format!(
+            "tag {} is not a release the {} poll policy allows ({})",
+            tag,
+            policy.as_str(),
+            stale.join(", ")
+        )
+    } else if tracking.is_empty() {
+        "repository is not a configured project_remote source".to_string()
+    } else {
+        format!(
+            "sources {} follow track_branch, not tags",
+            tracking.join(", ")
+        )
+    }))
+}
+
+/// Drops targets that the deployed releases have caught up with since they
+/// were planned, e.g. an older tag queued behind a newer one. Pinned targets
+/// are kept. Callers hold `acquire_project_remote_lock`.
+pub fn recheck_webhook_targets<P: AsRef<Path>>(
+    work_root: P,
+    dict: &EnvDict,
+    targets: Vec<WebhookTarget>,
+) -> RunResult<Vec<WebhookTarget>> {
+    let work_root = work_root.as_ref();
+    let policy = poll_policy(load_project_remote_ext(work_root, dict)?.poll.as_ref())?;
+    let state = load_state(work_root)?;
+    Ok(targets
+        .into_iter()
+        .filter(|target| {
+            let current = deployed_version(state.as_ref(), target.group.as_ref())
+                .and_then(|v| Version::parse(v).ok());
+            match (current, Version::parse(&target.version)) {
+                (Some(current), Ok(candidate)) if !target.pinned => {
+                    policy.allows(&current, &candidate)
+                }
+                _ => true,
+            }
+        })
+        .collect())
+}
+
+fn deployed_version<'s>(
+    state: Option<&'s ProjectRemoteState>,
+    group: Option<&RemoteGroup>,
+) -> Option<&'s str> {
+    match (state?, group) {
+        (state, None) => state.single_version(),
+        (state, Some(group)) => state.group(group).map(|g| g.current_version.as_str()),
+    }
+}
+
+/// Webhook updates waiting for the reload gate. Deliveries that arrive while
+/// one is pending are merged into it instead of queueing behind it, and
+/// redelivered IDs are dropped.
+#[derive(Debug, Default)]
+pub struct WebhookQueue {
+    seen: VecDeque<String>,
+    pending: Option<(String, Vec<WebhookTarget>)>,
+    running: bool,
+}
+
+impl WebhookQueue {
+    /// Records `delivery` and reports whether it was already seen.
+    pub fn is_duplicate(&mut self, delivery: &str) -> bool {
+        if self.seen.iter().any(|seen| seen == delivery) {
+            return true;
+        }
+        if self.seen.len() == SEEN_DELIVERIES {
+            self.seen.pop_front();
+        }
+        self.seen.push_back(delivery.to_string());
+        false
+    }
+
+    /// Queues `targets` under `request_id`. A pending target for the same
+    /// group is replaced by a pinned target or a higher release. Returns
+    /// `true` when no worker is draining the queue and the caller must start
+    /// one.
+    pub fn push(&mut self, request_id: String, targets: Vec<WebhookTarget>) -> bool {
+        match &mut self.pending {
+            Some((pending_id, pending)) => {
+                *pending_id = request_id;
+                for target in targets {
+                    match pending.iter_mut().find(|p| p.group == target.group) {
+                        Some(slot) if target.pinned || is_newer(&target, slot) => *slot = target,
+                        Some(_) => {}
+                        None => pending.push(target),
+                    }
+                }
+            }
+            None => self.pending = Some((request_id, targets)),
+        }
+        !std::mem::replace(&mut self.running, true)
+    }
+
+    /// Takes the pending update; `None` also marks the worker as stopped.
+    pub fn take(&mut self) -> Option<(String, Vec<WebhookTarget>)> {
+        let next = self.pending.take();
+        if next.is_none() {
+            self.running = false;
+        }
+        next
+    }
+}
+
+fn is_newer(target: &WebhookTarget, than: &WebhookTarget) -> bool {
+    match (
+        Version::parse(&target.version),
+        Version::parse(&than.version),
+    ) {
+        (Ok(target), Ok(than)) => target > than,
+        _ => true,
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PushPayload {
+    #[serde(rename = "ref", default)]
+    git_ref: String,
+    #[serde(default)]
+    deleted: bool,
+    #[serde(default)]
+    after: String,
+    #[serde(default)]
+    object_kind: String,
+    #[serde(default)]
+    repository: Option<RepoPayload>,
+    #[serde(default)]
+    project: Option<RepoPayload>,
+}
+
+/// URL fields of the GitHub/Gitea `repository` and GitLab `project` /
+/// `repository` objects.
+#[derive(Debug, Default, Deserialize)]
+struct RepoPayload {
+    #[serde(default)]
+    clone_url: Option<String>,
+    #[serde(default)]
+    ssh_url: Option<String>,
+    #[serde(default)]
+    html_url: Option<String>,
+    #[serde(default)]
+    git_http_url: Option<String>,
+    #[serde(default)]
+    git_ssh_url: Option<String>,
+    #[serde(default)]
+    web_url: Option<String>,
+    #[serde(default)]
+    homepage: Option<String>,
+}
+
+impl RepoPayload {
+    fn urls(&self) -> Vec<String> {
+        [
+            &self.clone_url,
+            &self.ssh_url,
+            &self.html_url,
+            &self.git_http_url,
+            &self.git_ssh_url,
+            &self.web_url,
+            &self.homepage,
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|url| !url.trim().is_empty())
+        .cloned()
+        .collect()
+    }
+}
+
+/// `https://Host/org/repo.git`, `ssh://git@host/org/repo` and
+/// `git@host:org/repo.git` all become `host/org/repo`; local paths only lose
+/// a trailing `/` or `.git`.
+fn normalize_repo_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let (rest, scp_like) = match url.split_once("://") {
+        Some((_, rest)) => (rest, false),
+        None => (url, !url.starts_with('/') && url.contains(':')),
+    };
+    let (authority, path) = if scp_like {
+        rest.split_once(':').unwrap_or((rest, ""))
+    } else {
+        rest.split_once('/').unwrap_or((rest, ""))
+    };
+    if authority.is_empty() {
+        return url.to_string();
+    }
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    format!(
+        "{}/{}",
+        host.to_ascii_lowercase(),
+        path.trim_start_matches('/')
+    )
+}
+
+fn is_zero_oid(oid: &str) -> bool {
+    oid.bytes().all(|b| b == b'0')
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => Some((hex_digit(*hi)? << 4) | hex_digit(*lo)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+fn unauthorized(detail: &str) -> WebhookError {
+    WebhookError::Unauthorized(detail.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const SECRET: &[u8] = b"webhook-secret";
+
+    // Trimmed captures of tag pushes for `wp-labs/wp-rule`.
+    const GITHUB_TAG_PUSH: &str = r#"{"ref":"refs/tags/v1.5.0","before":"0000000000000000000000000000000000000000","after":"5c2b4f0d9e1a7b3c6d8e0f1a2b3c4d5e6f708192","created":true,"deleted":false,"forced":false,"base_ref":"refs/heads/main","repository":{"id":812345678,"name":"wp-rule","full_name":"wp-labs/wp-rule","html_url":"https://github.com/wp-labs/wp-rule","clone_url":"https://github.com/wp-labs/wp-rule.git","ssh_url":"git@github.com:wp-labs/wp-rule.git","default_branch":"main"},"pusher":{"name":"release-bot"},"commits":[],"head_commit":{"id":"5c2b4f0d9e1a7b3c6d8e0f1a2b3c4d5e6f708192","message":"release 1.5.0"}}"#;
+    const GITLAB_TAG_PUSH: &str = r#"{"object_kind":"tag_push","event_name":"tag_push","before":"0000000000000000000000000000000000000000","after":"82b3d5ae55f7080f1e6022629cdb57bfae7cccc7","ref":"refs/tags/v1.5.0","checkout_sha":"82b3d5ae55f7080f1e6022629cdb57bfae7cccc7","user_username":"release-bot","project_id":42,"project":{"id":42,"name":"wp-rule","web_url":"https://gitlab.example.com/wp-labs/wp-rule","git_ssh_url":"git@gitlab.example.com:wp-labs/wp-rule.git","git_http_url":"https://gitlab.example.com/wp-labs/wp-rule.git","path_with_namespace":"wp-labs/wp-rule","default_branch":"main"},"repository":{"name":"wp-rule","url":"git@gitlab.example.com:wp-labs/wp-rule.git","homepage":"https://gitlab.example.com/wp-labs/wp-rule","git_http_url":"https://gitlab.example.com/wp-labs/wp-rule.git","git_ssh_url":"git@gitlab.example.com:wp-labs/wp-rule.git"},"commits":[],"total_commits_count":0}"#;
+    const GITEA_TAG_PUSH: &str = r#"{"ref":"refs/tags/v1.5.0","before":"0000000000000000000000000000000000000000","after":"9f1e2d3c4b5a69788796a5b4c3d2e1f009182736","compare_url":"https://git.example.com/wp-labs/wp-rule/compare/v1.5.0","commits":[],"total_commits":0,"repository":{"id":7,"name":"wp-rule","full_name":"wp-labs/wp-rule","html_url":"https://git.example.com/wp-labs/wp-rule","ssh_url":"ssh://git@git.example.com:2222/wp-labs/wp-rule.git","clone_url":"https://git.example.com/wp-labs/wp-rule.git","default_branch":"main"},"pusher":{"login":"release-bot"}}"#;
+
+    fn sign(body: &str) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET);
+        hmac::sign(&key, body.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn verify(headers: &[(&str, String)], body: &str) -> Result<WebhookEvent, WebhookError> {
+        let headers: BTreeMap<&str, &str> = headers
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        verify_webhook(
+            &ProjectRemoteWebhookConf::from_secret(SECRET),
+            |name| headers.get(name).copied(),
+            body.as_bytes(),
+        )
+    }
+
+    #[test]
+    fn github_tag_push_is_verified_and_read() {
+        let event = verify(
+            &[
+                ("x-github-event", "push".to_string()),
+                (
+                    "x-hub-signature-256",
+                    format!("sha256={}", sign(GITHUB_TAG_PUSH)),
+                ),
+            ],
+            GITHUB_TAG_PUSH,
+        )
+        .expect("verified");
+        assert_eq!(event.provider, WebhookProvider::Github);
+        assert_eq!(event.tag.as_deref(), Some("v1.5.0"));
+        assert!(event
+            .repo_urls
+            .contains(&"git@github.com:wp-labs/wp-rule.git".to_string()));
+    }
+
+    #[test]
+    fn gitlab_and_gitea_tag_pushes_are_verified() {
+        let gitlab = verify(
+            &[
+                ("x-gitlab-event", "Tag Push Hook".to_string()),
+                ("x-gitlab-token", "webhook-secret".to_string()),
+            ],
+            GITLAB_TAG_PUSH,
+        )
+        .expect("gitlab verified");
+        assert_eq!(gitlab.provider, WebhookProvider::Gitlab);
+        assert_eq!(gitlab.tag.as_deref(), Some("v1.5.0"));
+
+        // Gitea sends the GitHub headers too.
+        let gitea = verify(
+            &[
+                ("x-gitea-event", "push".to_string()),
+                ("x-github-event", "push".to_string()),
+                ("x-gitea-signature", sign(GITEA_TAG_PUSH)),
+            ],
+            GITEA_TAG_PUSH,
+        )
+        .expect("gitea verified");
+        assert_eq!(gitea.provider, WebhookProvider::Gitea);
+        assert_eq!(gitea.tag.as_deref(), Some("v1.5.0"));
+    }
+
+    #[test]
+    fn wrong_or_missing_signatures_are_rejected() {
+        let tampered = GITHUB_TAG_PUSH.replace("v1.5.0", "v9.9.9");
+        let err = verify(
+            &[
+                ("x-github-event", "push".to_string()),
+                (
+                    "x-hub-signature-256",
+                    format!("sha256={}", sign(GITHUB_TAG_PUSH)),
+                ),
+            ],
+            &tampered,
+        )
+        .expect_err("tampered body");
+        assert!(matches!(err, WebhookError::Unauthorized(_)));
+        let err = verify(
+            &[
+                ("x-gitlab-event", "Tag Push Hook".to_string()),
+                ("x-gitlab-token", "guess".to_string()),
+            ],
+            GITLAB_TAG_PUSH,
+        )
+        .expect_err("wrong token");
+        assert_eq!(
+            err,
+            WebhookError::Unauthorized("X-Gitlab-Token does not match".to_string())
+        );
+        let err = verify(&[], GITHUB_TAG_PUSH).expect_err("no provider");
+        assert!(matches!(err, WebhookError::Unauthorized(_)));
+    }
+
+    #[test]
+    fn deletions_and_branch_pushes_carry_no_tag() {
+        let deleted = GITHUB_TAG_PUSH.replace("\"deleted\":false", "\"deleted\":true");
+        let event = verify(
+            &[
+                ("x-github-event", "push".to_string()),
+                ("x-hub-signature-256", format!("sha256={}", sign(&deleted))),
+            ],
+            &deleted,
+        )
+        .expect("verified");
+        assert_eq!(event.tag, None);
+        let branch = GITEA_TAG_PUSH.replace("refs/tags/v1.5.0", "refs/heads/main");
+        let event = verify(
+            &[
+                ("x-gitea-event", "push".to_string()),
+                ("x-gitea-signature", sign(&branch)),
+            ],
+            &branch,
+        )
+        .expect("verified");
+        assert_eq!(event.tag, None);
+    }
+
+    #[test]
+    fn delivery_ids_are_read_per_provider() {
+        let github = verify(
+            &[
+                ("x-github-event", "push".to_string()),
+                ("x-github-delivery", "72d3162e-cc78".to_string()),
+                (
+                    "x-hub-signature-256",
+                    format!("sha256={}", sign(GITHUB_TAG_PUSH)),
+                ),
+            ],
+            GITHUB_TAG_PUSH,
+        )
+        .expect("verified");
+        assert_eq!(github.delivery.as_deref(), Some("72d3162e-cc78"));
+        let gitlab = verify(
+            &[
+                ("x-gitlab-event", "Tag Push Hook".to_string()),
+                ("x-gitlab-token", "webhook-secret".to_string()),
+                ("x-gitlab-event-uuid", "13792a34-cac6".to_string()),
+            ],
+            GITLAB_TAG_PUSH,
+        )
+        .expect("verified");
+        assert_eq!(gitlab.delivery.as_deref(), Some("13792a34-cac6"));
+        let gitea = verify(
+            &[
+                ("x-gitea-event", "push".to_string()),
+                ("x-gitea-signature", sign(GITEA_TAG_PUSH)),
+            ],
+            GITEA_TAG_PUSH,
+        )
+        .expect("verified");
+        assert_eq!(gitea.delivery, None);
+    }
+
+    fn target(group: Option<RemoteGroup>, version: &str, pinned: bool) -> WebhookTarget {
+        WebhookTarget {
+            group,
+            version: version.to_string(),
+            tag: format!("v{}", version),
+            pinned,
+        }
+    }
+
+    #[test]
+    fn queue_drops_redelivered_ids() {
+        let mut queue = WebhookQueue::default();
+        assert!(!queue.is_duplicate("a"));
+        assert!(queue.is_duplicate("a"));
+        for i in 0..SEEN_DELIVERIES {
+            assert!(!queue.is_duplicate(&i.to_string()));
+        }
+        // The oldest ID has been forgotten.
+        assert!(!queue.is_duplicate("a"));
+    }
+
+    #[test]
+    fn queue_coalesces_pending_updates_per_group() {
+        let mut queue = WebhookQueue::default();
+        assert!(queue.push("r1".to_string(), vec![target(None, "1.5.0", false)]));
+        assert!(!queue.push("r2".to_string(), vec![target(None, "1.4.4", false)]));
+        assert!(!queue.push(
+            "r3".to_string(),
+            vec![target(Some(RemoteGroup::Infra), "0.2.0", false)]
+        ));
+        assert_eq!(
+            queue.take(),
+            Some((
+                "r3".to_string(),
+                vec![
+                    target(None, "1.5.0", false),
+                    target(Some(RemoteGroup::Infra), "0.2.0", false)
+                ]
+            ))
+        );
+
+        // The worker is still draining, so a new delivery does not start
+        // another one; a pinned target replaces a higher pending release.
+        assert!(!queue.push("r4".to_string(), vec![target(None, "1.6.0", false)]));
+        assert!(!queue.push("r5".to_string(), vec![target(None, "1.4.2", true)]));
+        assert_eq!(
+            queue.take(),
+            Some(("r5".to_string(), vec![target(None, "1.4.2", true)]))
+        );
+        assert_eq!(queue.take(), None);
+        assert!(queue.push("r6".to_string(), vec![target(None, "1.6.0", false)]));
+    }
+
+    #[test]
+    fn repo_urls_normalize_across_transports() {
+        let expected = "github.com/wp-labs/wp-rule";
+        for url in [
+            "https://github.com/wp-labs/wp-rule.git",
+            "https://GitHub.com/wp-labs/wp-rule/",
+            "ssh://git@github.com/wp-labs/wp-rule.git",
+            "git@github.com:wp-labs/wp-rule.git",
+        ] {
+            assert_eq!(normalize_repo_url(url), expected, "{}", url);
+        }
+        assert_eq!(
+            normalize_repo_url("/srv/git/wp-rule.git/"),
+            "/srv/git/wp-rule"
+        );
+    }
+}
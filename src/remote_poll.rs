@@ -0,0 +1,454 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::Utc;
+use orion_variate::EnvDict;
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use wp_engine::facade::RuntimeControlHandle;
+use wp_error::run_error::RunResult;
+use wp_log::{info_ctrl, warn_ctrl};
+
+use crate::admin_api::{update_and_reload, ReloadCoordinator, UnattendedReload};
+use crate::project_remote::{self, PendingUpdate, PollPolicy, ProjectRemotePollConf, RemoteGroup};
+
+/// How long a poll waits for the runtime to finish a reload before leaving
+/// it to the background monitor.
+const POLL_RELOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const POLL_ORIGIN: &str = "project_remote_poll";
+
+/// Background task that keeps a daemon on the newest release the
+/// `[project_remote.poll]` policy allows.
+#[derive(Debug)]
+pub struct ProjectRemotePoller {
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl ProjectRemotePoller {
+    pub async fn shutdown(mut self) {
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+        let _ = self.task.await;
+    }
+}
+
+/// Poller state reported under `project_remote_poll` in
+/// `GET /admin/v1/runtime/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectRemotePollStatus {
+    pub policy: &'static str,
+    pub interval_secs: u64,
+    pub last_poll_at: Option<String>,
+    pub last_result: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_applied: Option<String>,
+    pub consecutive_failures: u32,
+    pub next_poll_at: Option<String>,
+}
+
+pub async fn start_if_enabled(
+    work_root: &Path,
+    dict: &EnvDict,
+    control_handle: RuntimeControlHandle,
+    coordinator: ReloadCoordinator,
+) -> RunResult<Option<ProjectRemotePoller>> {
+    let Some(conf) = project_remote::load_poll_conf(work_root, dict)? else {
+        return Ok(None);
+    };
+    info_ctrl!(
+        "project remote poll enabled work_root={} interval_secs={} jitter_secs={} max_backoff_secs={} policy={}",
+        work_root.display(),
+        conf.interval.as_secs(),
+        conf.jitter.as_secs(),
+        conf.max_backoff.as_secs(),
+        conf.policy.as_str()
+    );
+    coordinator.set_poll_status(ProjectRemotePollStatus {
+        policy: conf.policy.as_str(),
+        interval_secs: conf.interval.as_secs(),
+        last_poll_at: None,
+        last_result: None,
+        last_error: None,
+        last_applied: None,
+        consecutive_failures: 0,
+        next_poll_at: None,
+    });
+    let poll = PollContext {
+        work_root: work_root.to_path_buf(),
+        dict: dict.clone(),
+        control_handle,
+        coordinator,
+        conf,
+    };
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let task = tokio::spawn(run_poll_loop(poll, shutdown_rx));
+    Ok(Some(ProjectRemotePoller {
+        shutdown_tx: Some(shutdown_tx),
+        task,
+    }))
+}
+
+struct PollContext {
+    work_root: PathBuf,
+    dict: EnvDict,
+    control_handle: RuntimeControlHandle,
+    coordinator: ReloadCoordinator,
+    conf: ProjectRemotePollConf,
+}
+
+/// Target tag that failed the validation gate, per group (`None` for the
+/// single remote). A poll skips the group until a newer tag shows up.
+type RejectedTags = BTreeMap<Option<RemoteGroup>, String>;
+
+enum PollOutcome {
+    UpToDate,
+    /// Another update or reload holds the gate, the lock or the runtime.
+    Busy(String),
+    Updated(Vec<String>),
+    /// Every pending release was already rejected by the validation gate.
+    HeldBack(Vec<String>),
+    ReloadRunning(String),
+    /// At least one release failed the validation gate; `applied` lists the
+    /// groups that were still updated.
+    Blocked {
+        applied: Vec<String>,
+        detail: String,
+    },
+    Failed(String),
+}
+
+impl PollOutcome {
+    fn code(&self) -> &'static str {
+        match self {
+            PollOutcome::UpToDate => "up_to_date",
+            PollOutcome::Busy(_) => "busy",
+            PollOutcome::Updated(_) => "updated",
+            PollOutcome::HeldBack(_) => "held_back",
+            PollOutcome::ReloadRunning(_) => "reload_running",
+            PollOutcome::Blocked { .. } => "validation_failed",
+            PollOutcome::Failed(_) => "failed",
+        }
+    }
+}
+
+async fn run_poll_loop(poll: PollContext, mut shutdown_rx: oneshot::Receiver<()>) {
+    let mut consecutive_failures = 0u32;
+    let mut rejected = RejectedTags::new();
+    loop {
+        let delay = poll.conf.next_delay(consecutive_failures);
+        let next_poll_at = Utc::now()
+            + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::zero());
+        poll.coordinator.update_poll_status(|status| {
+            status.next_poll_at = Some(next_poll_at.to_rfc3339());
+        });
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = &mut shutdown_rx => break,
+        }
+
+        let outcome = poll_once(&poll, &mut rejected).await;
+        match &outcome {
+            PollOutcome::UpToDate => {
+                consecutive_failures = 0;
+                info_ctrl!(
+                    "project remote poll up to date work_root={}",
+                    poll.work_root.display()
+                );
+            }
+            PollOutcome::Busy(reason) => {
+                info_ctrl!(
+                    "project remote poll skipped work_root={} reason={}",
+                    poll.work_root.display(),
+                    reason
+                );
+            }
+            PollOutcome::Updated(applied) => {
+                consecutive_failures = 0;
+                info_ctrl!(
+                    "project remote poll updated work_root={} applied={}",
+                    poll.work_root.display(),
+                    applied.join(",")
+                );
+            }
+            PollOutcome::HeldBack(held) => {
+                info_ctrl!(
+                    "project remote poll held back work_root={} rejected={}",
+                    poll.work_root.display(),
+                    held.join(",")
+                );
+            }
+            PollOutcome::ReloadRunning(applied) => {
+                consecutive_failures = 0;
+                info_ctrl!(
+                    "project remote poll reload still running work_root={} applied={}",
+                    poll.work_root.display(),
+                    applied
+                );
+            }
+            PollOutcome::Blocked { detail, .. } | PollOutcome::Failed(detail) => {
+                consecutive_failures = consecutive_failures.saturating_add(1);
+                warn_ctrl!(
+                    "project remote poll failed work_root={} result={} consecutive_failures={} error={}",
+                    poll.work_root.display(),
+                    outcome.code(),
+                    consecutive_failures,
+                    detail
+                );
+            }
+        }
+        poll.coordinator.update_poll_status(|status| {
+            status.last_poll_at = Some(Utc::now().to_rfc3339());
+            status.last_result = Some(outcome.code());
+            status.consecutive_failures = consecutive_failures;
+            status.last_error = match &outcome {
+                PollOutcome::Blocked { detail, .. } | PollOutcome::Failed(detail) => {
+                    Some(detail.clone())
+                }
+                _ => None,
+            };
+            match &outcome {
+                PollOutcome::Updated(applied) => status.last_applied = Some(applied.join(",")),
+                PollOutcome::Blocked { applied, .. } if !applied.is_empty() => {
+                    status.last_applied = Some(applied.join(","))
+                }
+                PollOutcome::ReloadRunning(applied) => status.last_applied = Some(applied.clone()),
+                _ => {}
+            }
+        });
+    }
+    info_ctrl!(
+        "project remote poll stopped work_root={}",
+        poll.work_root.display()
+    );
+}
+
+async fn poll_once(poll: &PollContext, rejected: &mut RejectedTags) -> PollOutcome {
+    // Same gate as the admin API, so a poll never interleaves with a
+    // manual reload.
+    let Some(_reload_guard) = poll.coordinator.try_gate() else {
+        return PollOutcome::Busy("reload in progress".to_string());
+    };
+    let runtime_status = poll.control_handle.status_snapshot();
+    if !runtime_status.accepting_commands {
+        return PollOutcome::Busy("runtime not ready".to_string());
+    }
+    if runtime_status.reloading {
+        return PollOutcome::Busy("reload in progress".to_string());
+    }
+
+    let pending = match check_pending(&poll.work_root, &poll.dict, poll.conf.policy).await {
+        Ok(pending) => pending,
+        Err(outcome) => return outcome,
+    };
+    if pending.is_empty() {
+        return PollOutcome::UpToDate;
+    }
+    let (pending, held) = hold_back_rejected(pending, rejected);
+    if pending.is_empty() {
+        return PollOutcome::HeldBack(held);
+    }
+
+    let mut applied = Vec::new();
+    let mut blocked = Vec::new();
+    for update in pending {
+        let lock_guard = match project_remote::acquire_project_remote_lock(&poll.work_root) {
+            Ok(lock) => lock,
+            Err(err) => return PollOutcome::Busy(err.to_string()),
+        };
+        let request_id = format!("{}-{}", POLL_ORIGIN, Uuid::new_v4());
+        info_ctrl!(
+            "project remote poll update start request_id={} work_root={} group={} current_version={} target_version={} target_tag={}",
+            request_id,
+            poll.work_root.display(),
+            update.group.as_ref().map(|g| g.name()).unwrap_or("-"),
+            update.current_version.as_deref().unwrap_or("-"),
+            update.target_version,
+            update.target_tag
+        );
+        let outcome = update_and_reload(
+            &poll.work_root,
+            &poll.dict,
+            &poll.control_handle,
+            lock_guard,
+            &request_id,
+            POLL_ORIGIN,
+            update.group.clone(),
+            update.requested_version(),
+            POLL_RELOAD_TIMEOUT,
+        )
+        .await;
+        match outcome {
+            Ok(UnattendedReload::Reloaded(result)) | Ok(UnattendedReload::Unchanged(result)) => {
+                rejected.remove(&update.group);
+                applied.push(applied_label(&update, &result.current_version));
+            }
+            Ok(UnattendedReload::Running(result)) => {
+                rejected.remove(&update.group);
+                applied.push(applied_label(&update, &result.current_version));
+                return PollOutcome::ReloadRunning(applied.join(","));
+            }
+            Ok(UnattendedReload::Blocked(result)) => {
+                let summary = result
+                    .validation
+                    .as_ref()
+                    .map(project_remote::ProjectRemoteValidation::summary)
+                    .unwrap_or_default();
+                warn_ctrl!(
+                    "project remote poll rejected request_id={} work_root={} group={} target_tag={} error={}",
+                    request_id,
+                    poll.work_root.display(),
+                    update.group.as_ref().map(|g| g.name()).unwrap_or("-"),
+                    update.target_tag,
+                    summary
+                );
+                blocked.push(format!(
+                    "{} blocked by validation: {}",
+                    result.resolved_tag, summary
+                ));
+                rejected.insert(update.group.clone(), update.target_tag.clone());
+            }
+            Err(err) => return PollOutcome::Failed(err.to_string()),
+        }
+    }
+    if blocked.is_empty() {
+        PollOutcome::Updated(applied)
+    } else {
+        PollOutcome::Blocked {
+            applied,
+            detail: blocked.join("; "),
+        }
+    }
+}
+
+/// Splits off the updates whose target tag already failed validation; the
+/// second list labels them for the log.
+fn hold_back_rejected(
+    pending: Vec<PendingUpdate>,
+    rejected: &RejectedTags,
+) -> (Vec<PendingUpdate>, Vec<String>) {
+    let mut held = Vec::new();
+    let pending = pending
+        .into_iter()
+        .filter(|update| {
+            let is_rejected = rejected.get(&update.group) == Some(&update.target_tag);
+            if is_rejected {
+                held.push(applied_label(update, &update.target_tag));
+            }
+            !is_rejected
+        })
+        .collect();
+    (pending, held)
+}
+
+/// Looks for newer releases under the project remote lock. The fetch is
+/// blocking git I/O, so it runs on the blocking pool rather than on a
+/// runtime worker.
+async fn check_pending(
+    work_root: &Path,
+    dict: &EnvDict,
+    policy: PollPolicy,
+) -> Result<Vec<PendingUpdate>, PollOutcome> {
+    let work_root = work_root.to_path_buf();
+    let dict = dict.clone();
+    tokio::task::spawn_blocking(move || {
+        let _lock_guard = project_remote::acquire_project_remote_lock(&work_root)
+            .map_err(|err| PollOutcome::Busy(err.to_string()))?;
+        project_remote::check_project_remote_updates(&work_root, &dict, policy)
+            .map_err(|err| PollOutcome::Failed(err.to_string()))
+    })
+    .await
+    .unwrap_or_else(|err| {
+        Err(PollOutcome::Failed(format!(
+            "project remote poll task failed: {}",
+            err
+        )))
+    })
+}
+
+fn applied_label(update: &PendingUpdate, version: &str) -> String {
+    match update.group.as_ref() {
+        Some(group) => format!("{}={}", group.name(), version),
+        None => version.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn check_pending_is_busy_while_the_lock_is_held() {
+        let work_root = tempdir().expect("tempdir");
+        let _held = project_remote::acquire_project_remote_lock(work_root.path()).expect("lock");
+
+        let outcome = check_pending(work_root.path(), &EnvDict::default(), PollPolicy::Latest)
+            .await
+            .expect_err("locked work root");
+
+        assert_eq!(outcome.code(), "busy");
+    }
+
+    #[tokio::test]
+    async fn check_pending_fails_without_project_remote_config() {
+        let work_root = tempdir().expect("tempdir");
+
+        let outcome = check_pending(work_root.path(), &EnvDict::default(), PollPolicy::Latest)
+            .await
+            .expect_err("no engine config");
+
+        assert_eq!(outcome.code(), "failed");
+        // The lock is released once the check returns.
+        project_remote::acquire_project_remote_lock(work_root.path()).expect("lock again");
+    }
+
+    #[test]
+    fn applied_label_names_the_group() {
+        let update = PendingUpdate {
+            group: Some("models".parse().expect("group")),
+            current_version: Some("1.4.2".to_string()),
+            target_version: "1.4.3".to_string(),
+            target_tag: "v1.4.3".to_string(),
+        };
+        assert_eq!(applied_label(&update, "1.4.3"), "models=1.4.3");
+        let single = PendingUpdate {
+            group: None,
+            ..update
+        };
+        assert_eq!(applied_label(&single, "1.4.3"), "1.4.3");
+    }
+
+    #[test]
+    fn hold_back_rejected_skips_only_the_rejected_tag() {
+        let models: RemoteGroup = "models".parse().expect("group");
+        let infra: RemoteGroup = "infra".parse().expect("group");
+        let pending = |group: &RemoteGroup, tag: &str| PendingUpdate {
+            group: Some(group.clone()),
+            current_version: Some("1.4.2".to_string()),
+            target_version: tag.trim_start_matches('v').to_string(),
+            target_tag: tag.to_string(),
+        };
+        let mut rejected = RejectedTags::new();
+        rejected.insert(Some(models.clone()), "v1.4.3".to_string());
+
+        let (left, held) = hold_back_rejected(
+            vec![pending(&models, "v1.4.3"), pending(&infra, "v1.1.0")],
+            &rejected,
+        );
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].group, Some(infra));
+        assert_eq!(held, vec!["models=v1.4.3"]);
+
+        // A newer tag for the same group is tried again.
+        let (left, held) = hold_back_rejected(vec![pending(&models, "v1.4.4")], &rejected);
+        assert_eq!(left.len(), 1);
+        assert!(held.is_empty());
+    }
+}
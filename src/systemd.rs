@@ -0,0 +1,546 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use wp_engine::facade::RuntimeControlHandle;
+use wp_log::{info_ctrl, warn_ctrl};
+
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+const WATCHDOG_USEC_ENV: &str = "WATCHDOG_USEC";
+const WATCHDOG_PID_ENV: &str = "WATCHDOG_PID";
+/// How often `STATUS=` is refreshed when no watchdog is configured.
+const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// A runtime that does not answer a status read this fast counts as hung.
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(5);
+/// A reload running longer than this counts as hung.
+const RELOAD_STALL_LIMIT: Duration = Duration::from_secs(600);
+
+/// Cumulative number of events the engine has processed, sampled on each
+/// tick for the events/s figure in `STATUS=`; `None` while unavailable.
+pub type EventCounter = Arc<dyn Fn() -> Option<u64> + Send + Sync>;
+
+/// Datagram socket to the service manager (`sd_notify(3)`).
+#[derive(Debug)]
+pub struct SdNotifier {
+    #[cfg(target_os = "linux")]
+    socket: std::os::unix::net::UnixDatagram,
+    #[cfg(target_os = "linux")]
+    addr: std::os::unix::net::SocketAddr,
+}
+
+impl SdNotifier {
+    /// Reads `NOTIFY_SOCKET`; `None` when the daemon does not run under a
+    /// `Type=notify` unit.
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var_os(NOTIFY_SOCKET_ENV)?;
+        match Self::connect(Path::new(&path)) {
+            Ok(notifier) => Some(notifier),
+            Err(err) => {
+                warn_ctrl!(
+                    "systemd notify disabled: NOTIFY_SOCKET {} unusable: {}",
+                    Path::new(&path).display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// A leading `@` names a socket in the abstract namespace.
+    #[cfg(target_os = "linux")]
+    fn connect(path: &Path) -> std::io::Result<Self> {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+        let raw = path.as_os_str().as_bytes();
+        let addr = match raw.strip_prefix(b"@") {
+            Some(name) => SocketAddr::from_abstract_name(name)?,
+            None => SocketAddr::from_pathname(path)?,
+        };
+        Ok(Self {
+            socket: UnixDatagram::unbound()?,
+            addr,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn connect(_path: &Path) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "sd_notify is only supported on Linux",
+        ))
+    }
+
+    /// Sends newline-separated `KEY=VALUE` assignments.
+    pub fn notify(&self, state: &str) {
+        if let Err(err) = self.send(state) {
+            warn_ctrl!("systemd notify failed state={:?} error={}", state, err);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn send(&self, state: &str) -> std::io::Result<()> {
+        self.socket
+            .send_to_addr(state.as_bytes(), &self.addr)
+            .map(|_| ())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn send(&self, _state: &str) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Registers listeners passed by systemd socket activation (`LISTEN_FDS`
+/// addressed to this process) so the admin API adopts its socket; the
+/// engine's sources bind their own. Run before the async runtime is built.
+pub fn take_socket_activation() {
+    if let Some(names) = crate::hot_restart::register_inherited() {
+        info_ctrl!("systemd socket activation listeners={}", names);
+    }
+}
+
+/// Background task that reports readiness, status and watchdog pings to
+/// systemd while the daemon runs.
+#[derive(Debug)]
+pub struct SystemdSupervisor {
+    notifier: Arc<SdNotifier>,
+    stopping: Arc<AtomicBool>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+}
+
+impl SystemdSupervisor {
+    /// Sends `STOPPING=1` unless the supervisor already saw the drain begin.
+    pub async fn stopping(mut self) {
+        notify_stopping(&self.notifier, &self.stopping);
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+        let _ = self.task.await;
+    }
+}
+
+/// Starts the supervisor when `NOTIFY_SOCKET` is set. `READY=1` is sent
+/// once the runtime accepts commands, i.e. after the sources and the admin
+/// API are up; call this after `admin_api::start_with_coordinator`.
+/// `events` feeds the throughput in `STATUS=`.
+pub fn start_if_notify_socket(
+    work_root: &Path,
+    control_handle: RuntimeControlHandle,
+    events: Option<EventCounter>,
+) -> Option<SystemdSupervisor> {
+    let notifier = Arc::new(SdNotifier::from_env()?);
+    let stopping = Arc::new(AtomicBool::new(false));
+    let watchdog = watchdog_interval(
+        std::env::var(WATCHDOG_USEC_ENV).ok().as_deref(),
+        std::env::var(WATCHDOG_PID_ENV).ok().as_deref(),
+        std::process::id(),
+    );
+    info_ctrl!(
+        "systemd notify enabled watchdog_ms={}",
+        watchdog.map(|d| d.as_millis()).unwrap_or(0)
+    );
+    let ctx = SupervisorContext {
+        notifier: notifier.clone(),
+        stopping: stopping.clone(),
+        work_root: work_root.to_path_buf(),
+        control_handle,
+        watchdog,
+        events,
+    };
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let task = tokio::spawn(run_supervisor(ctx, shutdown_rx));
+    Some(SystemdSupervisor {
+        notifier,
+        stopping,
+        shutdown_tx: Some(shutdown_tx),
+        task,
+    })
+}
+
+struct SupervisorContext {
+    notifier: Arc<SdNotifier>,
+    stopping: Arc<AtomicBool>,
+    work_root: PathBuf,
+    control_handle: RuntimeControlHandle,
+    watchdog: Option<Duration>,
+    events: Option<EventCounter>,
+}
+
+async fn run_supervisor(ctx: SupervisorContext, mut shutdown_rx: oneshot::Receiver<()>) {
+    let drain = drain_requested();
+    tokio::pin!(drain);
+    loop {
+        if probe_runtime(&ctx.control_handle)
+            .await
+            .is_some_and(|probe| probe.accepting)
+        {
+            break;
+        }
+        tokio::select! {
+            _ = &mut shutdown_rx => return,
+            _ = &mut drain => {
+                notify_stopping(&ctx.notifier, &ctx.stopping);
+                return;
+            }
+            _ = tokio::time::sleep(READY_POLL_INTERVAL) => {}
+        }
+    }
+    let mut throughput = Throughput::default();
+    let probe = probe_runtime(&ctx.control_handle).await;
+    let events_per_sec = sample_events(&ctx, &mut throughput);
+    let mut status = current_status(&ctx, probe.as_ref(), events_per_sec);
+    // MAINPID lets a hot-restarted successor take over the unit.
+    ctx.notifier.notify(&format!(
+        "READY=1\nMAINPID={}\nSTATUS={}",
+        std::process::id(),
+        status
+    ));
+    info_ctrl!(
+        "systemd notified ready pid={} status={}",
+        std::process::id(),
+        status
+    );
+
+    let mut ticker = tokio::time::interval(ctx.watchdog.unwrap_or(STATUS_INTERVAL));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_rx => return,
+            // SIGTERM starts the engine's drain; report it right away rather
+            // than on the next tick or after the pipelines have stopped.
+            _ = &mut drain => {
+                notify_stopping(&ctx.notifier, &ctx.stopping);
+                return;
+            }
+            _ = ticker.tick() => {}
+        }
+        // The successor reports for the unit from here on.
+        if crate::hot_restart::handed_off() {
+            return;
+        }
+        let probe = probe_runtime(&ctx.control_handle).await;
+        // Once ready, the runtime only stops accepting commands to drain.
+        if probe
+            .as_ref()
+            .is_some_and(|probe| !probe.accepting && !probe.reloading)
+        {
+            notify_stopping(&ctx.notifier, &ctx.stopping);
+            return;
+        }
+        let mut message = String::new();
+        if ctx.watchdog.is_some() {
+            if runtime_alive(probe.as_ref(), SystemTime::now()) {
+                message.push_str("WATCHDOG=1\n");
+            } else {
+                warn_ctrl!(
+                    "systemd watchdog ping withheld: runtime unresponsive or reload stalled"
+                );
+            }
+        }
+        let events_per_sec = sample_events(&ctx, &mut throughput);
+        let next = current_status(&ctx, probe.as_ref(), events_per_sec);
+        if next != status {
+            message.push_str(&format!("STATUS={}\n", next));
+            status = next;
+        }
+        if !message.is_empty() {
+            ctx.notifier.notify(message.trim_end());
+        }
+    }
+}
+
+/// Resolves when the process receives `SIGTERM`, which starts the engine's
+/// drain.
+#[cfg(unix)]
+async fn drain_requested() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut term) => {
+            term.recv().await;
+        }
+        Err(err) => {
+            warn_ctrl!("systemd drain signal unavailable: {}", err);
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn drain_requested() {
+    std::future::pending::<()>().await
+}
+
+/// Runtime state read for one tick.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RuntimeProbe {
+    accepting: bool,
+    reloading: bool,
+    request_id: Option<String>,
+    reload_started_at: Option<SystemTime>,
+}
+
+/// Reads the runtime state off the async workers; `None` when the engine
+/// does not answer within `LIVENESS_TIMEOUT`.
+async fn probe_runtime(control_handle: &RuntimeControlHandle) -> Option<RuntimeProbe> {
+    let control_handle = control_handle.clone();
+    let read = tokio::task::spawn_blocking(move || {
+        let snapshot = control_handle.status_snapshot();
+        RuntimeProbe {
+            accepting: snapshot.accepting_commands,
+            reloading: snapshot.reloading,
+            request_id: snapshot.current_request_id,
+            reload_started_at: snapshot.last_reload_started_at,
+        }
+    });
+    tokio::time::timeout(LIVENESS_TIMEOUT, read)
+        .await
+        .ok()?
+        .ok()
+}
+
+/// The watchdog vouches for a runtime that answered the status read and is
+/// either serving or inside a reload that has not stalled.
+fn runtime_alive(probe: Option<&RuntimeProbe>, now: SystemTime) -> bool {
+    let Some(probe) = probe else {
+        return false;
+    };
+    match (probe.reloading, probe.reload_started_at) {
+        (true, Some(started)) => {
+            now.duration_since(started).unwrap_or_default() < RELOAD_STALL_LIMIT
+        }
+        (true, None) => true,
+        (false, _) => probe.accepting,
+    }
+}
+
+/// Turns successive event totals into a rate.
+#[derive(Debug, Default)]
+struct Throughput {
+    last: Option<(u64, Instant)>,
+}
+
+impl Throughput {
+    /// Events per second since the previous sample; `None` for the first
+    /// sample or after the counter went backwards, e.g. across a reload.
+    fn sample(&mut self, total: u64, at: Instant) -> Option<f64> {
+        let rate = self.last.and_then(|(prev, prev_at)| {
+            let secs = at.saturating_duration_since(prev_at).as_secs_f64();
+            (secs > 0.0 && total >= prev).then(|| (total - prev) as f64 / secs)
+        });
+        self.last = Some((total, at));
+        rate
+    }
+}
+
+fn sample_events(ctx: &SupervisorContext, throughput: &mut Throughput) -> Option<f64> {
+    let total = (ctx.events.as_ref()?)()?;
+    throughput.sample(total, Instant::now())
+}
+
+/// Sends `STOPPING=1` once. A process that handed its sockets to a
+/// successor stays quiet: the service keeps running.
+fn notify_stopping(notifier: &SdNotifier, sent: &AtomicBool) {
+    if crate::hot_restart::handed_off() || sent.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    notifier.notify("STOPPING=1\nSTATUS=draining");
+    info_ctrl!("systemd notified stopping pid={}", std::process::id());
+}
+
+fn current_status(
+    ctx: &SupervisorContext,
+    probe: Option<&RuntimeProbe>,
+    events_per_sec: Option<f64>,
+) -> String {
+    let project = match crate::project_remote::current_project_version(&ctx.work_root) {
+        Ok(Some(version)) => Some(version),
+        Ok(None) => crate::project_remote::current_project_group_versions(&ctx.work_root)
+            .ok()
+            .flatten()
+            .and_then(|groups| group_versions(&groups)),
+        Err(_) => None,
+    };
+    let Some(probe) = probe else {
+        return "not responding".to_string();
+    };
+    status_text(
+        probe.accepting,
+        probe.reloading,
+        probe.request_id.as_deref(),
+        project.as_deref(),
+        events_per_sec,
+    )
+}
+
+fn group_versions(groups: &serde_json::Value) -> Option<String> {
+    let listed: Vec<String> = groups
+        .as_object()?
+        .iter()
+        .filter_map(|(name, group)| Some(format!("{}={}", name, group["version"].as_str()?)))
+        .collect();
+    (!listed.is_empty()).then(|| listed.join(","))
+}
+
+fn status_text(
+    accepting: bool,
+    reloading: bool,
+    request_id: Option<&str>,
+    project: Option<&str>,
+    events_per_sec: Option<f64>,
+) -> String {
+    let mut text = if reloading {
+        format!("reloading request={}", request_id.unwrap_or("-"))
+    } else if accepting {
+        "serving".to_string()
+    } else {
+        "not accepting commands".to_string()
+    };
+    if let Some(rate) = events_per_sec {
+        text.push_str(&format!(", {:.0} events/s", rate));
+    }
+    if let Some(project) = project {
+        text.push_str(&format!(", project {}", project));
+    }
+    text
+}
+
+/// Half of `WATCHDOG_USEC`, as `sd_watchdog_enabled(3)` recommends; `None`
+/// when unset or addressed to another process.
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.trim().parse::<u32>().ok()? != own_pid {
+            return None;
+        }
+    }
+    let usec: u64 = usec?.trim().parse().ok()?;
+    if usec == 0 {
+        return None;
+    }
+    Some(Duration::from_micros(usec / 2))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn notifier_sends_state_to_path_socket() {
+        let temp = tempdir().expect("tempdir");
+        let path = temp.path().join("notify.sock");
+        let server = std::os::unix::net::UnixDatagram::bind(&path).expect("bind notify socket");
+        let notifier = SdNotifier::connect(&path).expect("connect notifier");
+
+        notifier.notify("READY=1\nSTATUS=serving");
+        let mut buf = [0u8; 128];
+        let len = server.recv(&mut buf).expect("recv");
+        assert_eq!(&buf[..len], b"READY=1\nSTATUS=serving");
+    }
+
+    #[test]
+    fn notifier_accepts_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+
+        let name = format!("wparse-notify-test-{}", std::process::id());
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())
+            .expect("abstract addr");
+        let server = std::os::unix::net::UnixDatagram::bind_addr(&addr).expect("bind abstract");
+        let notifier = SdNotifier::connect(Path::new(&format!("@{}", name))).expect("connect");
+
+        notifier.notify("WATCHDOG=1");
+        let mut buf = [0u8; 32];
+        let len = server.recv(&mut buf).expect("recv");
+        assert_eq!(&buf[..len], b"WATCHDOG=1");
+    }
+
+    #[test]
+    fn watchdog_interval_halves_usec_for_this_process() {
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some("42"), 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(watchdog_interval(None, None, 42), None);
+    }
+
+    #[test]
+    fn status_text_reports_state_and_project_version() {
+        assert_eq!(
+            status_text(true, false, None, Some("1.4.3"), None),
+            "serving, project 1.4.3"
+        );
+        assert_eq!(
+            status_text(true, false, None, Some("1.4.3"), Some(1234.4)),
+            "serving, 1234 events/s, project 1.4.3"
+        );
+        assert_eq!(
+            status_text(true, true, Some("req-1"), None, None),
+            "reloading request=req-1"
+        );
+        assert_eq!(
+            status_text(false, false, None, None, None),
+            "not accepting commands"
+        );
+        let groups = serde_json::json!({
+            "infra": {"version": "0.1.7", "tag": "v0.1.7"},
+            "models": {"version": "1.4.2", "tag": "v1.4.2"}
+        });
+        assert_eq!(
+            group_versions(&groups).as_deref(),
+            Some("infra=0.1.7,models=1.4.2")
+        );
+    }
+
+    #[test]
+    fn throughput_is_the_rate_between_samples() {
+        let start = Instant::now();
+        let mut throughput = Throughput::default();
+        assert_eq!(throughput.sample(100, start), None);
+        assert_eq!(
+            throughput.sample(2_100, start + Duration::from_secs(2)),
+            Some(1_000.0)
+        );
+        // A counter reset across a reload yields no figure for one tick.
+        assert_eq!(throughput.sample(50, start + Duration::from_secs(3)), None);
+        assert_eq!(
+            throughput.sample(550, start + Duration::from_secs(4)),
+            Some(500.0)
+        );
+    }
+
+    #[test]
+    fn watchdog_follows_runtime_liveness() {
+        let now = SystemTime::now();
+        let probe = |accepting: bool, reloading: bool, started: Option<Duration>| RuntimeProbe {
+            accepting,
+            reloading,
+            request_id: None,
+            reload_started_at: started.map(|age| now - age),
+        };
+        assert!(runtime_alive(Some(&probe(true, false, None)), now));
+        assert!(runtime_alive(
+            Some(&probe(false, true, Some(Duration::from_secs(30)))),
+            now
+        ));
+        assert!(!runtime_alive(
+            Some(&probe(false, true, Some(RELOAD_STALL_LIMIT * 2))),
+            now
+        ));
+        assert!(!runtime_alive(Some(&probe(false, false, None)), now));
+        // No answer within the liveness timeout.
+        assert!(!runtime_alive(None, now));
+    }
+}
@@ -20,22 +20,28 @@ use wp_engine::facade::WpApp;
 use wp_error::run_error::RunResult;
 mod cli;
 
-use crate::cli::WParseCLI;
+use crate::cli::{CliParseArgs, WParseCLI};
 fn register_extension() {
     // Register all built-in sinks, sources, and optional connectors
     // Using the shared feats module for unified registration
     warp_parse::feats::register_for_runtime();
 }
 
-#[tokio::main(flavor = "multi_thread")]
-async fn main() {
-    if let Err(e) = do_main().await {
+fn main() {
+    if let Err(e) = do_main() {
         print_run_error("wparse", &e);
         std::process::exit(exit_code_for(e.reason()));
     }
 }
 
-async fn do_main() -> RunResult<()> {
+fn build_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed building the Runtime")
+}
+
+fn do_main() -> RunResult<()> {
     init_rustls_crypto_provider();
     let argv: Vec<String> = env::args().collect();
     let (_quiet, filtered_args) = split_quiet_args(argv);
@@ -44,29 +50,71 @@ async fn do_main() -> RunResult<()> {
     let env_dict = load_sec_dict()?;
     match cmd {
         WParseCLI::Daemon(args) => {
-            let work_root = wp_engine::facade::args::resolve_run_work_root(&args.work_root)?;
-            let engine_args: wp_engine::facade::args::ParseArgs = args.into();
-
-            let mut app = WpApp::try_from(engine_args, env_dict.clone())?;
-            let admin_api = warp_parse::admin_api::start_if_enabled(
-                Path::new(&work_root),
-                &env_dict,
-                app.control_handle(),
-            )
-            .await?;
-            log_build_info_once();
-            let run_result = app.run_daemon().await;
-            if let Some(admin_api) = admin_api {
-                admin_api.shutdown().await;
-            }
-            run_result?;
+            // These read and rewrite the environment, so they run before the
+            // runtime starts its worker threads.
+            let successor = warp_parse::hot_restart::take_successor();
+            warp_parse::systemd::take_socket_activation();
+            build_runtime().block_on(run_daemon(args, env_dict, successor))
         }
-        WParseCLI::Batch(args) => {
+        WParseCLI::Batch(args) => build_runtime().block_on(async move {
             let engine_args: wp_engine::facade::args::ParseArgs = args.into();
             let mut app = WpApp::try_from(engine_args, env_dict)?;
             log_build_info_once();
-            app.run_batch().await?;
-        }
+            app.run_batch().await
+        }),
+    }
+}
+
+async fn run_daemon(
+    args: CliParseArgs,
+    env_dict: orion_variate::EnvDict,
+    successor: Option<warp_parse::hot_restart::HandoffSuccessor>,
+) -> RunResult<()> {
+    let work_root = wp_engine::facade::args::resolve_run_work_root(&args.work_root)?;
+    let engine_args: wp_engine::facade::args::ParseArgs = args.into();
+
+    let mut app = WpApp::try_from(engine_args, env_dict.clone())?;
+    let restart = warp_parse::hot_restart::HotRestart::current()?;
+    let coordinator =
+        warp_parse::admin_api::ReloadCoordinator::default().with_hot_restart(restart.clone());
+    let admin_api = warp_parse::admin_api::start_with_coordinator(
+        Path::new(&work_root),
+        &env_dict,
+        app.control_handle(),
+        coordinator.clone(),
+    )
+    .await?;
+    let poller = warp_parse::remote_poll::start_if_enabled(
+        Path::new(&work_root),
+        &env_dict,
+        app.control_handle(),
+        coordinator.clone(),
+    )
+    .await?;
+    let restart_signal = warp_parse::hot_restart::listen_for_signal(restart, coordinator)?;
+    if let Some(successor) = successor {
+        let control_handle = app.control_handle();
+        tokio::spawn(
+            successor
+                .report_ready_when(move || control_handle.status_snapshot().accepting_commands),
+        );
+    }
+    let supervisor = warp_parse::systemd::start_if_notify_socket(
+        Path::new(&work_root),
+        app.control_handle(),
+        None,
+    );
+    log_build_info_once();
+    let run_result = app.run_daemon().await;
+    if let Some(supervisor) = supervisor {
+        supervisor.stopping().await;
+    }
+    restart_signal.abort();
+    if let Some(poller) = poller {
+        poller.shutdown().await;
+    }
+    if let Some(admin_api) = admin_api {
+        admin_api.shutdown().await;
     }
-    Ok(())
+    run_result
 }
@@ -0,0 +1,61 @@
+//! 统计分配次数的全局分配器，供 `wproj rule bench` 报告每事件分配数。
+//!
+//! 计数按线程进行，且只在 [`count_allocations`] 包住的区间内生效；其它命令与
+//! 其它线程只多一次线程局部变量读取。
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    // const 初始化，分配器内访问时不会再触发分配
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// 转发给系统分配器，当前线程处于计数区间时额外计数 |
+/// Forwards to the system allocator, counting calls made inside a measured section.
+pub struct CountingAlloc;
+
+impl CountingAlloc {
+    #[inline]
+    fn count(&self) {
+        // 线程退出阶段 TLS 可能已销毁，此时直接跳过
+        let _ = COUNTING.try_with(|on| {
+            if on.get() {
+                let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            }
+        });
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.count();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.count();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// 在当前线程执行 `f`，返回其结果与期间的分配次数（含 realloc）|
+/// Runs `f` on the current thread and returns its result with the allocations
+/// (including reallocs) it made.
+pub fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, u64) {
+    ALLOCATIONS.with(|n| n.set(0));
+    COUNTING.with(|on| on.set(true));
+    let out = f();
+    COUNTING.with(|on| on.set(false));
+    (out, ALLOCATIONS.with(Cell::get))
+}
@@ -71,6 +71,12 @@ pub enum WProj {
     /// 远程规则版本更新工具 | Remote rule version update tools
     #[command(subcommand, name = "conf")]
     Conf(ConfCmd),
+
+    /// 多实例批量管理工具 | Fleet-wide engine admin tools
+    ///
+    /// 按清单文件中的管理面地址，批量查询状态、更新工程并重载
+    #[command(subcommand, name = "fleet")]
+    Fleet(FleetCmd),
 }
 
 #[derive(Subcommand, Debug)]
@@ -109,6 +115,10 @@ pub enum EngineCmd {
     /// 触发运行时 reload | Trigger runtime reload
     #[command(name = "reload", visible_alias = "重载")]
     Reload(EngineReloadArgs),
+
+    /// 热重启 daemon，移交监听套接字（零停机升级）| Hot-restart the daemon, handing over its listening sockets (zero-downtime upgrade)
+    #[command(name = "restart", visible_alias = "热重启")]
+    Restart(EngineRestartArgs),
 }
 
 #[derive(Subcommand, Debug)]
@@ -120,6 +130,176 @@ pub enum ConfCmd {
     /// 执行远程规则版本更新 | Run remote rule version update
     #[command(name = "update", visible_alias = "更新")]
     Update(ConfUpdateArgs),
+
+    /// 检查受管目录的本地修改 | Report local changes in managed dirs
+    #[command(name = "status", visible_alias = "状态")]
+    Status(ConfStatusArgs),
+
+    /// 预览更新将带来的变更 | Preview what an update would change
+    #[command(name = "diff", visible_alias = "差异")]
+    Diff(ConfDiffArgs),
+
+    /// 远端仓库缓存管理 | Remote repository cache tools
+    #[command(subcommand, name = "cache", visible_alias = "缓存")]
+    Cache(ConfCacheCmd),
+}
+
+#[derive(Subcommand, Debug)]
+#[command(
+    name = "cache",
+    about = "远端仓库缓存管理 | Remote repository cache tools"
+)]
+pub enum ConfCacheCmd {
+    /// 查看缓存大小与对象数 | Report cache size and object count
+    #[command(name = "info", visible_alias = "信息")]
+    Info(ConfCacheArgs),
+
+    /// 重建缓存并删除不再使用的缓存 | Rebuild caches and remove unused ones
+    #[command(name = "clean", visible_alias = "清理")]
+    Clean(ConfCacheArgs),
+}
+
+#[derive(Subcommand, Debug)]
+#[command(
+    name = "fleet",
+    about = "多实例批量管理工具 | Fleet-wide engine admin tools"
+)]
+pub enum FleetCmd {
+    /// 查询清单中所有实例的状态 | Query the status of every instance in the inventory
+    #[command(name = "status", visible_alias = "状态")]
+    Status(FleetStatusArgs),
+
+    /// 分批更新工程并重载 | Update the project and reload, batch by batch
+    #[command(name = "update", visible_alias = "更新")]
+    Update(FleetUpdateArgs),
+
+    /// 分批触发重载 | Trigger reload, batch by batch
+    #[command(name = "reload", visible_alias = "重载")]
+    Reload(FleetReloadArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FleetTargetArgs {
+    /// 实例清单文件（TOML）| Instance inventory file (TOML)
+    #[clap(
+        short,
+        long,
+        visible_alias = "清单",
+        help = "实例清单文件（TOML）| Instance inventory file (TOML)"
+    )]
+    pub inventory: String,
+
+    /// 同时处理的实例数 | Number of instances handled at the same time
+    #[clap(
+        long = "concurrency",
+        default_value_t = 4,
+        visible_alias = "并发",
+        help = "同时处理的实例数 | Number of instances handled at the same time"
+    )]
+    pub concurrency: usize,
+
+    /// JSON 输出 | JSON output
+    #[clap(
+        long = "json",
+        default_value_t = false,
+        visible_alias = "输出JSON",
+        help = "JSON 输出 | JSON output"
+    )]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FleetRolloutArgs {
+    /// 每批实例数，默认等于并发数；某批失败后停止 | Instances per batch, defaults to the concurrency; the rollout stops after a failed batch
+    #[clap(
+        long = "batch-size",
+        visible_alias = "批大小",
+        help = "每批实例数，默认等于并发数；某批失败后停止 | Instances per batch, defaults to the concurrency; the rollout stops after a failed batch"
+    )]
+    pub batch_size: Option<usize>,
+
+    /// 每个实例等待 reload 的超时（毫秒）| Per-instance reload wait timeout in milliseconds
+    #[clap(
+        long = "timeout-ms",
+        default_value_t = 15000,
+        visible_alias = "超时毫秒",
+        help = "每个实例等待 reload 的超时（毫秒）| Per-instance reload wait timeout in milliseconds"
+    )]
+    pub timeout_ms: u64,
+
+    /// 触发原因（审计用途）| Trigger reason for audit
+    #[clap(
+        long = "reason",
+        visible_alias = "原因",
+        help = "触发原因（审计用途）| Trigger reason for audit"
+    )]
+    pub reason: Option<String>,
+
+    /// 请求 ID 前缀，实例名会追加在后 | Request ID prefix; the host name is appended
+    #[clap(
+        long = "request-id",
+        visible_alias = "请求ID",
+        help = "请求 ID 前缀，实例名会追加在后 | Request ID prefix; the host name is appended"
+    )]
+    pub request_id: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FleetStatusArgs {
+    #[clap(flatten)]
+    pub target: FleetTargetArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FleetReloadArgs {
+    #[clap(flatten)]
+    pub target: FleetTargetArgs,
+
+    #[clap(flatten)]
+    pub rollout: FleetRolloutArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FleetUpdateArgs {
+    #[clap(flatten)]
+    pub target: FleetTargetArgs,
+
+    #[clap(flatten)]
+    pub rollout: FleetRolloutArgs,
+
+    /// 本次更新目标版本 | Target version for this update
+    #[clap(
+        long = "version",
+        visible_alias = "版本",
+        help = "本次更新目标版本 | Target version for this update"
+    )]
+    pub version: Option<String>,
+
+    /// 更新目标组（多 repo 模式必填）| Target group for update (required in multi-repo mode)
+    #[clap(
+        long = "group",
+        visible_alias = "组",
+        help = "更新目标组：models、infra 或已配置的组名 | Target group: models, infra or a configured group name"
+    )]
+    pub group: Option<String>,
+
+    /// 跳过同步后的工程校验（仅限紧急情况）| Skip the post-sync project check (emergency only)
+    #[clap(
+        long = "skip-validate",
+        default_value_t = false,
+        visible_alias = "跳过校验",
+        help = "跳过同步后的工程校验（仅限紧急情况）| Skip the post-sync project check (emergency only)"
+    )]
+    pub skip_validate: bool,
+
+    /// 覆盖受管目录中的本地修改 | Overwrite local changes in managed dirs
+    #[clap(
+        long = "discard-local",
+        default_value_t = false,
+        visible_alias = "丢弃本地修改",
+        help = "覆盖受管目录中的本地修改 | Overwrite local changes in managed dirs"
+    )]
+    pub discard_local: bool,
 }
 
 #[derive(Args, Debug, Clone, Default)]
@@ -223,15 +403,72 @@ pub struct EngineReloadArgs {
     )]
     pub version: Option<String>,
 
-    /// 更新目标组（双 repo 模式必填）| Target group for update (required in dual-repo mode)
+    /// 更新目标组（多 repo 模式必填）| Target group for update (required in multi-repo mode)
     #[clap(
         long = "group",
         visible_alias = "组",
-        value_parser = ["models", "infra"],
-        help = "更新目标组：models 或 infra | Target group: models or infra"
+        help = "更新目标组：models、infra 或已配置的组名 | Target group: models, infra or a configured group name"
     )]
     pub group: Option<String>,
 
+    /// 跳过同步后的工程校验（仅限紧急情况）| Skip the post-sync project check (emergency only)
+    #[clap(
+        long = "skip-validate",
+        default_value_t = false,
+        visible_alias = "跳过校验",
+        help = "跳过同步后的工程校验（仅限紧急情况）| Skip the post-sync project check (emergency only)"
+    )]
+    pub skip_validate: bool,
+
+    /// 覆盖受管目录中的本地修改 | Overwrite local changes in managed dirs
+    #[clap(
+        long = "discard-local",
+        default_value_t = false,
+        visible_alias = "丢弃本地修改",
+        help = "覆盖受管目录中的本地修改 | Overwrite local changes in managed dirs"
+    )]
+    pub discard_local: bool,
+
+    /// 自定义请求 ID | Override request ID
+    #[clap(
+        long = "request-id",
+        visible_alias = "请求ID",
+        help = "自定义请求 ID | Override request ID"
+    )]
+    pub request_id: Option<String>,
+
+    /// JSON 输出 | JSON output
+    #[clap(
+        long = "json",
+        default_value_t = false,
+        visible_alias = "输出JSON",
+        help = "JSON 输出 | JSON output"
+    )]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct EngineRestartArgs {
+    #[clap(flatten)]
+    pub target: EngineTargetArgs,
+
+    /// 等待新进程就绪的超时（毫秒）| How long to wait for the new process to become ready, in milliseconds
+    #[clap(
+        long = "timeout-ms",
+        default_value_t = 120000,
+        visible_alias = "超时毫秒",
+        help = "等待新进程就绪的超时（毫秒）| How long to wait for the new process to become ready, in milliseconds"
+    )]
+    pub timeout_ms: u64,
+
+    /// 触发原因（审计用途）| Trigger reason for audit
+    #[clap(
+        long = "reason",
+        visible_alias = "原因",
+        help = "触发原因（审计用途）| Trigger reason for audit"
+    )]
+    pub reason: Option<String>,
+
     /// 自定义请求 ID | Override request ID
     #[clap(
         long = "request-id",
@@ -270,12 +507,87 @@ pub struct ConfUpdateArgs {
     )]
     pub version: Option<String>,
 
-    /// 更新目标组（双 repo 模式必填）| Target group for update (required in dual-repo mode)
+    /// 更新目标组（多 repo 模式必填）| Target group for update (required in multi-repo mode)
+    #[clap(
+        long = "group",
+        visible_alias = "组",
+        help = "更新目标组：models、infra 或已配置的组名 | Target group: models, infra or a configured group name"
+    )]
+    pub group: Option<String>,
+
+    /// 多组原子发布集 | Atomic multi-group release set
+    #[clap(
+        long = "release-set",
+        value_delimiter = ',',
+        conflicts_with_all = ["version", "group"],
+        visible_alias = "发布集",
+        help = "多组原子发布集：逗号分隔的 group=version，全部成功或全部回滚 | Atomic multi-group release set: comma-separated group=version; every group moves or none does"
+    )]
+    pub release_set: Vec<String>,
+
+    /// 跳过同步后的工程校验（仅限紧急情况）| Skip the post-sync project check (emergency only)
+    #[clap(
+        long = "skip-validate",
+        default_value_t = false,
+        visible_alias = "跳过校验",
+        help = "跳过同步后的工程校验（仅限紧急情况）| Skip the post-sync project check (emergency only)"
+    )]
+    pub skip_validate: bool,
+
+    /// 覆盖受管目录中的本地修改 | Overwrite local changes in managed dirs
+    #[clap(
+        long = "discard-local",
+        default_value_t = false,
+        visible_alias = "丢弃本地修改",
+        help = "覆盖受管目录中的本地修改 | Overwrite local changes in managed dirs"
+    )]
+    pub discard_local: bool,
+
+    /// 仅预览变更，不应用 | Preview the changes without applying them
+    #[clap(
+        long = "plan",
+        default_value_t = false,
+        conflicts_with_all = ["release_set", "skip_validate", "discard_local"],
+        visible_alias = "预览",
+        help = "仅预览变更，不应用：与 conf diff 相同 | Preview the changes without applying them, like conf diff"
+    )]
+    pub plan: bool,
+
+    /// JSON 输出 | JSON output
+    #[clap(
+        long = "json",
+        default_value_t = false,
+        visible_alias = "输出JSON",
+        help = "JSON 输出 | JSON output"
+    )]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ConfDiffArgs {
+    /// 工作目录 | Work directory
+    #[clap(
+        short,
+        long,
+        default_value = ".",
+        visible_alias = "工作目录",
+        help = "工作目录 | Work directory"
+    )]
+    pub work_root: String,
+
+    /// 预览的目标版本 | Target version to preview
+    #[clap(
+        long = "version",
+        visible_alias = "版本",
+        help = "预览的目标版本（默认与 update 相同）| Target version to preview (defaults to what update would pick)"
+    )]
+    pub version: Option<String>,
+
+    /// 预览的目标组（多 repo 模式必填）| Target group (required in multi-repo mode)
     #[clap(
         long = "group",
         visible_alias = "组",
-        value_parser = ["models", "infra"],
-        help = "更新目标组：models 或 infra | Target group: models or infra"
+        help = "预览的目标组：models、infra 或已配置的组名 | Target group: models, infra or a configured group name"
     )]
     pub group: Option<String>,
 
@@ -289,6 +601,50 @@ pub struct ConfUpdateArgs {
     pub json: bool,
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct ConfStatusArgs {
+    /// 工作目录 | Work directory
+    #[clap(
+        short,
+        long,
+        default_value = ".",
+        visible_alias = "工作目录",
+        help = "工作目录 | Work directory"
+    )]
+    pub work_root: String,
+
+    /// JSON 输出 | JSON output
+    #[clap(
+        long = "json",
+        default_value_t = false,
+        visible_alias = "输出JSON",
+        help = "JSON 输出 | JSON output"
+    )]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ConfCacheArgs {
+    /// 工作目录 | Work directory
+    #[clap(
+        short,
+        long,
+        default_value = ".",
+        visible_alias = "工作目录",
+        help = "工作目录 | Work directory"
+    )]
+    pub work_root: String,
+
+    /// JSON 输出 | JSON output
+    #[clap(
+        long = "json",
+        default_value_t = false,
+        visible_alias = "输出JSON",
+        help = "JSON 输出 | JSON output"
+    )]
+    pub json: bool,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct SelfSourceArgs {
     /// 更新通道 | Update channel
@@ -458,14 +814,14 @@ pub enum ModelCmd {
         name = "sources",
         about = "列出并检查源连接器 | List and check source connectors"
     )]
-    Sources(SourcesCommonArgs),
+    Sources(SourcesArgs),
 
     /// 列出汇组和路由配置 | List sink groups and route configurations
     #[command(
         name = "sinks",
         about = "列出汇组和路由配置 | List sink groups and route configurations"
     )]
-    Sinks(SinksCommonArgs),
+    Sinks(SinksArgs),
 
     /// 显示数据流路径：规则→OML→汇 | Display data flow paths: rules→OML→sinks
     #[command(
@@ -474,6 +830,14 @@ pub enum ModelCmd {
     )]
     Route(SinksRouteArgs),
 
+    /// 导出数据流拓扑图：源→规则→OML→汇 | Export the data flow topology: sources→rules→OML→sinks
+    #[command(
+        name = "graph",
+        visible_alias = "拓扑图",
+        about = "导出数据流拓扑图（DOT / Mermaid / JSON），标出孤立与停用的节点 | Export the data flow topology as DOT, Mermaid or JSON, highlighting orphaned and disabled nodes"
+    )]
+    Graph(ModelGraphArgs),
+
     /// 知识库管理工具（V2）| Knowledge base management tools (V2)
     #[command(subcommand, name = "knowdb")]
     Knowdb(KnowdbCmd),
@@ -528,6 +892,70 @@ pub enum RuleCmd {
         about = "使用规则执行离线解析测试 | Execute offline parsing tests with rules"
     )]
     Parse(ParseArgs),
+
+    /// 交互式规则工作台：试跑表达式、查看字段与 OML 结果 | Interactive rule workbench: try expressions, inspect fields and OML output
+    #[command(
+        name = "analyse",
+        visible_alias = "分析",
+        about = "交互式规则工作台：试跑表达式、查看字段与 OML 结果 | Interactive rule workbench: try expressions, inspect fields and OML output"
+    )]
+    Analyse(AnalyseArgs),
+
+    /// 运行规则包旁的回归用例 | Run regression cases kept next to rule packages
+    #[command(
+        name = "test",
+        visible_alias = "测试",
+        about = "运行规则包旁的回归用例（tests/*.case）| Run regression cases kept next to rule packages (tests/*.case)"
+    )]
+    Test(RuleTestArgs),
+
+    /// 逐个规则包回放样本，统计吞吐与延迟 | Replay samples through each rule package and profile throughput and latency
+    #[command(
+        name = "bench",
+        visible_alias = "基准",
+        about = "逐个规则包回放样本，统计吞吐、延迟、分配与命中率 | Replay samples per rule package: throughput, latency, allocations and match ratio"
+    )]
+    Bench(RuleBenchArgs),
+
+    /// 统计语料对各规则与分支的覆盖 | Report which rules and branches a corpus exercises
+    #[command(
+        name = "coverage",
+        visible_alias = "覆盖率",
+        about = "统计语料（样本或 rescue 数据）对各规则与分支的覆盖 | Report which rules and branches a corpus (samples or rescue data) exercises"
+    )]
+    Coverage(RuleCoverageArgs),
+
+    /// 从未标注的样本日志生成候选规则 | Draft WPL rules from unlabelled sample logs
+    #[command(
+        name = "infer",
+        visible_alias = "推断",
+        about = "按结构聚类样本行，生成候选 WPL 规则包与 sample.dat | Cluster sample lines by structure and draft a WPL package with sample.dat"
+    )]
+    Infer(RuleInferArgs),
+
+    /// 把 Grok / 正则 / Vector VRL 模式转换为 WPL | Convert Grok, regex or Vector VRL patterns into WPL
+    #[command(
+        name = "convert",
+        visible_alias = "转换",
+        about = "把 Grok 模式、正则或 Logstash / Vector 配置转换为 WPL 规则包，可用样本核对 | Convert Grok patterns, regexes or Logstash / Vector configs into a WPL package, optionally verified against samples"
+    )]
+    Convert(RuleConvertArgs),
+
+    /// 规范 WPL / OML 源码格式 | Format WPL and OML sources
+    #[command(
+        name = "fmt",
+        visible_alias = "格式化",
+        about = "规范 WPL / OML 源码格式，--check 用于 CI | Format WPL and OML sources, --check for CI"
+    )]
+    Fmt(RuleFmtArgs),
+
+    /// 检查规则、OML 与 sink 路由的一致性 | Check rules, OML models and sink routes for mistakes
+    #[command(
+        name = "lint",
+        visible_alias = "检查",
+        about = "检查被遮蔽或不可达的规则、OML 引用的未知字段及 sink 路由缺失的模型 | Warn on shadowed or unreachable rules, unknown OML fields and sink routes to missing models"
+    )]
+    Lint(RuleLintArgs),
 }
 
 #[derive(Subcommand, Debug)]
@@ -743,6 +1171,26 @@ pub enum SourcesCmd {
     List(SourcesCommonArgs),
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct SourcesArgs {
+    #[clap(flatten)]
+    pub common: SourcesCommonArgs,
+    /// 子命令：probe；缺省列出源连接器
+    #[command(subcommand)]
+    pub command: Option<SourcesCmd>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SourcesCmd {
+    /// 实际连接每个启用的源 | Connect to every enabled source
+    #[command(
+        name = "probe",
+        visible_alias = "探测",
+        about = "实际连接每个启用的源，报告可达性、认证与权限错误及耗时 | Connect to every enabled source and report reachability, auth and permission errors, and latency"
+    )]
+    Probe(ConnectorProbeArgs),
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct SourcesCommonArgs {
     /// 工作目录 | Work root
@@ -759,6 +1207,26 @@ pub struct SourcesRouteArgs {
     pub common: CommonFiltArgs,
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct SinksArgs {
+    #[clap(flatten)]
+    pub common: SinksCommonArgs,
+    /// 子命令：probe；缺省列出汇组
+    #[command(subcommand)]
+    pub command: Option<SinksCmd>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SinksCmd {
+    /// 实际连接每个启用的 sink | Connect to every enabled sink
+    #[command(
+        name = "probe",
+        visible_alias = "探测",
+        about = "实际连接每个启用的 sink，报告可达性、认证与权限错误及耗时 | Connect to every enabled sink and report reachability, auth and permission errors, and latency"
+    )]
+    Probe(ConnectorProbeArgs),
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct SinksCommonArgs {
     /// 工作目录 | Work root
@@ -775,6 +1243,42 @@ pub struct SinksRouteArgs {
     pub common: CommonFiltArgs,
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ModelGraphArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 输出格式 | Output format
+    #[clap(long, value_enum, default_value_t = GraphFormat::Dot, visible_alias = "格式")]
+    pub format: GraphFormat,
+    /// 输出文件（默认标准输出）| Output file (default stdout)
+    #[clap(short, long, visible_alias = "输出路径")]
+    pub out_path: Option<String>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ConnectorProbeArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 每个连接器的超时（毫秒）| Per-connector timeout in milliseconds
+    #[clap(long = "timeout-ms", default_value = "3000", visible_alias = "超时")]
+    pub timeout_ms: u64,
+    /// 只探测指定名称（源 key 或 组/sink，可重复）| Probe only these names (source key or group/sink, repeatable)
+    #[clap(long = "name", visible_alias = "名称")]
+    pub names: Vec<String>,
+    /// JSON 输出 | JSON output
+    #[clap(long = "json", default_value = "false", visible_alias = "输出JSON")]
+    pub json: bool,
+}
+
 #[derive(Args, Debug, Clone, Default)]
 pub struct CommonFiltArgs {
     /// 工作目录 | Work root
@@ -864,11 +1368,18 @@ pub struct ChkArgs {
 #[derive(Args, Debug, Clone)]
 #[command(name = "parse")]
 pub struct ParseArgs {
-    /// 输入文件路径 | Input file path
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 输入文件路径（- 表示标准输入）| Input file path (- for stdin)
     #[clap(short, long, visible_alias = "输入路径")]
     pub in_path: Option<String>,
+    /// 规则文件或规则包目录（默认 models/wpl）| WPL file or package directory (default models/wpl)
     #[clap(short = 'R', long, visible_alias = "规则文件")]
     pub rule_file: Option<String>,
+    /// 未命中率上限（百分比），超出时返回非零 | Max miss rate in percent; exceeding it exits non-zero
+    #[clap(long = "max-miss-rate", visible_alias = "未命中率上限")]
+    pub max_miss_rate: Option<f64>,
     /// JSON 输出 | JSON output
     #[clap(long = "json", default_value = "false", visible_alias = "输出JSON")]
     pub json: bool,
@@ -877,6 +1388,218 @@ pub struct ParseArgs {
     pub quiet: bool,
 }
 
+#[derive(Args, Debug, Clone)]
+#[command(name = "test")]
+pub struct RuleTestArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 规则目录（默认 models/wpl）| Rule directory (default models/wpl)
+    #[clap(short = 'R', long, visible_alias = "规则文件")]
+    pub rule_file: Option<String>,
+    /// OML 模型目录（默认 models/oml）| OML model directory (default models/oml)
+    #[clap(long = "oml-root", visible_alias = "模型目录")]
+    pub oml_root: Option<String>,
+    /// 知识库配置路径 | Knowledge base config path
+    #[clap(short = 'k', long, visible_alias = "知识库路径")]
+    pub knowledge_path: Option<String>,
+    /// 只运行路径包含该字符串的用例 | Only run cases whose path contains this text
+    #[clap(short = 'f', long, visible_alias = "过滤")]
+    pub filter: Option<String>,
+    /// 用实际输出更新失败用例的期望 | Update expectations of failing cases from actual output
+    #[clap(long, visible_alias = "更新期望")]
+    pub bless: bool,
+    /// JUnit XML 报告输出路径 | Write a JUnit XML report to this path
+    #[clap(long, visible_alias = "junit报告")]
+    pub junit: Option<String>,
+    /// JSON 输出 | JSON output
+    #[clap(long = "json", default_value = "false", visible_alias = "输出JSON")]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(name = "bench")]
+pub struct RuleBenchArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 规则文件或目录（默认 models/wpl）| Rule file or directory (default models/wpl)
+    #[clap(short = 'R', long, visible_alias = "规则文件")]
+    pub rule_file: Option<String>,
+    /// 样本文件，可重复；缺省时使用各规则包旁的 sample.dat | Sample files, repeatable; defaults to sample.dat next to each package
+    #[clap(short, long, visible_alias = "输入路径")]
+    pub in_path: Vec<String>,
+    /// 每个规则包测量的事件数 | Events measured per package
+    #[clap(short = 'n', long, default_value = "100000", visible_alias = "事件数")]
+    pub events: usize,
+    /// 预热事件数（不计入统计）| Warmup events (not measured)
+    #[clap(long, default_value = "1000", visible_alias = "预热")]
+    pub warmup: usize,
+    /// 对比的基线文件（JSON）| Baseline file to compare against (JSON)
+    #[clap(long, visible_alias = "基线")]
+    pub baseline: Option<String>,
+    /// 将本次结果保存为基线 | Save this run as a baseline
+    #[clap(long = "save-baseline", visible_alias = "保存基线")]
+    pub save_baseline: Option<String>,
+    /// 允许的退化百分比（吞吐下降或 p99 上升）| Allowed regression in percent (throughput drop or p99 growth)
+    #[clap(
+        long = "max-regression",
+        default_value = "10",
+        visible_alias = "最大退化"
+    )]
+    pub max_regression: f64,
+    /// JSON 输出 | JSON output
+    #[clap(long = "json", default_value = "false", visible_alias = "输出JSON")]
+    pub json: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum CoverageFormat {
+    Text,
+    Json,
+    Html,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(name = "coverage")]
+pub struct RuleCoverageArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 规则文件或目录（默认 models/wpl）| Rule file or directory (default models/wpl)
+    #[clap(short = 'R', long, visible_alias = "规则文件")]
+    pub rule_file: Option<String>,
+    /// 语料文件或目录，可重复；缺省时使用 rescue 数据 | Corpus files or directories, repeatable; defaults to rescue data
+    #[clap(short, long, visible_alias = "输入路径")]
+    pub in_path: Vec<String>,
+    /// 同时纳入 rescue 数据 | Include rescue data as well
+    #[clap(long, visible_alias = "包含rescue")]
+    pub rescue: bool,
+    /// Rescue 目录路径（相对于工作目录或绝对路径）| Rescue directory path
+    #[clap(long, default_value = "./data/rescue", visible_alias = "rescue路径")]
+    pub rescue_path: String,
+    /// 输出格式：text|json|html | Output format: text|json|html
+    #[clap(long, value_enum, default_value_t = CoverageFormat::Text, visible_alias = "格式")]
+    pub format: CoverageFormat,
+    /// 报告输出路径（默认标准输出）| Report output path (default stdout)
+    #[clap(short, long, visible_alias = "输出路径")]
+    pub out_path: Option<String>,
+    /// 最多列出的未命中簇数 | Max unmatched clusters to list
+    #[clap(long, default_value = "20", visible_alias = "簇数")]
+    pub clusters: usize,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(name = "infer")]
+pub struct RuleInferArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 样本日志文件 | Sample log file
+    #[clap(short, long, visible_alias = "输入路径")]
+    pub in_path: String,
+    /// 输出目录（默认 models/wpl/<样本文件名>）| Output directory (default models/wpl/<sample file stem>)
+    #[clap(short, long, visible_alias = "输出路径")]
+    pub out_path: Option<String>,
+    /// 包名（默认 /<样本文件名>/）| Package name (default /<sample file stem>/)
+    #[clap(short, long, visible_alias = "包名")]
+    pub package: Option<String>,
+    /// 最多生成的规则数 | Max rules to generate
+    #[clap(long = "max-rules", default_value = "20", visible_alias = "最大规则数")]
+    pub max_rules: usize,
+    /// 生成规则所需的最少行数 | Min lines a cluster needs to get a rule
+    #[clap(long = "min-lines", default_value = "1", visible_alias = "最少行数")]
+    pub min_lines: usize,
+    /// 每条规则写入 sample.dat 的样本行数 | Sample lines per rule written to sample.dat
+    #[clap(
+        long = "samples-per-rule",
+        default_value = "3",
+        visible_alias = "样本行数"
+    )]
+    pub samples_per_rule: usize,
+    /// 覆盖已存在的文件 | Overwrite existing files
+    #[clap(long, visible_alias = "强制")]
+    pub force: bool,
+    /// JSON 输出 | JSON output
+    #[clap(long = "json", default_value = "false", visible_alias = "输出JSON")]
+    pub json: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ConvertFrom {
+    /// Grok 模式、patterns 文件或 Logstash 配置 | Grok patterns, patterns files or Logstash configs
+    Grok,
+    /// 每行一条正则 | One regex per line
+    Regex,
+    /// Vector VRL 中的 parse_grok / parse_regex | parse_grok / parse_regex calls in Vector VRL
+    VectorVrlParse,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(name = "convert")]
+pub struct RuleConvertArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 源格式 | Source format
+    #[clap(long, value_enum, visible_alias = "来源")]
+    pub from: ConvertFrom,
+    /// 待转换的模式或配置文件 | Pattern or config file to convert
+    #[clap(short, long, visible_alias = "输入路径")]
+    pub in_path: String,
+    /// 额外的 Grok 模式定义文件（可重复）| Extra Grok pattern definition files (repeatable)
+    #[clap(long, visible_alias = "模式文件")]
+    pub patterns: Vec<String>,
+    /// 输出目录（默认 models/wpl/<输入文件名>）| Output directory (default models/wpl/<input file stem>)
+    #[clap(short, long, visible_alias = "输出路径")]
+    pub out_path: Option<String>,
+    /// 包名（默认 /<输入文件名>/）| Package name (default /<input file stem>/)
+    #[clap(short, long, visible_alias = "包名")]
+    pub package: Option<String>,
+    /// 用样本核对原模式与生成的规则 | Verify the original and generated rules against a sample file
+    #[clap(long, visible_alias = "样本")]
+    pub sample: Option<String>,
+    /// 覆盖已存在的文件 | Overwrite existing files
+    #[clap(long, visible_alias = "强制")]
+    pub force: bool,
+    /// JSON 输出 | JSON output
+    #[clap(long = "json", default_value = "false", visible_alias = "输出JSON")]
+    pub json: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(name = "fmt")]
+pub struct RuleFmtArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 文件或目录（默认 models/wpl 与 models/oml）| Files or directories (default models/wpl and models/oml)
+    pub paths: Vec<String>,
+    /// 只检查不写回，有未格式化文件时返回非零 | Check only; exit non-zero when a file needs formatting
+    #[clap(long, visible_alias = "仅检查")]
+    pub check: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(name = "lint")]
+pub struct RuleLintArgs {
+    /// 工作目录 | Work root
+    #[clap(short, long, default_value = DEFAULT_WORK_ROOT, visible_alias = "工作目录")]
+    pub work_root: String,
+    /// 规则目录（默认 models/wpl）| Rule directory (default models/wpl)
+    #[clap(short = 'R', long, visible_alias = "规则文件")]
+    pub rule_file: Option<String>,
+    /// OML 模型目录（默认 models/oml）| OML model directory (default models/oml)
+    #[clap(long = "oml-root", visible_alias = "模型目录")]
+    pub oml_root: Option<String>,
+    /// 警告也视为错误 | Treat warnings as errors
+    #[clap(long, visible_alias = "严格")]
+    pub strict: bool,
+    /// JSON 输出 | JSON output
+    #[clap(long = "json", default_value = "false", visible_alias = "输出JSON")]
+    pub json: bool,
+}
+
 #[derive(Args, Debug)]
 #[command(name = "analyse")]
 pub struct AnalyseArgs {
@@ -889,7 +1612,7 @@ pub struct AnalyseArgs {
     /// 输出文件路径 | Output file path
     #[clap(short, long, visible_alias = "输出路径")]
     pub out_path: Option<String>,
-    /// 模式（i 交互）| Mode (i interactive)
+    /// 模式（i 交互，b 批量）| Mode (i interactive, b batch)
     #[clap(short, long, default_value = DEFAULT_ANALYSE_MODE, visible_alias = "模式")]
     pub mode: String,
     /// 规则表达式 | Rule expression
@@ -901,7 +1624,7 @@ pub struct AnalyseArgs {
     /// 规则文件 | Rule file
     #[clap(short = 'R', long, visible_alias = "规则文件")]
     pub rule_file: Option<String>,
-    /// 检查强度 | Check level
+    /// 检查强度（0-3，输出详细度）| Check level (0-3, output detail)
     #[clap(short = 's', long, default_value = "2", visible_alias = "检查强度")]
     pub check: usize,
     /// JSON 输出 | JSON output
@@ -910,6 +1633,9 @@ pub struct AnalyseArgs {
     /// 知识库路径 | Knowledge path
     #[clap(short = 'k', long, visible_alias = "知识库路径")]
     pub knowledge_path: Option<String>,
+    /// OML 模型文件，预览转换结果 | OML model file to preview transformations
+    #[clap(long = "oml", visible_alias = "模型")]
+    pub oml: Option<String>,
 }
 
 #[cfg(test)]
@@ -1,8 +1,14 @@
 use crate::args::{
-    ConfCmd, EngineCmd, KnowdbCmd, ModelCmd, SelfCmd, StatCmd, ValidateCmd, WProj, WProjCli,
+    ConfCacheCmd, ConfCmd, EngineCmd, FleetCmd, KnowdbCmd, ModelCmd, SelfCmd, SinksCmd, SourcesCmd,
+    StatCmd, ValidateCmd, WProj, WProjCli,
 };
-use crate::handlers::conf::run_conf_update;
-use crate::handlers::engine::{run_engine_reload, run_engine_status};
+use crate::handlers::conf::{
+    run_conf_cache_clean, run_conf_cache_info, run_conf_diff, run_conf_status, run_conf_update,
+};
+use crate::handlers::engine::{run_engine_reload, run_engine_restart, run_engine_status};
+use crate::handlers::fleet::{run_fleet_reload, run_fleet_status, run_fleet_update};
+use crate::handlers::graph::{load_model_topology, show_model_graph};
+use crate::handlers::probe::{probe_sinks, probe_sources};
 use crate::handlers::rescue::dispatch_rescue_cmd;
 use crate::handlers::rule::dispatch_rule_cmd;
 use crate::handlers::self_update::{run_self_check, run_self_update};
@@ -20,10 +26,11 @@ pub async fn dispatch_cli(cli: WProjCli) -> RunResult<()> {
         WProj::SelfUpdate(sub) => dispatch_self_cmd(sub).await?,
         WProj::Engine(sub) => dispatch_engine_cmd(sub).await?,
         WProj::Conf(sub) => dispatch_conf_cmd(sub).await?,
+        WProj::Fleet(sub) => dispatch_fleet_cmd(sub).await?,
         other => {
             let dict = load_sec_dict()?;
             match other {
-                WProj::Rule(sub) => dispatch_rule_cmd(sub, &dict)?,
+                WProj::Rule(sub) => dispatch_rule_cmd(sub, cli.quiet, &dict).await?,
                 WProj::Init(args) => project::init_project(args, &dict).await?,
                 WProj::Check(args) => project::check_project(args, &dict)?,
                 WProj::Data(sub) => data::dispatch_data_cmd(sub, &dict).await?,
@@ -32,6 +39,7 @@ pub async fn dispatch_cli(cli: WProjCli) -> RunResult<()> {
                 WProj::SelfUpdate(_) => unreachable!("self command handled above"),
                 WProj::Engine(_) => unreachable!("engine command handled above"),
                 WProj::Conf(_) => unreachable!("conf command handled above"),
+                WProj::Fleet(_) => unreachable!("fleet command handled above"),
             }
         }
     }
@@ -49,20 +57,43 @@ async fn dispatch_engine_cmd(cmd: EngineCmd) -> RunResult<()> {
     match cmd {
         EngineCmd::Status(args) => run_engine_status(args).await,
         EngineCmd::Reload(args) => run_engine_reload(args).await,
+        EngineCmd::Restart(args) => run_engine_restart(args).await,
     }
 }
 
 async fn dispatch_conf_cmd(cmd: ConfCmd) -> RunResult<()> {
     match cmd {
         ConfCmd::Update(args) => run_conf_update(args).await,
+        ConfCmd::Status(args) => run_conf_status(args),
+        ConfCmd::Diff(args) => run_conf_diff(args),
+        ConfCmd::Cache(ConfCacheCmd::Info(args)) => run_conf_cache_info(args),
+        ConfCmd::Cache(ConfCacheCmd::Clean(args)) => run_conf_cache_clean(args),
+    }
+}
+
+async fn dispatch_fleet_cmd(cmd: FleetCmd) -> RunResult<()> {
+    match cmd {
+        FleetCmd::Status(args) => run_fleet_status(args).await,
+        FleetCmd::Update(args) => run_fleet_update(args).await,
+        FleetCmd::Reload(args) => run_fleet_reload(args).await,
     }
 }
 
 async fn dispatch_model_cmd(cmd: ModelCmd, dict: &EnvDict) -> RunResult<()> {
     match cmd {
-        ModelCmd::Sources(args) => list_sources_for_cli(&args, dict),
-        ModelCmd::Sinks(args) => list_sinks(args, dict),
+        ModelCmd::Sources(args) => match args.command {
+            Some(SourcesCmd::Probe(probe)) => probe_sources(probe, dict).await,
+            None => list_sources_for_cli(&args.common, dict),
+        },
+        ModelCmd::Sinks(args) => match args.command {
+            Some(SinksCmd::Probe(probe)) => probe_sinks(probe, dict).await,
+            None => list_sinks(args.common, dict),
+        },
         ModelCmd::Route(args) => show_sink_routes(args, dict).await,
+        ModelCmd::Graph(args) => {
+            let topology = load_model_topology(&args.work_root, dict).await?;
+            show_model_graph(args, &topology)
+        }
         ModelCmd::Knowdb(sub) => dispatch_knowdb_cmd(sub, dict),
     }
 }
@@ -1,11 +1,11 @@
 use std::path::PathBuf;
 
-use crate::args::ConfUpdateArgs;
+use crate::args::{ConfCacheArgs, ConfDiffArgs, ConfStatusArgs, ConfUpdateArgs};
 use crate::format::print_json;
 use orion_error::conversion::ToStructError;
 use warp_parse::compat::UvsFrom;
 use warp_parse::project_remote;
-use warp_parse::project_remote::RemoteGroup;
+use warp_parse::project_remote::{RemoteGroup, SyncOptions};
 use wp_engine::facade::args::ParseArgs;
 use wp_engine::facade::WpApp;
 use wp_error::run_error::{RunReason, RunResult};
@@ -13,7 +13,14 @@ use wp_log::{info_ctrl, warn_ctrl};
 
 pub async fn run_conf_update(args: ConfUpdateArgs) -> RunResult<()> {
     let work_root = resolve_work_root(&args.work_root)?;
+    if !args.release_set.is_empty() {
+        return run_conf_update_release_set(work_root, &args).await;
+    }
     let group = parse_group(args.group.as_deref())?;
+    if args.plan {
+        return run_conf_plan(&work_root, args.version.as_deref(), group, args.json);
+    }
+    let opts = sync_options(&args);
     run_conf_update_with_sync(
         work_root,
         args.version.as_deref(),
@@ -25,15 +32,27 @@ pub async fn run_conf_update(args: ConfUpdateArgs) -> RunResult<()> {
                 g,
                 requested_version,
                 dict,
+                &opts,
+            ),
+            None => project_remote::sync_project_remote_with_dict(
+                work_root,
+                requested_version,
+                dict,
+                &opts,
             ),
-            None => {
-                project_remote::sync_project_remote_with_dict(work_root, requested_version, dict)
-            }
         },
     )
     .await
 }
 
+fn sync_options(args: &ConfUpdateArgs) -> SyncOptions {
+    SyncOptions {
+        skip_validate: args.skip_validate,
+        discard_local: args.discard_local,
+        ..SyncOptions::default()
+    }
+}
+
 fn parse_group(raw: Option<&str>) -> RunResult<Option<RemoteGroup>> {
     match raw {
         None | Some("") => Ok(None),
@@ -61,8 +80,14 @@ pub async fn run_conf_update_from_repo(
         requested_version,
         false,
         None,
-        |work_root, requested_version, _dict, _group| {
-            project_remote::sync_project_remote_from_repo(work_root, repo_url, requested_version)
+        |work_root, requested_version, dict, _group| {
+            project_remote::sync_project_remote_from_repo(
+                work_root,
+                repo_url,
+                requested_version,
+                dict,
+                &SyncOptions::default(),
+            )
         },
     )
     .await
@@ -88,16 +113,11 @@ where
         work_root.display(),
         requested_version.unwrap_or("(auto)"),
         json,
-        group
-            .map(|g| match g {
-                RemoteGroup::Models => "models",
-                RemoteGroup::Infra => "infra",
-            })
-            .unwrap_or("-")
+        group.as_ref().map(RemoteGroup::name).unwrap_or("-")
     );
     let _lock_guard = project_remote::acquire_project_remote_lock(&work_root)?;
     let rollback_snapshot =
-        project_remote::capture_project_remote_snapshot_with_group(&work_root, group)?;
+        project_remote::capture_project_remote_snapshot_with_group(&work_root, group.clone())?;
     let dict = warp_parse::load_sec_dict()?;
     let result = sync_fn(&work_root, requested_version, &dict, group)?;
     info_ctrl!(
@@ -110,6 +130,9 @@ where
         result.to_revision,
         result.changed
     );
+    if result.validation_failed() {
+        return report_validation_failure(&work_root, requested_version, json, &result);
+    }
 
     let check_result = async {
         info_ctrl!(
@@ -202,9 +225,341 @@ where
     );
     println!("  To        : {}", result.to_revision);
     println!("  Changed   : {}", result.changed);
+    if let Some(validation) = result.validation.as_ref() {
+        println!("  Validate  : {}", validation.status.as_str());
+    }
+    if let Some(notes) = result.release_notes.as_ref() {
+        if let Some(title) = notes.tag_message.as_deref().and_then(|m| m.lines().next()) {
+            println!("  Notes     : {}", title);
+        }
+        if !notes.commits.is_empty() {
+            println!("  Commits   : {}", notes.commits.len());
+            for commit in &notes.commits {
+                let short = commit.id.get(..12).unwrap_or(&commit.id);
+                println!("    {} {}", short, commit.summary);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `--release-set`: moves several groups as one release. The groups are
+/// synced and checked together, and a failed load check restores all of them.
+async fn run_conf_update_release_set(work_root: PathBuf, args: &ConfUpdateArgs) -> RunResult<()> {
+    let release_set = project_remote::parse_release_set(&args.release_set)
+        .map_err(|e| RunReason::from_conf().to_err().with_detail(e))?;
+    let listing = args.release_set.join(",");
+    info_ctrl!(
+        "wproj conf update release set start work_root={} groups={} json={}",
+        work_root.display(),
+        listing,
+        args.json
+    );
+    let _lock_guard = project_remote::acquire_project_remote_lock(&work_root)?;
+    let rollback_snapshot = project_remote::capture_project_remote_snapshot(&work_root)?;
+    let dict = warp_parse::load_sec_dict()?;
+    let result = project_remote::sync_project_remote_release_set_with_dict(
+        &work_root,
+        &release_set,
+        &dict,
+        &sync_options(args),
+    )?;
+    if result.validation_failed() {
+        let summary = result
+            .validation
+            .as_ref()
+            .map(project_remote::ProjectRemoteValidation::summary)
+            .unwrap_or_default();
+        warn_ctrl!(
+            "wproj conf update release set blocked by validation work_root={} groups={} failures={}",
+            work_root.display(),
+            listing,
+            summary
+        );
+        if args.json {
+            print_json(&result)?;
+        } else {
+            println!("Project remote release set blocked");
+            println!("  Work Root : {}", work_root.display());
+            println!("  Groups    : {}", result.summary());
+            for failure in result.validation.iter().flat_map(|v| v.failures.iter()) {
+                println!("  Failed    : {}: {}", failure.component, failure.error);
+            }
+        }
+        return Err(RunReason::from_conf().to_err().with_detail(format!(
+            "project validation failed, release set {} was not applied: {}",
+            listing, summary
+        )));
+    }
+
+    let check_result = async {
+        let _cwd_guard = WorkRootGuard::enter(&work_root)?;
+        validate_load_model(&work_root, &dict).await
+    }
+    .await;
+    if let Err(check_err) = check_result {
+        warn_ctrl!(
+            "wproj conf update release set validate failed work_root={} groups={} error={}",
+            work_root.display(),
+            result.summary(),
+            check_err
+        );
+        if let Err(rollback_err) = project_remote::restore_project_remote_release_set(
+            &work_root,
+            &rollback_snapshot,
+            &result,
+        ) {
+            return Err(RunReason::from_conf().to_err().with_detail(format!(
+                "project check failed after release set: {}; rollback failed: {}",
+                check_err, rollback_err
+            )));
+        }
+        info_ctrl!(
+            "wproj conf update release set rollback done work_root={} groups={}",
+            work_root.display(),
+            result.summary()
+        );
+        return Err(RunReason::from_conf().to_err().with_detail(format!(
+            "project check failed after release set: {}",
+            check_err.display_chain()
+        )));
+    }
+    info_ctrl!(
+        "wproj conf update release set done work_root={} groups={} changed={}",
+        work_root.display(),
+        result.summary(),
+        result.changed()
+    );
+
+    if args.json {
+        return print_json(&result);
+    }
+    println!("Project remote release set");
+    println!("  Work Root : {}", work_root.display());
+    for group in &result.groups {
+        println!(
+            "  {:<10}: {} ({}) changed={}",
+            group.group.as_deref().unwrap_or("-"),
+            group.current_version,
+            group.resolved_tag,
+            group.changed
+        );
+    }
+    if let Some(validation) = result.validation.as_ref() {
+        println!("  Validate  : {}", validation.status.as_str());
+    }
+    Ok(())
+}
+
+pub fn run_conf_status(args: ConfStatusArgs) -> RunResult<()> {
+    let work_root = resolve_work_root(&args.work_root)?;
+    let statuses = project_remote::project_remote_drift_status(&work_root)?;
+    if args.json {
+        return print_json(&statuses);
+    }
+
+    println!("Project remote status");
+    println!("  Work Root : {}", work_root.display());
+    if statuses.is_empty() {
+        println!("  State     : not synced");
+        return Ok(());
+    }
+    for status in &statuses {
+        if let Some(group) = status.group.as_deref() {
+            println!("  Group     : {}", group);
+        }
+        println!("  Version   : {}", status.version);
+        println!("  Revision  : {}", status.revision);
+        println!("  Drift     : {}", status.status.as_str());
+        for path in &status.drift.modified {
+            println!("    modified: {}", path);
+        }
+        for path in &status.drift.added {
+            println!("    added   : {}", path);
+        }
+        for path in &status.drift.deleted {
+            println!("    deleted : {}", path);
+        }
+    }
     Ok(())
 }
 
+pub fn run_conf_diff(args: ConfDiffArgs) -> RunResult<()> {
+    let work_root = resolve_work_root(&args.work_root)?;
+    let group = parse_group(args.group.as_deref())?;
+    run_conf_plan(&work_root, args.version.as_deref(), group, args.json)
+}
+
+/// `conf diff` and `conf update --plan`: fetches the target release into the
+/// remote cache and prints what an update would change. The work root, the
+/// state file and the lock are left alone.
+fn run_conf_plan(
+    work_root: &std::path::Path,
+    requested_version: Option<&str>,
+    group: Option<RemoteGroup>,
+    json: bool,
+) -> RunResult<()> {
+    let dict = warp_parse::load_sec_dict()?;
+    let plan =
+        project_remote::plan_project_remote_update(work_root, requested_version, group, &dict)?;
+    info_ctrl!(
+        "wproj conf diff done work_root={} requested_version={} resolved_tag={} changed={} files={}",
+        work_root.display(),
+        requested_version.unwrap_or("(auto)"),
+        plan.resolved_tag,
+        plan.changed,
+        plan.files.len()
+    );
+    if json {
+        return print_json(&plan);
+    }
+
+    println!("Project remote plan");
+    println!("  Work Root : {}", work_root.display());
+    if let Some(group) = plan.group.as_deref() {
+        println!("  Group     : {}", group);
+    }
+    println!(
+        "  Version   : {} -> {}",
+        plan.current_version.as_deref().unwrap_or("-"),
+        plan.target_version
+    );
+    println!("  Tag       : {}", plan.resolved_tag);
+    println!(
+        "  From      : {}",
+        plan.from_revision.as_deref().unwrap_or("-")
+    );
+    println!("  To        : {}", plan.to_revision);
+    println!("  Changed   : {}", plan.changed);
+    if let Some(reason) = plan.incompatible.as_deref() {
+        println!("  Blocked   : {}", reason);
+    }
+    for (name, items) in plan.summary.categories() {
+        for item in &items.added {
+            println!("    + {:<10} {}", name, item);
+        }
+        for item in &items.removed {
+            println!("    - {:<10} {}", name, item);
+        }
+    }
+    for file in &plan.files {
+        println!("  {:<9} : {}", file.change.as_str(), file.path);
+    }
+    for file in &plan.files {
+        if file.binary {
+            println!();
+            println!("Binary file {} differs", file.path);
+            continue;
+        }
+        if let Some(diff) = file.diff.as_deref() {
+            println!();
+            print!("{}", diff);
+            if file.truncated {
+                println!("... diff of {} truncated", file.path);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run_conf_cache_info(args: ConfCacheArgs) -> RunResult<()> {
+    let work_root = resolve_work_root(&args.work_root)?;
+    let dict = warp_parse::load_sec_dict()?;
+    let caches = project_remote::project_remote_cache_info(&work_root, &dict)?;
+    print_cache_info("Project remote cache", &work_root, &caches, args.json)
+}
+
+pub fn run_conf_cache_clean(args: ConfCacheArgs) -> RunResult<()> {
+    let work_root = resolve_work_root(&args.work_root)?;
+    let dict = warp_parse::load_sec_dict()?;
+    let _lock_guard = project_remote::acquire_project_remote_lock(&work_root)?;
+    info_ctrl!(
+        "wproj conf cache clean start work_root={}",
+        work_root.display()
+    );
+    let caches = project_remote::rebuild_project_remote_cache(&work_root, &dict)?;
+    info_ctrl!(
+        "wproj conf cache clean done work_root={} caches={}",
+        work_root.display(),
+        caches.len()
+    );
+    print_cache_info(
+        "Project remote cache rebuilt",
+        &work_root,
+        &caches,
+        args.json,
+    )
+}
+
+fn print_cache_info(
+    title: &str,
+    work_root: &std::path::Path,
+    caches: &[project_remote::RemoteCacheInfo],
+    json: bool,
+) -> RunResult<()> {
+    if json {
+        return print_json(&caches);
+    }
+    println!("{}", title);
+    println!("  Work Root : {}", work_root.display());
+    if caches.is_empty() {
+        println!("  Cache     : none");
+        return Ok(());
+    }
+    for cache in caches {
+        println!("  Cache     : {}", cache.path);
+        if let Some(group) = cache.group.as_deref() {
+            println!("    Group   : {}", group);
+        }
+        println!(
+            "    Repo    : {}",
+            cache.repo.as_deref().unwrap_or("(unused)")
+        );
+        if !cache.exists {
+            println!("    State   : not fetched");
+            continue;
+        }
+        println!("    Size    : {} bytes", cache.size_bytes);
+        println!("    Objects : {}", cache.objects);
+        println!("    Shallow : {}", cache.shallow);
+    }
+    Ok(())
+}
+
+fn report_validation_failure(
+    work_root: &std::path::Path,
+    requested_version: Option<&str>,
+    json: bool,
+    result: &project_remote::ProjectRemoteUpdateResult,
+) -> RunResult<()> {
+    let summary = result
+        .validation
+        .as_ref()
+        .map(project_remote::ProjectRemoteValidation::summary)
+        .unwrap_or_default();
+    warn_ctrl!(
+        "wproj conf update blocked by validation work_root={} requested_version={} resolved_tag={} failures={}",
+        work_root.display(),
+        requested_version.unwrap_or("(auto)"),
+        result.resolved_tag,
+        summary
+    );
+    if json {
+        print_json(result)?;
+    } else {
+        println!("Project remote update blocked");
+        println!("  Work Root : {}", work_root.display());
+        println!("  Tag       : {}", result.resolved_tag);
+        for failure in result.validation.iter().flat_map(|v| v.failures.iter()) {
+            println!("  Failed    : {}: {}", failure.component, failure.error);
+        }
+    }
+    Err(RunReason::from_conf().to_err().with_detail(format!(
+        "project validation failed, {} was not applied: {}",
+        result.resolved_tag, summary
+    )))
+}
+
 async fn validate_load_model(
     work_root: &std::path::Path,
     dict: &orion_variate::EnvDict,
@@ -2,7 +2,7 @@ use std::env;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use crate::args::{EngineReloadArgs, EngineStatusArgs, EngineTargetArgs};
+use crate::args::{EngineReloadArgs, EngineRestartArgs, EngineStatusArgs, EngineTargetArgs};
 use crate::format::print_json;
 use orion_error::conversion::ToStructError;
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
@@ -14,54 +14,81 @@ use warp_parse::load_sec_dict;
 use wp_error::run_error::{RunReason, RunResult};
 
 #[derive(Debug, Serialize, Deserialize)]
-struct EngineStatusResponse {
-    instance_id: String,
-    version: String,
+pub(crate) struct EngineStatusResponse {
+    pub(crate) instance_id: String,
+    pub(crate) version: String,
     #[serde(default)]
-    project_version: Option<serde_json::Value>,
-    accepting_commands: bool,
-    reloading: bool,
-    current_request_id: Option<String>,
-    last_reload_request_id: Option<String>,
-    last_reload_result: Option<String>,
-    last_reload_started_at: Option<String>,
-    last_reload_finished_at: Option<String>,
+    pub(crate) project_version: Option<serde_json::Value>,
+    pub(crate) accepting_commands: bool,
+    pub(crate) reloading: bool,
+    pub(crate) current_request_id: Option<String>,
+    pub(crate) last_reload_request_id: Option<String>,
+    pub(crate) last_reload_result: Option<String>,
+    pub(crate) last_reload_started_at: Option<String>,
+    pub(crate) last_reload_finished_at: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct EngineReloadResponse {
-    request_id: String,
-    accepted: bool,
-    result: String,
-    update: Option<bool>,
-    requested_version: Option<String>,
-    current_version: Option<String>,
-    resolved_tag: Option<String>,
-    group: Option<String>,
-    force_replaced: Option<bool>,
-    warning: Option<String>,
-    error: Option<String>,
+pub(crate) struct EngineReloadResponse {
+    pub(crate) request_id: String,
+    pub(crate) accepted: bool,
+    pub(crate) result: String,
+    pub(crate) update: Option<bool>,
+    pub(crate) requested_version: Option<String>,
+    pub(crate) current_version: Option<String>,
+    pub(crate) resolved_tag: Option<String>,
+    pub(crate) group: Option<String>,
+    pub(crate) force_replaced: Option<bool>,
+    pub(crate) warning: Option<String>,
+    pub(crate) error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) release_notes: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct EngineErrorResponse {
-    request_id: String,
-    accepted: bool,
-    result: String,
-    error: String,
+pub(crate) struct EngineErrorResponse {
+    pub(crate) request_id: String,
+    pub(crate) accepted: bool,
+    pub(crate) result: String,
+    pub(crate) error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct EngineRestartResponse {
+    pub(crate) request_id: String,
+    pub(crate) accepted: bool,
+    pub(crate) result: String,
+    pub(crate) new_pid: u32,
+    #[serde(default)]
+    pub(crate) listeners: Vec<EngineRestartListener>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct EngineRestartListener {
+    pub(crate) name: String,
+    pub(crate) addr: String,
 }
 
 #[derive(Debug, Serialize)]
-struct EngineReloadRequest<'a> {
-    wait: bool,
-    timeout_ms: u64,
-    update: bool,
+pub(crate) struct EngineRestartRequest<'a> {
+    pub(crate) timeout_ms: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
-    version: Option<&'a str>,
+    pub(crate) reason: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct EngineReloadRequest<'a> {
+    pub(crate) wait: bool,
+    pub(crate) timeout_ms: u64,
+    pub(crate) update: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) version: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    group: Option<&'a str>,
+    pub(crate) group: Option<&'a str>,
+    pub(crate) skip_validate: bool,
+    pub(crate) discard_local: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    reason: Option<&'a str>,
+    pub(crate) reason: Option<&'a str>,
 }
 
 pub async fn run_engine_status(args: EngineStatusArgs) -> RunResult<()> {
@@ -136,6 +163,12 @@ pub async fn run_engine_reload(args: EngineReloadArgs) -> RunResult<()> {
     if !args.update && args.group.is_some() {
         return Err(reload_args_err("--group requires --update"));
     }
+    if !args.update && args.skip_validate {
+        return Err(reload_args_err("--skip-validate requires --update"));
+    }
+    if !args.update && args.discard_local {
+        return Err(reload_args_err("--discard-local requires --update"));
+    }
 
     let profile = resolve_target(&args.target)?;
     let client = build_client(&profile, args.target.insecure)?;
@@ -158,6 +191,8 @@ pub async fn run_engine_reload(args: EngineReloadArgs) -> RunResult<()> {
             update: args.update,
             version: args.version.as_deref(),
             group: args.group.as_deref(),
+            skip_validate: args.skip_validate,
+            discard_local: args.discard_local,
             reason: args.reason.as_deref(),
         })
         .send()
@@ -228,6 +263,61 @@ pub async fn run_engine_reload(args: EngineReloadArgs) -> RunResult<()> {
     }
 }
 
+pub async fn run_engine_restart(args: EngineRestartArgs) -> RunResult<()> {
+    let mut profile = resolve_target(&args.target)?;
+    // The reply only comes once the new process is ready.
+    profile.request_timeout = profile
+        .request_timeout
+        .max(Duration::from_millis(args.timeout_ms) + Duration::from_secs(5));
+    let client = build_client(&profile, args.target.insecure)?;
+    let url = format!(
+        "{}/admin/v1/runtime/restart",
+        profile.base_url.trim_end_matches('/')
+    );
+    let request_id = args
+        .request_id
+        .clone()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let response = client
+        .post(&url)
+        .headers(auth_headers(&profile.token)?)
+        .header("X-Request-Id", &request_id)
+        .json(&EngineRestartRequest {
+            timeout_ms: args.timeout_ms,
+            reason: args.reason.as_deref(),
+        })
+        .send()
+        .await
+        .map_err(|e| conf_err_source(format!("request {} failed", url), e))?;
+
+    if response.status().is_success() {
+        let body: EngineRestartResponse = response
+            .json()
+            .await
+            .map_err(|e| conf_err_source("decode restart response failed", e))?;
+        if args.json {
+            return print_json(&body);
+        }
+
+        println!("Engine restart");
+        println!("  Endpoint : {}", profile.base_url);
+        println!("  Request  : {}", body.request_id);
+        println!("  Result   : {}", body.result);
+        println!("  New PID  : {}", body.new_pid);
+        for listener in &body.listeners {
+            println!("  Listener : {} ({})", listener.name, listener.addr);
+        }
+        return Ok(());
+    }
+
+    let err = decode_error_response(response).await?;
+    if err.result == "reload_in_progress" {
+        return Err(reload_in_progress_err());
+    }
+    Err(restart_request_rejected(&err))
+}
+
 struct ResolvedTarget {
     base_url: String,
     token: String,
@@ -300,7 +390,7 @@ fn build_client(target: &ResolvedTarget, insecure: bool) -> RunResult<reqwest::C
         .map_err(|e| conf_err_source("build HTTP client failed", e))
 }
 
-fn auth_headers(token: &str) -> RunResult<HeaderMap> {
+pub(crate) fn auth_headers(token: &str) -> RunResult<HeaderMap> {
     let mut headers = HeaderMap::new();
     let value = HeaderValue::from_str(&format!("Bearer {}", token))
         .map_err(|e| conf_err_source("build Authorization header failed", e))?;
@@ -308,7 +398,9 @@ fn auth_headers(token: &str) -> RunResult<HeaderMap> {
     Ok(headers)
 }
 
-async fn decode_error_response(response: reqwest::Response) -> RunResult<EngineErrorResponse> {
+pub(crate) async fn decode_error_response(
+    response: reqwest::Response,
+) -> RunResult<EngineErrorResponse> {
     let status = response.status();
     response
         .json::<EngineErrorResponse>()
@@ -367,6 +459,13 @@ fn reload_request_rejected(err: &EngineErrorResponse) -> wp_error::RunError {
     ))
 }
 
+fn restart_request_rejected(err: &EngineErrorResponse) -> wp_error::RunError {
+    RunReason::from_biz().to_err().with_detail(format!(
+        "restart request rejected: {} ({})",
+        err.error, err.result
+    ))
+}
+
 fn conf_err_source<E>(detail: impl Into<String>, source: E) -> wp_error::RunError
 where
     E: std::error::Error + Send + Sync + 'static,
@@ -547,6 +646,8 @@ token_file = "{token_file}"
             update: false,
             version: Some("1.4.3".to_string()),
             group: None,
+            skip_validate: false,
+            discard_local: false,
             request_id: None,
             json: false,
         })
@@ -575,6 +676,8 @@ token_file = "{token_file}"
             update: false,
             version: None,
             group: Some("models".to_string()),
+            skip_validate: false,
+            discard_local: false,
             request_id: None,
             json: false,
         })
@@ -596,6 +699,8 @@ token_file = "{token_file}"
             update: true,
             version: None,
             group: Some("models"),
+            skip_validate: false,
+            discard_local: false,
             reason: None,
         };
         let json = serde_json::to_string(&req).expect("serialize");
@@ -614,6 +719,8 @@ token_file = "{token_file}"
             update: false,
             version: None,
             group: None,
+            skip_validate: false,
+            discard_local: false,
             reason: None,
         };
         let json = serde_json::to_string(&req).expect("serialize");
@@ -635,6 +742,15 @@ token_file = "{token_file}"
         assert_eq!(resp.group, None);
     }
 
+    #[test]
+    fn restart_response_deserializes_listeners() {
+        let json = r#"{"request_id":"r1","accepted":true,"result":"restart_done","new_pid":4242,"listeners":[{"name":"tcp-514","addr":"0.0.0.0:514"}]}"#;
+        let resp: EngineRestartResponse = serde_json::from_str(json).expect("deserialize");
+        assert_eq!(resp.new_pid, 4242);
+        assert_eq!(resp.listeners.len(), 1);
+        assert_eq!(resp.listeners[0].name, "tcp-514");
+    }
+
     #[test]
     fn status_response_deserializes_group_versions_object() {
         // Dual-repo mode returns a JSON object for project_version
@@ -0,0 +1,841 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::args::{
+    FleetReloadArgs, FleetRolloutArgs, FleetStatusArgs, FleetTargetArgs, FleetUpdateArgs,
+};
+use crate::format::print_json;
+use crate::handlers::engine::{
+    auth_headers, decode_error_response, EngineReloadRequest, EngineReloadResponse,
+    EngineStatusResponse,
+};
+use orion_error::conversion::ToStructError;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+use wp_log::{info_ctrl, warn_ctrl};
+
+const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 15_000;
+/// Headroom on top of the reload wait so the server answers before the
+/// client gives up.
+const RELOAD_TIMEOUT_MARGIN: Duration = Duration::from_secs(5);
+
+/// Inventory file as written by operators.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawInventory {
+    #[serde(default)]
+    defaults: RawHostDefaults,
+    #[serde(default)]
+    hosts: Vec<RawHost>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHostDefaults {
+    token_file: Option<String>,
+    ca_file: Option<String>,
+    insecure: Option<bool>,
+    request_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHost {
+    name: Option<String>,
+    url: String,
+    token_file: Option<String>,
+    ca_file: Option<String>,
+    insecure: Option<bool>,
+    request_timeout_ms: Option<u64>,
+}
+
+/// One admin API endpoint with its settings resolved against `[defaults]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FleetHost {
+    name: String,
+    url: String,
+    token_file: PathBuf,
+    ca_file: Option<PathBuf>,
+    insecure: bool,
+    request_timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+enum FleetAction {
+    Status,
+    Reload(ReloadPlan),
+}
+
+#[derive(Debug, Clone)]
+struct ReloadPlan {
+    update: bool,
+    version: Option<String>,
+    group: Option<String>,
+    skip_validate: bool,
+    discard_local: bool,
+    reason: Option<String>,
+    timeout_ms: u64,
+    request_id_prefix: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct FleetHostResult {
+    host: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch: Option<usize>,
+    result: String,
+    ok: bool,
+    current_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolved_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FleetSummary {
+    action: &'static str,
+    inventory: String,
+    total: usize,
+    succeeded: usize,
+    failed: usize,
+    skipped: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stopped_after_batch: Option<usize>,
+    hosts: Vec<FleetHostResult>,
+}
+
+pub async fn run_fleet_status(args: FleetStatusArgs) -> RunResult<()> {
+    let concurrency = positive_arg("--concurrency", args.target.concurrency)?;
+    let hosts = load_inventory(Path::new(&args.target.inventory))?;
+    info_ctrl!(
+        "wproj fleet status start inventory={} hosts={} concurrency={}",
+        args.target.inventory,
+        hosts.len(),
+        concurrency
+    );
+    let results = run_batch(&hosts, concurrency, &FleetAction::Status, None).await;
+    let summary = summarize("status", &args.target.inventory, results, None);
+    report(&summary, args.target.json)?;
+    if summary.failed > 0 {
+        return Err(fleet_err(format!(
+            "{} of {} hosts did not answer: {}",
+            summary.failed,
+            summary.total,
+            failed_hosts(&summary)
+        )));
+    }
+    Ok(())
+}
+
+pub async fn run_fleet_reload(args: FleetReloadArgs) -> RunResult<()> {
+    let plan = reload_plan(&args.rollout, false);
+    run_rollout("reload", &args.target, &args.rollout, plan).await
+}
+
+pub async fn run_fleet_update(args: FleetUpdateArgs) -> RunResult<()> {
+    let plan = ReloadPlan {
+        version: args.version.clone(),
+        group: args.group.clone(),
+        skip_validate: args.skip_validate,
+        discard_local: args.discard_local,
+        ..reload_plan(&args.rollout, true)
+    };
+    run_rollout("update", &args.target, &args.rollout, plan).await
+}
+
+fn reload_plan(rollout: &FleetRolloutArgs, update: bool) -> ReloadPlan {
+    ReloadPlan {
+        update,
+        version: None,
+        group: None,
+        skip_validate: false,
+        discard_local: false,
+        reason: rollout.reason.clone(),
+        timeout_ms: rollout.timeout_ms,
+        request_id_prefix: rollout
+            .request_id
+            .clone()
+            .unwrap_or_else(|| format!("fleet-{}", uuid::Uuid::new_v4())),
+    }
+}
+
+async fn run_rollout(
+    action: &'static str,
+    target: &FleetTargetArgs,
+    rollout: &FleetRolloutArgs,
+    plan: ReloadPlan,
+) -> RunResult<()> {
+    let concurrency = positive_arg("--concurrency", target.concurrency)?;
+    let batch_size = positive_arg("--batch-size", rollout.batch_size.unwrap_or(concurrency))?;
+    let hosts = load_inventory(Path::new(&target.inventory))?;
+    info_ctrl!(
+        "wproj fleet {} start inventory={} hosts={} batch_size={} concurrency={} request_id_prefix={} version={} group={}",
+        action,
+        target.inventory,
+        hosts.len(),
+        batch_size,
+        concurrency,
+        plan.request_id_prefix,
+        plan.version.as_deref().unwrap_or("(auto)"),
+        plan.group.as_deref().unwrap_or("-")
+    );
+
+    let action_plan = FleetAction::Reload(plan);
+    let mut results = Vec::with_capacity(hosts.len());
+    let mut stopped_after = None;
+    for (index, batch) in hosts.chunks(batch_size).enumerate() {
+        let batch_no = index + 1;
+        let batch_results = run_batch(batch, concurrency, &action_plan, Some(batch_no)).await;
+        let failed = batch_results.iter().filter(|r| !r.ok).count();
+        info_ctrl!(
+            "wproj fleet {} batch done batch={} hosts={} failed={}",
+            action,
+            batch_no,
+            batch_results.len(),
+            failed
+        );
+        results.extend(batch_results);
+        if failed > 0 {
+            stopped_after = Some(batch_no);
+            break;
+        }
+    }
+    if let Some(batch_no) = stopped_after {
+        warn_ctrl!(
+            "wproj fleet {} stopped after failed batch={} skipped={}",
+            action,
+            batch_no,
+            hosts.len() - results.len()
+        );
+        for host in &hosts[results.len()..] {
+            results.push(skipped_result(host));
+        }
+    }
+
+    let summary = summarize(action, &target.inventory, results, stopped_after);
+    report(&summary, target.json)?;
+    if let Some(batch_no) = stopped_after {
+        return Err(fleet_err(format!(
+            "fleet {} stopped after batch {}: {} failed, {} skipped",
+            action,
+            batch_no,
+            failed_hosts(&summary),
+            summary.skipped
+        )));
+    }
+    Ok(())
+}
+
+fn load_inventory(path: &Path) -> RunResult<Vec<FleetHost>> {
+    let body = std::fs::read_to_string(path).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("read fleet inventory {} failed", path.display()))
+            .with_source(e)
+    })?;
+    let raw: RawInventory = toml::from_str(&body).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("parse fleet inventory {} failed", path.display()))
+            .with_source(e)
+    })?;
+    let base = path.parent().unwrap_or_else(|| Path::new("."));
+    resolve_hosts(raw, base).map_err(|detail| {
+        fleet_conf_err(format!(
+            "invalid fleet inventory {}: {}",
+            path.display(),
+            detail
+        ))
+    })
+}
+
+/// Relative `token_file` and `ca_file` paths are taken from the inventory
+/// file's directory.
+fn resolve_hosts(raw: RawInventory, base: &Path) -> Result<Vec<FleetHost>, String> {
+    if raw.hosts.is_empty() {
+        return Err("no [[hosts]] entries".to_string());
+    }
+    let defaults = raw.defaults;
+    let mut names = BTreeSet::new();
+    let mut hosts = Vec::with_capacity(raw.hosts.len());
+    for host in raw.hosts {
+        let url = host.url.trim().trim_end_matches('/').to_string();
+        if url.is_empty() {
+            return Err("hosts.url must not be empty".to_string());
+        }
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!(
+                "host url '{}' must start with http:// or https://",
+                url
+            ));
+        }
+        let name = host
+            .name
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| url.clone());
+        if !names.insert(name.clone()) {
+            return Err(format!("duplicate host '{}'", name));
+        }
+        let token_file = host
+            .token_file
+            .or_else(|| defaults.token_file.clone())
+            .ok_or_else(|| format!("host '{}' has no token_file and [defaults] sets none", name))?;
+        let ca_file = host.ca_file.or_else(|| defaults.ca_file.clone());
+        hosts.push(FleetHost {
+            name,
+            url,
+            token_file: base.join(token_file),
+            ca_file: ca_file.map(|p| base.join(p)),
+            insecure: host.insecure.or(defaults.insecure).unwrap_or(false),
+            request_timeout: Duration::from_millis(
+                host.request_timeout_ms
+                    .or(defaults.request_timeout_ms)
+                    .unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS),
+            ),
+        });
+    }
+    Ok(hosts)
+}
+
+/// Runs `action` on every host with at most `concurrency` in flight and
+/// returns the results in inventory order.
+async fn run_batch(
+    hosts: &[FleetHost],
+    concurrency: usize,
+    action: &FleetAction,
+    batch: Option<usize>,
+) -> Vec<FleetHostResult> {
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = JoinSet::new();
+    for (index, host) in hosts.iter().cloned().enumerate() {
+        let permits = permits.clone();
+        let action = action.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let mut result = run_host(&host, &action).await;
+            result.batch = batch;
+            (index, result)
+        });
+    }
+    let mut results: Vec<Option<FleetHostResult>> = vec![None; hosts.len()];
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            Err(err) => warn_ctrl!("wproj fleet host task failed error={}", err),
+        }
+    }
+    results
+        .into_iter()
+        .zip(hosts)
+        .map(|(result, host)| {
+            result.unwrap_or_else(|| {
+                let mut failed = error_result(host, "error", "host task aborted".to_string());
+                failed.batch = batch;
+                failed
+            })
+        })
+        .collect()
+}
+
+async fn run_host(host: &FleetHost, action: &FleetAction) -> FleetHostResult {
+    let wait = match action {
+        FleetAction::Status => None,
+        FleetAction::Reload(plan) => Some(Duration::from_millis(plan.timeout_ms)),
+    };
+    let client = match host_client(host, wait) {
+        Ok(client) => client,
+        Err(err) => return error_result(host, "error", err),
+    };
+    let result = match action {
+        FleetAction::Status => host_status(host, &client).await,
+        FleetAction::Reload(plan) => host_reload(host, &client, plan).await,
+    };
+    if result.ok {
+        info_ctrl!(
+            "wproj fleet host done host={} url={} result={} current_version={}",
+            result.host,
+            result.url,
+            result.result,
+            result.current_version.as_deref().unwrap_or("-")
+        );
+    } else {
+        warn_ctrl!(
+            "wproj fleet host failed host={} url={} result={} error={}",
+            result.host,
+            result.url,
+            result.result,
+            result.error.as_deref().unwrap_or("-")
+        );
+    }
+    result
+}
+
+struct HostClient {
+    http: reqwest::Client,
+    token: String,
+}
+
+fn host_client(host: &FleetHost, reload_wait: Option<Duration>) -> Result<HostClient, String> {
+    let token = std::fs::read_to_string(&host.token_file)
+        .map_err(|e| {
+            format!(
+                "read token file {} failed: {}",
+                host.token_file.display(),
+                e
+            )
+        })?
+        .trim()
+        .to_string();
+    if token.is_empty() {
+        return Err(format!("token file {} is empty", host.token_file.display()));
+    }
+    let timeout = match reload_wait {
+        Some(wait) => host.request_timeout.max(wait + RELOAD_TIMEOUT_MARGIN),
+        None => host.request_timeout,
+    };
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .timeout(timeout)
+        .danger_accept_invalid_certs(host.insecure);
+    if let Some(ca_file) = host.ca_file.as_ref() {
+        let pem = std::fs::read(ca_file)
+            .map_err(|e| format!("read ca file {} failed: {}", ca_file.display(), e))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .map_err(|e| format!("parse ca file {} failed: {}", ca_file.display(), e))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    let http = builder
+        .build()
+        .map_err(|e| format!("build HTTP client failed: {}", e))?;
+    Ok(HostClient { http, token })
+}
+
+async fn host_status(host: &FleetHost, client: &HostClient) -> FleetHostResult {
+    match fetch_status(host, client).await {
+        Ok(status) => {
+            let result = if status.reloading {
+                "reloading"
+            } else if status.accepting_commands {
+                "ready"
+            } else {
+                "not_ready"
+            };
+            FleetHostResult {
+                host: host.name.clone(),
+                url: host.url.clone(),
+                batch: None,
+                result: result.to_string(),
+                ok: true,
+                current_version: status.project_version.as_ref().map(format_project_version),
+                resolved_tag: None,
+                error: None,
+            }
+        }
+        Err((result, error)) => error_result(host, result, error),
+    }
+}
+
+async fn host_reload(host: &FleetHost, client: &HostClient, plan: &ReloadPlan) -> FleetHostResult {
+    let url = format!("{}/admin/v1/reloads/model", host.url);
+    let request_id = format!("{}-{}", plan.request_id_prefix, host.name);
+    let headers = match auth_headers(&client.token) {
+        Ok(headers) => headers,
+        Err(err) => return error_result(host, "error", err.to_string()),
+    };
+    let response = client
+        .http
+        .post(&url)
+        .headers(headers)
+        .header("X-Request-Id", &request_id)
+        .json(&EngineReloadRequest {
+            wait: true,
+            timeout_ms: plan.timeout_ms,
+            update: plan.update,
+            version: plan.version.as_deref(),
+            group: plan.group.as_deref(),
+            skip_validate: plan.skip_validate,
+            discard_local: plan.discard_local,
+            reason: plan.reason.as_deref(),
+        })
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(err) => {
+            return error_result(
+                host,
+                "unreachable",
+                format!("request {} failed: {}", url, err),
+            )
+        }
+    };
+
+    let status = response.status();
+    if !(status.is_success()
+        || status == StatusCode::CONFLICT
+        || status == StatusCode::UNPROCESSABLE_ENTITY)
+    {
+        return match decode_error_response(response).await {
+            Ok(err) => error_result(host, &err.result, err.error),
+            Err(err) => error_result(host, "error", err.to_string()),
+        };
+    }
+    let body: EngineReloadResponse = match response.json().await {
+        Ok(body) => body,
+        Err(err) => {
+            return error_result(
+                host,
+                "error",
+                format!("decode reload response failed: {}", err),
+            )
+        }
+    };
+    // Anything short of a finished reload, including one still running
+    // after the wait, stops the rollout.
+    let ok = status.is_success() && body.result == "reload_done";
+    let mut current_version = body.current_version.clone();
+    if ok && current_version.is_none() {
+        current_version = fetch_status(host, client)
+            .await
+            .ok()
+            .and_then(|status| status.project_version.as_ref().map(format_project_version));
+    }
+    FleetHostResult {
+        host: host.name.clone(),
+        url: host.url.clone(),
+        batch: None,
+        result: body.result,
+        ok,
+        current_version,
+        resolved_tag: body.resolved_tag,
+        error: body.error.or(body.warning),
+    }
+}
+
+async fn fetch_status(
+    host: &FleetHost,
+    client: &HostClient,
+) -> Result<EngineStatusResponse, (&'static str, String)> {
+    let url = format!("{}/admin/v1/runtime/status", host.url);
+    let headers = auth_headers(&client.token).map_err(|e| ("error", e.to_string()))?;
+    let response = client
+        .http
+        .get(&url)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(|e| ("unreachable", format!("request {} failed: {}", url, e)))?;
+    if !response.status().is_success() {
+        let err = decode_error_response(response)
+            .await
+            .map_err(|e| ("error", e.to_string()))?;
+        return Err(("rejected", format!("{} ({})", err.error, err.result)));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| ("error", format!("decode status response failed: {}", e)))
+}
+
+/// `1.4.3` in single-repo mode, `infra=0.1.7,models=1.4.3` with groups.
+fn format_project_version(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(version) => version.clone(),
+        serde_json::Value::Object(groups) => groups
+            .iter()
+            .map(|(name, group)| {
+                let version = group
+                    .get("version")
+                    .and_then(serde_json::Value::as_str)
+                    .unwrap_or("-");
+                format!("{}={}", name, version)
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        other => other.to_string(),
+    }
+}
+
+fn error_result(host: &FleetHost, result: &str, error: String) -> FleetHostResult {
+    FleetHostResult {
+        host: host.name.clone(),
+        url: host.url.clone(),
+        batch: None,
+        result: result.to_string(),
+        ok: false,
+        current_version: None,
+        resolved_tag: None,
+        error: Some(error),
+    }
+}
+
+fn skipped_result(host: &FleetHost) -> FleetHostResult {
+    FleetHostResult {
+        host: host.name.clone(),
+        url: host.url.clone(),
+        batch: None,
+        result: "skipped".to_string(),
+        ok: false,
+        current_version: None,
+        resolved_tag: None,
+        error: None,
+    }
+}
+
+fn summarize(
+    action: &'static str,
+    inventory: &str,
+    hosts: Vec<FleetHostResult>,
+    stopped_after_batch: Option<usize>,
+) -> FleetSummary {
+    let skipped = hosts.iter().filter(|h| h.result == "skipped").count();
+    let succeeded = hosts.iter().filter(|h| h.ok).count();
+    FleetSummary {
+        action,
+        inventory: inventory.to_string(),
+        total: hosts.len(),
+        succeeded,
+        failed: hosts.len() - succeeded - skipped,
+        skipped,
+        stopped_after_batch,
+        hosts,
+    }
+}
+
+fn failed_hosts(summary: &FleetSummary) -> String {
+    summary
+        .hosts
+        .iter()
+        .filter(|h| !h.ok && h.result != "skipped")
+        .map(|h| h.host.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn report(summary: &FleetSummary, json: bool) -> RunResult<()> {
+    if json {
+        return print_json(summary);
+    }
+
+    println!("Fleet {}", summary.action);
+    println!("  Inventory : {}", summary.inventory);
+    let mut rows = vec![[
+        "HOST".to_string(),
+        "BATCH".to_string(),
+        "RESULT".to_string(),
+        "VERSION".to_string(),
+        "DETAIL".to_string(),
+    ]];
+    for host in &summary.hosts {
+        rows.push([
+            host.host.clone(),
+            host.batch
+                .map(|b| b.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            host.result.clone(),
+            host.current_version
+                .clone()
+                .unwrap_or_else(|| "-".to_string()),
+            host.error.clone().unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    let widths: Vec<usize> = (0..4)
+        .map(|col| {
+            rows.iter()
+                .map(|r| r[col].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in &rows {
+        println!(
+            "  {:<w0$}  {:<w1$}  {:<w2$}  {:<w3$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        );
+    }
+    print!(
+        "  Summary   : {} succeeded, {} failed, {} skipped",
+        summary.succeeded, summary.failed, summary.skipped
+    );
+    match summary.stopped_after_batch {
+        Some(batch) => println!("; stopped after batch {}", batch),
+        None => println!(),
+    }
+    Ok(())
+}
+
+fn positive_arg(flag: &str, value: usize) -> RunResult<usize> {
+    if value == 0 {
+        return Err(fleet_conf_err(format!("{} must be at least 1", flag)));
+    }
+    Ok(value)
+}
+
+fn fleet_conf_err(detail: impl Into<String>) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail.into())
+}
+
+fn fleet_err(detail: impl Into<String>) -> wp_error::RunError {
+    RunReason::from_biz().to_err().with_detail(detail.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn parse(body: &str) -> Result<Vec<FleetHost>, String> {
+        let raw: RawInventory = toml::from_str(body).map_err(|e| e.to_string())?;
+        resolve_hosts(raw, Path::new("/etc/wparse"))
+    }
+
+    #[test]
+    fn inventory_hosts_inherit_defaults() {
+        let hosts = parse(
+            r#"
+[defaults]
+token_file = "tokens/fleet.token"
+ca_file = "certs/ca.pem"
+request_timeout_ms = 30000
+
+[[hosts]]
+name = "edge-01"
+url = "https://10.0.0.1:19090/"
+
+[[hosts]]
+url = "http://127.0.0.1:19090"
+token_file = "/run/wparse/local.token"
+insecure = true
+"#,
+        )
+        .expect("parse inventory");
+
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[0].name, "edge-01");
+        assert_eq!(hosts[0].url, "https://10.0.0.1:19090");
+        assert_eq!(
+            hosts[0].token_file,
+            PathBuf::from("/etc/wparse/tokens/fleet.token")
+        );
+        assert_eq!(
+            hosts[0].ca_file.as_deref(),
+            Some(Path::new("/etc/wparse/certs/ca.pem"))
+        );
+        assert_eq!(hosts[0].request_timeout, Duration::from_secs(30));
+        assert!(!hosts[0].insecure);
+        assert_eq!(hosts[1].name, "http://127.0.0.1:19090");
+        assert_eq!(
+            hosts[1].token_file,
+            PathBuf::from("/run/wparse/local.token")
+        );
+        assert!(hosts[1].insecure);
+    }
+
+    #[test]
+    fn inventory_rejects_missing_token_and_duplicates() {
+        let err = parse(
+            r#"
+[[hosts]]
+url = "http://127.0.0.1:19090"
+"#,
+        )
+        .expect_err("token file is required");
+        assert!(
+            err.contains("has no token_file"),
+            "unexpected error: {}",
+            err
+        );
+
+        let err = parse(
+            r#"
+[defaults]
+token_file = "t"
+
+[[hosts]]
+name = "a"
+url = "http://127.0.0.1:19090"
+
+[[hosts]]
+name = "a"
+url = "http://127.0.0.1:19091"
+"#,
+        )
+        .expect_err("duplicate names are rejected");
+        assert!(
+            err.contains("duplicate host 'a'"),
+            "unexpected error: {}",
+            err
+        );
+
+        let err = parse("hosts = []").expect_err("empty inventory is rejected");
+        assert!(err.contains("no [[hosts]]"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn project_version_is_flattened_for_groups() {
+        assert_eq!(format_project_version(&serde_json::json!("1.4.3")), "1.4.3");
+        assert_eq!(
+            format_project_version(&serde_json::json!({
+                "models": {"version": "1.4.3", "tag": "v1.4.3"},
+                "infra": {"version": "0.1.7", "tag": "v0.1.7"}
+            })),
+            "infra=0.1.7,models=1.4.3"
+        );
+    }
+
+    #[tokio::test]
+    async fn rollout_stops_after_failed_batch() {
+        let temp = tempdir().expect("tempdir");
+        fs::write(temp.path().join("fleet.token"), "test-token\n").expect("write token");
+        // Nothing listens on these ports, so every reload fails to connect.
+        let mut inventory = String::from("[defaults]\ntoken_file = \"fleet.token\"\n");
+        for name in ["a", "b", "c"] {
+            inventory.push_str(&format!(
+                "\n[[hosts]]\nname = \"{name}\"\nurl = \"http://127.0.0.1:9\"\n"
+            ));
+        }
+        let inventory_path = temp.path().join("fleet.toml");
+        fs::write(&inventory_path, inventory).expect("write inventory");
+
+        let err = run_fleet_reload(FleetReloadArgs {
+            target: FleetTargetArgs {
+                inventory: inventory_path.to_string_lossy().to_string(),
+                concurrency: 2,
+                json: true,
+            },
+            rollout: FleetRolloutArgs {
+                batch_size: Some(1),
+                timeout_ms: 1_000,
+                reason: None,
+                request_id: Some("fleet-test".to_string()),
+            },
+        })
+        .await
+        .expect_err("unreachable host should stop the rollout");
+
+        let detail = err.to_string();
+        assert!(
+            detail.contains("stopped after batch 1") && detail.contains("2 skipped"),
+            "unexpected error: {}",
+            detail
+        );
+    }
+}
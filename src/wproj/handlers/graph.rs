@@ -0,0 +1,787 @@
+//! `wproj model graph`：把 源 → WPL 包 → OML 模型 → sink 组 → sink 的数据流导出为图。
+//!
+//! 源与 sink 取自与 `wproj sources` / `wproj sinks route` 相同的解析结果（已套用
+//! 环境变量字典，`connectors/*.d` 中停用的连接器不会出现）；WPL 包由 wp-lang
+//! 解析得到。没有上下游的节点会在图中标出。
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use glob::Pattern;
+use orion_error::conversion::{SourceErr, ToStructError};
+use orion_variate::EnvDict;
+use serde::Serialize;
+use warp_parse::compat::UvsFrom;
+use wp_cli_core::business::connectors::sources as sources_core;
+use wp_error::run_error::{RunReason, RunResult};
+use wp_proj::sinks::{collect_oml_models, expand_route_rows, Sinks};
+
+use crate::args::{GraphFormat, ModelGraphArgs};
+use crate::handlers::engine_config::load_resolved_engine_config;
+use crate::handlers::rule::{
+    collect_wpl_files, default_oml_root, default_rule_root, oml_files, package_rules, read_source,
+    rule_selectors,
+};
+
+/// 引擎直接写入的基础设施组 | Infrastructure groups, which the engine feeds directly.
+const INFRA_GROUPS: [&str; 5] = ["default", "miss", "residue", "error", "monitor"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum NodeKind {
+    Source,
+    Package,
+    Oml,
+    Group,
+    Sink,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Node {
+    id: String,
+    kind: NodeKind,
+    label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connect: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<String>,
+    disabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    orphan: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Edge {
+    from: String,
+    to: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    /// 没有路由的规则 | Rules that reach no sink group
+    unrouted_rules: Vec<String>,
+}
+
+impl Graph {
+    fn orphans(&self) -> impl Iterator<Item = &Node> {
+        self.nodes
+            .iter()
+            .filter(|n| n.orphan.is_some() || n.disabled)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SourceConf {
+    key: String,
+    connect: String,
+    enabled: bool,
+}
+
+#[derive(Debug, Clone)]
+struct PackageConf {
+    name: String,
+    rules: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct ModelConf {
+    name: String,
+    selectors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct GroupConf {
+    name: String,
+    infra: bool,
+    oml: Vec<String>,
+    rule: Vec<String>,
+    sinks: Vec<SinkConf>,
+}
+
+#[derive(Debug, Clone)]
+struct SinkConf {
+    name: String,
+    connect: String,
+    filter: Option<String>,
+}
+
+/// sink 路由表中的一行，即组内的一个 sink | One row of the sink route table: a sink within its group.
+#[derive(Debug, Clone)]
+struct RouteEntry {
+    group: String,
+    sink: SinkConf,
+    oml: Vec<String>,
+    rule: Vec<String>,
+}
+
+/// 作图所需的工程拓扑 | The project topology the graph is drawn from.
+pub struct ModelTopology {
+    sources: Vec<SourceConf>,
+    packages: Vec<PackageConf>,
+    models: Vec<ModelConf>,
+    groups: Vec<GroupConf>,
+}
+
+/// 按 `wproj sources` / `wproj sinks route` 的方式加载拓扑 |
+/// Load the topology the way `wproj sources` and `wproj sinks route` do.
+pub async fn load_model_topology(work_root: &str, dict: &EnvDict) -> RunResult<ModelTopology> {
+    let eng_conf = Arc::new(load_resolved_engine_config(work_root, dict)?);
+    let sink_root = PathBuf::from(eng_conf.sink_root());
+    if !sink_root.exists() {
+        return Err(graph_conf_err(format!(
+            "sink root not found: {}",
+            sink_root.display()
+        )));
+    }
+    let sources = sources_core::route_table(work_root, eng_conf.as_ref(), None, dict)
+        .source_err(RunReason::from_conf(), "load source route table failed")?
+        .into_iter()
+        .map(|row| SourceConf {
+            key: row.key,
+            connect: row.connect,
+            enabled: row.enable,
+        })
+        .collect();
+
+    let rows = Sinks::new(work_root, eng_conf).route_rows(&[], &[], dict)?;
+    let oml_map = collect_oml_models(work_root, dict).await?;
+    let groups =
+        fold_routes(
+            expand_route_rows(&rows, &oml_map)
+                .into_iter()
+                .map(|row| RouteEntry {
+                    group: row.group,
+                    sink: SinkConf {
+                        name: row.name,
+                        connect: row.connect,
+                        filter: row.filter,
+                    },
+                    oml: row.oml,
+                    rule: row.rule,
+                }),
+        );
+
+    let mut packages = Vec::new();
+    for file in collect_wpl_files(&default_rule_root(work_root))? {
+        let (name, rules) = package_rules(&file, &read_source(&file)?)?;
+        packages.push(PackageConf { name, rules });
+    }
+    let mut models = Vec::new();
+    for file in oml_files(&default_oml_root(work_root)) {
+        let code = read_source(&file)?;
+        if let Some(name) = oml_name(&code) {
+            models.push(ModelConf {
+                name,
+                selectors: rule_selectors(&code),
+            });
+        }
+    }
+    Ok(ModelTopology {
+        sources,
+        packages,
+        models,
+        groups,
+    })
+}
+
+pub fn show_model_graph(args: ModelGraphArgs, topology: &ModelTopology) -> RunResult<()> {
+    let graph = build_graph(
+        &topology.sources,
+        &topology.packages,
+        &topology.models,
+        &topology.groups,
+    );
+    let body = match args.format {
+        GraphFormat::Dot => render_dot(&graph),
+        GraphFormat::Mermaid => render_mermaid(&graph),
+        GraphFormat::Json => {
+            serde_json::to_string_pretty(&graph)
+                .map_err(|e| graph_conf_err("serialize graph failed".to_string()).with_source(e))?
+                + "\n"
+        }
+    };
+    match args.out_path.as_deref() {
+        Some(out) => {
+            std::fs::write(out, body).map_err(|e| {
+                RunReason::from_conf()
+                    .to_err()
+                    .with_detail(format!("write {} failed", out))
+                    .with_source(e)
+            })?;
+            println!(
+                "model graph: {} nodes, {} edges, {} orphan(s) -> {}",
+                graph.nodes.len(),
+                graph.edges.len(),
+                graph.orphans().count(),
+                out
+            );
+            for node in graph.orphans() {
+                println!("  {}", describe_orphan(node));
+            }
+            for rule in &graph.unrouted_rules {
+                println!("  rule {}: no route", rule);
+            }
+        }
+        None => print!("{}", body),
+    }
+    Ok(())
+}
+
+/// 按组归并路由行，保持首次出现的顺序 | Fold route rows into groups, in first-seen order.
+fn fold_routes(entries: impl IntoIterator<Item = RouteEntry>) -> Vec<GroupConf> {
+    let mut groups: Vec<GroupConf> = Vec::new();
+    for entry in entries {
+        let idx = match groups.iter().position(|g| g.name == entry.group) {
+            Some(idx) => idx,
+            None => {
+                groups.push(GroupConf {
+                    infra: INFRA_GROUPS.contains(&entry.group.as_str()),
+                    name: entry.group,
+                    ..GroupConf::default()
+                });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[idx];
+        for selector in entry.oml {
+            if !group.oml.contains(&selector) {
+                group.oml.push(selector);
+            }
+        }
+        for selector in entry.rule {
+            if !group.rule.contains(&selector) {
+                group.rule.push(selector);
+            }
+        }
+        group.sinks.push(entry.sink);
+    }
+    groups
+}
+
+fn describe_orphan(node: &Node) -> String {
+    let kind = match node.kind {
+        NodeKind::Source => "source",
+        NodeKind::Package => "package",
+        NodeKind::Oml => "oml",
+        NodeKind::Group => "group",
+        NodeKind::Sink => "sink",
+    };
+    match &node.orphan {
+        Some(reason) => format!("{} {}: {}", kind, node.label, reason),
+        None => format!("{} {}: disabled", kind, node.label),
+    }
+}
+
+/// 连接源、包、模型、组与 sink，并标出孤立节点 | Wire up the topology and mark orphans.
+fn build_graph(
+    sources: &[SourceConf],
+    packages: &[PackageConf],
+    models: &[ModelConf],
+    groups: &[GroupConf],
+) -> Graph {
+    let mut graph = Graph::default();
+    let mut fed: BTreeSet<String> = BTreeSet::new();
+    let mut feeds: BTreeSet<String> = BTreeSet::new();
+    let mut edge = |graph: &mut Graph, from: String, to: String, label: Option<String>| {
+        feeds.insert(from.clone());
+        fed.insert(to.clone());
+        graph.edges.push(Edge { from, to, label });
+    };
+
+    for source in sources {
+        let id = format!("source:{}", source.key);
+        graph.nodes.push(Node {
+            id: id.clone(),
+            kind: NodeKind::Source,
+            label: source.key.clone(),
+            connect: Some(source.connect.clone()),
+            filter: None,
+            disabled: !source.enabled,
+            orphan: None,
+        });
+        if source.enabled {
+            for package in packages {
+                edge(&mut graph, id.clone(), package_id(package), None);
+            }
+        }
+    }
+
+    // 规则经由 OML 或直接被 sink 组选中即视为有路由 | A rule is routed via OML or a direct group selector.
+    let mut routed_rules: BTreeSet<&str> = BTreeSet::new();
+    let model_groups: BTreeMap<&str, Vec<&GroupConf>> = models
+        .iter()
+        .map(|model| {
+            let targets = groups
+                .iter()
+                .filter(|g| !g.infra && selects(&g.oml, &model.name))
+                .collect();
+            (model.name.as_str(), targets)
+        })
+        .collect();
+    for model in models {
+        let id = format!("oml:{}", model.name);
+        graph.nodes.push(Node {
+            id: id.clone(),
+            kind: NodeKind::Oml,
+            label: model.name.clone(),
+            connect: None,
+            filter: None,
+            disabled: false,
+            orphan: None,
+        });
+        let targets = &model_groups[model.name.as_str()];
+        for package in packages {
+            let matched: Vec<&str> = package
+                .rules
+                .iter()
+                .map(String::as_str)
+                .filter(|rule| model.selectors.iter().any(|s| selects_one(s, rule)))
+                .collect();
+            if matched.is_empty() {
+                continue;
+            }
+            if !targets.is_empty() {
+                routed_rules.extend(matched);
+            }
+            edge(&mut graph, package_id(package), id.clone(), None);
+        }
+        for group in targets {
+            edge(&mut graph, id.clone(), group_id(group), None);
+        }
+    }
+    for group in groups.iter().filter(|g| !g.infra) {
+        for package in packages {
+            let matched: Vec<&str> = package
+                .rules
+                .iter()
+                .map(String::as_str)
+                .filter(|rule| selects(&group.rule, rule))
+                .collect();
+            if !matched.is_empty() {
+                routed_rules.extend(matched);
+                edge(&mut graph, package_id(package), group_id(group), None);
+            }
+        }
+    }
+
+    for package in packages {
+        graph.nodes.push(Node {
+            id: package_id(package),
+            kind: NodeKind::Package,
+            label: package.name.clone(),
+            connect: None,
+            filter: None,
+            disabled: false,
+            orphan: None,
+        });
+        graph.unrouted_rules.extend(
+            package
+                .rules
+                .iter()
+                .filter(|rule| !routed_rules.contains(rule.as_str()))
+                .cloned(),
+        );
+    }
+
+    for group in groups {
+        let id = group_id(group);
+        graph.nodes.push(Node {
+            id: id.clone(),
+            kind: NodeKind::Group,
+            label: group.name.clone(),
+            connect: None,
+            filter: None,
+            disabled: false,
+            orphan: None,
+        });
+        for (idx, sink) in group.sinks.iter().enumerate() {
+            let sink_id = format!("sink:{}/{}", group.name, idx);
+            graph.nodes.push(Node {
+                id: sink_id.clone(),
+                kind: NodeKind::Sink,
+                label: format!("{}/{}", group.name, sink.name),
+                connect: Some(sink.connect.clone()),
+                filter: sink.filter.clone(),
+                disabled: false,
+                orphan: None,
+            });
+            edge(&mut graph, id.clone(), sink_id, sink.filter.clone());
+        }
+    }
+
+    let infra: BTreeSet<String> = groups
+        .iter()
+        .filter(|g| g.infra)
+        .flat_map(|g| {
+            let id = group_id(g);
+            (0..g.sinks.len())
+                .map(move |idx| format!("sink:{}/{}", g.name, idx))
+                .chain(std::iter::once(id))
+        })
+        .collect();
+    let producing: BTreeSet<&str> = graph
+        .nodes
+        .iter()
+        .filter(|n| n.kind == NodeKind::Group && fed.contains(&n.id))
+        .map(|n| n.id.as_str())
+        .collect::<BTreeSet<_>>();
+    let live_sinks: BTreeSet<String> = graph
+        .edges
+        .iter()
+        .filter(|e| producing.contains(e.from.as_str()))
+        .map(|e| e.to.clone())
+        .collect();
+    let unrouted: BTreeSet<&str> = graph.unrouted_rules.iter().map(String::as_str).collect();
+    let orphan_packages: BTreeSet<String> = packages
+        .iter()
+        .filter(|p| !p.rules.is_empty() && p.rules.iter().all(|r| unrouted.contains(r.as_str())))
+        .map(package_id)
+        .collect();
+    for node in &mut graph.nodes {
+        node.orphan = match node.kind {
+            NodeKind::Package if orphan_packages.contains(&node.id) => {
+                Some("no route to any sink group".to_string())
+            }
+            NodeKind::Oml if !fed.contains(&node.id) => Some("matches no rule".to_string()),
+            NodeKind::Oml if !feeds.contains(&node.id) => {
+                Some("no sink group routes it".to_string())
+            }
+            NodeKind::Group if !infra.contains(&node.id) && !fed.contains(&node.id) => {
+                Some("no producer".to_string())
+            }
+            NodeKind::Sink if !infra.contains(&node.id) && !live_sinks.contains(&node.id) => {
+                Some("no producer".to_string())
+            }
+            _ => None,
+        };
+    }
+    graph.nodes.sort_by_key(|n| n.kind);
+    graph
+}
+
+fn package_id(package: &PackageConf) -> String {
+    format!("wpl:{}", package.name)
+}
+
+fn group_id(group: &GroupConf) -> String {
+    format!("group:{}", group.name)
+}
+
+fn selects(selectors: &[String], name: &str) -> bool {
+    selectors.iter().any(|s| selects_one(s, name))
+}
+
+fn selects_one(selector: &str, name: &str) -> bool {
+    selector == name || Pattern::new(selector).is_ok_and(|p| p.matches(name))
+}
+
+fn render_dot(graph: &Graph) -> String {
+    let mut out =
+        String::from("digraph wp_model {\n    rankdir=LR;\n    node [fontname=\"Helvetica\"];\n");
+    for node in &graph.nodes {
+        let shape = match node.kind {
+            NodeKind::Source => "cds",
+            NodeKind::Package => "folder",
+            NodeKind::Oml => "component",
+            NodeKind::Group => "tab",
+            NodeKind::Sink => "cylinder",
+        };
+        let mut attrs = vec![
+            format!("label=\"{}\"", dot_escape(&node_label(node, "\\n"))),
+            format!("shape={}", shape),
+        ];
+        if node.disabled {
+            attrs.push("style=dashed".to_string());
+            attrs.push("color=gray".to_string());
+            attrs.push("fontcolor=gray".to_string());
+        }
+        if node.orphan.is_some() {
+            attrs.push("color=red".to_string());
+            attrs.push("fontcolor=red".to_string());
+        }
+        out.push_str(&format!(
+            "    \"{}\" [{}];\n",
+            dot_escape(&node.id),
+            attrs.join(", ")
+        ));
+    }
+    for edge in &graph.edges {
+        let label = edge
+            .label
+            .as_deref()
+            .map(|l| format!(" [label=\"{}\"]", dot_escape(l)))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "    \"{}\" -> \"{}\"{};\n",
+            dot_escape(&edge.from),
+            dot_escape(&edge.to),
+            label
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn render_mermaid(graph: &Graph) -> String {
+    let ids: BTreeMap<&str, String> = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(idx, node)| (node.id.as_str(), format!("n{}", idx)))
+        .collect();
+    let mut out = String::from("flowchart LR\n");
+    for node in &graph.nodes {
+        let label = mermaid_escape(&node_label(node, "<br/>"));
+        let shape = match node.kind {
+            NodeKind::Source => format!("[/\"{}\"/]", label),
+            NodeKind::Package => format!("[\"{}\"]", label),
+            NodeKind::Oml => format!("[[\"{}\"]]", label),
+            NodeKind::Group => format!("{{{{\"{}\"}}}}", label),
+            NodeKind::Sink => format!("[(\"{}\")]", label),
+        };
+        out.push_str(&format!("    {}{}\n", ids[node.id.as_str()], shape));
+    }
+    for edge in &graph.edges {
+        let (Some(from), Some(to)) = (ids.get(edge.from.as_str()), ids.get(edge.to.as_str()))
+        else {
+            continue;
+        };
+        match &edge.label {
+            Some(label) => out.push_str(&format!(
+                "    {} -->|\"{}\"| {}\n",
+                from,
+                mermaid_escape(label),
+                to
+            )),
+            None => out.push_str(&format!("    {} --> {}\n", from, to)),
+        }
+    }
+    let class_of = |pick: &dyn Fn(&Node) -> bool| -> Vec<&str> {
+        graph
+            .nodes
+            .iter()
+            .filter(|n| pick(n))
+            .map(|n| ids[n.id.as_str()].as_str())
+            .collect()
+    };
+    let orphans = class_of(&|n| n.orphan.is_some());
+    let disabled = class_of(&|n| n.disabled);
+    if !orphans.is_empty() {
+        out.push_str("    classDef orphan stroke:#d33,stroke-width:2px,color:#d33\n");
+        out.push_str(&format!("    class {} orphan\n", orphans.join(",")));
+    }
+    if !disabled.is_empty() {
+        out.push_str("    classDef disabled stroke:#999,stroke-dasharray:4 3,color:#999\n");
+        out.push_str(&format!("    class {} disabled\n", disabled.join(",")));
+    }
+    out
+}
+
+fn node_label(node: &Node, newline: &str) -> String {
+    let mut label = node.label.clone();
+    if let Some(connect) = &node.connect {
+        label.push_str(&format!("{}({})", newline, connect));
+    }
+    if node.disabled {
+        label.push_str(&format!("{}[disabled]", newline));
+    }
+    if let Some(reason) = &node.orphan {
+        label.push_str(&format!("{}[{}]", newline, reason));
+    }
+    label
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('"', "\\\"")
+}
+
+fn mermaid_escape(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+/// OML 头部的 `name :` | The `name :` header of an OML model.
+fn oml_name(code: &str) -> Option<String> {
+    code.lines()
+        .take_while(|line| line.trim() != "---")
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "name")
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+fn graph_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn sink(name: &str, filter: Option<&str>) -> SinkConf {
+        SinkConf {
+            name: name.into(),
+            connect: "file_json_sink".into(),
+            filter: filter.map(str::to_string),
+        }
+    }
+
+    fn sample_graph() -> Graph {
+        let sources = vec![
+            SourceConf {
+                key: "file_1".into(),
+                connect: "file_src".into(),
+                enabled: true,
+            },
+            SourceConf {
+                key: "syslog_1".into(),
+                connect: "syslog_tcp_src".into(),
+                enabled: false,
+            },
+        ];
+        let packages = vec![
+            PackageConf {
+                name: "/nginx/".into(),
+                rules: strings(&["/nginx/access", "/nginx/error"]),
+            },
+            PackageConf {
+                name: "/fw/".into(),
+                rules: strings(&["/fw/deny"]),
+            },
+        ];
+        let models = vec![
+            ModelConf {
+                name: "nginx_access".into(),
+                selectors: strings(&["/nginx/access"]),
+            },
+            ModelConf {
+                name: "unused".into(),
+                selectors: strings(&["/none/*"]),
+            },
+        ];
+        let groups = vec![
+            GroupConf {
+                name: "web".into(),
+                oml: strings(&["nginx_*"]),
+                sinks: vec![sink("all", None), sink("errors", Some("./filter/5xx.wpl"))],
+                ..GroupConf::default()
+            },
+            GroupConf {
+                name: "audit".into(),
+                rule: strings(&["/audit/*"]),
+                sinks: vec![sink("kafka", None)],
+                ..GroupConf::default()
+            },
+            GroupConf {
+                name: "miss".into(),
+                infra: true,
+                sinks: vec![sink("file_json_sink", None)],
+                ..GroupConf::default()
+            },
+        ];
+        build_graph(&sources, &packages, &models, &groups)
+    }
+
+    fn orphan_of<'a>(graph: &'a Graph, id: &str) -> Option<&'a str> {
+        graph
+            .nodes
+            .iter()
+            .find(|n| n.id == id)
+            .and_then(|n| n.orphan.as_deref())
+    }
+
+    #[test]
+    fn graph_wires_fan_out_and_marks_orphans() {
+        let graph = sample_graph();
+        let has = |from: &str, to: &str| graph.edges.iter().any(|e| e.from == from && e.to == to);
+        assert!(has("source:file_1", "wpl:/nginx/"));
+        assert!(has("source:file_1", "wpl:/fw/"));
+        assert!(!has("source:syslog_1", "wpl:/nginx/"));
+        assert!(has("wpl:/nginx/", "oml:nginx_access"));
+        assert!(has("oml:nginx_access", "group:web"));
+        assert!(has("group:web", "sink:web/0"));
+        assert!(graph
+            .edges
+            .iter()
+            .any(|e| e.to == "sink:web/1" && e.label.as_deref() == Some("./filter/5xx.wpl")));
+
+        assert_eq!(graph.unrouted_rules, strings(&["/nginx/error", "/fw/deny"]));
+        assert_eq!(orphan_of(&graph, "wpl:/nginx/"), None);
+        assert_eq!(
+            orphan_of(&graph, "wpl:/fw/"),
+            Some("no route to any sink group")
+        );
+        assert_eq!(orphan_of(&graph, "oml:unused"), Some("matches no rule"));
+        assert_eq!(orphan_of(&graph, "group:audit"), Some("no producer"));
+        assert_eq!(orphan_of(&graph, "sink:audit/0"), Some("no producer"));
+        assert_eq!(orphan_of(&graph, "group:miss"), None);
+        assert_eq!(orphan_of(&graph, "sink:miss/0"), None);
+        assert!(graph
+            .nodes
+            .iter()
+            .any(|n| n.id == "source:syslog_1" && n.disabled));
+    }
+
+    #[test]
+    fn dot_and_mermaid_highlight_orphans_and_disabled_nodes() {
+        let graph = sample_graph();
+        let dot = render_dot(&graph);
+        assert!(dot.starts_with("digraph wp_model {"));
+        assert!(dot.contains(
+            "\"source:syslog_1\" [label=\"syslog_1\\n(syslog_tcp_src)\\n[disabled]\", shape=cds, style=dashed"
+        ));
+        assert!(dot.contains(
+            "\"wpl:/fw/\" [label=\"/fw/\\n[no route to any sink group]\", shape=folder, color=red"
+        ));
+        assert!(dot.contains("\"group:web\" -> \"sink:web/1\" [label=\"./filter/5xx.wpl\"];"));
+
+        let mermaid = render_mermaid(&graph);
+        assert!(mermaid.starts_with("flowchart LR\n    n0[/\"file_1<br/>(file_src)\"/]\n"));
+        assert!(mermaid.contains("-->|\"./filter/5xx.wpl\"|"));
+        assert!(mermaid.contains("classDef orphan"));
+        assert!(mermaid.contains("class n3,n5,n7,n11 orphan"));
+        assert!(mermaid.contains("class n1 disabled"));
+    }
+
+    #[test]
+    fn route_rows_fold_into_groups_with_infra_marked() {
+        let entry = |group: &str, name: &str, oml: &[&str], rule: &[&str]| RouteEntry {
+            group: group.into(),
+            sink: sink(name, None),
+            oml: strings(oml),
+            rule: strings(rule),
+        };
+        let groups = fold_routes([
+            entry("demo", "json", &["nginx_*"], &[]),
+            entry("miss", "file_json_sink", &[], &[]),
+            entry("demo", "kafka", &["nginx_*", "fw"], &["/audit/*"]),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "demo");
+        assert!(!groups[0].infra);
+        assert_eq!(groups[0].oml, strings(&["nginx_*", "fw"]));
+        assert_eq!(groups[0].rule, strings(&["/audit/*"]));
+        assert_eq!(
+            groups[0]
+                .sinks
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            ["json", "kafka"]
+        );
+        assert!(groups[1].infra);
+
+        assert_eq!(
+            oml_name("name : nginx\nrule : /nginx/*\n---\nname = take();\n").as_deref(),
+            Some("nginx")
+        );
+    }
+}
@@ -7,6 +7,9 @@ pub mod cli;
 //pub mod init;
 pub mod engine;
 pub mod engine_config;
+pub mod fleet;
+pub mod graph;
+pub mod probe;
 pub mod rescue;
 pub mod rule;
 pub mod self_update;
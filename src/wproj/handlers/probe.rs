@@ -0,0 +1,475 @@
+//! `wproj model sources probe` / `wproj model sinks probe`：实际打开每个启用的连接器。
+//!
+//! 源与 sink 取自与 `wproj sources` / `wproj sinks route` 相同的解析结果，经
+//! `feats::register_for_runtime` 注册的连接器工厂构建，在超时内打开后即释放。
+//! 监听型连接器的端口已被占用（通常是正在运行的引擎）时报告为 `in use`，不算失败。
+
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use orion_error::conversion::{SourceErr, ToStructError};
+use orion_variate::EnvDict;
+use serde::Serialize;
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::task::JoinSet;
+use warp_parse::compat::UvsFrom;
+use wp_cli_core::business::connectors::sources as sources_core;
+use wp_connector_api::{ParamMap, ResolvedSinkSpec, SinkBuildCtx, SourceBuildCtx, SourceSpec};
+use wp_engine::connectors::registry::{get_sink_factory, get_source_factory};
+use wp_error::run_error::{RunReason, RunResult};
+use wp_proj::sinks::Sinks;
+
+use crate::args::ConnectorProbeArgs;
+use crate::format::print_json;
+use crate::handlers::engine_config::load_resolved_engine_config;
+
+/// 作为源时监听端口的连接器类型 | Connector types that listen on a port as a source.
+const LISTEN_SOURCE_KINDS: [&str; 3] = ["syslog", "tcp", "http"];
+/// 作为 sink 时监听端口的连接器类型 | Connector types that listen on a port as a sink.
+const LISTEN_SINK_KINDS: [&str; 1] = ["prometheus"];
+const DEFAULT_LISTEN_HOST: &str = "0.0.0.0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Source,
+    Sink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ProbeStatus {
+    Ok,
+    /// 监听端口已被占用，多为运行中的引擎 | The listen port is taken, usually by the running engine.
+    InUse,
+    Unreachable,
+    Error,
+}
+
+impl ProbeStatus {
+    fn failed(self) -> bool {
+        matches!(self, ProbeStatus::Unreachable | ProbeStatus::Error)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ProbeStatus::Ok => "ok",
+            ProbeStatus::InUse => "in use",
+            ProbeStatus::Unreachable => "unreachable",
+            ProbeStatus::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ProbeTarget {
+    /// sink 所在组；源为 `None` | Group of a sink; `None` for sources.
+    group: Option<String>,
+    name: String,
+    connect: String,
+    kind: String,
+    params: ParamMap,
+}
+
+impl ProbeTarget {
+    /// 源为 key，sink 为 `组/名称` | The source key, or `group/name` for a sink.
+    fn label(&self) -> String {
+        match &self.group {
+            Some(group) => format!("{}/{}", group, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ProbeResult {
+    name: String,
+    connector: String,
+    target: String,
+    status: ProbeStatus,
+    latency_ms: f64,
+    detail: String,
+}
+
+pub async fn probe_sources(args: ConnectorProbeArgs, dict: &EnvDict) -> RunResult<()> {
+    let eng_conf = load_resolved_engine_config(&args.work_root, dict)?;
+    let targets = sources_core::route_table(&args.work_root, &eng_conf, None, dict)
+        .source_err(RunReason::from_conf(), "load source route table failed")?
+        .into_iter()
+        .filter(|row| row.enable)
+        .map(|row| ProbeTarget {
+            group: None,
+            name: row.key,
+            connect: row.connect,
+            kind: row.kind,
+            params: row.params,
+        })
+        .collect();
+    run_probes(Role::Source, &args, targets).await
+}
+
+pub async fn probe_sinks(args: ConnectorProbeArgs, dict: &EnvDict) -> RunResult<()> {
+    let eng_conf = load_resolved_engine_config(&args.work_root, dict)?;
+    let sink_root = PathBuf::from(eng_conf.sink_root());
+    if !sink_root.exists() {
+        return Err(RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("sink root not found: {}", sink_root.display())));
+    }
+    let targets = Sinks::new(&args.work_root, Arc::new(eng_conf))
+        .route_rows(&[], &[], dict)?
+        .into_iter()
+        .map(|row| ProbeTarget {
+            group: Some(row.group),
+            name: row.name,
+            connect: row.connect,
+            kind: row.kind,
+            params: row.params,
+        })
+        .collect();
+    run_probes(Role::Sink, &args, targets).await
+}
+
+async fn run_probes(
+    role: Role,
+    args: &ConnectorProbeArgs,
+    targets: Vec<ProbeTarget>,
+) -> RunResult<()> {
+    let timeout = Duration::from_millis(args.timeout_ms);
+    let work_root = PathBuf::from(&args.work_root);
+    let mut tasks = JoinSet::new();
+    for (idx, target) in targets.into_iter().enumerate() {
+        if !args.names.is_empty() && !args.names.contains(&target.label()) {
+            continue;
+        }
+        let work_root = work_root.clone();
+        tasks.spawn(async move { (idx, probe(role, target, work_root, timeout).await) });
+    }
+    let mut results = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (idx, result) = joined.map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail("probe task failed")
+                .with_source(e)
+        })?;
+        results.push((idx, result));
+    }
+    results.sort_by_key(|(idx, _)| *idx);
+    let results: Vec<ProbeResult> = results.into_iter().map(|(_, r)| r).collect();
+    let failed = results.iter().filter(|r| r.status.failed()).count();
+
+    if args.json {
+        print_json(&serde_json::json!({
+            "ok": failed == 0,
+            "probed": results.len(),
+            "failed": failed,
+            "items": results,
+        }))?;
+    } else {
+        print!("{}", render_table(&results));
+    }
+    if failed > 0 {
+        return Err(RunReason::from_biz().to_err().with_detail(format!(
+            "{} of {} connector(s) failed the probe",
+            failed,
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+fn render_table(results: &[ProbeResult]) -> String {
+    let name_w = results
+        .iter()
+        .map(|r| r.name.len())
+        .max()
+        .unwrap_or(4)
+        .max(4);
+    let target_w = results
+        .iter()
+        .map(|r| r.target.len())
+        .max()
+        .unwrap_or(6)
+        .max(6);
+    let mut out = format!(
+        "{:<name_w$}  {:<15}  {:<target_w$}  {:<11}  {:>9}  DETAIL\n",
+        "NAME", "TYPE", "TARGET", "STATUS", "LATENCY"
+    );
+    for r in results {
+        let latency = format!("{:.1}ms", r.latency_ms);
+        out.push_str(&format!(
+            "{:<name_w$}  {:<15}  {:<target_w$}  {:<11}  {:>9}  {}\n",
+            r.name,
+            r.connector,
+            r.target,
+            r.status.label(),
+            latency,
+            r.detail
+        ));
+    }
+    out
+}
+
+async fn probe(
+    role: Role,
+    target: ProbeTarget,
+    work_root: PathBuf,
+    timeout: Duration,
+) -> ProbeResult {
+    let started = Instant::now();
+    let listen = listen_addr(role, &target.kind, &target.params);
+    let checked = match &listen {
+        Some((addr, udp)) => probe_listen(addr, *udp).await,
+        None => Ok(()),
+    };
+    let (status, detail) = match checked {
+        Ok(()) => open_connector(role, &target, work_root, timeout).await,
+        Err(busy) => busy,
+    };
+    ProbeResult {
+        name: target.label(),
+        connector: target.kind.clone(),
+        target: listen
+            .map(|(addr, _)| addr)
+            .unwrap_or_else(|| describe_target(&target.params)),
+        status,
+        latency_ms: (started.elapsed().as_secs_f64() * 1_000_000.0).round() / 1000.0,
+        detail,
+    }
+}
+
+/// 经注册的工厂构建连接器，在超时内完成即视为已打开，随后释放 |
+/// Build the connector through its registered factory; finishing within the
+/// timeout counts as opened, and the instance is dropped right away.
+async fn open_connector(
+    role: Role,
+    target: &ProbeTarget,
+    work_root: PathBuf,
+    timeout: Duration,
+) -> (ProbeStatus, String) {
+    let opened = match role {
+        Role::Source => {
+            let Some(factory) = get_source_factory(&target.kind) else {
+                return no_factory(role, &target.kind);
+            };
+            let spec = SourceSpec {
+                name: target.name.clone(),
+                kind: target.kind.clone(),
+                connector_id: target.connect.clone(),
+                params: target.params.clone(),
+                tags: Vec::new(),
+            };
+            let ctx = SourceBuildCtx::new(work_root);
+            tokio::time::timeout(timeout, async {
+                factory
+                    .build(&spec, &ctx)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            })
+            .await
+        }
+        Role::Sink => {
+            let Some(factory) = get_sink_factory(&target.kind) else {
+                return no_factory(role, &target.kind);
+            };
+            let spec = ResolvedSinkSpec {
+                group: target.group.clone().unwrap_or_default(),
+                name: target.name.clone(),
+                kind: target.kind.clone(),
+                connector_id: target.connect.clone(),
+                params: target.params.clone(),
+                filter: None,
+            };
+            let ctx = SinkBuildCtx::new(work_root);
+            tokio::time::timeout(timeout, async {
+                factory
+                    .build(&spec, &ctx)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            })
+            .await
+        }
+    };
+    match opened {
+        Ok(Ok(())) => (ProbeStatus::Ok, "opened".to_string()),
+        Ok(Err(e)) => (ProbeStatus::Error, e),
+        Err(_) => (
+            ProbeStatus::Unreachable,
+            format!("timed out after {}ms", timeout.as_millis()),
+        ),
+    }
+}
+
+fn no_factory(role: Role, kind: &str) -> (ProbeStatus, String) {
+    let role = match role {
+        Role::Source => "source",
+        Role::Sink => "sink",
+    };
+    (
+        ProbeStatus::Error,
+        format!("no {} factory registered for type {}", role, kind),
+    )
+}
+
+/// 监听型连接器：先确认端口可以绑定，被占用时报告 `in use` |
+/// Listening connectors: check the port can be bound first, reporting `in use` when taken.
+async fn probe_listen(addr: &str, udp: bool) -> Result<(), (ProbeStatus, String)> {
+    let bound = if udp {
+        UdpSocket::bind(addr).await.map(drop)
+    } else {
+        TcpListener::bind(addr).await.map(drop)
+    };
+    match bound {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::AddrInUse => Err((
+            ProbeStatus::InUse,
+            "address in use (is wparse already running?)".to_string(),
+        )),
+        Err(e) => Err((ProbeStatus::Error, e.to_string())),
+    }
+}
+
+/// 监听地址与是否为 UDP；非监听型连接器为 `None` |
+/// Listen address and whether it is UDP; `None` for connectors that do not listen.
+fn listen_addr(role: Role, kind: &str, params: &ParamMap) -> Option<(String, bool)> {
+    let kinds: &[&str] = match role {
+        Role::Source => &LISTEN_SOURCE_KINDS,
+        Role::Sink => &LISTEN_SINK_KINDS,
+    };
+    if !kinds.contains(&kind) {
+        return None;
+    }
+    let port = param(params, "port")?;
+    let host = param(params, "addr")
+        .or_else(|| param(params, "host"))
+        .unwrap_or_else(|| DEFAULT_LISTEN_HOST.to_string());
+    let udp = param(params, "protocol").is_some_and(|p| p.eq_ignore_ascii_case("udp"));
+    Some((format!("{}:{}", host, port), udp))
+}
+
+/// 表格中显示的目标：地址、URL、broker 或文件 | The target shown in the table.
+fn describe_target(params: &ParamMap) -> String {
+    ["endpoint", "url", "insert_url", "address", "brokers"]
+        .iter()
+        .find_map(|key| param(params, key))
+        .or_else(|| {
+            let host = param(params, "addr").or_else(|| param(params, "host"))?;
+            match param(params, "port") {
+                Some(port) => Some(format!("{}:{}", host, port)),
+                None => Some(host),
+            }
+        })
+        .or_else(|| {
+            let file = param(params, "file")?;
+            match param(params, "base") {
+                Some(base) => Some(format!("{}/{}", base.trim_end_matches('/'), file)),
+                None => Some(file),
+            }
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn param(params: &ParamMap, key: &str) -> Option<String> {
+    match params.get(key)? {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(serde_json::Value::as_str)
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(value: serde_json::Value) -> ParamMap {
+        serde_json::from_value(value).expect("params")
+    }
+
+    #[tokio::test]
+    async fn taken_listen_port_is_in_use_and_not_a_failure() {
+        let held = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = held.local_addr().expect("addr").to_string();
+        let (status, detail) = probe_listen(&addr, false).await.expect_err("port is held");
+        assert_eq!(status, ProbeStatus::InUse);
+        assert!(!status.failed());
+        assert!(detail.contains("address in use"));
+
+        drop(held);
+        assert!(probe_listen(&addr, false).await.is_ok());
+        assert_eq!(
+            probe_listen("192.0.2.1:0", false).await.map_err(|(s, _)| s),
+            Err(ProbeStatus::Error)
+        );
+    }
+
+    #[test]
+    fn listeners_and_targets_come_from_the_resolved_params() {
+        let syslog =
+            params(serde_json::json!({"addr": "127.0.0.1", "port": 1514, "protocol": "UDP"}));
+        assert_eq!(
+            listen_addr(Role::Source, "syslog", &syslog),
+            Some(("127.0.0.1:1514".to_string(), true))
+        );
+        assert_eq!(listen_addr(Role::Sink, "syslog", &syslog), None);
+        assert_eq!(
+            listen_addr(
+                Role::Sink,
+                "prometheus",
+                &params(serde_json::json!({"port": "9100"}))
+            ),
+            Some(("0.0.0.0:9100".to_string(), false))
+        );
+        assert_eq!(describe_target(&syslog), "127.0.0.1:1514");
+        assert_eq!(
+            describe_target(&params(
+                serde_json::json!({"brokers": ["k1:9092", "k2:9092"]})
+            )),
+            "k1:9092,k2:9092"
+        );
+        assert_eq!(
+            describe_target(&params(
+                serde_json::json!({"base": "./data/out/", "file": "demo.json"})
+            )),
+            "./data/out/demo.json"
+        );
+        assert_eq!(describe_target(&ParamMap::new()), "-");
+    }
+
+    #[test]
+    fn table_lists_in_use_listeners_without_failing() {
+        let results = vec![
+            ProbeResult {
+                name: "syslog_1".into(),
+                connector: "syslog".into(),
+                target: "0.0.0.0:1514".into(),
+                status: ProbeStatus::InUse,
+                latency_ms: 0.4,
+                detail: "address in use (is wparse already running?)".into(),
+            },
+            ProbeResult {
+                name: "demo/kafka".into(),
+                connector: "kafka".into(),
+                target: "k1:9092".into(),
+                status: ProbeStatus::Unreachable,
+                latency_ms: 3000.0,
+                detail: "timed out after 3000ms".into(),
+            },
+        ];
+        let table = render_table(&results);
+        assert!(table.starts_with("NAME        TYPE             TARGET        STATUS"));
+        assert!(table.contains("in use       "));
+        assert!(table.contains("   3000.0ms  timed out after 3000ms"));
+        assert_eq!(results.iter().filter(|r| r.status.failed()).count(), 1);
+    }
+}
@@ -0,0 +1,755 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use orion_error::conversion::ToStructError;
+use orion_variate::EnvDict;
+use serde::Serialize;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+
+use super::oml::{load_knowledge, KnowledgeBase, OmlModel};
+use super::wpl::{read_source, record_fields, wrap_expression, LineMatch, ParsedField, RuleSet};
+use crate::args::AnalyseArgs;
+use crate::format::print_json;
+
+const DRAFT_PACKAGE: &str = "/draft/";
+const DRAFT_RULE: &str = "main";
+const DRAFT_ORIGIN: &str = "<inline>";
+const SAMPLE_FILE: &str = "sample.dat";
+const PROMPT: &str = "wpl> ";
+
+const REPL_HELP: &str = "\
+  <expr>           试跑规则表达式 | Try a rule expression, e.g. (ip:sip,_^2,chars:msg)
+  :rule <expr>     同上 | Same as above
+  :load <file>     从 .wpl 文件加载规则 | Load rules from a .wpl file
+  :samples <file>  重新加载样本 | Reload samples from a file
+  :add <line>      追加一行样本 | Append one sample line
+  :oml <file|off>  加载或关闭 OML 模型 | Load or drop an OML model
+  :check <0-3>     调整输出详细度 | Change the detail level
+  :run             对全部样本重新求值 | Re-run all samples
+  :show            查看当前规则与样本 | Show the working rule and samples
+  :save [file]     保存当前规则 | Save the working rule
+  :quit            退出 | Quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Interactive,
+    Batch,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Empty,
+    Help,
+    Rule(String),
+    Load(String),
+    Samples(String),
+    Add(String),
+    Oml(Option<String>),
+    Check(String),
+    Run,
+    Show,
+    Save(Option<String>),
+    Quit,
+    Unknown(String),
+}
+
+#[derive(Debug, Serialize)]
+struct SampleOutcome {
+    line: usize,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<ParsedField>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    oml: Vec<ParsedField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AnalyseReport {
+    rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oml: Option<String>,
+    samples: usize,
+    hit: usize,
+    miss: usize,
+    lines: Vec<SampleOutcome>,
+}
+
+struct KnowledgeState {
+    work_root: PathBuf,
+    conf: Option<PathBuf>,
+    loaded: bool,
+    /// 会话期间保持知识库可用 | Keeps the knowledge base alive for the session.
+    base: Option<KnowledgeBase>,
+}
+
+/// 规则工作台：当前规则、样本与可选的 OML 模型 | Working rule, samples and optional OML model.
+struct Workbench {
+    samples: Vec<String>,
+    line_max: Option<usize>,
+    source: Option<String>,
+    origin: PathBuf,
+    rules: Option<RuleSet>,
+    oml: Option<OmlModel>,
+    knowledge: KnowledgeState,
+    check: usize,
+    out_path: Option<PathBuf>,
+}
+
+pub(super) fn run_rule_analyse(args: AnalyseArgs, dict: &EnvDict) -> RunResult<()> {
+    let mode = parse_mode(&args.mode)?;
+    let mut bench = Workbench::new(&args)?;
+    if let Some(expr) = args.rule.as_deref() {
+        bench.set_expression(expr)?;
+    } else if let Some(file) = args.rule_file.as_deref() {
+        bench.load_rule_file(Path::new(file))?;
+    }
+    match sample_path(&args) {
+        Some(path) => {
+            bench.load_samples(&path)?;
+        }
+        None if mode == Mode::Batch => {
+            return Err(analyse_conf_err(
+                "batch mode needs samples: pass --in-path or a --rule-file with sample.dat"
+                    .to_string(),
+            ));
+        }
+        None => {}
+    }
+    if let Some(oml) = args.oml.as_deref() {
+        bench.load_oml(Path::new(oml), dict)?;
+    }
+
+    match mode {
+        Mode::Batch => {
+            let report = bench.analyse()?;
+            if args.json {
+                print_json(&report)?;
+            } else {
+                println!("Rule analyse");
+                println!("  Rule    : {}", report.rule);
+                if let Some(oml) = report.oml.as_deref() {
+                    println!("  OML     : {}", oml);
+                }
+                let stdout = io::stdout();
+                render_report(&report, bench.check, &mut stdout.lock())?;
+            }
+            if bench.out_path.is_some() {
+                let saved = bench.save(None)?;
+                if !args.json {
+                    println!("  Saved   : {}", saved.display());
+                }
+            }
+            Ok(())
+        }
+        Mode::Interactive => {
+            let stdin = io::stdin();
+            let stdout = io::stdout();
+            repl(&mut bench, stdin.lock(), &mut stdout.lock(), dict)
+        }
+    }
+}
+
+fn parse_mode(mode: &str) -> RunResult<Mode> {
+    match mode {
+        "i" | "interactive" => Ok(Mode::Interactive),
+        "b" | "batch" => Ok(Mode::Batch),
+        other => Err(analyse_conf_err(format!(
+            "unknown analyse mode '{}', expected i (interactive) or b (batch)",
+            other
+        ))),
+    }
+}
+
+/// 未指定 `--in-path` 时，使用规则文件同目录下的 `sample.dat` |
+/// Without `--in-path`, fall back to the `sample.dat` next to the rule file.
+fn sample_path(args: &AnalyseArgs) -> Option<PathBuf> {
+    if let Some(path) = args.in_path.as_deref() {
+        return Some(PathBuf::from(path));
+    }
+    let rule_file = Path::new(args.rule_file.as_deref()?);
+    let dir = if rule_file.is_dir() {
+        rule_file
+    } else {
+        rule_file.parent()?
+    };
+    Some(dir.join(SAMPLE_FILE)).filter(|p| p.is_file())
+}
+
+impl Workbench {
+    fn new(args: &AnalyseArgs) -> RunResult<Self> {
+        Ok(Self {
+            samples: Vec::new(),
+            line_max: args.line_max,
+            source: None,
+            origin: PathBuf::from(DRAFT_ORIGIN),
+            rules: None,
+            oml: None,
+            knowledge: KnowledgeState {
+                work_root: PathBuf::from(&args.work_root),
+                conf: args.knowledge_path.as_deref().map(PathBuf::from),
+                loaded: false,
+                base: None,
+            },
+            check: check_level(args.check)?,
+            out_path: args.out_path.as_deref().map(PathBuf::from),
+        })
+    }
+
+    fn set_expression(&mut self, expr: &str) -> RunResult<()> {
+        let source = wrap_expression(DRAFT_PACKAGE, DRAFT_RULE, expr);
+        let origin = PathBuf::from(DRAFT_ORIGIN);
+        self.rules = Some(RuleSet::from_code(&origin, &source)?);
+        self.source = Some(source);
+        self.origin = origin;
+        Ok(())
+    }
+
+    /// 规则包目录只用于试跑；单个 `.wpl` 文件还可以另存 |
+    /// A package directory can only be tried; a single `.wpl` file can also be saved.
+    fn load_rule_file(&mut self, path: &Path) -> RunResult<()> {
+        if path.is_dir() {
+            self.rules = Some(RuleSet::load(path)?);
+            self.source = None;
+        } else {
+            let source = read_source(path)?;
+            self.rules = Some(RuleSet::from_code(path, &source)?);
+            self.source = Some(source);
+        }
+        self.origin = path.to_path_buf();
+        Ok(())
+    }
+
+    fn load_samples(&mut self, path: &Path) -> RunResult<usize> {
+        let body = read_source(path)?;
+        self.samples = body
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.trim().is_empty())
+            .take(self.line_max.unwrap_or(usize::MAX))
+            .map(str::to_string)
+            .collect();
+        Ok(self.samples.len())
+    }
+
+    fn load_oml(&mut self, path: &Path, dict: &EnvDict) -> RunResult<()> {
+        if !self.knowledge.loaded {
+            self.knowledge.base = load_knowledge(
+                &self.knowledge.work_root,
+                self.knowledge.conf.as_deref(),
+                dict,
+            )?;
+            self.knowledge.loaded = true;
+        }
+        self.oml = Some(OmlModel::load(path)?);
+        Ok(())
+    }
+
+    fn analyse(&self) -> RunResult<AnalyseReport> {
+        let rules = self
+            .rules
+            .as_ref()
+            .ok_or_else(|| analyse_conf_err("no rule yet: pass --rule or --rule-file".into()))?;
+        let mut report = AnalyseReport {
+            rule: self.origin.display().to_string(),
+            oml: self.oml.as_ref().map(|m| m.path().display().to_string()),
+            samples: self.samples.len(),
+            hit: 0,
+            miss: 0,
+            lines: Vec::with_capacity(self.samples.len()),
+        };
+        for (idx, sample) in self.samples.iter().enumerate() {
+            let outcome = match rules.eval_record(sample) {
+                Ok((rule, record)) => {
+                    report.hit += 1;
+                    let fields = record_fields(&record);
+                    let oml = self
+                        .oml
+                        .as_ref()
+                        .map(|model| model.transform(record))
+                        .unwrap_or_default();
+                    SampleOutcome {
+                        line: idx + 1,
+                        ok: true,
+                        rule: Some(rule.to_string()),
+                        fields,
+                        oml,
+                        column: None,
+                        reason: None,
+                    }
+                }
+                Err(LineMatch::Miss {
+                    rule,
+                    column,
+                    reason,
+                }) => {
+                    report.miss += 1;
+                    SampleOutcome {
+                        line: idx + 1,
+                        ok: false,
+                        rule,
+                        fields: Vec::new(),
+                        oml: Vec::new(),
+                        column,
+                        reason: Some(reason),
+                    }
+                }
+                Err(LineMatch::Hit { .. }) => unreachable!("eval_record returns hits as Ok"),
+            };
+            report.lines.push(outcome);
+        }
+        Ok(report)
+    }
+
+    fn save(&self, path: Option<&Path>) -> RunResult<PathBuf> {
+        let source = self
+            .source
+            .as_deref()
+            .ok_or_else(|| analyse_conf_err("no rule to save".to_string()))?;
+        let target = path
+            .map(Path::to_path_buf)
+            .or_else(|| self.out_path.clone())
+            .ok_or_else(|| analyse_conf_err("no output path: pass a file or --out-path".into()))?;
+        if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| write_file_err(parent, e))?;
+        }
+        fs::write(&target, source).map_err(|e| write_file_err(&target, e))?;
+        Ok(target)
+    }
+}
+
+fn repl<R: BufRead, W: Write>(
+    bench: &mut Workbench,
+    input: R,
+    out: &mut W,
+    dict: &EnvDict,
+) -> RunResult<()> {
+    writeln!(
+        out,
+        "WPL workbench: {} samples loaded, :help for commands",
+        bench.samples.len()
+    )
+    .map_err(write_err)?;
+    if bench.rules.is_some() && !bench.samples.is_empty() {
+        render_report(&bench.analyse()?, bench.check, out)?;
+    }
+    let mut lines = input.lines();
+    loop {
+        write!(out, "{}", PROMPT).map_err(write_err)?;
+        out.flush().map_err(write_err)?;
+        let Some(line) = lines.next() else {
+            writeln!(out).map_err(write_err)?;
+            return Ok(());
+        };
+        let line = line.map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail("read command failed")
+                .with_source(e)
+        })?;
+        let command = parse_command(&line);
+        if command == Command::Quit {
+            return Ok(());
+        }
+        // 单条命令失败只提示，不退出工作台
+        if let Err(err) = apply_command(bench, command, out, dict) {
+            writeln!(out, "error: {}", err).map_err(write_err)?;
+        }
+    }
+}
+
+fn apply_command<W: Write>(
+    bench: &mut Workbench,
+    command: Command,
+    out: &mut W,
+    dict: &EnvDict,
+) -> RunResult<()> {
+    let rerun = match command {
+        Command::Empty | Command::Quit => false,
+        Command::Help => {
+            writeln!(out, "{}", REPL_HELP).map_err(write_err)?;
+            false
+        }
+        Command::Rule(expr) => {
+            bench.set_expression(&expr)?;
+            true
+        }
+        Command::Load(path) => {
+            bench.load_rule_file(Path::new(&path))?;
+            true
+        }
+        Command::Samples(path) => {
+            let count = bench.load_samples(Path::new(&path))?;
+            writeln!(out, "  {} samples loaded", count).map_err(write_err)?;
+            bench.rules.is_some()
+        }
+        Command::Add(line) => {
+            bench.samples.push(line);
+            writeln!(out, "  sample #{} added", bench.samples.len()).map_err(write_err)?;
+            bench.rules.is_some()
+        }
+        Command::Oml(Some(path)) => {
+            bench.load_oml(Path::new(&path), dict)?;
+            bench.rules.is_some()
+        }
+        Command::Oml(None) => {
+            bench.oml = None;
+            false
+        }
+        Command::Check(level) => {
+            let level = level
+                .parse::<usize>()
+                .map_err(|_| analyse_conf_err(format!("invalid check level '{}'", level)))?;
+            bench.check = check_level(level)?;
+            false
+        }
+        Command::Run => true,
+        Command::Show => {
+            show_bench(bench, out)?;
+            false
+        }
+        Command::Save(path) => {
+            let saved = bench.save(path.as_deref().map(Path::new))?;
+            writeln!(out, "  saved to {}", saved.display()).map_err(write_err)?;
+            false
+        }
+        Command::Unknown(cmd) => {
+            writeln!(out, "unknown command '{}', :help for commands", cmd).map_err(write_err)?;
+            false
+        }
+    };
+    if rerun {
+        render_report(&bench.analyse()?, bench.check, out)?;
+    }
+    Ok(())
+}
+
+fn parse_command(line: &str) -> Command {
+    let line = line.trim();
+    if line.is_empty() {
+        return Command::Empty;
+    }
+    let Some(rest) = line.strip_prefix(':') else {
+        return Command::Rule(line.to_string());
+    };
+    let (name, arg) = match rest.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, Some(arg.trim().to_string()).filter(|a| !a.is_empty())),
+        None => (rest, None),
+    };
+    match (name, arg) {
+        ("h" | "help", _) => Command::Help,
+        ("q" | "quit" | "exit", _) => Command::Quit,
+        ("r" | "rule", Some(expr)) => Command::Rule(expr),
+        ("l" | "load", Some(path)) => Command::Load(path),
+        ("samples", Some(path)) => Command::Samples(path),
+        ("a" | "add", Some(sample)) => Command::Add(sample),
+        ("oml", Some(arg)) if arg == "off" => Command::Oml(None),
+        ("oml", arg) => Command::Oml(arg),
+        ("check", Some(level)) => Command::Check(level),
+        ("run", None) => Command::Run,
+        ("show", None) => Command::Show,
+        ("s" | "save", path) => Command::Save(path),
+        _ => Command::Unknown(line.to_string()),
+    }
+}
+
+fn check_level(level: usize) -> RunResult<usize> {
+    if level > 3 {
+        return Err(analyse_conf_err(format!(
+            "check level must be 0-3, got {}",
+            level
+        )));
+    }
+    Ok(level)
+}
+
+/// 详细度：0 仅汇总，1 逐行命中，2 加字段与 OML 输出，3 再加字段类型 |
+/// Detail: 0 summary, 1 per-line result, 2 fields and OML output, 3 field types.
+fn render_report<W: Write>(report: &AnalyseReport, check: usize, out: &mut W) -> RunResult<()> {
+    if check >= 1 {
+        for line in &report.lines {
+            let rule = line.rule.as_deref().unwrap_or("-");
+            if line.ok {
+                writeln!(out, "  #{:<4} hit   {}", line.line, rule).map_err(write_err)?;
+            } else {
+                let reason = line.reason.as_deref().unwrap_or("-");
+                match line.column {
+                    Some(col) => writeln!(
+                        out,
+                        "  #{:<4} miss  {}  col {}: {}",
+                        line.line, rule, col, reason
+                    ),
+                    None => writeln!(out, "  #{:<4} miss  {}  {}", line.line, rule, reason),
+                }
+                .map_err(write_err)?;
+            }
+            if check >= 2 {
+                render_fields(&line.fields, check >= 3, out)?;
+                if !line.oml.is_empty() {
+                    writeln!(out, "        oml:").map_err(write_err)?;
+                    render_fields(&line.oml, check >= 3, out)?;
+                }
+            }
+        }
+    }
+    writeln!(
+        out,
+        "  Summary : {} samples, {} hit, {} miss",
+        report.samples, report.hit, report.miss
+    )
+    .map_err(write_err)
+}
+
+fn render_fields<W: Write>(fields: &[ParsedField], with_type: bool, out: &mut W) -> RunResult<()> {
+    let width = fields
+        .iter()
+        .map(|f| f.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for field in fields {
+        if with_type {
+            writeln!(
+                out,
+                "        {:<width$} : {} = {}",
+                field.name,
+                field.kind,
+                field.value,
+                width = width
+            )
+        } else {
+            writeln!(
+                out,
+                "        {:<width$} = {}",
+                field.name,
+                field.value,
+                width = width
+            )
+        }
+        .map_err(write_err)?;
+    }
+    Ok(())
+}
+
+fn show_bench<W: Write>(bench: &Workbench, out: &mut W) -> RunResult<()> {
+    writeln!(out, "  Rule    : {}", bench.origin.display()).map_err(write_err)?;
+    if let Some(source) = bench.source.as_deref() {
+        for line in source.lines() {
+            writeln!(out, "    {}", line).map_err(write_err)?;
+        }
+    }
+    if let Some(model) = bench.oml.as_ref() {
+        writeln!(out, "  OML     : {}", model.path().display()).map_err(write_err)?;
+    }
+    writeln!(out, "  Samples : {}", bench.samples.len()).map_err(write_err)?;
+    for (idx, sample) in bench.samples.iter().enumerate() {
+        writeln!(out, "    #{:<4} {}", idx + 1, sample).map_err(write_err)?;
+    }
+    Ok(())
+}
+
+fn analyse_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+fn write_file_err(path: &Path, e: io::Error) -> wp_error::RunError {
+    RunReason::from_conf()
+        .to_err()
+        .with_detail(format!("write {} failed", path.display()))
+        .with_source(e)
+}
+
+fn write_err(e: io::Error) -> wp_error::RunError {
+    RunReason::from_conf()
+        .to_err()
+        .with_detail("write output failed")
+        .with_source(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn bench(out_path: Option<PathBuf>) -> Workbench {
+        Workbench {
+            samples: Vec::new(),
+            line_max: Some(2),
+            source: None,
+            origin: PathBuf::from(DRAFT_ORIGIN),
+            rules: None,
+            oml: None,
+            knowledge: KnowledgeState {
+                work_root: PathBuf::from("."),
+                conf: None,
+                loaded: false,
+                base: None,
+            },
+            check: 2,
+            out_path,
+        }
+    }
+
+    #[test]
+    fn commands_parse_with_aliases_and_bare_expressions() {
+        assert_eq!(parse_command("   "), Command::Empty);
+        assert_eq!(
+            parse_command("(ip:sip,_^2)"),
+            Command::Rule("(ip:sip,_^2)".into())
+        );
+        assert_eq!(
+            parse_command(":rule  (digit:code) "),
+            Command::Rule("(digit:code)".into())
+        );
+        assert_eq!(
+            parse_command(":add 10.0.0.1 - -"),
+            Command::Add("10.0.0.1 - -".into())
+        );
+        assert_eq!(parse_command(":oml off"), Command::Oml(None));
+        assert_eq!(
+            parse_command(":oml models/oml/a.oml"),
+            Command::Oml(Some("models/oml/a.oml".into()))
+        );
+        assert_eq!(parse_command(":save"), Command::Save(None));
+        assert_eq!(parse_command(":q"), Command::Quit);
+        assert_eq!(parse_command(":load"), Command::Unknown(":load".into()));
+    }
+
+    #[test]
+    fn mode_and_check_level_are_validated() {
+        assert_eq!(parse_mode("i").expect("i"), Mode::Interactive);
+        assert_eq!(parse_mode("batch").expect("batch"), Mode::Batch);
+        assert!(parse_mode("x").is_err());
+        assert!(check_level(3).is_ok());
+        assert!(check_level(4).is_err());
+    }
+
+    #[test]
+    fn samples_skip_blank_lines_and_respect_line_max() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("sample.dat");
+        fs::write(&path, "a 1\r\n\n  \nb 2\nc 3\n").expect("write");
+        let mut bench = bench(None);
+        assert_eq!(bench.load_samples(&path).expect("load"), 2);
+        assert_eq!(bench.samples, vec!["a 1".to_string(), "b 2".to_string()]);
+    }
+
+    #[test]
+    fn repl_keeps_running_after_errors_and_saves_the_rule() {
+        let dir = tempdir().expect("tempdir");
+        let target = dir.path().join("out/draft.wpl");
+        let mut bench = bench(Some(target.clone()));
+        let mut out = Vec::new();
+        repl(
+            &mut bench,
+            ":save\n:add 10.0.0.1 200\n:show\n:bogus\n:quit\n:add never\n".as_bytes(),
+            &mut out,
+            &EnvDict::default(),
+        )
+        .expect("repl");
+        let out = String::from_utf8(out).expect("utf8");
+        assert!(out.contains("error: "));
+        assert!(out.contains("no rule to save"));
+        assert!(out.contains("sample #1 added"));
+        assert!(out.contains("    #1    10.0.0.1 200"));
+        assert!(out.contains("unknown command ':bogus'"));
+        assert_eq!(bench.samples.len(), 1);
+
+        bench.source = Some(wrap_expression(DRAFT_PACKAGE, DRAFT_RULE, "(ip:sip)"));
+        assert_eq!(bench.save(None).expect("save"), target);
+        assert!(fs::read_to_string(&target)
+            .expect("read saved")
+            .contains("rule main {"));
+    }
+
+    #[test]
+    fn real_rule_and_oml_transform_the_samples() {
+        let dir = tempdir().expect("tempdir");
+        let oml = dir.path().join("draft.oml");
+        fs::write(
+            &oml,
+            "name : draft\nrule : /draft/*\n---\nsrc : ip = read(sip) ;\nstatus : digit = read(code) ;\n",
+        )
+        .expect("write oml");
+        let mut bench = bench(None);
+        bench.knowledge.work_root = dir.path().to_path_buf();
+        bench.set_expression("(ip:sip,digit:code)").expect("rule");
+        bench.samples = vec!["10.0.0.1 200".into(), "not-an-ip 200".into()];
+        bench.load_oml(&oml, &EnvDict::default()).expect("load oml");
+        assert!(bench.knowledge.base.is_none());
+
+        let report = bench.analyse().expect("analyse");
+        assert_eq!((report.hit, report.miss), (1, 1));
+        let hit = &report.lines[0];
+        assert_eq!(hit.rule.as_deref(), Some("/draft/main"));
+        let value = |fields: &[ParsedField], name: &str| {
+            fields
+                .iter()
+                .find(|f| f.name == name)
+                .map(|f| f.value.clone())
+        };
+        assert_eq!(value(&hit.fields, "sip").as_deref(), Some("10.0.0.1"));
+        assert_eq!(value(&hit.fields, "code").as_deref(), Some("200"));
+        assert_eq!(value(&hit.oml, "src").as_deref(), Some("10.0.0.1"));
+        assert_eq!(value(&hit.oml, "status").as_deref(), Some("200"));
+        assert!(!report.lines[1].ok);
+        assert!(report.lines[1].reason.is_some());
+    }
+
+    #[test]
+    fn report_detail_follows_check_level() {
+        let report = AnalyseReport {
+            rule: DRAFT_ORIGIN.to_string(),
+            oml: None,
+            samples: 2,
+            hit: 1,
+            miss: 1,
+            lines: vec![
+                SampleOutcome {
+                    line: 1,
+                    ok: true,
+                    rule: Some("/draft/main".into()),
+                    fields: vec![ParsedField {
+                        name: "sip".into(),
+                        kind: "ip".into(),
+                        value: "10.0.0.1".into(),
+                    }],
+                    oml: vec![ParsedField {
+                        name: "src".into(),
+                        kind: "ip".into(),
+                        value: "10.0.0.1".into(),
+                    }],
+                    column: None,
+                    reason: None,
+                },
+                SampleOutcome {
+                    line: 2,
+                    ok: false,
+                    rule: Some("/draft/main".into()),
+                    fields: Vec::new(),
+                    oml: Vec::new(),
+                    column: Some(4),
+                    reason: Some("expect ip".into()),
+                },
+            ],
+        };
+        let render = |check| {
+            let mut out = Vec::new();
+            render_report(&report, check, &mut out).expect("render");
+            String::from_utf8(out).expect("utf8")
+        };
+        assert_eq!(render(0), "  Summary : 2 samples, 1 hit, 1 miss\n");
+        let detailed = render(3);
+        assert!(detailed.contains("  #1    hit   /draft/main"));
+        assert!(detailed.contains("        sip : ip = 10.0.0.1"));
+        assert!(detailed.contains("        oml:\n        src : ip = 10.0.0.1"));
+        assert!(detailed.contains("  #2    miss  /draft/main  col 4: expect ip"));
+        assert!(!render(1).contains("sip"));
+    }
+}
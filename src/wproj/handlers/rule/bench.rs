@@ -0,0 +1,542 @@
+//! `wproj rule bench`：逐个规则包回放样本，统计吞吐、延迟、分配与命中率。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use orion_error::conversion::ToStructError;
+use serde::{Deserialize, Serialize};
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+
+use super::wpl::{collect_wpl_files, default_rule_root, read_source, RuleSet};
+use crate::alloc_count::count_allocations;
+use crate::args::RuleBenchArgs;
+use crate::format::print_json;
+
+const SAMPLE_FILE: &str = "sample.dat";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RuleBench {
+    rule: String,
+    hits: usize,
+    match_ratio: f64,
+    p50_us: f64,
+    p99_us: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PackageBench {
+    package: String,
+    samples: usize,
+    events: usize,
+    eps: f64,
+    p50_us: f64,
+    p99_us: f64,
+    allocs_per_event: f64,
+    match_ratio: f64,
+    rules: Vec<RuleBench>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BenchReport {
+    events_per_package: usize,
+    packages: Vec<PackageBench>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    regressions: Vec<Regression>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Regression {
+    package: String,
+    metric: String,
+    baseline: f64,
+    current: f64,
+    change_pct: f64,
+}
+
+/// 单个事件的测量值 | Measurements of one event.
+struct Sample {
+    rule: Option<String>,
+    nanos: u64,
+    allocs: u64,
+}
+
+pub(super) fn run_rule_bench(args: RuleBenchArgs) -> RunResult<()> {
+    if args.events == 0 {
+        return Err(bench_conf_err("--events must be at least 1".to_string()));
+    }
+    let rule_root = args
+        .rule_file
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_rule_root(&args.work_root));
+    let shared_samples = if args.in_path.is_empty() {
+        None
+    } else {
+        let mut lines = Vec::new();
+        for path in &args.in_path {
+            lines.extend(read_samples(Path::new(path))?);
+        }
+        Some(lines)
+    };
+    let baseline = args
+        .baseline
+        .as_deref()
+        .map(|path| load_report(Path::new(path)))
+        .transpose()?;
+
+    let mut report = BenchReport {
+        events_per_package: args.events,
+        ..BenchReport::default()
+    };
+    for file in collect_wpl_files(&rule_root)? {
+        let name = file
+            .strip_prefix(&rule_root)
+            .ok()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(&file)
+            .display()
+            .to_string();
+        let samples = match &shared_samples {
+            Some(lines) => lines.clone(),
+            None => {
+                let path = file
+                    .parent()
+                    .map(|dir| dir.join(SAMPLE_FILE))
+                    .filter(|p| p.is_file());
+                match path {
+                    Some(path) => read_samples(&path)?,
+                    None => Vec::new(),
+                }
+            }
+        };
+        if samples.is_empty() {
+            report.skipped.push(format!("{}: no samples", name));
+            continue;
+        }
+        let rules = RuleSet::from_code(&file, &read_source(&file)?)?;
+        let measured = replay(
+            &samples,
+            args.events,
+            args.warmup,
+            |line| rules.eval_record(line),
+            |outcome| outcome.ok().map(|(rule, _)| rule.to_string()),
+        );
+        report
+            .packages
+            .push(summarize(name, samples.len(), &measured.0, measured.1));
+    }
+    if report.packages.is_empty() {
+        return Err(bench_conf_err(format!(
+            "no package with samples under {}: pass --in-path or add {} next to the rules",
+            rule_root.display(),
+            SAMPLE_FILE
+        )));
+    }
+    if let Some(baseline) = baseline.as_ref() {
+        report.regressions = compare_baseline(&report, baseline, args.max_regression);
+    }
+    if let Some(path) = args.save_baseline.as_deref() {
+        save_report(Path::new(path), &report)?;
+    }
+
+    if args.json {
+        print_json(&report)?;
+    } else {
+        render(&report, baseline.is_some());
+    }
+    if !report.regressions.is_empty() {
+        return Err(RunReason::from_biz().to_err().with_detail(format!(
+            "{} performance regressions beyond {:.1}% against the baseline",
+            report.regressions.len(),
+            args.max_regression
+        )));
+    }
+    Ok(())
+}
+
+fn read_samples(path: &Path) -> RunResult<Vec<String>> {
+    Ok(read_source(path)?
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// 循环回放样本直到凑满 `events` 个事件；预热阶段不计入。只有 `eval` 在计时与
+/// 计数窗口内，命中规则名由 `rule_of` 在窗口外取出 |
+/// Cycle through the samples until `events` events are measured, after an unmeasured
+/// warmup. Only `eval` is timed and counted; `rule_of` names the hit outside the window.
+fn replay<T, F, R>(
+    samples: &[String],
+    events: usize,
+    warmup: usize,
+    mut eval: F,
+    rule_of: R,
+) -> (Vec<Sample>, f64)
+where
+    F: FnMut(&str) -> T,
+    R: Fn(T) -> Option<String>,
+{
+    for line in samples.iter().cycle().take(warmup) {
+        let _ = eval(line);
+    }
+    let mut measured = Vec::with_capacity(events);
+    let mut eval_nanos = 0u64;
+    for line in samples.iter().cycle().take(events) {
+        let ((outcome, nanos), allocs) = count_allocations(|| {
+            let begin = Instant::now();
+            let outcome = eval(line);
+            (outcome, begin.elapsed().as_nanos() as u64)
+        });
+        eval_nanos += nanos;
+        measured.push(Sample {
+            rule: rule_of(outcome),
+            nanos,
+            allocs,
+        });
+    }
+    (measured, eval_nanos as f64 / 1e9)
+}
+
+fn summarize(package: String, samples: usize, measured: &[Sample], seconds: f64) -> PackageBench {
+    let events = measured.len();
+    let mut all: Vec<u64> = measured.iter().map(|s| s.nanos).collect();
+    let mut per_rule: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+    for sample in measured {
+        if let Some(rule) = sample.rule.as_deref() {
+            per_rule.entry(rule).or_default().push(sample.nanos);
+        }
+    }
+    let hits: usize = per_rule.values().map(Vec::len).sum();
+    let rules = per_rule
+        .into_iter()
+        .map(|(rule, mut nanos)| RuleBench {
+            rule: rule.to_string(),
+            hits: nanos.len(),
+            match_ratio: ratio(nanos.len(), events),
+            p50_us: percentile_us(&mut nanos, 50.0),
+            p99_us: percentile_us(&mut nanos, 99.0),
+        })
+        .collect();
+    PackageBench {
+        package,
+        samples,
+        events,
+        eps: if seconds > 0.0 {
+            events as f64 / seconds
+        } else {
+            0.0
+        },
+        p50_us: percentile_us(&mut all, 50.0),
+        p99_us: percentile_us(&mut all, 99.0),
+        allocs_per_event: measured.iter().map(|s| s.allocs).sum::<u64>() as f64
+            / events.max(1) as f64,
+        match_ratio: ratio(hits, events),
+        rules,
+    }
+}
+
+/// 最近秩法求分位数，单位微秒 | Nearest-rank percentile, in microseconds.
+fn percentile_us(nanos: &mut [u64], pct: f64) -> f64 {
+    if nanos.is_empty() {
+        return 0.0;
+    }
+    nanos.sort_unstable();
+    let rank = ((pct / 100.0) * nanos.len() as f64).ceil() as usize;
+    nanos[rank.clamp(1, nanos.len()) - 1] as f64 / 1000.0
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// 吞吐下降或 p99 上升超过阈值即视为退化 | Lower EPS or higher p99 beyond the threshold is a regression.
+fn compare_baseline(
+    current: &BenchReport,
+    baseline: &BenchReport,
+    max_pct: f64,
+) -> Vec<Regression> {
+    let mut regressions = Vec::new();
+    for package in &current.packages {
+        let Some(base) = baseline
+            .packages
+            .iter()
+            .find(|b| b.package == package.package)
+        else {
+            continue;
+        };
+        let checks = [
+            ("eps", base.eps, package.eps, -1.0),
+            ("p99_us", base.p99_us, package.p99_us, 1.0),
+        ];
+        for (metric, before, after, worse_sign) in checks {
+            if before <= 0.0 {
+                continue;
+            }
+            let change_pct = (after - before) * 100.0 / before;
+            if change_pct * worse_sign > max_pct {
+                regressions.push(Regression {
+                    package: package.package.clone(),
+                    metric: metric.to_string(),
+                    baseline: before,
+                    current: after,
+                    change_pct,
+                });
+            }
+        }
+    }
+    regressions
+}
+
+fn load_report(path: &Path) -> RunResult<BenchReport> {
+    serde_json::from_str(&read_source(path)?).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("parse baseline {} failed", path.display()))
+            .with_source(e)
+    })
+}
+
+fn save_report(path: &Path, report: &BenchReport) -> RunResult<()> {
+    let baseline = BenchReport {
+        regressions: Vec::new(),
+        ..report.clone()
+    };
+    let body = serde_json::to_string_pretty(&baseline).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail("serialize baseline failed")
+            .with_source(e)
+    })?;
+    fs::write(path, body).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("write baseline {} failed", path.display()))
+            .with_source(e)
+    })
+}
+
+fn render(report: &BenchReport, with_baseline: bool) {
+    println!(
+        "Rule bench ({} events per package)",
+        report.events_per_package
+    );
+    let mut rows = vec![[
+        "PACKAGE".to_string(),
+        "RULE".to_string(),
+        "EVENTS/S".to_string(),
+        "P50(us)".to_string(),
+        "P99(us)".to_string(),
+        "ALLOC/EV".to_string(),
+        "MATCH".to_string(),
+    ]];
+    for package in &report.packages {
+        rows.push([
+            package.package.clone(),
+            "*".to_string(),
+            format!("{:.0}", package.eps),
+            format!("{:.2}", package.p50_us),
+            format!("{:.2}", package.p99_us),
+            format!("{:.1}", package.allocs_per_event),
+            format!("{:.2}%", package.match_ratio),
+        ]);
+        for rule in &package.rules {
+            rows.push([
+                String::new(),
+                rule.rule.clone(),
+                "-".to_string(),
+                format!("{:.2}", rule.p50_us),
+                format!("{:.2}", rule.p99_us),
+                "-".to_string(),
+                format!("{:.2}%", rule.match_ratio),
+            ]);
+        }
+    }
+    let widths: Vec<usize> = (0..6)
+        .map(|col| {
+            rows.iter()
+                .map(|r| r[col].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in &rows {
+        println!(
+            "  {:<w0$}  {:<w1$}  {:>w2$}  {:>w3$}  {:>w4$}  {:>w5$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            row[5],
+            row[6],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+            w4 = widths[4],
+            w5 = widths[5]
+        );
+    }
+    for skipped in &report.skipped {
+        println!("  Skipped : {}", skipped);
+    }
+    if with_baseline {
+        if report.regressions.is_empty() {
+            println!("  Baseline: no regression");
+        }
+        for r in &report.regressions {
+            println!(
+                "  Baseline: {} {} {:.2} -> {:.2} ({:+.1}%) REGRESSED",
+                r.package, r.metric, r.baseline, r.current, r.change_pct
+            );
+        }
+    }
+}
+
+fn bench_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::wpl::fixtures::*;
+    use super::*;
+
+    fn package(name: &str, eps: f64, p99_us: f64) -> PackageBench {
+        PackageBench {
+            package: name.to_string(),
+            samples: 1,
+            events: 1,
+            eps,
+            p50_us: 0.0,
+            p99_us,
+            allocs_per_event: 0.0,
+            match_ratio: 100.0,
+            rules: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let mut nanos: Vec<u64> = (1..=100).rev().map(|n| n * 1000).collect();
+        assert_eq!(percentile_us(&mut nanos, 50.0), 50.0);
+        assert_eq!(percentile_us(&mut nanos, 99.0), 99.0);
+        assert_eq!(percentile_us(&mut [], 99.0), 0.0);
+        assert_eq!(percentile_us(&mut [7000], 1.0), 7.0);
+    }
+
+    #[test]
+    fn replay_cycles_samples_and_attributes_hits_to_rules() {
+        let samples = vec!["a 1".to_string(), "b x".to_string(), "c 2".to_string()];
+        let mut calls = 0;
+        let (measured, _) = replay(
+            &samples,
+            6,
+            2,
+            |line| {
+                calls += 1;
+                line.ends_with(|c: char| c.is_ascii_digit())
+            },
+            |hit| hit.then(|| "/demo/num".to_string()),
+        );
+        assert_eq!(calls, 8);
+        assert_eq!(measured.len(), 6);
+
+        let bench = summarize("demo.wpl".into(), samples.len(), &measured, 0.5);
+        assert_eq!(bench.events, 6);
+        assert_eq!(bench.eps, 12.0);
+        assert_eq!(bench.rules.len(), 1);
+        assert_eq!(bench.rules[0].hits, 4);
+        assert!((bench.match_ratio - 66.666).abs() < 0.01);
+    }
+
+    #[test]
+    fn real_package_is_benched_from_its_samples() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let package = write_nginx_package(dir.path());
+        fs::write(
+            package.join(SAMPLE_FILE),
+            format!("{}\nnot an access log line\n", NGINX_LINE),
+        )
+        .expect("write samples");
+        let baseline = dir.path().join("baseline.json");
+        run_rule_bench(RuleBenchArgs {
+            work_root: dir.path().display().to_string(),
+            rule_file: Some(dir.path().display().to_string()),
+            in_path: Vec::new(),
+            events: 20,
+            warmup: 2,
+            baseline: None,
+            save_baseline: Some(baseline.display().to_string()),
+            max_regression: 10.0,
+            json: false,
+        })
+        .expect("bench");
+
+        let report = load_report(&baseline).expect("load");
+        let bench = &report.packages[0];
+        assert_eq!(bench.package, "example/parse.wpl");
+        assert_eq!((bench.samples, bench.events), (2, 20));
+        assert_eq!(bench.match_ratio, 50.0);
+        assert_eq!(bench.rules[0].rule, NGINX_RULE);
+        assert_eq!(bench.rules[0].hits, 10);
+        assert!(bench.eps > 0.0);
+    }
+
+    #[test]
+    fn baseline_flags_throughput_drops_and_latency_growth_only() {
+        let baseline = BenchReport {
+            packages: vec![
+                package("a.wpl", 1000.0, 10.0),
+                package("b.wpl", 1000.0, 10.0),
+            ],
+            ..BenchReport::default()
+        };
+        let current = BenchReport {
+            packages: vec![
+                package("a.wpl", 850.0, 10.5),
+                package("b.wpl", 2000.0, 5.0),
+                package("new.wpl", 1.0, 1000.0),
+            ],
+            ..BenchReport::default()
+        };
+        let regressions = compare_baseline(&current, &baseline, 10.0);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].package, "a.wpl");
+        assert_eq!(regressions[0].metric, "eps");
+        assert!((regressions[0].change_pct + 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn saved_baseline_round_trips_without_regressions() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("baseline.json");
+        let report = BenchReport {
+            events_per_package: 10,
+            packages: vec![package("a.wpl", 1000.0, 10.0)],
+            skipped: Vec::new(),
+            regressions: vec![Regression {
+                package: "a.wpl".into(),
+                metric: "eps".into(),
+                baseline: 1.0,
+                current: 0.5,
+                change_pct: -50.0,
+            }],
+        };
+        save_report(&path, &report).expect("save");
+        let loaded = load_report(&path).expect("load");
+        assert_eq!(loaded.packages, report.packages);
+        assert!(loaded.regressions.is_empty());
+    }
+}
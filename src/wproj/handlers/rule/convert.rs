@@ -0,0 +1,1230 @@
+//! `wproj rule convert`：把 Grok / 正则 / Vector VRL 中的解析模式转换为 WPL 规则包。
+//!
+//! 以空白切分模式，逐段映射为 WPL 字段；无法对应的片段生成 `chars:todo_N`
+//! 占位字段并在报告中列出原文。`--sample` 会用原模式与生成的规则分别解析样本并比对。
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use orion_error::conversion::ToStructError;
+use regex::Regex;
+use serde::Serialize;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+
+use super::infer::{compact_skips, sanitize, write_file};
+use super::wpl::{default_rule_root, read_source, wrap_expression, LineMatch, RuleSet};
+use crate::args::{ConvertFrom, RuleConvertArgs};
+use crate::format::print_json;
+
+const RULE_FILE: &str = "parse.wpl";
+const MAX_DEPTH: usize = 16;
+const MAX_EXAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WplKind {
+    Ip,
+    Digit,
+    Float,
+    Time,
+    ClfTime,
+    HttpRequest,
+    Chars,
+}
+
+impl WplKind {
+    fn type_name(self) -> &'static str {
+        match self {
+            WplKind::Ip => "ip",
+            WplKind::Digit => "digit",
+            WplKind::Float => "float",
+            WplKind::Time => "time",
+            WplKind::ClfTime => "time/clf",
+            WplKind::HttpRequest => "http/request",
+            WplKind::Chars => "chars",
+        }
+    }
+
+    /// 时间与小数在 WPL 中会被规范化，比对时只看是否提取到 |
+    /// Times and decimals are normalised by WPL, so only their presence is compared.
+    fn compare_value(self) -> bool {
+        !matches!(self, WplKind::Time | WplKind::ClfTime | WplKind::Float)
+    }
+}
+
+/// Grok 基础模式：名称、正则、WPL 类型、取值是否带引号 |
+/// Base grok patterns: name, regex, WPL type and whether the match includes quotes.
+const GROK_BASE: &[(&str, &str, WplKind, bool)] = &[
+    ("INT", r"[+-]?\d+", WplKind::Digit, false),
+    ("POSINT", r"\d+", WplKind::Digit, false),
+    ("NONNEGINT", r"\d+", WplKind::Digit, false),
+    (
+        "NUMBER",
+        r"[+-]?(?:\d+(?:\.\d*)?|\.\d+)",
+        WplKind::Float,
+        false,
+    ),
+    (
+        "BASE10NUM",
+        r"[+-]?(?:\d+(?:\.\d*)?|\.\d+)",
+        WplKind::Float,
+        false,
+    ),
+    ("IPV4", r"(?:\d{1,3}\.){3}\d{1,3}", WplKind::Ip, false),
+    ("IPV6", r"[0-9A-Fa-f]*:[0-9A-Fa-f:.]*", WplKind::Ip, false),
+    (
+        "IP",
+        r"(?:[0-9A-Fa-f]*:[0-9A-Fa-f:.]*|(?:\d{1,3}\.){3}\d{1,3})",
+        WplKind::Ip,
+        false,
+    ),
+    (
+        "HOSTNAME",
+        r"[0-9A-Za-z][0-9A-Za-z.-]*",
+        WplKind::Chars,
+        false,
+    ),
+    ("IPORHOST", r"[0-9A-Za-z:.-]+", WplKind::Chars, false),
+    ("WORD", r"\w+", WplKind::Chars, false),
+    ("NOTSPACE", r"\S+", WplKind::Chars, false),
+    ("USER", r"[a-zA-Z0-9._-]+", WplKind::Chars, false),
+    ("USERNAME", r"[a-zA-Z0-9._-]+", WplKind::Chars, false),
+    ("HTTPDUSER", r"\S+", WplKind::Chars, false),
+    ("EMAILADDRESS", r"\S+@\S+", WplKind::Chars, false),
+    (
+        "UUID",
+        r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}",
+        WplKind::Chars,
+        false,
+    ),
+    (
+        "MAC",
+        r"(?:[A-Fa-f0-9]{2}[:-]){5}[A-Fa-f0-9]{2}",
+        WplKind::Chars,
+        false,
+    ),
+    ("PATH", r"\S+", WplKind::Chars, false),
+    ("URIPATH", r"/[^\s?#]*", WplKind::Chars, false),
+    ("URIPATHPARAM", r"\S+", WplKind::Chars, false),
+    ("URI", r"\S+", WplKind::Chars, false),
+    ("DATA", r".*?", WplKind::Chars, false),
+    ("GREEDYDATA", r".*", WplKind::Chars, false),
+    ("QS", r#""(?:[^"\\]|\\.)*""#, WplKind::Chars, true),
+    ("QUOTEDSTRING", r#""(?:[^"\\]|\\.)*""#, WplKind::Chars, true),
+    (
+        "LOGLEVEL",
+        r"(?i:trace|debug|info|notice|warn(?:ing)?|err(?:or)?|crit(?:ical)?|fatal|severe|emerg(?:ency)?|alert)",
+        WplKind::Chars,
+        false,
+    ),
+    (
+        "HTTPDATE",
+        r"\d{2}/\w{3}/\d{4}:\d{2}:\d{2}:\d{2} [+-]\d{4}",
+        WplKind::ClfTime,
+        false,
+    ),
+    (
+        "TIMESTAMP_ISO8601",
+        r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z|[+-]\d{2}:?\d{2})?",
+        WplKind::Time,
+        false,
+    ),
+    (
+        "HTTPREQUEST",
+        r"\w+ \S+(?: HTTP/[\d.]+)?",
+        WplKind::HttpRequest,
+        false,
+    ),
+];
+
+/// 常用组合模式（按 WPL 可表达的形式简化）| Common composite patterns, simplified to what WPL can express.
+const GROK_COMPOSITE: &[(&str, &str)] = &[
+    (
+        "COMMONAPACHELOG",
+        r#"%{IPORHOST:clientip} %{HTTPDUSER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] "%{HTTPREQUEST:request}" %{INT:response} %{NOTSPACE:bytes}"#,
+    ),
+    (
+        "COMBINEDAPACHELOG",
+        r#"%{COMMONAPACHELOG} %{QS:referrer} %{QS:agent}"#,
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Field {
+        kind: WplKind,
+        name: Option<String>,
+        quoted: bool,
+        raw: String,
+    },
+    Lit(String),
+    Space,
+    Todo(String),
+}
+
+impl Piece {
+    fn raw(&self) -> String {
+        match self {
+            Piece::Field { raw, .. } | Piece::Lit(raw) | Piece::Todo(raw) => raw.clone(),
+            Piece::Space => " ".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Grok,
+    Regex,
+}
+
+/// 待转换的一条模式 | One pattern to convert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Source {
+    name: String,
+    syntax: Syntax,
+    pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Todo {
+    marker: String,
+    original: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Converted {
+    rule: String,
+    pattern: String,
+    expression: String,
+    todos: Vec<Todo>,
+    #[serde(skip)]
+    fields: BTreeMap<String, WplKind>,
+    #[serde(skip)]
+    regex: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct RuleCheck {
+    rule: String,
+    both: usize,
+    original_only: usize,
+    wpl_only: usize,
+    value_mismatch: usize,
+    examples: Vec<String>,
+}
+
+/// Grok 模式库：内置模式加上用户定义 | Grok library: built-in patterns plus user definitions.
+struct Grok {
+    defs: BTreeMap<String, String>,
+}
+
+impl Grok {
+    fn new(custom: BTreeMap<String, String>) -> Self {
+        let mut defs: BTreeMap<String, String> = GROK_COMPOSITE
+            .iter()
+            .map(|(name, body)| (name.to_string(), body.to_string()))
+            .collect();
+        defs.extend(custom);
+        Self { defs }
+    }
+
+    fn base(name: &str) -> Option<&'static (&'static str, &'static str, WplKind, bool)> {
+        GROK_BASE.iter().find(|(base, ..)| *base == name)
+    }
+
+    /// 展开为正则，供核对时使用 | Expand into a regex for verification.
+    fn to_regex(&self, pattern: &str, depth: usize, names: &mut Names) -> Result<String, String> {
+        if depth > MAX_DEPTH {
+            return Err("grok patterns nest too deep".to_string());
+        }
+        let mut out = String::new();
+        let mut rest = pattern;
+        while let Some(start) = rest.find("%{") {
+            out.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .map(|e| start + e)
+                .ok_or_else(|| format!("unclosed %{{ in {}", pattern))?;
+            let (name, field) = grok_spec(&rest[start + 2..end]);
+            let body = match Self::base(name) {
+                Some((_, regex, ..)) => regex.to_string(),
+                None => match self.defs.get(name) {
+                    Some(def) => self.to_regex(def, depth + 1, names)?,
+                    None => return Err(format!("unknown grok pattern {}", name)),
+                },
+            };
+            match field {
+                Some(field) => out.push_str(&format!("(?P<{}>{})", names.next(&field), body)),
+                None => out.push_str(&format!("(?:{})", body)),
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn to_pieces(&self, pattern: &str, depth: usize) -> Vec<Piece> {
+        if depth > MAX_DEPTH {
+            return vec![Piece::Todo(pattern.to_string())];
+        }
+        let mut pieces = Vec::new();
+        let mut rest = pattern;
+        while let Some(start) = rest.find("%{") {
+            pieces.extend(lex_regex(&rest[..start]));
+            let Some(end) = rest[start..].find('}').map(|e| start + e) else {
+                pieces.push(Piece::Todo(rest[start..].to_string()));
+                return pieces;
+            };
+            let raw = &rest[start..=end];
+            let (name, field) = grok_spec(&rest[start + 2..end]);
+            if name == "SPACE" {
+                pieces.push(Piece::Space);
+            } else if let Some((_, _, kind, quoted)) = Self::base(name) {
+                pieces.push(Piece::Field {
+                    kind: *kind,
+                    name: field,
+                    quoted: *quoted,
+                    raw: raw.to_string(),
+                });
+            } else if let Some(def) = self.defs.get(name) {
+                pieces.extend(self.to_pieces(def, depth + 1));
+            } else {
+                pieces.push(Piece::Todo(raw.to_string()));
+            }
+            rest = &rest[end + 1..];
+        }
+        pieces.extend(lex_regex(rest));
+        pieces
+    }
+}
+
+/// `%{NAME:field:type}` 中的名称与字段 | Name and field of a `%{NAME:field:type}` reference.
+fn grok_spec(spec: &str) -> (&str, Option<String>) {
+    let mut parts = spec.splitn(3, ':');
+    let name = parts.next().unwrap_or("").trim();
+    let field = parts.next().map(field_name).filter(|f| !f.is_empty());
+    (name, field)
+}
+
+/// `[http][status]` 之类的嵌套字段名写成 `http_status` | Nested names become `http_status`.
+fn field_name(raw: &str) -> String {
+    let name = sanitize(raw.trim().trim_matches(['[', ']']));
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if !(c == '_' && out.ends_with('_')) {
+            out.push(c);
+        }
+    }
+    out.trim_matches('_').to_string()
+}
+
+/// 为重复的字段名追加序号，保证 WPL 与正则两侧一致 |
+/// Suffix repeated field names the same way on the WPL and regex sides.
+#[derive(Default)]
+struct Names {
+    used: BTreeMap<String, usize>,
+}
+
+impl Names {
+    fn next(&mut self, base: &str) -> String {
+        let count = self.used.entry(base.to_string()).or_default();
+        *count += 1;
+        if *count == 1 {
+            base.to_string()
+        } else {
+            format!("{}_{}", base, count)
+        }
+    }
+}
+
+/// 把正则片段切成字面量、空白、命名捕获与无法转换的部分 |
+/// Split a regex fragment into literals, whitespace, named captures and untranslatable parts.
+fn lex_regex(text: &str) -> Vec<Piece> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut pieces = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '^' if i == 0 => i += 1,
+            '$' if i + 1 == chars.len() => i += 1,
+            ' ' | '\t' => {
+                i += 1;
+                while i < chars.len() && chars[i] == ' ' {
+                    i += 1;
+                }
+                i = skip_quantifier(&chars, i);
+                pieces.push(Piece::Space);
+            }
+            '\\' if i + 1 < chars.len() => {
+                let next = chars[i + 1];
+                let end = skip_quantifier(&chars, i + 2);
+                match next {
+                    's' | 't' => pieces.push(Piece::Space),
+                    'b' | 'B' => {}
+                    c if c.is_ascii_alphanumeric() => {
+                        pieces.push(Piece::Todo(chars[i..end].iter().collect()))
+                    }
+                    c if end > i + 2 => pieces.push(Piece::Todo(format!(
+                        "{}{}",
+                        c,
+                        chars[i + 2..end].iter().collect::<String>()
+                    ))),
+                    c => pieces.push(Piece::Lit(c.to_string())),
+                }
+                i = end;
+            }
+            '(' => {
+                let close = closing(&chars, i, '(', ')');
+                let end = skip_quantifier(&chars, close + 1);
+                let group: String = chars[i..=close.min(chars.len() - 1)].iter().collect();
+                let raw: String = chars[i..end.min(chars.len())].iter().collect();
+                let inner = &group[1..group.len().saturating_sub(1)];
+                match named_group(inner) {
+                    Some((name, body)) if end == close + 1 => {
+                        let (kind, quoted) = classify_regex(body);
+                        pieces.push(Piece::Field {
+                            kind,
+                            name: Some(field_name(name)),
+                            quoted,
+                            raw,
+                        });
+                    }
+                    _ => pieces.push(Piece::Todo(raw)),
+                }
+                i = end;
+            }
+            '[' => {
+                let close = closing(&chars, i, '[', ']');
+                let end = skip_quantifier(&chars, close + 1);
+                pieces.push(Piece::Todo(chars[i..end.min(chars.len())].iter().collect()));
+                i = end;
+            }
+            '.' | '|' | '*' | '+' | '?' | '{' => {
+                let end = skip_quantifier(&chars, i + 1).max(i + 1);
+                pieces.push(Piece::Todo(chars[i..end].iter().collect()));
+                i = end;
+            }
+            c => {
+                let end = skip_quantifier(&chars, i + 1);
+                if end > i + 1 {
+                    pieces.push(Piece::Todo(chars[i..end].iter().collect()));
+                } else {
+                    pieces.push(Piece::Lit(c.to_string()));
+                }
+                i = end;
+            }
+        }
+    }
+    merge_adjacent(pieces)
+}
+
+fn skip_quantifier(chars: &[char], mut i: usize) -> usize {
+    if i >= chars.len() {
+        return i;
+    }
+    match chars[i] {
+        '*' | '+' | '?' => i += 1,
+        '{' => {
+            if let Some(len) = chars[i..].iter().position(|&c| c == '}') {
+                i += len + 1;
+            }
+        }
+        _ => return i,
+    }
+    if i < chars.len() && matches!(chars[i], '?' | '+') {
+        i += 1;
+    }
+    i
+}
+
+/// 配对括号的位置，跳过转义与字符类 | Index of the matching bracket, skipping escapes and classes.
+fn closing(chars: &[char], open_at: usize, open: char, close: char) -> usize {
+    let mut depth = 0;
+    let mut i = open_at;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' if open != '[' => i = closing(chars, i, '[', ']'),
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    chars.len().saturating_sub(1)
+}
+
+fn named_group(inner: &str) -> Option<(&str, &str)> {
+    let rest = inner
+        .strip_prefix("?P<")
+        .or_else(|| inner.strip_prefix("?<"))?;
+    if rest.starts_with(['=', '!']) {
+        return None;
+    }
+    rest.split_once('>')
+}
+
+/// 按捕获组内容推断 WPL 类型 | Guess the WPL type from a capture group's body.
+fn classify_regex(body: &str) -> (WplKind, bool) {
+    match body {
+        r"\d+" | "[0-9]+" | r"[+-]?\d+" | r"-?\d+" => (WplKind::Digit, false),
+        r"\d+\.\d+" | r"[\d.]+" | "[0-9.]+" | r"-?\d+\.\d+" => (WplKind::Float, false),
+        _ if body.contains(r"\d{1,3}") && body.contains(r"\.") => (WplKind::Ip, false),
+        _ if body.starts_with('"') && body.ends_with('"') => (WplKind::Chars, true),
+        _ => (WplKind::Chars, false),
+    }
+}
+
+fn merge_adjacent(pieces: Vec<Piece>) -> Vec<Piece> {
+    let mut out: Vec<Piece> = Vec::with_capacity(pieces.len());
+    for piece in pieces {
+        match (out.last_mut(), piece) {
+            (Some(Piece::Lit(prev)), Piece::Lit(next)) => prev.push_str(&next),
+            (Some(Piece::Todo(prev)), Piece::Todo(next)) => prev.push_str(&next),
+            (Some(Piece::Space), Piece::Space) => {}
+            (_, piece) => out.push(piece),
+        }
+    }
+    out
+}
+
+/// 按空白分段；以引号或方括号开头的段会并入后续段直到闭合 |
+/// Split at whitespace; a slot opened by a quote or bracket runs until it closes.
+fn slots(pieces: &[Piece]) -> Vec<Vec<Piece>> {
+    let mut raw: Vec<Vec<Piece>> = vec![Vec::new()];
+    for piece in pieces {
+        match piece {
+            Piece::Space => raw.push(Vec::new()),
+            other => raw.last_mut().expect("slot").push(other.clone()),
+        }
+    }
+    let mut out: Vec<Vec<Piece>> = Vec::new();
+    let mut open: Option<char> = None;
+    for slot in raw.into_iter().filter(|s| !s.is_empty()) {
+        if open.is_some() {
+            let current = out.last_mut().expect("open slot");
+            current.push(Piece::Space);
+            current.extend(slot);
+        } else {
+            out.push(slot);
+        }
+        let current = out.last().expect("slot");
+        let first = match current.first() {
+            Some(Piece::Lit(lit)) => lit.chars().next(),
+            _ => None,
+        };
+        let closer = match first {
+            Some('"') => Some('"'),
+            Some('[') => Some(']'),
+            _ => None,
+        };
+        open = closer.filter(|closer| {
+            let closed = match current.last() {
+                Some(Piece::Lit(lit)) => {
+                    lit.ends_with(*closer) && !(current.len() == 1 && lit.chars().count() == 1)
+                }
+                _ => false,
+            };
+            !closed
+        });
+    }
+    out
+}
+
+/// 渲染一条规则表达式 | Render one rule expression.
+fn render(pieces: &[Piece], names: &mut Names, todo_seq: &mut usize) -> Converted {
+    let mut items = Vec::new();
+    let mut todos = Vec::new();
+    let mut fields = BTreeMap::new();
+    for slot in slots(pieces) {
+        let (wrap, inner) = unwrap_slot(&slot);
+        let item = match inner.as_slice() {
+            [Piece::Field {
+                kind, name, quoted, ..
+            }] => {
+                let name = name.as_deref().map(|n| names.next(n));
+                if let Some(name) = &name {
+                    fields.insert(name.clone(), *kind);
+                }
+                let mut item = match &name {
+                    Some(name) => format!("{}:{}", kind.type_name(), name),
+                    None => kind.type_name().to_string(),
+                };
+                match wrap {
+                    Some('[') => item.push_str("<[,]>"),
+                    Some(_) => item.push('"'),
+                    None if *quoted => item.push('"'),
+                    None => {}
+                }
+                item
+            }
+            _ if inner.iter().all(|p| matches!(p, Piece::Lit(_))) => "_".to_string(),
+            _ => {
+                *todo_seq += 1;
+                let marker = format!("todo_{}", todo_seq);
+                todos.push(Todo {
+                    marker: marker.clone(),
+                    original: slot.iter().map(Piece::raw).collect(),
+                });
+                match wrap {
+                    Some('[') => format!("chars:{}<[,]>", marker),
+                    Some(_) => format!("chars:{}\"", marker),
+                    None => format!("chars:{}", marker),
+                }
+            }
+        };
+        items.push(item);
+    }
+    Converted {
+        rule: String::new(),
+        pattern: String::new(),
+        expression: format!("({})", compact_skips(items).join(",")),
+        todos,
+        fields,
+        regex: None,
+    }
+}
+
+/// 去掉成对的引号或方括号字面量 | Strip a matching pair of quote or bracket literals.
+fn unwrap_slot(slot: &[Piece]) -> (Option<char>, Vec<Piece>) {
+    let mut inner = slot.to_vec();
+    let (Some(Piece::Lit(first)), Some(Piece::Lit(last))) = (inner.first(), inner.last()) else {
+        return (None, inner);
+    };
+    let open = match first.chars().next() {
+        Some('"') => '"',
+        Some('[') => '[',
+        _ => return (None, inner),
+    };
+    let close = if open == '[' { ']' } else { '"' };
+    if inner.len() < 2 || !last.ends_with(close) {
+        return (None, inner);
+    }
+    let first_rest = first[1..].to_string();
+    let last_rest = last[..last.len() - 1].to_string();
+    let tail = inner.len() - 1;
+    inner[tail] = Piece::Lit(last_rest);
+    inner[0] = Piece::Lit(first_rest);
+    inner.retain(|p| !matches!(p, Piece::Lit(l) if l.is_empty()));
+    (Some(open), inner)
+}
+
+pub(super) fn run_rule_convert(args: RuleConvertArgs) -> RunResult<()> {
+    let in_path = Path::new(&args.in_path);
+    let text = read_source(in_path)?;
+    let mut custom = BTreeMap::new();
+    for path in &args.patterns {
+        custom.extend(grok_definitions(&read_source(Path::new(path))?));
+    }
+    let sources = match args.from {
+        ConvertFrom::Grok => grok_sources(&text, &mut custom),
+        ConvertFrom::Regex => regex_sources(&text),
+        ConvertFrom::VectorVrlParse => vrl_sources(&text),
+    };
+    if sources.is_empty() {
+        return Err(convert_conf_err(format!(
+            "no pattern found in {}",
+            in_path.display()
+        )));
+    }
+    let grok = Grok::new(custom);
+
+    let stem = in_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(sanitize)
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "converted".to_string());
+    let package = args
+        .package
+        .clone()
+        .unwrap_or_else(|| format!("/{}/", stem));
+    let out_dir = args
+        .out_path
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_rule_root(&args.work_root).join(&stem));
+    let rule_file = out_dir.join(RULE_FILE);
+    if rule_file.exists() && !args.force {
+        return Err(convert_conf_err(format!(
+            "{} already exists, pass --force to overwrite",
+            rule_file.display()
+        )));
+    }
+
+    let mut todo_seq = 0;
+    let converted: Vec<Converted> = sources
+        .iter()
+        .map(|source| convert_one(&grok, source, &mut todo_seq))
+        .collect();
+    let code = render_package(&package, &converted);
+    // TODO 标记也是合法字段，生成的包必须能被 wp-lang 编译 |
+    // TODO markers are plain fields, so the generated package must compile.
+    RuleSet::from_code(&rule_file, &code)
+        .map_err(|e| convert_conf_err(format!("generated WPL does not compile: {}", e)))?;
+    std::fs::create_dir_all(&out_dir).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("create {} failed", out_dir.display()))
+            .with_source(e)
+    })?;
+    write_file(&rule_file, &code)?;
+
+    let checks = match args.sample.as_deref() {
+        Some(sample) => Some(verify(
+            &package,
+            &rule_file,
+            &converted,
+            &read_source(Path::new(sample))?,
+        )?),
+        None => None,
+    };
+    let disagreements: usize = checks
+        .iter()
+        .flatten()
+        .map(|c| c.original_only + c.wpl_only + c.value_mismatch)
+        .sum();
+
+    if args.json {
+        print_json(&serde_json::json!({
+            "package": package,
+            "rule_file": rule_file.display().to_string(),
+            "rules": converted,
+            "verify": checks,
+        }))?;
+    } else {
+        println!("Rule convert");
+        println!(
+            "  Input  : {} ({} patterns)",
+            in_path.display(),
+            converted.len()
+        );
+        println!("  Package: {}", package);
+        println!("  Rules  : {}", rule_file.display());
+        for rule in &converted {
+            println!("  {:<12} {}", rule.rule, rule.expression);
+            for todo in &rule.todos {
+                println!("  {:<12}   TODO {}: {}", "", todo.marker, todo.original);
+            }
+        }
+        for check in checks.iter().flatten() {
+            println!(
+                "  Verify {}: {} agree, {} original only, {} wpl only, {} value mismatch",
+                check.rule, check.both, check.original_only, check.wpl_only, check.value_mismatch
+            );
+            for example in &check.examples {
+                println!("    {}", example);
+            }
+        }
+    }
+    if disagreements > 0 {
+        return Err(RunReason::from_biz().to_err().with_detail(format!(
+            "{} sample lines parse differently after conversion",
+            disagreements
+        )));
+    }
+    Ok(())
+}
+
+fn convert_one(grok: &Grok, source: &Source, todo_seq: &mut usize) -> Converted {
+    let (pieces, regex) = match source.syntax {
+        Syntax::Grok => (
+            grok.to_pieces(&source.pattern, 0),
+            grok.to_regex(&source.pattern, 0, &mut Names::default())
+                .ok(),
+        ),
+        Syntax::Regex => (lex_regex(&source.pattern), Some(source.pattern.clone())),
+    };
+    let mut converted = render(&pieces, &mut Names::default(), todo_seq);
+    converted.rule = source.name.clone();
+    converted.pattern = source.pattern.clone();
+    converted.regex = regex;
+    converted
+}
+
+fn render_package(package: &str, rules: &[Converted]) -> String {
+    let mut code = format!("package {} {{\n", package);
+    for rule in rules {
+        code.push_str(&format!(
+            "   rule {} {{\n        {}\n   }}\n",
+            rule.rule, rule.expression
+        ));
+    }
+    code.push_str("}\n");
+    code
+}
+
+/// 用原模式与生成的规则分别解析样本并比对 | Parse the sample with both forms and compare.
+fn verify(
+    package: &str,
+    origin: &Path,
+    rules: &[Converted],
+    sample: &str,
+) -> RunResult<Vec<RuleCheck>> {
+    let mut checks = Vec::new();
+    for rule in rules {
+        let mut check = RuleCheck {
+            rule: rule.rule.clone(),
+            ..RuleCheck::default()
+        };
+        let Some(regex) = rule.regex.as_deref().and_then(|r| Regex::new(r).ok()) else {
+            check
+                .examples
+                .push("original pattern cannot be compiled, skipped".to_string());
+            checks.push(check);
+            continue;
+        };
+        let wpl = RuleSet::from_code(
+            origin,
+            &wrap_expression(package, &rule.rule, &rule.expression),
+        )?;
+        for line in sample.lines().filter(|l| !l.trim().is_empty()) {
+            let original = regex.captures(line).map(|caps| {
+                regex
+                    .capture_names()
+                    .flatten()
+                    .filter_map(|name| {
+                        caps.name(name)
+                            .map(|m| (name.to_string(), m.as_str().to_string()))
+                    })
+                    .collect::<BTreeMap<String, String>>()
+            });
+            if let Some(issue) = compare(original.as_ref(), &wpl.eval_line(line), &rule.fields) {
+                match issue {
+                    Disagreement::OriginalOnly(_) => check.original_only += 1,
+                    Disagreement::WplOnly => check.wpl_only += 1,
+                    Disagreement::Values(_) => check.value_mismatch += 1,
+                }
+                if check.examples.len() < MAX_EXAMPLES {
+                    check
+                        .examples
+                        .push(format!("{}: {}", issue.describe(), line));
+                }
+            } else if original.is_some() {
+                check.both += 1;
+            }
+        }
+        checks.push(check);
+    }
+    Ok(checks)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Disagreement {
+    OriginalOnly(String),
+    WplOnly,
+    Values(Vec<String>),
+}
+
+impl Disagreement {
+    fn describe(&self) -> String {
+        match self {
+            Disagreement::OriginalOnly(reason) => format!("wpl misses ({})", reason),
+            Disagreement::WplOnly => "original misses".to_string(),
+            Disagreement::Values(fields) => format!("values differ [{}]", fields.join(", ")),
+        }
+    }
+}
+
+fn compare(
+    original: Option<&BTreeMap<String, String>>,
+    wpl: &LineMatch,
+    kinds: &BTreeMap<String, WplKind>,
+) -> Option<Disagreement> {
+    match (original, wpl) {
+        (None, LineMatch::Miss { .. }) => None,
+        (None, LineMatch::Hit { .. }) => Some(Disagreement::WplOnly),
+        (Some(_), LineMatch::Miss { reason, .. }) => {
+            Some(Disagreement::OriginalOnly(reason.clone()))
+        }
+        (Some(values), LineMatch::Hit { fields, .. }) => {
+            let mut differ = Vec::new();
+            for field in fields {
+                let (Some(expected), Some(kind)) =
+                    (values.get(&field.name), kinds.get(&field.name))
+                else {
+                    continue;
+                };
+                if kind.compare_value()
+                    && expected.trim_matches('"') != field.value.trim_matches('"')
+                {
+                    differ.push(field.name.clone());
+                }
+            }
+            (!differ.is_empty()).then_some(Disagreement::Values(differ))
+        }
+    }
+}
+
+/// `NAME pattern` 形式的 grok 定义（patterns_dir 文件）| Grok definitions as in a patterns_dir file.
+fn grok_definitions(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (name, body) = line.split_once(char::is_whitespace)?;
+            let is_name = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+            is_name.then(|| (name.to_string(), body.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Logstash 配置取 `match` 中含 `%{` 的字符串；否则按行读取定义或独立模式 |
+/// From a Logstash config take the `match` strings holding `%{`; otherwise read
+/// definitions (one rule each) or bare patterns line by line.
+fn grok_sources(text: &str, custom: &mut BTreeMap<String, String>) -> Vec<Source> {
+    if text.contains("=>") {
+        return quoted_strings(text)
+            .into_iter()
+            .filter(|s| s.contains("%{"))
+            .enumerate()
+            .map(|(idx, pattern)| Source {
+                name: format!("rule_{}", idx + 1),
+                syntax: Syntax::Grok,
+                pattern,
+            })
+            .collect();
+    }
+    let defs = grok_definitions(text);
+    let mut sources = Vec::new();
+    let mut bare = 0;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(char::is_whitespace) {
+            Some((name, body)) if defs.contains_key(name) => sources.push(Source {
+                name: name.to_ascii_lowercase(),
+                syntax: Syntax::Grok,
+                pattern: body.trim().to_string(),
+            }),
+            _ => {
+                bare += 1;
+                sources.push(Source {
+                    name: format!("rule_{}", bare),
+                    syntax: Syntax::Grok,
+                    pattern: line.to_string(),
+                });
+            }
+        }
+    }
+    custom.extend(defs);
+    sources
+}
+
+fn regex_sources(text: &str) -> Vec<Source> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .enumerate()
+        .map(|(idx, line)| Source {
+            name: format!("rule_{}", idx + 1),
+            syntax: Syntax::Regex,
+            pattern: line.to_string(),
+        })
+        .collect()
+}
+
+/// VRL 中 `parse_grok(..., "...")` 与 `parse_regex(..., r'...')` 的模式 |
+/// Patterns passed to `parse_grok` / `parse_regex` in a VRL program.
+fn vrl_sources(text: &str) -> Vec<Source> {
+    let mut sources = Vec::new();
+    for (func, syntax) in [("parse_grok", Syntax::Grok), ("parse_regex", Syntax::Regex)] {
+        let mut rest = text;
+        while let Some(idx) = rest.find(func) {
+            rest = &rest[idx + func.len()..];
+            let call = rest.trim_start_matches('!').trim_start();
+            if !call.starts_with('(') {
+                continue;
+            }
+            let Some((pattern, _)) = first_literal(call) else {
+                continue;
+            };
+            sources.push((idx, syntax, pattern));
+        }
+    }
+    sources
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (_, syntax, pattern))| Source {
+            name: format!("rule_{}", idx + 1),
+            syntax,
+            pattern,
+        })
+        .collect()
+}
+
+/// 调用参数中的第一个字符串字面量：`"..."`、`r'...'` 或 `r"..."` |
+/// The first string literal in a call: `"..."`, `r'...'` or `r"..."`.
+fn first_literal(call: &str) -> Option<(String, usize)> {
+    let end_of_call = call.find(')').unwrap_or(call.len());
+    let raw_at = call.find("r'").or_else(|| call.find("r\""));
+    let quote_at = call.find('"');
+    let raw_first = match (raw_at, quote_at) {
+        (Some(raw), Some(quote)) => raw < quote,
+        (Some(_), None) => true,
+        _ => false,
+    };
+    match (raw_at, quote_at) {
+        (Some(raw), _) if raw_first => {
+            let delim = call[raw + 1..].chars().next()?;
+            let body = &call[raw + 2..];
+            let end = body.find(delim)?;
+            Some((body[..end].to_string(), raw))
+        }
+        (_, Some(quote)) if quote < end_of_call || raw_at.is_none() => {
+            let (text, _) = quoted_at(call, quote)?;
+            Some((unescape(&text, true), quote))
+        }
+        _ => None,
+    }
+}
+
+/// 文本中所有双引号字符串（Logstash 只反转义 `\"`）|
+/// All double-quoted strings; Logstash only unescapes `\"`.
+fn quoted_strings(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut from = 0;
+    while let Some(idx) = text[from..].find('"') {
+        let at = from + idx;
+        match quoted_at(text, at) {
+            Some((body, end)) => {
+                out.push(unescape(&body, false));
+                from = end + 1;
+            }
+            None => break,
+        }
+    }
+    out
+}
+
+fn quoted_at(text: &str, at: usize) -> Option<(String, usize)> {
+    let mut escaped = false;
+    for (idx, ch) in text[at + 1..].char_indices() {
+        match ch {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some((text[at + 1..at + 1 + idx].to_string(), at + 1 + idx)),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn unescape(text: &str, backslashes: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.peek() {
+                Some('"') => {
+                    out.push('"');
+                    chars.next();
+                    continue;
+                }
+                Some('\\') if backslashes => {
+                    out.push('\\');
+                    chars.next();
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn convert_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::rule::wpl::ParsedField;
+
+    fn convert(syntax: Syntax, pattern: &str) -> Converted {
+        let source = Source {
+            name: "r".into(),
+            syntax,
+            pattern: pattern.into(),
+        };
+        convert_one(&Grok::new(BTreeMap::new()), &source, &mut 0)
+    }
+
+    #[test]
+    fn combined_apache_log_maps_to_wpl_fields() {
+        let rule = convert(Syntax::Grok, "%{COMBINEDAPACHELOG}");
+        assert_eq!(
+            rule.expression,
+            "(chars:clientip,chars:ident,chars:auth,time/clf:timestamp<[,]>,http/request:request\",digit:response,chars:bytes,chars:referrer\",chars:agent\")"
+        );
+        assert!(rule.todos.is_empty());
+        let regex = Regex::new(rule.regex.as_deref().expect("regex")).expect("compile");
+        let caps = regex
+            .captures(r#"10.0.0.1 - - [06/Aug/2019:12:12:19 +0800] "GET / HTTP/1.1" 200 612 "-" "curl/7.64""#)
+            .expect("match");
+        assert_eq!(&caps["response"], "200");
+        assert_eq!(&caps["agent"], "\"curl/7.64\"");
+    }
+
+    #[test]
+    fn untranslatable_constructs_become_todo_markers() {
+        let rule = convert(
+            Syntax::Grok,
+            r"%{IP:client}:%{INT:port} - (?:%{NUMBER:bytes}|-) \[%{WORD:level}\] %{UNKNOWN:x}",
+        );
+        assert_eq!(
+            rule.expression,
+            "(chars:todo_1,_,chars:todo_2,chars:level<[,]>,chars:todo_3)"
+        );
+        let originals: Vec<&str> = rule.todos.iter().map(|t| t.original.as_str()).collect();
+        assert_eq!(
+            originals,
+            vec![
+                "%{IP:client}:%{INT:port}",
+                "(?:%{NUMBER:bytes}|-)",
+                "%{UNKNOWN:x}"
+            ]
+        );
+    }
+
+    #[test]
+    fn named_regex_groups_are_typed_from_their_body() {
+        let rule = convert(
+            Syntax::Regex,
+            r#"^(?P<ip>\d{1,3}(?:\.\d{1,3}){3})\s+\[(?<ts>[^\]]+)\] "(?P<msg>[^"]*)" (?P<code>\d+) took (?P<secs>[\d.]+)s$"#,
+        );
+        assert_eq!(
+            rule.expression,
+            "(ip:ip,chars:ts<[,]>,chars:msg\",digit:code,_,chars:todo_1)"
+        );
+        assert_eq!(rule.todos[0].original, r"(?P<secs>[\d.]+)s");
+    }
+
+    #[test]
+    fn verification_reports_each_kind_of_disagreement() {
+        let kinds: BTreeMap<String, WplKind> = [
+            ("code".to_string(), WplKind::Digit),
+            ("ts".to_string(), WplKind::Time),
+        ]
+        .into_iter()
+        .collect();
+        let original: BTreeMap<String, String> = [("code", "200"), ("ts", "2024-01-01")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let hit = |code: &str, ts: &str| LineMatch::Hit {
+            rule: "r".into(),
+            fields: vec![
+                ParsedField {
+                    name: "code".into(),
+                    kind: "digit".into(),
+                    value: code.into(),
+                },
+                ParsedField {
+                    name: "ts".into(),
+                    kind: "time".into(),
+                    value: ts.into(),
+                },
+            ],
+        };
+        let miss = LineMatch::Miss {
+            rule: None,
+            column: None,
+            reason: "eof".into(),
+        };
+        assert_eq!(
+            compare(Some(&original), &hit("200", "2024-01-01 00:00:00"), &kinds),
+            None
+        );
+        assert_eq!(
+            compare(Some(&original), &hit("201", ""), &kinds),
+            Some(Disagreement::Values(vec!["code".into()]))
+        );
+        assert_eq!(
+            compare(Some(&original), &miss, &kinds),
+            Some(Disagreement::OriginalOnly("eof".into()))
+        );
+        assert_eq!(
+            compare(None, &hit("1", ""), &kinds),
+            Some(Disagreement::WplOnly)
+        );
+        assert_eq!(compare(None, &miss, &kinds), None);
+    }
+
+    #[test]
+    fn converted_package_compiles_and_agrees_with_the_sample() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let input = dir.path().join("web.grok");
+        std::fs::write(
+            &input,
+            "%{COMBINEDAPACHELOG}\n%{IP:client}:%{INT:port} - (?:%{NUMBER:bytes}|-) \\[%{WORD:level}\\] %{UNKNOWN:x}\n",
+        )
+        .expect("write patterns");
+        let line =
+            r#"10.0.0.1 - - [06/Aug/2019:12:12:19 +0800] "GET / HTTP/1.1" 200 612 "-" "curl/7.64""#;
+        let sample = dir.path().join("sample.dat");
+        std::fs::write(&sample, format!("{}\n", line)).expect("write sample");
+        let out_dir = dir.path().join("wpl/web");
+        run_rule_convert(RuleConvertArgs {
+            work_root: dir.path().display().to_string(),
+            from: ConvertFrom::Grok,
+            in_path: input.display().to_string(),
+            patterns: Vec::new(),
+            out_path: Some(out_dir.display().to_string()),
+            package: None,
+            sample: Some(sample.display().to_string()),
+            force: false,
+            json: false,
+        })
+        .expect("convert");
+
+        let rules = RuleSet::load(&out_dir).expect("generated package compiles");
+        assert_eq!(
+            rules.names().collect::<Vec<_>>(),
+            vec!["/web/rule_1", "/web/rule_2"]
+        );
+        match rules.eval_line(line) {
+            LineMatch::Hit { rule, fields } => {
+                assert_eq!(rule, "/web/rule_1");
+                assert!(fields
+                    .iter()
+                    .any(|f| f.name == "response" && f.value == "200"));
+            }
+            miss => panic!("sample did not parse: {:?}", miss),
+        }
+    }
+
+    #[test]
+    fn sources_come_from_logstash_pattern_files_and_vrl() {
+        let logstash =
+            r#"filter { grok { match => { "message" => "%{IP:ip} \"%{DATA:msg}\"" } } }"#;
+        let mut custom = BTreeMap::new();
+        let sources = grok_sources(logstash, &mut custom);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].pattern, r#"%{IP:ip} "%{DATA:msg}""#);
+
+        let patterns = "# comment\nFWLOG %{IP:src} %{INT:port}\n%{FWLOG} extra\n";
+        let sources = grok_sources(patterns, &mut custom);
+        assert_eq!(sources[0].name, "fwlog");
+        assert_eq!(sources[1].name, "rule_1");
+        assert!(custom.contains_key("FWLOG"));
+
+        let vrl = r#". = parse_grok!(.message, "%{IP:ip} \\[%{HTTPDATE:ts}\\]")
+.x = parse_regex(.message, r'^(?P<code>\d+)$') ?? {}"#;
+        let sources = vrl_sources(vrl);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].syntax, Syntax::Grok);
+        assert_eq!(sources[0].pattern, r"%{IP:ip} \[%{HTTPDATE:ts}\]");
+        assert_eq!(sources[1].pattern, r"^(?P<code>\d+)$");
+    }
+}
@@ -0,0 +1,551 @@
+//! `wproj rule coverage`：用真实语料统计各规则与分支的命中情况。
+//!
+//! 分支以命中记录的字段集合区分：同一规则中可选或备选的子表达式会产出不同的字段组合。
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use orion_error::conversion::ToStructError;
+use serde::Serialize;
+use walkdir::WalkDir;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+
+use super::golden::xml_escape;
+use super::wpl::{default_rule_root, LineMatch, RuleSet};
+use crate::args::{CoverageFormat, RuleCoverageArgs};
+use crate::format::print_json;
+
+const EXAMPLE_MAX_CHARS: usize = 160;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct BranchCoverage {
+    fields: Vec<String>,
+    hits: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct RuleCoverage {
+    rule: String,
+    hits: usize,
+    branches: Vec<BranchCoverage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct MissCluster {
+    pattern: String,
+    count: usize,
+    example: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct CoverageReport {
+    rule_root: String,
+    corpus: Vec<String>,
+    lines: usize,
+    hits: usize,
+    rules: Vec<RuleCoverage>,
+    dead_rules: Vec<String>,
+    unmatched: Vec<MissCluster>,
+}
+
+/// 累积命中与未命中 | Accumulates hits and misses while the corpus is replayed.
+struct Tally {
+    lines: usize,
+    rules: BTreeMap<String, BTreeMap<Vec<String>, usize>>,
+    misses: BTreeMap<String, (usize, String)>,
+}
+
+impl Tally {
+    fn new<'a>(rules: impl Iterator<Item = &'a str>) -> Self {
+        Self {
+            lines: 0,
+            rules: rules.map(|r| (r.to_string(), BTreeMap::new())).collect(),
+            misses: BTreeMap::new(),
+        }
+    }
+
+    fn record(&mut self, line: &str, outcome: LineMatch) {
+        self.lines += 1;
+        match outcome {
+            LineMatch::Hit { rule, fields } => {
+                let shape = fields.into_iter().map(|f| f.name).collect();
+                *self
+                    .rules
+                    .entry(rule)
+                    .or_default()
+                    .entry(shape)
+                    .or_default() += 1;
+            }
+            LineMatch::Miss { .. } => {
+                let entry = self
+                    .misses
+                    .entry(cluster_key(line))
+                    .or_insert_with(|| (0, truncate(line)));
+                entry.0 += 1;
+            }
+        }
+    }
+
+    fn into_report(self, cluster_max: usize) -> CoverageReport {
+        let mut rules: Vec<RuleCoverage> = self
+            .rules
+            .into_iter()
+            .map(|(rule, shapes)| {
+                let mut branches: Vec<BranchCoverage> = shapes
+                    .into_iter()
+                    .map(|(fields, hits)| BranchCoverage { fields, hits })
+                    .collect();
+                branches.sort_by_key(|b| Reverse(b.hits));
+                RuleCoverage {
+                    rule,
+                    hits: branches.iter().map(|b| b.hits).sum(),
+                    branches,
+                }
+            })
+            .collect();
+        rules.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.rule.cmp(&b.rule)));
+        let dead_rules = rules
+            .iter()
+            .filter(|r| r.hits == 0)
+            .map(|r| r.rule.clone())
+            .collect();
+        let mut unmatched: Vec<MissCluster> = self
+            .misses
+            .into_iter()
+            .map(|(pattern, (count, example))| MissCluster {
+                pattern,
+                count,
+                example,
+            })
+            .collect();
+        unmatched.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.pattern.cmp(&b.pattern))
+        });
+        unmatched.truncate(cluster_max);
+        CoverageReport {
+            lines: self.lines,
+            hits: rules.iter().map(|r| r.hits).sum(),
+            rules,
+            dead_rules,
+            unmatched,
+            ..CoverageReport::default()
+        }
+    }
+}
+
+pub(super) fn run_rule_coverage(args: RuleCoverageArgs) -> RunResult<()> {
+    let rule_root = args
+        .rule_file
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_rule_root(&args.work_root));
+    let mut corpus: Vec<PathBuf> = args.in_path.iter().map(PathBuf::from).collect();
+    if args.rescue || corpus.is_empty() {
+        let rescue = Path::new(&args.rescue_path);
+        corpus.push(if rescue.is_absolute() {
+            rescue.to_path_buf()
+        } else {
+            Path::new(&args.work_root).join(rescue)
+        });
+    }
+    let files = collect_corpus(&corpus)?;
+    if files.is_empty() {
+        return Err(coverage_conf_err(format!(
+            "no corpus file found in {}",
+            corpus
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+
+    let rules = RuleSet::load(&rule_root)?;
+    let mut tally = Tally::new(rules.names());
+    for file in &files {
+        let bytes = fs::read(file).map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail(format!("read {} failed", file.display()))
+                .with_source(e)
+        })?;
+        for line in String::from_utf8_lossy(&bytes).lines() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() {
+                continue;
+            }
+            tally.record(line, rules.eval_line(line));
+        }
+    }
+    let report = CoverageReport {
+        rule_root: rule_root.display().to_string(),
+        corpus: files.iter().map(|p| p.display().to_string()).collect(),
+        ..tally.into_report(args.clusters)
+    };
+
+    let body = match args.format {
+        CoverageFormat::Text => render_text(&report),
+        CoverageFormat::Html => render_html(&report),
+        CoverageFormat::Json => {
+            if args.out_path.is_none() {
+                return print_json(&report);
+            }
+            serde_json::to_string_pretty(&report).map_err(|e| {
+                RunReason::from_conf()
+                    .to_err()
+                    .with_detail("serialize coverage report failed")
+                    .with_source(e)
+            })?
+        }
+    };
+    match args.out_path.as_deref() {
+        Some(path) => fs::write(path, body).map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail(format!("write {} failed", path))
+                .with_source(e)
+        }),
+        None => {
+            print!("{}", body);
+            Ok(())
+        }
+    }
+}
+
+/// 文件直接使用，目录递归收集其中的普通文件 | Files are used as is; directories are walked.
+fn collect_corpus(paths: &[PathBuf]) -> RunResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_file() {
+            files.push(path.clone());
+        } else if path.is_dir() {
+            let mut found: Vec<PathBuf> = WalkDir::new(path)
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path())
+                .filter(|p| !is_hidden(p))
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            return Err(coverage_conf_err(format!(
+                "corpus path not found: {}",
+                path.display()
+            )));
+        }
+    }
+    Ok(files)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.'))
+}
+
+/// 将数字、十六进制串等易变部分归一，使同类未命中样本落入同一簇 |
+/// Mask volatile tokens (numbers, hex ids) so similar unmatched lines share a cluster.
+fn cluster_key(line: &str) -> String {
+    line.split_whitespace()
+        .map(|token| {
+            let digits = token.chars().filter(char::is_ascii_digit).count();
+            if digits == 0 {
+                return token.to_string();
+            }
+            let numeric = token
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '.' | ':' | '-' | '/' | '+' | ','));
+            let hex = token.len() >= 8 && token.chars().all(|c| c.is_ascii_hexdigit());
+            if numeric || hex {
+                return "<*>".to_string();
+            }
+            let mut masked = String::with_capacity(token.len());
+            for c in token.chars() {
+                if !c.is_ascii_digit() {
+                    masked.push(c);
+                } else if !masked.ends_with('#') {
+                    masked.push('#');
+                }
+            }
+            masked
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn truncate(line: &str) -> String {
+    match line.char_indices().nth(EXAMPLE_MAX_CHARS) {
+        Some((offset, _)) => format!("{}…", &line[..offset]),
+        None => line.to_string(),
+    }
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+fn render_text(report: &CoverageReport) -> String {
+    let mut out = String::new();
+    out.push_str("Rule coverage\n");
+    out.push_str(&format!("  Rules : {}\n", report.rule_root));
+    out.push_str(&format!(
+        "  Corpus: {} files, {} lines, {} hits ({:.2}%)\n",
+        report.corpus.len(),
+        report.lines,
+        report.hits,
+        ratio(report.hits, report.lines)
+    ));
+    let width = report
+        .rules
+        .iter()
+        .map(|r| r.rule.chars().count())
+        .max()
+        .unwrap_or(0);
+    for rule in &report.rules {
+        out.push_str(&format!(
+            "  {:<width$}  {:>8}  {:>6.2}%\n",
+            rule.rule,
+            rule.hits,
+            ratio(rule.hits, report.lines),
+            width = width
+        ));
+        if rule.branches.len() > 1 {
+            for branch in &rule.branches {
+                out.push_str(&format!(
+                    "  {:<width$}  {:>8}  [{}]\n",
+                    "",
+                    branch.hits,
+                    branch.fields.join(", "),
+                    width = width
+                ));
+            }
+        }
+    }
+    if !report.dead_rules.is_empty() {
+        out.push_str(&format!("  Never hit ({}):\n", report.dead_rules.len()));
+        for rule in &report.dead_rules {
+            out.push_str(&format!("    {}\n", rule));
+        }
+    }
+    if !report.unmatched.is_empty() {
+        out.push_str(&format!(
+            "  Unmatched clusters ({} lines):\n",
+            report.lines - report.hits
+        ));
+        for cluster in &report.unmatched {
+            out.push_str(&format!("    {:>8}  {}\n", cluster.count, cluster.pattern));
+            out.push_str(&format!("    {:>8}  e.g. {}\n", "", cluster.example));
+        }
+    }
+    out
+}
+
+fn render_html(report: &CoverageReport) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Rule coverage</title>\n",
+    );
+    out.push_str("<style>body{font-family:sans-serif}table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:2px 8px;text-align:left}tr.dead td{color:#b00}.n{text-align:right}code{white-space:pre-wrap}</style>\n");
+    out.push_str("</head>\n<body>\n<h1>Rule coverage</h1>\n");
+    out.push_str(&format!(
+        "<p>Rules: <code>{}</code><br>Corpus: {} files, {} lines, {} hits ({:.2}%)</p>\n",
+        xml_escape(&report.rule_root),
+        report.corpus.len(),
+        report.lines,
+        report.hits,
+        ratio(report.hits, report.lines)
+    ));
+    out.push_str("<h2>Rules</h2>\n<table>\n<tr><th>Rule</th><th>Hits</th><th>Share</th><th>Branches (fields: hits)</th></tr>\n");
+    for rule in &report.rules {
+        let branches = rule
+            .branches
+            .iter()
+            .map(|b| format!("[{}]: {}", xml_escape(&b.fields.join(", ")), b.hits))
+            .collect::<Vec<_>>()
+            .join("<br>");
+        out.push_str(&format!(
+            "<tr{}><td><code>{}</code></td><td class=\"n\">{}</td><td class=\"n\">{:.2}%</td><td>{}</td></tr>\n",
+            if rule.hits == 0 { " class=\"dead\"" } else { "" },
+            xml_escape(&rule.rule),
+            rule.hits,
+            ratio(rule.hits, report.lines),
+            branches
+        ));
+    }
+    out.push_str("</table>\n");
+    out.push_str(&format!(
+        "<h2>Unmatched clusters ({} lines)</h2>\n<table>\n<tr><th>Count</th><th>Pattern</th><th>Example</th></tr>\n",
+        report.lines - report.hits
+    ));
+    for cluster in &report.unmatched {
+        out.push_str(&format!(
+            "<tr><td class=\"n\">{}</td><td><code>{}</code></td><td><code>{}</code></td></tr>\n",
+            cluster.count,
+            xml_escape(&cluster.pattern),
+            xml_escape(&cluster.example)
+        ));
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+fn coverage_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::wpl::fixtures::*;
+    use super::super::wpl::ParsedField;
+    use super::*;
+
+    fn hit(rule: &str, fields: &[&str]) -> LineMatch {
+        LineMatch::Hit {
+            rule: rule.to_string(),
+            fields: fields
+                .iter()
+                .map(|name| ParsedField {
+                    name: name.to_string(),
+                    kind: "chars".to_string(),
+                    value: String::new(),
+                })
+                .collect(),
+        }
+    }
+
+    fn miss() -> LineMatch {
+        LineMatch::Miss {
+            rule: None,
+            column: None,
+            reason: String::new(),
+        }
+    }
+
+    #[test]
+    fn cluster_keys_mask_volatile_tokens() {
+        assert_eq!(
+            cluster_key("2024-01-02 10:00:01 user42 login from 10.0.0.1 id=deadbeef01"),
+            "<*> <*> user# login from <*> id=deadbeef#"
+        );
+        assert_eq!(
+            cluster_key("error  code 500"),
+            cluster_key("error code 404")
+        );
+        assert_eq!(cluster_key("a1b2c3d4e5"), "<*>");
+    }
+
+    #[test]
+    fn tally_counts_rules_branches_dead_rules_and_clusters() {
+        let mut tally = Tally::new(["/nginx/access", "/nginx/error", "/old/unused"].into_iter());
+        tally.record("l1", hit("/nginx/access", &["sip", "status"]));
+        tally.record("l2", hit("/nginx/access", &["sip", "status", "agent"]));
+        tally.record("l3", hit("/nginx/access", &["sip", "status"]));
+        tally.record("l4", hit("/nginx/error", &["level"]));
+        tally.record("job 17 failed", miss());
+        tally.record("job 18 failed", miss());
+        tally.record("panic", miss());
+
+        let report = tally.into_report(1);
+        assert_eq!(report.lines, 7);
+        assert_eq!(report.hits, 4);
+        assert_eq!(report.rules[0].rule, "/nginx/access");
+        assert_eq!(report.rules[0].hits, 3);
+        assert_eq!(
+            report.rules[0].branches[0],
+            BranchCoverage {
+                fields: vec!["sip".into(), "status".into()],
+                hits: 2
+            }
+        );
+        assert_eq!(report.dead_rules, vec!["/old/unused".to_string()]);
+        assert_eq!(
+            report.unmatched,
+            vec![MissCluster {
+                pattern: "job <*> failed".into(),
+                count: 2,
+                example: "job 17 failed".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn corpus_dirs_are_walked_without_hidden_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        fs::create_dir_all(dir.path().join("sink_a")).expect("mkdir");
+        fs::write(dir.path().join("sink_a/2024.dat"), "x").expect("write");
+        fs::write(dir.path().join("sink_a/.lock"), "").expect("write");
+        fs::write(dir.path().join("extra.log"), "y").expect("write");
+
+        let files = collect_corpus(&[dir.path().to_path_buf()]).expect("collect");
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join("extra.log"),
+                dir.path().join("sink_a/2024.dat")
+            ]
+        );
+        assert!(collect_corpus(&[dir.path().join("missing")]).is_err());
+    }
+
+    #[test]
+    fn real_package_coverage_is_written_as_json() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let rules = dir.path().join("wpl");
+        write_nginx_package(&rules);
+        let corpus = dir.path().join("corpus.log");
+        fs::write(
+            &corpus,
+            format!(
+                "{line}\nnot an access log line\n{line}\nnot an access log line\n\n{{\"msg\":1}}\n",
+                line = NGINX_LINE
+            ),
+        )
+        .expect("write corpus");
+        let out = dir.path().join("coverage.json");
+        run_rule_coverage(RuleCoverageArgs {
+            work_root: dir.path().display().to_string(),
+            rule_file: Some(rules.display().to_string()),
+            in_path: vec![corpus.display().to_string()],
+            rescue: false,
+            rescue_path: "./data/rescue".into(),
+            format: CoverageFormat::Json,
+            out_path: Some(out.display().to_string()),
+            clusters: 20,
+        })
+        .expect("coverage");
+
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&out).expect("read report")).expect("json");
+        assert_eq!(report["lines"], 5);
+        assert_eq!(report["hits"], 2);
+        assert_eq!(report["rules"][0]["rule"], NGINX_RULE);
+        assert_eq!(report["rules"][0]["hits"], 2);
+        assert_eq!(
+            report["rules"][0]["branches"].as_array().map(Vec::len),
+            Some(1)
+        );
+        assert_eq!(report["dead_rules"].as_array().map(Vec::len), Some(0));
+        assert_eq!(report["unmatched"][0]["count"], 2);
+        assert_eq!(report["unmatched"][0]["example"], "not an access log line");
+        assert_eq!(report["unmatched"].as_array().map(Vec::len), Some(2));
+    }
+
+    #[test]
+    fn html_report_escapes_samples() {
+        let mut tally = Tally::new(["/a/b"].into_iter());
+        tally.record("<script>", miss());
+        let html = render_html(&tally.into_report(10));
+        assert!(html.contains("<code>&lt;script&gt;</code>"));
+        assert!(html.contains("<tr class=\"dead\">"));
+    }
+}
@@ -0,0 +1,576 @@
+//! `wproj rule fmt`：WPL / OML 源码的规范格式。
+//!
+//! WPL 只规范 `package` / `rule` 的布局与缩进，表达式内容原样保留；
+//! OML 规范头部 `key : value` 写法与空白。字符串字面量内的内容一律不动，
+//! 写回前用 wp-lang / wp-oml 解析前后两份源码，语法树不同则拒绝写入。
+
+use std::path::{Path, PathBuf};
+
+use orion_error::conversion::ToStructError;
+use walkdir::WalkDir;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+
+use super::oml::{default_oml_root, oml_syntax_tree};
+use super::wpl::{default_rule_root, read_source, syntax_tree};
+use crate::args::RuleFmtArgs;
+
+pub(super) const WPL_EXT: &str = "wpl";
+pub(super) const OML_EXT: &str = "oml";
+const RULE_INDENT: &str = "   ";
+const BODY_INDENT: &str = "        ";
+const OML_SEPARATOR: &str = "---";
+
+/// 源码中的一个包 | One package as written in the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct PackageSrc {
+    pub attrs: Vec<String>,
+    pub name: String,
+    pub rules: Vec<RuleSrc>,
+}
+
+/// 源码中的一条规则，`body` 为去掉缩进后的表达式行 |
+/// One rule as written; `body` holds the expression lines without indentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct RuleSrc {
+    pub attrs: Vec<String>,
+    pub name: String,
+    pub body: Vec<String>,
+    /// 1 起始的行号 | 1-based line of the `rule` keyword
+    pub line: usize,
+}
+
+pub(super) fn run_rule_fmt(args: RuleFmtArgs) -> RunResult<()> {
+    let roots: Vec<PathBuf> = if args.paths.is_empty() {
+        vec![
+            default_rule_root(&args.work_root),
+            default_oml_root(&args.work_root),
+        ]
+    } else {
+        args.paths.iter().map(PathBuf::from).collect()
+    };
+    let mut unformatted = Vec::new();
+    let mut failed = Vec::new();
+    let mut total = 0;
+    for file in collect_sources(&roots)? {
+        total += 1;
+        let code = read_source(&file)?;
+        let formatted = match format_source(&file, &code) {
+            Ok(formatted) => formatted,
+            Err(reason) => {
+                failed.push(format!("{}: {}", file.display(), reason));
+                continue;
+            }
+        };
+        if formatted == code {
+            continue;
+        }
+        if !args.check {
+            std::fs::write(&file, &formatted).map_err(|e| {
+                RunReason::from_conf()
+                    .to_err()
+                    .with_detail(format!("write {} failed", file.display()))
+                    .with_source(e)
+            })?;
+        }
+        unformatted.push(file);
+    }
+
+    let verb = if args.check {
+        "would reformat"
+    } else {
+        "reformatted"
+    };
+    for file in &unformatted {
+        println!("{} {}", verb, file.display());
+    }
+    println!(
+        "{} files checked, {} {}",
+        total,
+        unformatted.len(),
+        if args.check {
+            "need formatting"
+        } else {
+            "reformatted"
+        }
+    );
+    if !failed.is_empty() {
+        return Err(RunReason::from_conf().to_err().with_detail(format!(
+            "rule fmt failed: {} file(s) could not be parsed\n{}",
+            failed.len(),
+            failed.join("\n")
+        )));
+    }
+    if args.check && !unformatted.is_empty() {
+        return Err(RunReason::from_biz().to_err().with_detail(format!(
+            "{} file(s) are not formatted, run `wproj rule fmt`",
+            unformatted.len()
+        )));
+    }
+    Ok(())
+}
+
+/// 收集 `.wpl` / `.oml` 文件；不存在的默认目录直接跳过 |
+/// Collect `.wpl` / `.oml` files; missing roots are skipped.
+pub(super) fn collect_sources(roots: &[PathBuf]) -> RunResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for root in roots {
+        if root.is_file() {
+            files.push(root.clone());
+            continue;
+        }
+        if !root.is_dir() {
+            continue;
+        }
+        let mut found: Vec<PathBuf> = WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .filter(|p| {
+                p.extension()
+                    .is_some_and(|ext| ext == WPL_EXT || ext == OML_EXT)
+            })
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+    Ok(files)
+}
+
+/// 格式化并校验语义未变；解析失败或语法树改变时返回原因 |
+/// Format and check the meaning is unchanged; returns the reason when parsing
+/// fails or the syntax tree would change.
+fn format_source(path: &Path, code: &str) -> Result<String, String> {
+    let is_oml = path.extension().is_some_and(|ext| ext == OML_EXT);
+    let formatted = if is_oml {
+        format_oml(code)
+    } else {
+        format_wpl(&parse_wpl(code)?)
+    };
+    if formatted == code {
+        return Ok(formatted);
+    }
+    let tree = |code: &str| {
+        if is_oml {
+            oml_syntax_tree(path, code)
+        } else {
+            syntax_tree(path, code)
+        }
+        .map_err(|e| e.to_string())
+    };
+    if tree(code)? != tree(&formatted)? {
+        return Err("formatting would change the parsed rules, file left unchanged".to_string());
+    }
+    Ok(formatted)
+}
+
+pub(super) fn format_wpl(packages: &[PackageSrc]) -> String {
+    let mut out = String::new();
+    for (idx, package) in packages.iter().enumerate() {
+        if idx > 0 {
+            out.push('\n');
+        }
+        for attr in &package.attrs {
+            out.push_str(&format!("{}\n", attr));
+        }
+        out.push_str(&format!("package {} {{\n", package.name));
+        for rule in &package.rules {
+            for attr in &rule.attrs {
+                out.push_str(&format!("{}{}\n", RULE_INDENT, attr));
+            }
+            out.push_str(&format!("{}rule {} {{\n", RULE_INDENT, rule.name));
+            for line in &rule.body {
+                out.push_str(&format!("{}{}\n", BODY_INDENT, line));
+            }
+            out.push_str(&format!("{}}}\n", RULE_INDENT));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// 头部 `key : value` 行规范空白，正文去掉行尾空白、制表符缩进换成 4 空格、合并连续空行；
+/// 跨行字符串字面量内的行原样保留 |
+/// Header `key : value` lines get normalised spacing; the body loses trailing blanks, tabs
+/// and repeated empty lines. Lines inside a multi-line string literal are kept as written.
+pub(super) fn format_oml(code: &str) -> String {
+    let has_header = code.lines().any(|line| line.trim() == OML_SEPARATOR);
+    let mut in_header = has_header;
+    let mut literal = Literal::default();
+    // (行, 是否处于字面量内) | (line, inside a literal)
+    let mut out: Vec<(String, bool)> = Vec::new();
+    for raw in code.lines() {
+        if in_header {
+            let line = raw.trim_end();
+            if line.trim() == OML_SEPARATOR {
+                in_header = false;
+                out.push((OML_SEPARATOR.to_string(), false));
+                continue;
+            }
+            match line.split_once(':') {
+                Some((key, value)) if is_header_key(key.trim()) => {
+                    out.push((format!("{} : {}", key.trim(), value.trim()), false));
+                }
+                _ => out.push((line.to_string(), false)),
+            }
+            continue;
+        }
+        let starts_inside = literal.is_open();
+        literal.scan(raw);
+        let line = if literal.is_open() {
+            raw
+        } else {
+            raw.trim_end()
+        };
+        if starts_inside {
+            out.push((line.to_string(), true));
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        let width: usize = line[..indent]
+            .chars()
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        out.push((format!("{}{}", " ".repeat(width), line.trim_start()), false));
+    }
+    out.dedup_by(|a, b| a.0.is_empty() && b.0.is_empty() && !a.1 && !b.1);
+    let mut out: Vec<String> = out.into_iter().map(|(line, _)| line).collect();
+    while out.first().is_some_and(|l| l.is_empty()) {
+        out.remove(0);
+    }
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+    let mut text = out.join("\n");
+    text.push('\n');
+    text
+}
+
+/// 解析包与规则的外层结构，表达式按花括号配对截取 |
+/// Parse the package / rule skeleton; rule bodies are taken up to the matching brace.
+pub(super) fn parse_wpl(code: &str) -> Result<Vec<PackageSrc>, String> {
+    let mut scan = Scanner::new(code);
+    let mut packages = Vec::new();
+    loop {
+        scan.skip_ws();
+        if scan.at_end() {
+            break;
+        }
+        let attrs = scan.attrs()?;
+        scan.keyword("package")?;
+        let name = scan.word()?;
+        scan.expect('{')?;
+        let mut rules = Vec::new();
+        loop {
+            scan.skip_ws();
+            if scan.eat('}') {
+                break;
+            }
+            if scan.at_end() {
+                return Err(format!("package {} is not closed", name));
+            }
+            let attrs = scan.attrs()?;
+            let line = scan.line();
+            scan.keyword("rule")?;
+            let name = scan.word()?;
+            scan.expect('{')?;
+            let body = scan.block()?;
+            rules.push(RuleSrc {
+                attrs,
+                name,
+                body: logical_lines(&body)
+                    .into_iter()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                line,
+            });
+        }
+        packages.push(PackageSrc { attrs, name, rules });
+    }
+    if packages.is_empty() {
+        return Err("no package found".to_string());
+    }
+    Ok(packages)
+}
+
+struct Scanner<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.src.len()
+    }
+
+    fn line(&self) -> usize {
+        self.src[..self.pos].matches('\n').count() + 1
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.rest().starts_with(ch) {
+            self.pos += ch.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, ch: char) -> Result<(), String> {
+        self.skip_ws();
+        if self.eat(ch) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}` at line {}", ch, self.line()))
+        }
+    }
+
+    fn keyword(&mut self, word: &str) -> Result<(), String> {
+        self.skip_ws();
+        let rest = self.rest();
+        let boundary = rest[word.len().min(rest.len())..]
+            .chars()
+            .next()
+            .is_some_and(char::is_whitespace);
+        if rest.starts_with(word) && boundary {
+            self.pos += word.len();
+            Ok(())
+        } else {
+            Err(format!("expected `{}` at line {}", word, self.line()))
+        }
+    }
+
+    fn word(&mut self) -> Result<String, String> {
+        self.skip_ws();
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '{')
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return Err(format!("expected a name at line {}", self.line()));
+        }
+        self.pos += end;
+        Ok(rest[..end].to_string())
+    }
+
+    /// `#[...]` 注解，按方括号配对 | `#[...]` annotations, matched by brackets.
+    fn attrs(&mut self) -> Result<Vec<String>, String> {
+        let mut attrs = Vec::new();
+        loop {
+            self.skip_ws();
+            if !self.rest().starts_with("#[") {
+                return Ok(attrs);
+            }
+            let line = self.line();
+            let end = matching(self.rest(), '[', ']', 2)
+                .ok_or_else(|| format!("annotation at line {} is not closed", line))?;
+            attrs.push(self.rest()[..=end].trim().to_string());
+            self.pos += end + 1;
+        }
+    }
+
+    fn block(&mut self) -> Result<String, String> {
+        let line = self.line();
+        let end = matching(self.rest(), '{', '}', 0)
+            .ok_or_else(|| format!("rule at line {} is not closed", line))?;
+        let body = self.rest()[..end].to_string();
+        self.pos += end + 1;
+        Ok(body)
+    }
+}
+
+/// OML 头部的键为标识符，如 `name`、`rule` | OML header keys are identifiers such as `name` or `rule`.
+fn is_header_key(key: &str) -> bool {
+    key.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 字符串字面量跟踪；括号配对与空白整理都不能触碰其中内容 |
+/// Tracks string literals, whose content bracket matching and whitespace cleanup must not touch.
+#[derive(Debug, Default, Clone, Copy)]
+struct Literal {
+    quote: Option<char>,
+    escaped: bool,
+    prev: Option<char>,
+}
+
+impl Literal {
+    fn is_open(&self) -> bool {
+        self.quote.is_some()
+    }
+
+    /// 处理一个字符，返回它是否属于字面量（含引号）| Feed one char; true when it belongs to a literal, quotes included.
+    fn feed(&mut self, ch: char) -> bool {
+        let prev = self.prev.replace(ch);
+        match self.quote {
+            Some(quote) => {
+                if self.escaped {
+                    self.escaped = false;
+                } else if ch == '\\' {
+                    self.escaped = true;
+                } else if ch == quote {
+                    self.quote = None;
+                }
+                true
+            }
+            None if opens_literal(prev, ch) => {
+                self.quote = Some(ch);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn scan(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.feed(ch);
+        }
+        self.feed('\n');
+    }
+}
+
+/// WPL 中紧跟字段的 `"` 是格式标记（如 `chars:referer"`），引号只有出现在分隔符之后才开始字面量；
+/// 这样注释里的 `don't` 也不会被当作字面量 |
+/// In WPL a `"` right after a field is a format marker (as in `chars:referer"`), so a quote
+/// only opens a literal after a delimiter; this also keeps `don't` in a comment out of it.
+fn opens_literal(prev: Option<char>, ch: char) -> bool {
+    matches!(ch, '"' | '\'')
+        && !prev.is_some_and(|p| {
+            p.is_alphanumeric() || matches!(p, '_' | '"' | '\'' | '/' | '>' | ')' | ']' | '}')
+        })
+}
+
+/// 按字面量之外的换行切分 | Split at newlines outside string literals.
+fn logical_lines(body: &str) -> Vec<&str> {
+    let mut literal = Literal::default();
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (idx, ch) in body.char_indices() {
+        if !literal.feed(ch) && ch == '\n' {
+            lines.push(&body[start..idx]);
+            start = idx + 1;
+        }
+    }
+    lines.push(&body[start..]);
+    lines
+}
+
+/// 跳过前 `skip` 个字符（含开括号）后，查找配对闭括号的偏移；字面量中的括号不计 |
+/// Offset of the closing bracket, scanning after the first `skip` chars (which include the
+/// opener); brackets inside string literals do not count.
+fn matching(text: &str, open: char, close: char, skip: usize) -> Option<usize> {
+    let mut depth = 1;
+    let mut literal = Literal::default();
+    for (idx, ch) in text.char_indices() {
+        if literal.feed(ch) || idx < skip {
+            continue;
+        }
+        if ch == open {
+            depth += 1;
+        } else if ch == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(idx);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wpl_layout_is_normalised_and_idempotent() {
+        let messy = "package /nginx/{\n\trule example   {\n (ip:sip,_^2,time/clf:recv_time<[,]>,http/request\")  \n\n}\n  #[tag(k:\"v\")]\n rule other { (digit:code) }\n}";
+        let formatted = format_wpl(&parse_wpl(messy).expect("parse"));
+        assert_eq!(
+            formatted,
+            "package /nginx/ {\n   rule example {\n        (ip:sip,_^2,time/clf:recv_time<[,]>,http/request\")\n   }\n   #[tag(k:\"v\")]\n   rule other {\n        (digit:code)\n   }\n}\n"
+        );
+        assert_eq!(
+            format_wpl(&parse_wpl(&formatted).expect("reparse")),
+            formatted
+        );
+    }
+
+    #[test]
+    fn wpl_bodies_keep_nested_braces_and_lines() {
+        let packages = parse_wpl("package a {\n rule r {\n  (chars:x<{,}>)\n  |(digit)\n }\n}\n")
+            .expect("parse");
+        let rule = &packages[0].rules[0];
+        assert_eq!(rule.body, vec!["(chars:x<{,}>)", "|(digit)"]);
+        assert_eq!(rule.line, 2);
+        assert!(parse_wpl("package a {\n rule r { (digit)\n").is_err());
+        assert!(parse_wpl("rule r { (digit) }").is_err());
+    }
+
+    #[test]
+    fn oml_header_and_whitespace_are_normalised() {
+        let messy = "\nname:nginx\nrule :   /nginx/*  \n---\n\tsip : ip = take() ;   \n\n\n* : auto = take() ;\n\n";
+        assert_eq!(
+            format_oml(messy),
+            "name : nginx\nrule : /nginx/*\n---\n    sip : ip = take() ;\n\n* : auto = take() ;\n"
+        );
+        assert_eq!(format_oml(&format_oml(messy)), format_oml(messy));
+    }
+
+    #[test]
+    fn string_literals_are_left_untouched() {
+        let packages = parse_wpl(
+            "package a {\n #[tag(k:\"]\")]\n rule r {\n  (chars:msg\")\n  |chars_has(\"}\")\n  |chars_in(\"a\n     b\")\n }\n}\n",
+        )
+        .expect("parse");
+        let rule = &packages[0].rules[0];
+        assert_eq!(rule.attrs, vec!["#[tag(k:\"]\")]"]);
+        assert_eq!(
+            rule.body,
+            vec![
+                "(chars:msg\")",
+                "|chars_has(\"}\")",
+                "|chars_in(\"a\n     b\")"
+            ]
+        );
+
+        let oml = "name : a\nrule : /a/*\nnote: see http://x\n---\nmsg = chars('a  \n\n\n\tb') ;   \n# don't\n";
+        assert_eq!(
+            format_oml(oml),
+            "name : a\nrule : /a/*\nnote : see http://x\n---\nmsg = chars('a  \n\n\n\tb') ;\n# don't\n"
+        );
+        assert_eq!(
+            format_oml("name : a\n  /b/*: x \n---\n"),
+            "name : a\n  /b/*: x\n---\n"
+        );
+    }
+
+    #[test]
+    fn wp_lang_must_accept_the_source_before_it_is_rewritten() {
+        let messy = "package /nginx/ {\n rule nginx {\n (ip:sip,_^2,chars:timestamp<[,]>,http/request\",chars:status)\n }\n}\n";
+        let formatted = format_source(Path::new("nginx.wpl"), messy).expect("format");
+        assert_eq!(formatted, format_wpl(&parse_wpl(messy).expect("parse")));
+        assert_eq!(
+            format_source(Path::new("nginx.wpl"), &formatted).expect("idempotent"),
+            formatted
+        );
+        let broken = "package /nginx/ {\n rule nginx {\n (ip:sip,digit:code\n }\n}\n";
+        assert!(parse_wpl(broken).is_ok());
+        assert!(format_source(Path::new("nginx.wpl"), broken).is_err());
+    }
+}
@@ -0,0 +1,743 @@
+//! `wproj rule test`：规则包旁的 `tests/*.case` 回归用例。
+//!
+//! 用例为 TOML：`input` 为输入行，`rule` 可选地固定命中规则，`[[expect]]`
+//! 按顺序列出期望的输出字段（有匹配的 OML 模型时为转换后的记录），
+//! `miss = true` 表示该行应当无法解析。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use orion_error::conversion::ToStructError;
+use orion_variate::EnvDict;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+
+use super::oml::{default_oml_root, load_knowledge, OmlCatalog};
+use super::wpl::{default_rule_root, read_source, record_fields, LineMatch, RuleSet};
+use crate::args::RuleTestArgs;
+use crate::format::print_json;
+
+const TESTS_DIR: &str = "tests";
+const CASE_EXT: &str = "case";
+const RULE_KEY: &str = "@rule";
+const ANY_RULE: &str = "*";
+const ORDER_KEY: &str = "@order";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+struct CaseFile {
+    input: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    miss: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    expect: Vec<ExpectField>,
+}
+
+/// 输出记录中的一个字段；记录按顺序比较，允许同名字段 |
+/// One field of the output record; records compare in order and may repeat a name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+struct ExpectField {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct FieldDiff {
+    field: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actual: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CaseStatus {
+    Passed,
+    Failed,
+    Blessed,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+struct CaseResult {
+    case: String,
+    package: String,
+    status: CaseStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diffs: Vec<FieldDiff>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    elapsed_ms: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct TestSummary {
+    rules: String,
+    total: usize,
+    passed: usize,
+    failed: usize,
+    blessed: usize,
+    errors: usize,
+    cases: Vec<CaseResult>,
+}
+
+pub(super) fn run_rule_test(args: RuleTestArgs, dict: &EnvDict) -> RunResult<()> {
+    let rule_root = args
+        .rule_file
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_rule_root(&args.work_root));
+    let oml_root = args
+        .oml_root
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_oml_root(&args.work_root));
+    let catalog = OmlCatalog::load(&oml_root)?;
+    let _knowledge = if catalog.is_empty() {
+        None
+    } else {
+        load_knowledge(
+            Path::new(&args.work_root),
+            args.knowledge_path.as_deref().map(Path::new),
+            dict,
+        )?
+    };
+
+    let cases: Vec<PathBuf> = discover_cases(&rule_root)?
+        .into_iter()
+        .filter(|case| match args.filter.as_deref() {
+            Some(filter) => case.to_string_lossy().contains(filter),
+            None => true,
+        })
+        .collect();
+    if cases.is_empty() {
+        return Err(test_conf_err(format!(
+            "no test case found under {}/**/{}/*.{}",
+            rule_root.display(),
+            TESTS_DIR,
+            CASE_EXT
+        )));
+    }
+
+    let mut summary = TestSummary {
+        rules: rule_root.display().to_string(),
+        ..TestSummary::default()
+    };
+    let mut packages: BTreeMap<PathBuf, Result<RuleSet, String>> = BTreeMap::new();
+    for case in &cases {
+        let package_dir = package_dir(case);
+        let rules = packages
+            .entry(package_dir.clone())
+            .or_insert_with(|| RuleSet::load(&package_dir).map_err(|e| e.to_string()));
+        let started = Instant::now();
+        let mut result = CaseResult {
+            case: relative(case, &rule_root),
+            package: relative(&package_dir, &rule_root),
+            status: CaseStatus::Error,
+            diffs: Vec::new(),
+            message: None,
+            elapsed_ms: 0.0,
+        };
+        match (rules, load_case(case)) {
+            (Err(err), _) => result.message = Some(format!("load rules failed: {}", err)),
+            (_, Err(err)) => result.message = Some(err.to_string()),
+            (Ok(rules), Ok(expected)) => {
+                let actual = evaluate(rules, &catalog, &expected.input);
+                result.diffs = compare(&expected, &actual);
+                result.status = if result.diffs.is_empty() {
+                    CaseStatus::Passed
+                } else if args.bless {
+                    write_case(case, &blessed(&expected, &actual))?;
+                    CaseStatus::Blessed
+                } else {
+                    CaseStatus::Failed
+                };
+                if let LineMatch::Miss { column, reason, .. } = &actual {
+                    if !expected.miss {
+                        result.message = Some(match column {
+                            Some(col) => format!("no rule matched, col {}: {}", col, reason),
+                            None => format!("no rule matched: {}", reason),
+                        });
+                    }
+                }
+            }
+        }
+        result.elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+        match result.status {
+            CaseStatus::Passed => summary.passed += 1,
+            CaseStatus::Failed => summary.failed += 1,
+            CaseStatus::Blessed => summary.blessed += 1,
+            CaseStatus::Error => summary.errors += 1,
+        }
+        summary.cases.push(result);
+    }
+    summary.total = summary.cases.len();
+
+    if let Some(path) = args.junit.as_deref() {
+        fs::write(path, junit_xml(&summary)).map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail(format!("write junit report {} failed", path))
+                .with_source(e)
+        })?;
+    }
+    if args.json {
+        print_json(&summary)?;
+    } else {
+        report(&summary);
+    }
+    if summary.failed + summary.errors > 0 {
+        return Err(RunReason::from_biz().to_err().with_detail(format!(
+            "{} of {} rule test cases failed",
+            summary.failed + summary.errors,
+            summary.total
+        )));
+    }
+    Ok(())
+}
+
+/// 查找 `<root>/**/tests/*.case`，按路径排序 | Finds `<root>/**/tests/*.case`, sorted by path.
+fn discover_cases(root: &Path) -> RunResult<Vec<PathBuf>> {
+    if !root.is_dir() {
+        return Err(test_conf_err(format!(
+            "rule directory not found: {}",
+            root.display()
+        )));
+    }
+    let mut cases: Vec<PathBuf> = WalkDir::new(root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|p| {
+            p.extension().is_some_and(|ext| ext == CASE_EXT)
+                && p.parent()
+                    .and_then(Path::file_name)
+                    .is_some_and(|dir| dir == TESTS_DIR)
+        })
+        .collect();
+    cases.sort();
+    Ok(cases)
+}
+
+/// 用例所属的规则包目录，即 `tests/` 的上一级 | The package directory owning `tests/`.
+fn package_dir(case: &Path) -> PathBuf {
+    case.parent()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+fn load_case(path: &Path) -> RunResult<CaseFile> {
+    let body = read_source(path)?;
+    toml::from_str(&body).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("parse case {} failed", path.display()))
+            .with_source(e)
+    })
+}
+
+fn write_case(path: &Path, case: &CaseFile) -> RunResult<()> {
+    let body = toml::to_string(case).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("serialize case {} failed", path.display()))
+            .with_source(e)
+    })?;
+    fs::write(path, body).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("write case {} failed", path.display()))
+            .with_source(e)
+    })
+}
+
+/// 与 `rule parse` 相同的逐行求值，命中的规则有 OML 模型时输出转换后的记录 |
+/// The same per-line evaluation as `rule parse`, transformed by OML when a model selects the rule.
+fn evaluate(rules: &RuleSet, catalog: &OmlCatalog, input: &str) -> LineMatch {
+    rules.eval_line_with(input, |rule, record| match catalog.select(rule) {
+        Some(model) => model.transform(record),
+        None => record_fields(&record),
+    })
+}
+
+/// 逐字段按顺序比较；`@rule` 表示命中的规则（未固定时期望为 `*`），未命中记为缺失；
+/// 同名字段的第 n 次出现记为 `name#n`，字段全部相同但顺序不同时报告 `@order` |
+/// Ordered field comparison; `@rule` is the matched rule (`*` when not pinned), absent on a miss.
+/// The n-th repeat of a name is labelled `name#n`; equal fields in another order report `@order`.
+fn compare(expected: &CaseFile, actual: &LineMatch) -> Vec<FieldDiff> {
+    let (actual_rule, actual_record): (Option<&str>, Vec<ExpectField>) = match actual {
+        LineMatch::Hit { rule, fields } => (
+            Some(rule.as_str()),
+            fields
+                .iter()
+                .map(|f| ExpectField {
+                    name: f.name.clone(),
+                    value: f.value.clone(),
+                })
+                .collect(),
+        ),
+        LineMatch::Miss { .. } => (None, Vec::new()),
+    };
+    let mut diffs = Vec::new();
+    if expected.miss {
+        if let Some(rule) = actual_rule {
+            diffs.push(FieldDiff {
+                field: RULE_KEY.to_string(),
+                expected: None,
+                actual: Some(rule.to_string()),
+            });
+        }
+        return diffs;
+    }
+    let rule_differs = match expected.rule.as_deref() {
+        Some(rule) => actual_rule != Some(rule),
+        None => actual_rule.is_none(),
+    };
+    if rule_differs {
+        diffs.push(FieldDiff {
+            field: RULE_KEY.to_string(),
+            expected: Some(expected.rule.as_deref().unwrap_or(ANY_RULE).to_string()),
+            actual: actual_rule.map(str::to_string),
+        });
+    }
+    let want = labelled(&expected.expect);
+    let got = labelled(&actual_record);
+    let mut field_diffs = Vec::new();
+    for (label, value) in &want {
+        let actual = got.iter().find(|(l, _)| l == label).map(|(_, v)| *v);
+        if actual != Some(*value) {
+            field_diffs.push(FieldDiff {
+                field: label.clone(),
+                expected: Some(value.to_string()),
+                actual: actual.map(str::to_string),
+            });
+        }
+    }
+    for (label, value) in &got {
+        if !want.iter().any(|(l, _)| l == label) {
+            field_diffs.push(FieldDiff {
+                field: label.clone(),
+                expected: None,
+                actual: Some(value.to_string()),
+            });
+        }
+    }
+    if field_diffs.is_empty() && actual_rule.is_some() {
+        let order = |fields: &[(String, &str)]| {
+            fields
+                .iter()
+                .map(|(l, _)| l.as_str())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let (want_order, got_order) = (order(&want), order(&got));
+        if want_order != got_order {
+            field_diffs.push(FieldDiff {
+                field: ORDER_KEY.to_string(),
+                expected: Some(want_order),
+                actual: Some(got_order),
+            });
+        }
+    }
+    diffs.extend(field_diffs);
+    diffs
+}
+
+/// 为字段加上出现序号：首次为 `name`，之后为 `name#2`、`name#3`… |
+/// Labels fields by occurrence: `name`, then `name#2`, `name#3`, ...
+fn labelled(fields: &[ExpectField]) -> Vec<(String, &str)> {
+    let mut seen: BTreeMap<&str, usize> = BTreeMap::new();
+    fields
+        .iter()
+        .map(|field| {
+            let count = seen.entry(field.name.as_str()).or_insert(0);
+            *count += 1;
+            let label = if *count == 1 {
+                field.name.clone()
+            } else {
+                format!("{}#{}", field.name, count)
+            };
+            (label, field.value.as_str())
+        })
+        .collect()
+}
+
+fn blessed(expected: &CaseFile, actual: &LineMatch) -> CaseFile {
+    match actual {
+        LineMatch::Hit { rule, fields } => CaseFile {
+            input: expected.input.clone(),
+            rule: Some(rule.clone()),
+            miss: false,
+            expect: fields
+                .iter()
+                .map(|f| ExpectField {
+                    name: f.name.clone(),
+                    value: f.value.clone(),
+                })
+                .collect(),
+        },
+        LineMatch::Miss { .. } => CaseFile {
+            input: expected.input.clone(),
+            miss: true,
+            ..CaseFile::default()
+        },
+    }
+}
+
+fn report(summary: &TestSummary) {
+    println!("Rule test");
+    println!("  Rules   : {}", summary.rules);
+    for case in &summary.cases {
+        let label = match case.status {
+            CaseStatus::Passed => "PASS ",
+            CaseStatus::Failed => "FAIL ",
+            CaseStatus::Blessed => "BLESS",
+            CaseStatus::Error => "ERROR",
+        };
+        println!("  {} {}", label, case.case);
+        if let Some(message) = case.message.as_deref() {
+            println!("          {}", message);
+        }
+        for diff in &case.diffs {
+            println!("          {}", diff_line(diff));
+        }
+    }
+    println!(
+        "  Summary : {} cases, {} passed, {} failed, {} blessed, {} errors",
+        summary.total, summary.passed, summary.failed, summary.blessed, summary.errors
+    );
+}
+
+/// `~` 值不同，`-` 期望有但缺失，`+` 多出的字段 | `~` changed, `-` missing, `+` unexpected.
+fn diff_line(diff: &FieldDiff) -> String {
+    match (&diff.expected, &diff.actual) {
+        (Some(want), Some(got)) => format!("~ {}: {:?} -> {:?}", diff.field, want, got),
+        (Some(want), None) => format!("- {}: {:?}", diff.field, want),
+        (None, Some(got)) => format!("+ {}: {:?}", diff.field, got),
+        (None, None) => format!("~ {}", diff.field),
+    }
+}
+
+fn junit_xml(summary: &TestSummary) -> String {
+    let mut suites: BTreeMap<&str, Vec<&CaseResult>> = BTreeMap::new();
+    for case in &summary.cases {
+        suites.entry(case.package.as_str()).or_default().push(case);
+    }
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"wproj rule test\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n",
+        summary.total, summary.failed, summary.errors
+    ));
+    for (package, cases) in suites {
+        let count = |status| cases.iter().filter(|c| c.status == status).count();
+        let time: f64 = cases.iter().map(|c| c.elapsed_ms).sum::<f64>() / 1000.0;
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+            xml_escape(package),
+            cases.len(),
+            count(CaseStatus::Failed),
+            count(CaseStatus::Error),
+            time
+        ));
+        for case in cases {
+            xml.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+                xml_escape(package),
+                xml_escape(&case.case),
+                case.elapsed_ms / 1000.0
+            ));
+            let detail: Vec<String> = case
+                .message
+                .iter()
+                .cloned()
+                .chain(case.diffs.iter().map(diff_line))
+                .collect();
+            let tag = match case.status {
+                CaseStatus::Failed => "failure",
+                CaseStatus::Error => "error",
+                CaseStatus::Passed | CaseStatus::Blessed => {
+                    xml.push_str("/>\n");
+                    continue;
+                }
+            };
+            xml.push_str(&format!(
+                ">\n      <{} message=\"{}\">{}</{}>\n    </testcase>\n",
+                tag,
+                xml_escape(detail.first().map(String::as_str).unwrap_or(tag)),
+                xml_escape(&detail.join("\n")),
+                tag
+            ));
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+pub(super) fn xml_escape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for ch in raw.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+fn test_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::wpl::fixtures::*;
+    use super::super::wpl::ParsedField;
+    use super::*;
+    use tempfile::tempdir;
+
+    fn hit(rule: &str, fields: &[(&str, &str)]) -> LineMatch {
+        LineMatch::Hit {
+            rule: rule.to_string(),
+            fields: fields
+                .iter()
+                .map(|(k, v)| ParsedField {
+                    name: k.to_string(),
+                    kind: "chars".to_string(),
+                    value: v.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn miss(reason: &str) -> LineMatch {
+        LineMatch::Miss {
+            rule: None,
+            column: None,
+            reason: reason.to_string(),
+        }
+    }
+
+    fn expect(fields: &[(&str, &str)]) -> Vec<ExpectField> {
+        fields
+            .iter()
+            .map(|(k, v)| ExpectField {
+                name: k.to_string(),
+                value: v.to_string(),
+            })
+            .collect()
+    }
+
+    fn case(body: &str) -> CaseFile {
+        toml::from_str(body).expect("case toml")
+    }
+
+    #[test]
+    fn cases_are_found_only_under_tests_dirs() {
+        let dir = tempdir().expect("tempdir");
+        let tests = dir.path().join("nginx/tests");
+        fs::create_dir_all(&tests).expect("mkdir");
+        fs::write(tests.join("b.case"), "").expect("write");
+        fs::write(tests.join("a.case"), "").expect("write");
+        fs::write(dir.path().join("nginx/stray.case"), "").expect("write");
+
+        let cases = discover_cases(dir.path()).expect("discover");
+        assert_eq!(cases, vec![tests.join("a.case"), tests.join("b.case")]);
+        assert_eq!(package_dir(&cases[0]), dir.path().join("nginx"));
+        assert!(discover_cases(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn compare_reports_changed_missing_and_unexpected_fields() {
+        let expected = case(
+            r#"
+input = "10.0.0.1 200"
+rule = "/nginx/access"
+expect = [
+  { name = "sip", value = "10.0.0.1" },
+  { name = "status", value = "200" },
+  { name = "agent", value = "curl" },
+]
+"#,
+        );
+        let actual = hit(
+            "/nginx/access",
+            &[("sip", "10.0.0.1"), ("status", "404"), ("size", "12")],
+        );
+        let lines: Vec<String> = compare(&expected, &actual).iter().map(diff_line).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "~ status: \"200\" -> \"404\"",
+                "- agent: \"curl\"",
+                "+ size: \"12\""
+            ]
+        );
+        let miss = miss("expect ip");
+        assert_eq!(
+            diff_line(&compare(&expected, &miss)[0]),
+            "- @rule: \"/nginx/access\""
+        );
+        let unpinned = CaseFile {
+            rule: None,
+            ..expected.clone()
+        };
+        assert_eq!(diff_line(&compare(&unpinned, &miss)[0]), "- @rule: \"*\"");
+    }
+
+    #[test]
+    fn compare_keeps_repeated_names_and_field_order() {
+        let expected = CaseFile {
+            input: "a=1 a=2".to_string(),
+            expect: expect(&[("a", "1"), ("a", "2"), ("b", "3")]),
+            ..CaseFile::default()
+        };
+        assert!(compare(
+            &expected,
+            &hit("/kv", &[("a", "1"), ("a", "2"), ("b", "3")])
+        )
+        .is_empty());
+
+        let lines: Vec<String> = compare(&expected, &hit("/kv", &[("a", "1"), ("b", "3")]))
+            .iter()
+            .map(diff_line)
+            .collect();
+        assert_eq!(lines, vec!["- a#2: \"2\""]);
+
+        let reordered = hit("/kv", &[("b", "3"), ("a", "1"), ("a", "2")]);
+        let diffs = compare(&expected, &reordered);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].field, ORDER_KEY);
+        assert_eq!(diffs[0].expected.as_deref(), Some("a,a#2,b"));
+        assert_eq!(diffs[0].actual.as_deref(), Some("b,a,a#2"));
+    }
+
+    #[test]
+    fn miss_cases_pass_only_when_nothing_matches() {
+        let expected = case("input = \"garbage\"\nmiss = true\n");
+        assert!(compare(&expected, &miss("no match")).is_empty());
+        let diffs = compare(&expected, &hit("/any/rule", &[]));
+        assert_eq!(diffs[0].actual.as_deref(), Some("/any/rule"));
+    }
+
+    #[test]
+    fn bless_rewrites_expectations_from_actual_output() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("a.case");
+        let expected = CaseFile {
+            input: "10.0.0.1 200".to_string(),
+            expect: expect(&[("sip", "old")]),
+            ..CaseFile::default()
+        };
+        let actual = hit("/nginx/access", &[("sip", "10.0.0.1"), ("status", "200")]);
+        write_case(&path, &blessed(&expected, &actual)).expect("write");
+
+        let reloaded = load_case(&path).expect("reload");
+        assert_eq!(reloaded.rule.as_deref(), Some("/nginx/access"));
+        assert!(compare(&reloaded, &actual).is_empty());
+        assert!(!fs::read_to_string(&path).expect("read").contains("miss"));
+    }
+
+    #[test]
+    fn real_package_cases_are_blessed_then_pass() {
+        let dir = tempdir().expect("tempdir");
+        let package = write_nginx_package(&dir.path().join("wpl"));
+        let tests = package.join(TESTS_DIR);
+        fs::create_dir_all(&tests).expect("mkdir tests");
+        let access = tests.join("access.case");
+        let access_case = CaseFile {
+            input: NGINX_LINE.to_string(),
+            expect: expect(&[("src", "stale")]),
+            ..CaseFile::default()
+        };
+        write_case(&access, &access_case).expect("write case");
+        fs::write(
+            tests.join("garbage.case"),
+            "input = \"garbage\"\nmiss = true\n",
+        )
+        .expect("write miss case");
+        let oml_root = dir.path().join("oml");
+        fs::create_dir_all(&oml_root).expect("mkdir oml");
+        fs::write(
+            oml_root.join("nginx.oml"),
+            "name : nginx\nrule : /example/*\n---\nsrc : ip = read(sip) ;\nstatus : auto = read(status) ;\n",
+        )
+        .expect("write oml");
+        let args = |bless| RuleTestArgs {
+            work_root: dir.path().display().to_string(),
+            rule_file: Some(dir.path().join("wpl").display().to_string()),
+            oml_root: Some(oml_root.display().to_string()),
+            knowledge_path: None,
+            filter: None,
+            bless,
+            junit: None,
+            json: false,
+        };
+
+        assert!(run_rule_test(args(false), &EnvDict::default()).is_err());
+        run_rule_test(args(true), &EnvDict::default()).expect("bless");
+        let blessed = load_case(&access).expect("reload");
+        assert_eq!(blessed.rule.as_deref(), Some(NGINX_RULE));
+        assert_eq!(
+            blessed.expect,
+            expect(&[("src", "222.133.52.20"), ("status", "200")])
+        );
+        run_rule_test(args(false), &EnvDict::default()).expect("blessed cases pass");
+    }
+
+    #[test]
+    fn junit_groups_cases_by_package_and_escapes_details() {
+        let summary = TestSummary {
+            rules: "models/wpl".into(),
+            total: 2,
+            passed: 1,
+            failed: 1,
+            cases: vec![
+                CaseResult {
+                    case: "nginx/tests/ok.case".into(),
+                    package: "nginx".into(),
+                    status: CaseStatus::Passed,
+                    diffs: Vec::new(),
+                    message: None,
+                    elapsed_ms: 1.0,
+                },
+                CaseResult {
+                    case: "nginx/tests/bad.case".into(),
+                    package: "nginx".into(),
+                    status: CaseStatus::Failed,
+                    diffs: vec![FieldDiff {
+                        field: "msg".into(),
+                        expected: Some("<a>".into()),
+                        actual: None,
+                    }],
+                    message: None,
+                    elapsed_ms: 2.0,
+                },
+            ],
+            ..TestSummary::default()
+        };
+        let xml = junit_xml(&summary);
+        assert!(xml.contains("<testsuite name=\"nginx\" tests=\"2\" failures=\"1\" errors=\"0\""));
+        assert!(xml.contains("name=\"nginx/tests/ok.case\" time=\"0.001\"/>"));
+        assert!(xml.contains("<failure message=\"- msg: &quot;&lt;a&gt;&quot;\">"));
+    }
+}
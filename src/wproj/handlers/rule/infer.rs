@@ -0,0 +1,592 @@
+//! `wproj rule infer`：按词法结构聚类样本行，为每一类生成候选 WPL 规则。
+//!
+//! 生成的规则只是草稿，需要用 `wproj rule analyse` / `parse` 校对字段名与类型。
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use orion_error::conversion::ToStructError;
+use serde::Serialize;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+
+use super::wpl::{default_rule_root, read_source, LineMatch, RuleSet};
+use crate::args::RuleInferArgs;
+use crate::format::print_json;
+
+const RULE_FILE: &str = "parse.wpl";
+const SAMPLE_FILE: &str = "sample.dat";
+const HTTP_METHODS: &[&str] = &[
+    "GET", "POST", "PUT", "DELETE", "HEAD", "OPTIONS", "PATCH", "CONNECT", "TRACE",
+];
+
+/// 词法单元类型 | Token kinds recognised in a sample line.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Ip,
+    Digit,
+    Float,
+    Time,
+    /// `[06/Aug/2019:12:12:19 +0800]` 形式的 CLF 时间 | CLF time in brackets
+    ClfTime,
+    Bracketed,
+    HttpRequest,
+    Quoted,
+    Kv(String),
+    Json,
+    Chars,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    kind: Kind,
+    text: String,
+}
+
+#[derive(Debug)]
+struct Cluster {
+    signature: Vec<Kind>,
+    lines: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct InferredRule {
+    name: String,
+    lines: usize,
+    expression: String,
+    example: String,
+}
+
+#[derive(Debug, Serialize)]
+struct InferReport {
+    package: String,
+    rule_file: String,
+    sample_file: String,
+    lines: usize,
+    uncovered: usize,
+    rules: Vec<InferredRule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parsed: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    check_error: Option<String>,
+}
+
+pub(super) fn run_rule_infer(args: RuleInferArgs) -> RunResult<()> {
+    let in_path = Path::new(&args.in_path);
+    let lines: Vec<String> = read_source(in_path)?
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect();
+    if lines.is_empty() {
+        return Err(infer_conf_err(format!(
+            "no sample line in {}",
+            in_path.display()
+        )));
+    }
+    let stem = in_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .map(sanitize)
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "draft".to_string());
+    let package = args
+        .package
+        .clone()
+        .unwrap_or_else(|| format!("/{}/", stem));
+    let out_dir = args
+        .out_path
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_rule_root(&args.work_root).join(&stem));
+    let rule_file = out_dir.join(RULE_FILE);
+    let sample_file = out_dir.join(SAMPLE_FILE);
+    if !args.force {
+        for path in [&rule_file, &sample_file] {
+            if path.exists() {
+                return Err(infer_conf_err(format!(
+                    "{} already exists, pass --force to overwrite",
+                    path.display()
+                )));
+            }
+        }
+    }
+
+    let clusters = cluster_lines(&lines);
+    let covered: Vec<&Cluster> = clusters
+        .iter()
+        .filter(|c| c.lines.len() >= args.min_lines)
+        .take(args.max_rules)
+        .collect();
+    if covered.is_empty() {
+        return Err(infer_conf_err(format!(
+            "no cluster has at least {} lines",
+            args.min_lines
+        )));
+    }
+    let rules: Vec<InferredRule> = covered
+        .iter()
+        .enumerate()
+        .map(|(idx, cluster)| InferredRule {
+            name: format!("rule_{}", idx + 1),
+            lines: cluster.lines.len(),
+            expression: render_expression(cluster),
+            example: cluster.lines[0].clone(),
+        })
+        .collect();
+    let code = render_package(&package, &rules);
+    let samples: Vec<&str> = covered
+        .iter()
+        .flat_map(|c| c.lines.iter().take(args.samples_per_rule.max(1)))
+        .map(String::as_str)
+        .collect();
+
+    fs::create_dir_all(&out_dir).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("create {} failed", out_dir.display()))
+            .with_source(e)
+    })?;
+    write_file(&rule_file, &code)?;
+    write_file(&sample_file, &(samples.join("\n") + "\n"))?;
+
+    // 编译草稿并回放 sample.dat，便于立刻看出需要手工修正的规则
+    let (parsed, check_error) = match RuleSet::from_code(&rule_file, &code) {
+        Ok(set) => (
+            Some(
+                samples
+                    .iter()
+                    .filter(|line| matches!(set.eval_line(line), LineMatch::Hit { .. }))
+                    .count(),
+            ),
+            None,
+        ),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let report = InferReport {
+        package,
+        rule_file: rule_file.display().to_string(),
+        sample_file: sample_file.display().to_string(),
+        lines: lines.len(),
+        uncovered: lines.len() - rules.iter().map(|r| r.lines).sum::<usize>(),
+        rules,
+        parsed,
+        check_error,
+    };
+    if args.json {
+        return print_json(&report);
+    }
+    println!("Rule infer");
+    println!("  Input  : {} ({} lines)", in_path.display(), report.lines);
+    println!("  Package: {}", report.package);
+    println!("  Rules  : {}", report.rule_file);
+    println!("  Samples: {}", report.sample_file);
+    for rule in &report.rules {
+        println!(
+            "  {:<8} {:>6} lines  {}",
+            rule.name, rule.lines, rule.expression
+        );
+    }
+    if report.uncovered > 0 {
+        println!(
+            "  Uncovered: {} lines in smaller clusters (see --max-rules / --min-lines)",
+            report.uncovered
+        );
+    }
+    match (report.parsed, report.check_error.as_deref()) {
+        (Some(parsed), _) => println!(
+            "  Check  : {}/{} sample lines parsed",
+            parsed,
+            samples.len()
+        ),
+        (None, Some(err)) => println!("  Check  : draft does not compile yet: {}", err),
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+/// 按词法签名分组，保持首次出现顺序后按行数降序 |
+/// Group lines by token signature, largest clusters first.
+fn cluster_lines(lines: &[String]) -> Vec<Cluster> {
+    let mut index: BTreeMap<Vec<Kind>, usize> = BTreeMap::new();
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for line in lines {
+        let signature: Vec<Kind> = tokenize(line).into_iter().map(|t| t.kind).collect();
+        match index.get(&signature) {
+            Some(&idx) => clusters[idx].lines.push(line.clone()),
+            None => {
+                index.insert(signature.clone(), clusters.len());
+                clusters.push(Cluster {
+                    signature,
+                    lines: vec![line.clone()],
+                });
+            }
+        }
+    }
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.lines.len()));
+    clusters
+}
+
+fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let (token, tail) = next_token(rest);
+        tokens.push(token);
+        rest = tail.trim_start();
+    }
+    tokens
+}
+
+fn next_token(input: &str) -> (Token, &str) {
+    let token = |kind, text: &str| Token {
+        kind,
+        text: text.to_string(),
+    };
+    if input.starts_with('{') && serde_json::from_str::<serde_json::Value>(input.trim_end()).is_ok()
+    {
+        return (token(Kind::Json, input.trim_end()), "");
+    }
+    if let Some(body) = input.strip_prefix('"') {
+        if let Some(end) = closing_quote(body) {
+            let inner = &body[..end];
+            let kind = if is_http_request(inner) {
+                Kind::HttpRequest
+            } else {
+                Kind::Quoted
+            };
+            return (token(kind, inner), &body[end + 1..]);
+        }
+    }
+    if let Some(body) = input.strip_prefix('[') {
+        if let Some(end) = body.find(']') {
+            let inner = &body[..end];
+            let kind = if is_clf_time(inner) {
+                Kind::ClfTime
+            } else {
+                Kind::Bracketed
+            };
+            return (token(kind, inner), &body[end + 1..]);
+        }
+    }
+    let end = input.find(char::is_whitespace).unwrap_or(input.len());
+    let word = &input[..end];
+    (token(classify(word), word), &input[end..])
+}
+
+fn closing_quote(body: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (idx, ch) in body.char_indices() {
+        match ch {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(idx),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn classify(word: &str) -> Kind {
+    let trimmed = word.trim_end_matches([',', ';']);
+    if trimmed.parse::<IpAddr>().is_ok() {
+        return Kind::Ip;
+    }
+    if !trimmed.is_empty() && trimmed.chars().all(|c| c.is_ascii_digit()) {
+        return Kind::Digit;
+    }
+    if trimmed.contains('.') && trimmed.parse::<f64>().is_ok() {
+        return Kind::Float;
+    }
+    if is_iso_time(trimmed) {
+        return Kind::Time;
+    }
+    if let Some((key, value)) = trimmed.split_once('=') {
+        if !key.is_empty() && !value.is_empty() && key.chars().all(is_ident_char) {
+            return Kind::Kv(key.to_string());
+        }
+    }
+    Kind::Chars
+}
+
+/// `2024-01-02T10:00:00[.sss][Z|+08:00]` 或单独的 `2024-01-02` |
+/// ISO 8601 date-times and plain dates.
+fn is_iso_time(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    let date = bytes.len() >= 10
+        && bytes[..10].iter().enumerate().all(|(i, b)| {
+            if i == 4 || i == 7 {
+                *b == b'-'
+            } else {
+                b.is_ascii_digit()
+            }
+        });
+    date && (bytes.len() == 10 || matches!(bytes[10], b'T' | b't'))
+}
+
+fn is_clf_time(inner: &str) -> bool {
+    // 06/Aug/2019:12:12:19 +0800
+    let mut parts = inner.split(['/', ':', ' ']);
+    let day = parts.next().unwrap_or("");
+    let month = parts.next().unwrap_or("");
+    let year = parts.next().unwrap_or("");
+    day.len() == 2
+        && day.chars().all(|c| c.is_ascii_digit())
+        && month.len() == 3
+        && month.chars().all(|c| c.is_ascii_alphabetic())
+        && year.len() == 4
+        && year.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_http_request(inner: &str) -> bool {
+    let mut parts = inner.split(' ');
+    let method = parts.next().unwrap_or("");
+    parts.next().is_some()
+        && parts.next().is_some_and(|v| v.starts_with("HTTP/"))
+        && HTTP_METHODS.contains(&method)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// 生成规则表达式；在整个簇中取值不变的普通单词视为常量并忽略 |
+/// Render the rule expression; plain words constant across the cluster are skipped.
+fn render_expression(cluster: &Cluster) -> String {
+    let tokenized: Vec<Vec<Token>> = cluster.lines.iter().map(|l| tokenize(l)).collect();
+    let mut used: BTreeMap<String, usize> = BTreeMap::new();
+    let mut name = |base: &str| {
+        let count = used.entry(base.to_string()).or_default();
+        *count += 1;
+        if *count == 1 {
+            base.to_string()
+        } else {
+            format!("{}_{}", base, count)
+        }
+    };
+    let mut items: Vec<String> = Vec::with_capacity(cluster.signature.len());
+    for (pos, kind) in cluster.signature.iter().enumerate() {
+        let item = match kind {
+            Kind::Ip => format!("ip:{}", name("ip")),
+            Kind::Digit => format!("digit:{}", name("num")),
+            Kind::Float => format!("float:{}", name("num")),
+            Kind::Time => format!("time:{}", name("time")),
+            Kind::ClfTime => format!("time/clf:{}<[,]>", name("time")),
+            Kind::Bracketed => format!("chars:{}<[,]>", name("field")),
+            Kind::HttpRequest => format!("http/request:{}\"", name("request")),
+            Kind::Quoted => format!("chars:{}\"", name("text")),
+            Kind::Kv(key) => format!("kv:{}", name(&sanitize(key))),
+            Kind::Json => "json".to_string(),
+            Kind::Chars => {
+                let first = &tokenized[0][pos].text;
+                if tokenized.iter().all(|tokens| &tokens[pos].text == first) {
+                    "_".to_string()
+                } else {
+                    format!("chars:{}", name("word"))
+                }
+            }
+        };
+        items.push(item);
+    }
+    format!("({})", compact_skips(items).join(","))
+}
+
+/// 连续的 `_` 合并为 `_^n` | Collapse runs of `_` into `_^n`.
+pub(super) fn compact_skips(items: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::with_capacity(items.len());
+    let mut run = 0;
+    for item in items.into_iter().chain(std::iter::once(String::new())) {
+        if item == "_" {
+            run += 1;
+            continue;
+        }
+        match run {
+            0 => {}
+            1 => out.push("_".to_string()),
+            n => out.push(format!("_^{}", n)),
+        }
+        run = 0;
+        if !item.is_empty() {
+            out.push(item);
+        }
+    }
+    out
+}
+
+fn render_package(package: &str, rules: &[InferredRule]) -> String {
+    let mut code = format!("package {} {{\n", package);
+    for rule in rules {
+        code.push_str(&format!(
+            "   rule {} {{\n        {}\n   }}\n",
+            rule.name, rule.expression
+        ));
+    }
+    code.push_str("}\n");
+    code
+}
+
+pub(super) fn sanitize(raw: &str) -> String {
+    raw.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+pub(super) fn write_file(path: &Path, body: &str) -> RunResult<()> {
+    fs::write(path, body).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("write {} failed", path.display()))
+            .with_source(e)
+    })
+}
+
+fn infer_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NGINX: &str = r#"222.133.52.20 - - [06/Aug/2019:12:12:19 +0800] "GET /nginx-logo.png HTTP/1.1" 200 368 "http://119.122.1.4/" "Mozilla/5.0 (Macintosh)" "-""#;
+
+    fn kinds(line: &str) -> Vec<Kind> {
+        tokenize(line).into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn tokens_detect_common_field_types() {
+        assert_eq!(
+            kinds(NGINX),
+            vec![
+                Kind::Ip,
+                Kind::Chars,
+                Kind::Chars,
+                Kind::ClfTime,
+                Kind::HttpRequest,
+                Kind::Digit,
+                Kind::Digit,
+                Kind::Quoted,
+                Kind::Quoted,
+                Kind::Quoted,
+            ]
+        );
+        assert_eq!(
+            kinds(r#"2024-01-02T10:00:00Z fe80::1 user=alice took 0.25 {"a": [1, 2]}"#),
+            vec![
+                Kind::Time,
+                Kind::Ip,
+                Kind::Kv("user".into()),
+                Kind::Chars,
+                Kind::Float,
+                Kind::Json,
+            ]
+        );
+        assert_eq!(
+            kinds(r#""say \"hi\"" [x y]"#),
+            vec![Kind::Quoted, Kind::Bracketed]
+        );
+    }
+
+    #[test]
+    fn clusters_group_by_structure_largest_first() {
+        let lines: Vec<String> = [
+            "login ok user=a from 10.0.0.1",
+            "disk 91 full",
+            "login ok user=b from 10.0.0.2",
+            "login ok user=c from 10.0.0.3",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let clusters = cluster_lines(&lines);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].lines.len(), 3);
+        assert_eq!(render_expression(&clusters[0]), "(_^2,kv:user,_,ip:ip)");
+        assert_eq!(render_expression(&clusters[1]), "(_,digit:num,_)");
+    }
+
+    #[test]
+    fn nginx_sample_renders_like_the_bundled_rule() {
+        let lines = vec![
+            NGINX.to_string(),
+            NGINX
+                .replace("222.133.52.20", "10.1.1.1")
+                .replace("Macintosh", "X11"),
+        ];
+        let clusters = cluster_lines(&lines);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(
+            render_expression(&clusters[0]),
+            "(ip:ip,_^2,time/clf:time<[,]>,http/request:request\",digit:num,digit:num_2,chars:text\",chars:text_2\",chars:text_3\")"
+        );
+    }
+
+    #[test]
+    fn inferred_package_compiles_and_parses_its_samples() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let input = dir.path().join("web.log");
+        let lines = [
+            NGINX.to_string(),
+            "disk 91 full".to_string(),
+            NGINX.replace("222.133.52.20", "10.1.1.1"),
+            "disk 7 full".to_string(),
+        ];
+        fs::write(&input, lines.join("\n")).expect("write samples");
+        let out_dir = dir.path().join("wpl/web");
+        run_rule_infer(RuleInferArgs {
+            work_root: dir.path().display().to_string(),
+            in_path: input.display().to_string(),
+            out_path: Some(out_dir.display().to_string()),
+            package: None,
+            max_rules: 20,
+            min_lines: 1,
+            samples_per_rule: 3,
+            force: false,
+            json: false,
+        })
+        .expect("infer");
+
+        let rules = RuleSet::load(&out_dir).expect("inferred package compiles");
+        assert_eq!(
+            rules.names().collect::<Vec<_>>(),
+            vec!["/web/rule_1", "/web/rule_2"]
+        );
+        for line in &lines {
+            match rules.eval_line(line) {
+                LineMatch::Hit { rule, .. } => {
+                    let expected = if line.starts_with("disk") {
+                        "/web/rule_2"
+                    } else {
+                        "/web/rule_1"
+                    };
+                    assert_eq!(rule, expected, "{}", line);
+                }
+                miss => panic!("{} did not parse: {:?}", line, miss),
+            }
+        }
+    }
+
+    #[test]
+    fn package_wraps_one_rule_per_cluster() {
+        let rules = vec![InferredRule {
+            name: "rule_1".into(),
+            lines: 2,
+            expression: "(ip:ip)".into(),
+            example: String::new(),
+        }];
+        assert_eq!(
+            render_package("/fw/", &rules),
+            "package /fw/ {\n   rule rule_1 {\n        (ip:ip)\n   }\n}\n"
+        );
+        assert_eq!(sanitize("FW-2024.log"), "fw_2024_log");
+    }
+}
@@ -0,0 +1,532 @@
+//! `wproj rule lint`：在运行前发现 WPL / OML / sink 路由之间的不一致。
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+use orion_error::conversion::ToStructError;
+use orion_variate::EnvDict;
+use serde::Serialize;
+use walkdir::WalkDir;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+use wp_proj::sinks::collect_oml_models;
+
+use super::fmt::{collect_sources, parse_wpl, OML_EXT, WPL_EXT};
+use super::oml::{default_oml_root, oml_references, rule_selectors};
+use super::wpl::{declared_fields, default_rule_root, read_source};
+use crate::args::RuleLintArgs;
+use crate::format::print_json;
+use crate::handlers::engine_config::load_resolved_engine_config;
+
+/// 会产出动态字段的 WPL 类型，命中这些规则的 OML 不做字段检查 |
+/// WPL types with dynamic field names; OML fed by them is not field-checked.
+const DYNAMIC_TYPES: &[&str] = &["json", "kv", "kvarr", "xml"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+enum Severity {
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct LintIssue {
+    severity: Severity,
+    scope: &'static str,
+    msg: String,
+    file: String,
+}
+
+impl LintIssue {
+    fn line(&self) -> String {
+        format!("{}: {} in {}", self.scope, self.msg, self.file)
+    }
+}
+
+/// 一条规则的路径、表达式与产出字段 | A rule's path, expression and produced fields.
+#[derive(Debug)]
+struct RuleInfo {
+    path: String,
+    location: String,
+    expression: String,
+    fields: BTreeSet<String>,
+    dynamic: bool,
+}
+
+struct OmlInfo {
+    file: String,
+    selectors: Vec<String>,
+    references: BTreeSet<String>,
+}
+
+struct SinkRoute {
+    file: String,
+    group: String,
+    oml: Vec<String>,
+    rule: Vec<String>,
+}
+
+pub(super) async fn run_rule_lint(args: RuleLintArgs, dict: &EnvDict) -> RunResult<()> {
+    let rule_root = args
+        .rule_file
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_rule_root(&args.work_root));
+    let oml_root = args
+        .oml_root
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_oml_root(&args.work_root));
+
+    let mut issues = Vec::new();
+    let mut rules = Vec::new();
+    for file in collect_sources(&[rule_root])? {
+        if file.extension().is_some_and(|ext| ext == WPL_EXT) {
+            load_rules(&file, &read_source(&file)?, &mut rules, &mut issues);
+        }
+    }
+    let mut omls = Vec::new();
+    for file in collect_sources(&[oml_root])? {
+        if file.extension().is_some_and(|ext| ext == OML_EXT) {
+            let code = read_source(&file)?;
+            match oml_references(&file, &code) {
+                Ok(references) => omls.push(OmlInfo {
+                    file: file.display().to_string(),
+                    selectors: rule_selectors(&code),
+                    references,
+                }),
+                Err(e) => issues.push(LintIssue {
+                    severity: Severity::Error,
+                    scope: "oml",
+                    msg: format!("parse failed: {}", e),
+                    file: file.display().to_string(),
+                }),
+            }
+        }
+    }
+    let eng_conf = load_resolved_engine_config(&args.work_root, dict)?;
+    let routes = load_sink_routes(Path::new(eng_conf.sink_root()))?;
+    let models: BTreeSet<String> = collect_oml_models(&args.work_root, dict)
+        .await?
+        .keys()
+        .cloned()
+        .collect();
+
+    issues.extend(lint_rules(&rules));
+    issues.extend(lint_oml(&omls, &rules));
+    issues.extend(lint_routes(&routes, &models, &rules));
+    if args.strict {
+        for issue in &mut issues {
+            issue.severity = Severity::Error;
+        }
+    }
+    let errors: Vec<String> = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .map(LintIssue::line)
+        .collect();
+
+    if args.json {
+        print_json(&serde_json::json!({
+            "pass": errors.is_empty(),
+            "rules": rules.len(),
+            "oml_models": omls.len(),
+            "sink_groups": routes.len(),
+            "issues": issues,
+        }))?;
+    } else {
+        for issue in &issues {
+            let level = match issue.severity {
+                Severity::Warn => "warn",
+                Severity::Error => "error",
+            };
+            println!("{}: {}", level, issue.line());
+        }
+        println!(
+            "rule lint: {} rules, {} oml models, {} sink groups, {} warning(s), {} error(s)",
+            rules.len(),
+            omls.len(),
+            routes.len(),
+            issues.len() - errors.len(),
+            errors.len()
+        );
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(RunReason::from_conf().to_err().with_detail(format!(
+            "rule lint failed: {} error(s)\n{}",
+            errors.len(),
+            errors.join("\n")
+        )))
+    }
+}
+
+/// 规则位置与表达式取自源码结构，产出字段取自 wp-lang 语法树 |
+/// Locations and expressions come from the source layout; produced fields from the wp-lang syntax tree.
+fn load_rules(file: &Path, code: &str, rules: &mut Vec<RuleInfo>, issues: &mut Vec<LintIssue>) {
+    let parse_failed = |reason: String| LintIssue {
+        severity: Severity::Error,
+        scope: "wpl",
+        msg: format!("parse failed: {}", reason),
+        file: file.display().to_string(),
+    };
+    let packages = match parse_wpl(code) {
+        Ok(packages) => packages,
+        Err(reason) => {
+            issues.push(parse_failed(reason));
+            return;
+        }
+    };
+    let declared = match declared_fields(file, code) {
+        Ok(declared) => declared,
+        Err(e) => {
+            issues.push(parse_failed(e.to_string()));
+            return;
+        }
+    };
+    for package in packages {
+        for rule in package.rules {
+            let path = format!("{}/{}", package.name.trim_end_matches('/'), rule.name);
+            // 语法树中找不到的规则不做字段检查 | Rules missing from the tree are not field-checked.
+            let (fields, dynamic) = match declared.iter().find(|d| d.rule == path) {
+                Some(d) => (
+                    d.names.clone(),
+                    d.types.iter().any(|t| DYNAMIC_TYPES.contains(&t.as_str())),
+                ),
+                None => (BTreeSet::new(), true),
+            };
+            rules.push(RuleInfo {
+                path,
+                location: format!("{}:{}", file.display(), rule.line),
+                expression: rule.body.join(" "),
+                fields,
+                dynamic,
+            });
+        }
+    }
+}
+
+/// 同名规则会遮蔽前者；同一包内表达式相同的后续规则是重复规则，永远不会命中
+/// （首个成功者胜出）。只比较表达式文本，不判断一条规则是否覆盖另一条 |
+/// A repeated rule path shadows the earlier one; a later rule in the same package
+/// with the same expression is a duplicate that never matches, because the first
+/// match wins. Only identical expressions are detected, not subsumption.
+fn lint_rules(rules: &[RuleInfo]) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut by_path: BTreeMap<&str, &RuleInfo> = BTreeMap::new();
+    let mut by_expr: BTreeMap<String, &RuleInfo> = BTreeMap::new();
+    for rule in rules {
+        if let Some(first) = by_path.get(rule.path.as_str()) {
+            issues.push(LintIssue {
+                severity: Severity::Error,
+                scope: "wpl",
+                msg: format!(
+                    "rule {} shadows the one defined at {}",
+                    rule.path, first.location
+                ),
+                file: rule.location.clone(),
+            });
+            continue;
+        }
+        by_path.insert(&rule.path, rule);
+        let package = rule.path.rsplit_once('/').map_or("", |(pkg, _)| pkg);
+        let key = format!(
+            "{}\n{}",
+            package,
+            rule.expression.split_whitespace().collect::<String>()
+        );
+        match by_expr.get(&key) {
+            Some(first) => issues.push(LintIssue {
+                severity: Severity::Warn,
+                scope: "wpl",
+                msg: format!(
+                    "duplicate rule {}: {} has the same expression and is tried first",
+                    rule.path, first.path
+                ),
+                file: rule.location.clone(),
+            }),
+            None => {
+                by_expr.insert(key, rule);
+            }
+        }
+    }
+    issues
+}
+
+fn lint_oml(omls: &[OmlInfo], rules: &[RuleInfo]) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    for oml in omls {
+        let patterns: Vec<Pattern> = oml
+            .selectors
+            .iter()
+            .filter_map(|s| Pattern::new(s).ok())
+            .collect();
+        let matched: Vec<&RuleInfo> = rules
+            .iter()
+            .filter(|r| patterns.iter().any(|p| p.matches(&r.path)))
+            .collect();
+        if matched.is_empty() {
+            if !oml.selectors.is_empty() {
+                issues.push(LintIssue {
+                    severity: Severity::Warn,
+                    scope: "oml",
+                    msg: format!(
+                        "rule selector {} matches no wpl rule",
+                        oml.selectors.join(" ")
+                    ),
+                    file: oml.file.clone(),
+                });
+            }
+            continue;
+        }
+        if matched.iter().any(|r| r.dynamic) {
+            continue;
+        }
+        let produced: BTreeSet<&str> = matched
+            .iter()
+            .flat_map(|r| r.fields.iter().map(String::as_str))
+            .collect();
+        for field in &oml.references {
+            if !produced.contains(field.as_str()) {
+                issues.push(LintIssue {
+                    severity: Severity::Warn,
+                    scope: "oml",
+                    msg: format!(
+                        "field {} is never produced by {}",
+                        field,
+                        matched
+                            .iter()
+                            .map(|r| r.path.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    file: oml.file.clone(),
+                });
+            }
+        }
+    }
+    issues
+}
+
+fn lint_routes(
+    routes: &[SinkRoute],
+    models: &BTreeSet<String>,
+    rules: &[RuleInfo],
+) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    for route in routes {
+        for name in &route.oml {
+            if !selects_any(name, models.iter().map(String::as_str)) {
+                issues.push(LintIssue {
+                    severity: Severity::Error,
+                    scope: "sink",
+                    msg: format!(
+                        "group {} routes oml {} but no such model exists",
+                        route.group, name
+                    ),
+                    file: route.file.clone(),
+                });
+            }
+        }
+        for name in &route.rule {
+            if !selects_any(name, rules.iter().map(|r| r.path.as_str())) {
+                issues.push(LintIssue {
+                    severity: Severity::Error,
+                    scope: "sink",
+                    msg: format!(
+                        "group {} routes rule {} but no such rule exists",
+                        route.group, name
+                    ),
+                    file: route.file.clone(),
+                });
+            }
+        }
+    }
+    issues
+}
+
+fn selects_any<'a>(selector: &str, mut names: impl Iterator<Item = &'a str>) -> bool {
+    match Pattern::new(selector) {
+        Ok(pattern) => names.any(|name| name == selector || pattern.matches(name)),
+        Err(_) => names.any(|name| name == selector),
+    }
+}
+
+/// 读取 sink 组配置中的 `oml` / `rule` 路由 | Read `oml` / `rule` routes of sink groups.
+fn load_sink_routes(sink_root: &Path) -> RunResult<Vec<SinkRoute>> {
+    let mut routes = Vec::new();
+    if !sink_root.is_dir() {
+        return Ok(routes);
+    }
+    let mut files: Vec<PathBuf> = WalkDir::new(sink_root)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    files.sort();
+    for file in files {
+        let value: toml::Value = toml::from_str(&read_source(&file)?).map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail(format!("parse {} failed", file.display()))
+                .with_source(e)
+        })?;
+        let Some(group) = value.get("sink_group") else {
+            continue;
+        };
+        let names = |key: &str| -> Vec<String> {
+            group
+                .get(key)
+                .and_then(toml::Value::as_array)
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(toml::Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        routes.push(SinkRoute {
+            file: file.display().to_string(),
+            group: group
+                .get("name")
+                .and_then(toml::Value::as_str)
+                .unwrap_or("?")
+                .to_string(),
+            oml: names("oml"),
+            rule: names("rule"),
+        });
+    }
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(code: &str) -> Vec<RuleInfo> {
+        rules_in(&[("a.wpl", code)])
+    }
+
+    fn rules_in(files: &[(&str, &str)]) -> Vec<RuleInfo> {
+        let mut rules = Vec::new();
+        let mut issues = Vec::new();
+        for (file, code) in files {
+            load_rules(Path::new(file), code, &mut rules, &mut issues);
+        }
+        assert!(issues.is_empty(), "{:?}", issues);
+        rules
+    }
+
+    #[test]
+    fn fields_come_from_the_wp_lang_syntax_tree() {
+        let rules = rules(
+            "package /n/ {\n rule a { (ip:sip,_^2,time/clf:recv_time<[,]>,http/request\",digit:code) }\n rule b { (digit:id,json) }\n rule c { (digit:json_size) }\n}\n",
+        );
+        assert_eq!(
+            rules[0].fields,
+            ["code", "recv_time", "sip"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        );
+        assert!(!rules[0].dynamic);
+        assert!(rules[1].dynamic);
+        assert!(!rules[2].dynamic);
+
+        let mut issues = Vec::new();
+        load_rules(
+            Path::new("bad.wpl"),
+            "package /n/ {\n rule a { (ip:sip }\n}\n",
+            &mut Vec::new(),
+            &mut issues,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn shadowed_and_duplicate_rules_are_reported() {
+        let rules = rules_in(&[
+            (
+                "a.wpl",
+                "package /n/ {\n rule a { (ip:sip, digit:code) }\n rule b { (ip:sip,digit:code) }\n}\n",
+            ),
+            ("b.wpl", "package /n/ {\n rule a { (digit) }\n}\n"),
+        ]);
+        let issues = lint_rules(&rules);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].severity, Severity::Warn);
+        assert_eq!(issues[0].file, "a.wpl:3");
+        assert!(issues[0].msg.contains("duplicate rule /n/b"));
+        assert_eq!(issues[1].severity, Severity::Error);
+        assert_eq!(
+            issues[1].line(),
+            "wpl: rule /n/a shadows the one defined at a.wpl:2 in b.wpl:2"
+        );
+    }
+
+    #[test]
+    fn oml_references_unknown_fields_and_dead_selectors() {
+        let code = "name : nginx\nrule : /n/*\n---\nsip : ip = take() ;\nport = read(dport) ;\nsrc = take(option:[sip, src_ip]) ;\n# x = take(ignored) ;\n* : auto = take() ;\n";
+        let references = oml_references(Path::new("n.oml"), code).expect("parse oml");
+        assert_eq!(
+            references,
+            ["dport", "sip", "src_ip"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        );
+        let rules = rules("package /n/ {\n rule a { (ip:sip,digit:dport) }\n}\n");
+        let omls = vec![
+            OmlInfo {
+                file: "n.oml".into(),
+                selectors: vec!["/n/*".into()],
+                references,
+            },
+            OmlInfo {
+                file: "old.oml".into(),
+                selectors: vec!["/old/*".into()],
+                references: BTreeSet::new(),
+            },
+        ];
+        let lines: Vec<String> = lint_oml(&omls, &rules)
+            .iter()
+            .map(LintIssue::line)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "oml: field src_ip is never produced by /n/a in n.oml",
+                "oml: rule selector /old/* matches no wpl rule in old.oml",
+            ]
+        );
+    }
+
+    #[test]
+    fn sink_routes_must_reference_existing_models() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir_all(dir.path().join("business.d")).expect("mkdir");
+        std::fs::write(
+            dir.path().join("business.d/demo.toml"),
+            "[sink_group]\nname = \"demo\"\noml = [\"*\", \"nginx\", \"gone\"]\nrule = [\"/n/*\"]\n",
+        )
+        .expect("write");
+        std::fs::write(dir.path().join("defaults.toml"), "[defaults]\n").expect("write");
+        let routes = load_sink_routes(dir.path()).expect("routes");
+        assert_eq!(routes.len(), 1);
+
+        let models: BTreeSet<String> = ["nginx".to_string()].into_iter().collect();
+        let rules = rules("package /n/ {\n rule a { (ip:sip) }\n}\n");
+        let issues = lint_routes(&routes, &models, &rules);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert!(issues[0].msg.contains("routes oml gone"));
+        assert!(lint_routes(&routes, &BTreeSet::new(), &rules).len() == 3);
+    }
+}
@@ -0,0 +1,45 @@
+use orion_variate::EnvDict;
+use wp_error::run_error::RunResult;
+
+use crate::args::RuleCmd;
+
+mod analyse;
+mod bench;
+mod convert;
+mod coverage;
+mod fmt;
+mod golden;
+mod infer;
+mod lint;
+mod oml;
+mod parse;
+mod wpl;
+
+pub(crate) use oml::{default_oml_root, oml_files, rule_selectors};
+pub(crate) use wpl::{collect_wpl_files, default_rule_root, package_rules, read_source};
+
+pub async fn dispatch_rule_cmd(sub: RuleCmd, quiet: bool, dict: &EnvDict) -> RunResult<()> {
+    match sub {
+        RuleCmd::Parse(args) => parse::run_rule_parse(args, quiet, dict),
+        RuleCmd::Analyse(args) => analyse::run_rule_analyse(args, dict),
+        RuleCmd::Test(args) => golden::run_rule_test(args, dict),
+        RuleCmd::Bench(args) => bench::run_rule_bench(args),
+        RuleCmd::Coverage(args) => coverage::run_rule_coverage(args),
+        RuleCmd::Infer(args) => infer::run_rule_infer(args),
+        RuleCmd::Convert(args) => convert::run_rule_convert(args),
+        RuleCmd::Fmt(args) => fmt::run_rule_fmt(args),
+        RuleCmd::Lint(args) => lint::run_rule_lint(args, dict).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::print_json;
+
+    #[test]
+    fn wproj_rule_handlers_json_ok_shape() {
+        let obj = serde_json::json!({"ok": true, "action": "verify"});
+        // ensure print_json handles simple objects
+        let _ = print_json(&obj);
+    }
+}
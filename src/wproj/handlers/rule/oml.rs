@@ -0,0 +1,223 @@
+//! OML 模型加载与知识库初始化，供 `wproj rule analyse` / `test` 执行转换。
+//!
+//! 与 wp-oml / wp-knowledge 的交互集中在本文件。
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+use orion_error::conversion::ToStructError;
+use orion_variate::EnvDict;
+use walkdir::WalkDir;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+use wp_model_core::model::DataRecord;
+use wp_oml::core::{DataTransformer, FieldQueryCache};
+use wp_oml::language::{DirectAccessor, EvalExp, ObjModel, PreciseEvaluator};
+use wp_oml::parser::oml_parse_raw;
+
+use super::wpl::{read_source, record_fields, ParsedField};
+
+const DEFAULT_KNOWDB_CONF: &str = "models/knowledge/knowdb.toml";
+const DEFAULT_OML_DIR: &str = "models/oml";
+const OML_EXT: &str = "oml";
+const AUTHORITY_DB: &str = "authority.sqlite";
+
+pub struct OmlModel {
+    path: PathBuf,
+    model: ObjModel,
+}
+
+impl OmlModel {
+    pub fn load(path: &Path) -> RunResult<Self> {
+        let model = parse_model(path, &read_source(path)?)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            model,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn transform(&self, record: DataRecord) -> Vec<ParsedField> {
+        let mut cache = FieldQueryCache::default();
+        record_fields(&self.model.transform(record, &mut cache))
+    }
+}
+
+/// wp-oml 语法树的文本形式，用于判断两份源码是否等价 |
+/// Text form of the wp-oml syntax tree, used to tell whether two sources are equivalent.
+pub fn oml_syntax_tree(origin: &Path, code: &str) -> RunResult<String> {
+    parse_model(origin, code).map(|model| format!("{:?}", model))
+}
+
+/// 遍历 wp-oml 语法树，列出 `take` / `read` 读取的输入字段；空参数读取与目标同名的字段 |
+/// Walk the wp-oml syntax tree for the input fields read by `take` / `read`;
+/// an accessor without arguments reads the target's own name.
+pub fn oml_references(origin: &Path, code: &str) -> RunResult<BTreeSet<String>> {
+    let model = parse_model(origin, code)?;
+    let mut refs = BTreeSet::new();
+    for item in model.items() {
+        // `* = take()` 这类批量赋值读取的字段由输入决定，不做检查
+        let EvalExp::Single(exp) = item else {
+            continue;
+        };
+        let accessor = match exp.eval_way() {
+            PreciseEvaluator::Obj(accessor) => accessor,
+            PreciseEvaluator::Pipe(pipe) => pipe.from(),
+            _ => continue,
+        };
+        let (DirectAccessor::Take(get) | DirectAccessor::Read(get)) = accessor;
+        let fields: Vec<String> = get.get().iter().chain(get.option()).cloned().collect();
+        if fields.is_empty() {
+            refs.extend(exp.target().iter().filter_map(|t| t.name().clone()));
+        } else {
+            refs.extend(fields);
+        }
+    }
+    Ok(refs)
+}
+
+fn parse_model(origin: &Path, code: &str) -> RunResult<ObjModel> {
+    oml_parse_raw(&mut &*code).map_err(|e| {
+        RunReason::from_conf().to_err().with_detail(format!(
+            "parse {} failed: {}",
+            origin.display(),
+            e
+        ))
+    })
+}
+
+/// 按 `rule :` 选择器为 WPL 规则挑选 OML 模型 | Picks the OML model for a WPL rule by its `rule :` selector.
+pub struct OmlCatalog {
+    models: Vec<(Vec<Pattern>, OmlModel)>,
+}
+
+impl OmlCatalog {
+    /// 目录不存在时返回空目录 | A missing directory yields an empty catalog.
+    pub fn load(dir: &Path) -> RunResult<Self> {
+        let mut models = Vec::new();
+        for file in oml_files(dir) {
+            let selectors = rule_selectors(&read_source(&file)?)
+                .iter()
+                .filter_map(|s| Pattern::new(s).ok())
+                .collect();
+            models.push((selectors, OmlModel::load(&file)?));
+        }
+        Ok(Self { models })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    pub fn select(&self, rule: &str) -> Option<&OmlModel> {
+        self.models
+            .iter()
+            .find(|(selectors, _)| selectors.iter().any(|p| p.matches(rule)))
+            .map(|(_, model)| model)
+    }
+}
+
+/// 目录下的 `.oml` 文件，按路径排序；目录不存在时为空 |
+/// `.oml` files under `dir`, sorted by path; empty when the directory is missing.
+pub fn oml_files(dir: &Path) -> Vec<PathBuf> {
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+    let mut files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == OML_EXT))
+        .collect();
+    files.sort();
+    files
+}
+
+pub fn default_oml_root(work_root: &str) -> PathBuf {
+    Path::new(work_root).join(DEFAULT_OML_DIR)
+}
+
+/// 读取 `---` 之前头部中的 `rule :` 选择器 | Reads `rule :` selectors from the header before `---`.
+pub(crate) fn rule_selectors(code: &str) -> Vec<String> {
+    code.lines()
+        .take_while(|line| line.trim() != "---")
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "rule").then_some(value)
+        })
+        .flat_map(|value| value.split([',', ' ']))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 已加载的知识库。权威库建在私有临时目录中，不会覆盖运行中 daemon 的
+/// `.run/authority.sqlite`，随本值释放一并删除 |
+/// A loaded knowledge base. The authority DB is built in a private temp dir,
+/// never over a running daemon's `.run/authority.sqlite`, and is removed
+/// when this value is dropped.
+pub struct KnowledgeBase {
+    _authority_dir: tempfile::TempDir,
+}
+
+/// 加载知识库，使 OML 中的查询可用；未显式指定且默认配置不存在时跳过 |
+/// Load the knowledge base for OML lookups; skipped when the default config is absent.
+pub fn load_knowledge(
+    work_root: &Path,
+    knowdb_conf: Option<&Path>,
+    dict: &EnvDict,
+) -> RunResult<Option<KnowledgeBase>> {
+    let conf = match knowdb_conf {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let path = work_root.join(DEFAULT_KNOWDB_CONF);
+            if !path.exists() {
+                return Ok(None);
+            }
+            path
+        }
+    };
+    let authority_dir = tempfile::Builder::new()
+        .prefix("wproj-knowdb-")
+        .tempdir()
+        .map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail("create knowledge temp dir failed".to_string())
+                .with_source(e)
+        })?;
+    let authority = format!(
+        "file:{}?mode=rwc&uri=true",
+        authority_dir.path().join(AUTHORITY_DB).display()
+    );
+    wp_knowledge::facade::init_thread_cloned_from_knowdb(work_root, &conf, &authority, dict)
+        .map_err(|e| {
+            RunReason::from_conf().to_err().with_detail(format!(
+                "load knowledge {} failed: {}",
+                conf.display(),
+                e
+            ))
+        })?;
+    Ok(Some(KnowledgeBase {
+        _authority_dir: authority_dir,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_selectors_come_from_the_header_only() {
+        let code = "name : nginx\nrule : /nginx/* /apache/access\n---\nrule : ignored\n";
+        assert_eq!(rule_selectors(code), vec!["/nginx/*", "/apache/access"]);
+        let pattern = Pattern::new("/nginx/*").expect("pattern");
+        assert!(pattern.matches("/nginx/example"));
+        assert!(!pattern.matches("/apache/access"));
+    }
+}
@@ -0,0 +1,356 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use orion_error::conversion::{SourceErr, ToStructError};
+use orion_variate::EnvDict;
+use serde::Serialize;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+use wp_proj::wparse::samples::parse_wpl_samples;
+
+use super::wpl::{default_rule_root, LineMatch, ParsedField, RuleSet};
+use crate::args::ParseArgs;
+
+const STDIN_PATH: &str = "-";
+const FIELD_VALUE_MAX: usize = 40;
+
+#[derive(Debug, Serialize)]
+struct LineReport<'a> {
+    line: usize,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    fields: &'a [ParsedField],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<&'a str>,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct ParseSummary {
+    input: String,
+    rules: String,
+    total: usize,
+    hit: usize,
+    miss: usize,
+    miss_rate: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_miss_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OutputOpts {
+    json: bool,
+    quiet: bool,
+}
+
+pub(super) fn run_rule_parse(args: ParseArgs, quiet: bool, dict: &EnvDict) -> RunResult<()> {
+    wp_log::conf::log_init(&wp_log::conf::LogConf::log_to_console("error"))
+        .source_err(RunReason::from_conf(), "init log failed")?;
+    if let Some(limit) = args.max_miss_rate {
+        if !(0.0..=100.0).contains(&limit) {
+            return Err(parse_conf_err(
+                "--max-miss-rate must be between 0 and 100".to_string(),
+            ));
+        }
+    }
+    // 未给输入时沿用旧行为：解析规则包自带的样本；stdin 需显式传 `-i -`
+    let Some(input) = args.in_path.as_deref() else {
+        return parse_wpl_samples(&args.work_root, dict);
+    };
+
+    let rule_root = args
+        .rule_file
+        .as_deref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| default_rule_root(&args.work_root));
+    let rules = RuleSet::load(&rule_root)?;
+    let opts = OutputOpts {
+        json: args.json,
+        quiet: args.quiet || quiet,
+    };
+    let reader: Box<dyn BufRead> = if input == STDIN_PATH {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        let file = File::open(input).map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail(format!("open input {} failed", input))
+                .with_source(e)
+        })?;
+        Box::new(BufReader::new(file))
+    };
+
+    let mut summary = ParseSummary {
+        input: if input == STDIN_PATH {
+            "stdin".to_string()
+        } else {
+            input.to_string()
+        },
+        rules: format!("{} ({} rules)", rules.root().display(), rules.len()),
+        max_miss_rate: args.max_miss_rate,
+        ..ParseSummary::default()
+    };
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    parse_lines(
+        reader,
+        |line| rules.eval_line(line),
+        &mut summary,
+        opts,
+        &mut out,
+    )?;
+    out.flush().map_err(write_err)?;
+    check_miss_rate(&summary)
+}
+
+/// 逐行求值并即时输出，空行不计数 | Evaluate and emit line by line; blank lines are skipped.
+fn parse_lines<R, F, W>(
+    reader: R,
+    mut eval: F,
+    summary: &mut ParseSummary,
+    opts: OutputOpts,
+    out: &mut W,
+) -> RunResult<()>
+where
+    R: BufRead,
+    F: FnMut(&str) -> LineMatch,
+    W: Write,
+{
+    if !opts.json {
+        writeln!(out, "Rule parse").map_err(write_err)?;
+        writeln!(out, "  Rules : {}", summary.rules).map_err(write_err)?;
+        writeln!(out, "  Input : {}", summary.input).map_err(write_err)?;
+    }
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail(format!("read input line {} failed", idx + 1))
+                .with_source(e)
+        })?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        summary.total += 1;
+        let outcome = eval(line);
+        let report = match &outcome {
+            LineMatch::Hit { rule, fields } => {
+                summary.hit += 1;
+                LineReport {
+                    line: idx + 1,
+                    ok: true,
+                    rule: Some(rule),
+                    fields,
+                    column: None,
+                    reason: None,
+                }
+            }
+            LineMatch::Miss {
+                rule,
+                column,
+                reason,
+            } => {
+                summary.miss += 1;
+                LineReport {
+                    line: idx + 1,
+                    ok: false,
+                    rule: rule.as_deref(),
+                    fields: &[],
+                    column: *column,
+                    reason: Some(reason),
+                }
+            }
+        };
+        if opts.quiet && report.ok {
+            continue;
+        }
+        emit_line(&report, opts.json, out)?;
+    }
+    if summary.total > 0 {
+        summary.miss_rate = summary.miss as f64 * 100.0 / summary.total as f64;
+    }
+    emit_summary(summary, opts.json, out)
+}
+
+fn emit_line<W: Write>(report: &LineReport<'_>, json: bool, out: &mut W) -> RunResult<()> {
+    if json {
+        let body = serde_json::to_string(report).map_err(|err| {
+            RunReason::from_conf()
+                .to_err()
+                .with_detail("serialize json failed")
+                .with_source(err)
+        })?;
+        return writeln!(out, "{}", body).map_err(write_err);
+    }
+    let detail = if report.ok {
+        report
+            .fields
+            .iter()
+            .map(|f| format!("{}={}", f.name, clip(&f.value)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    } else {
+        match report.column {
+            Some(col) => format!("col {}: {}", col, report.reason.unwrap_or("-")),
+            None => report.reason.unwrap_or("-").to_string(),
+        }
+    };
+    writeln!(
+        out,
+        "  {:>6}  {:<4}  {}  {}",
+        report.line,
+        if report.ok { "hit" } else { "miss" },
+        report.rule.unwrap_or("-"),
+        detail
+    )
+    .map_err(write_err)
+}
+
+fn emit_summary<W: Write>(summary: &ParseSummary, json: bool, out: &mut W) -> RunResult<()> {
+    if json {
+        let body =
+            serde_json::to_string(&serde_json::json!({ "summary": summary })).map_err(|err| {
+                RunReason::from_conf()
+                    .to_err()
+                    .with_detail("serialize json failed")
+                    .with_source(err)
+            })?;
+        return writeln!(out, "{}", body).map_err(write_err);
+    }
+    writeln!(
+        out,
+        "  Summary : {} lines, {} hit, {} miss ({:.2}% miss)",
+        summary.total, summary.hit, summary.miss, summary.miss_rate
+    )
+    .map_err(write_err)
+}
+
+fn check_miss_rate(summary: &ParseSummary) -> RunResult<()> {
+    match summary.max_miss_rate {
+        Some(limit) if summary.miss_rate > limit => {
+            Err(RunReason::from_biz().to_err().with_detail(format!(
+                "miss rate {:.2}% exceeds --max-miss-rate {:.2}% ({} of {} lines)",
+                summary.miss_rate, limit, summary.miss, summary.total
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn clip(value: &str) -> String {
+    if value.chars().count() <= FIELD_VALUE_MAX {
+        return value.to_string();
+    }
+    let head: String = value.chars().take(FIELD_VALUE_MAX - 3).collect();
+    format!("{}...", head)
+}
+
+fn parse_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+fn write_err(e: io::Error) -> wp_error::RunError {
+    RunReason::from_conf()
+        .to_err()
+        .with_detail("write output failed")
+        .with_source(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_eval(line: &str) -> LineMatch {
+        match line.split_once(' ') {
+            Some((ip, status)) if status.parse::<u16>().is_ok() => LineMatch::Hit {
+                rule: "demo/access".to_string(),
+                fields: vec![
+                    ParsedField {
+                        name: "ip".to_string(),
+                        kind: "ip".to_string(),
+                        value: ip.to_string(),
+                    },
+                    ParsedField {
+                        name: "status".to_string(),
+                        kind: "digit".to_string(),
+                        value: status.to_string(),
+                    },
+                ],
+            },
+            _ => LineMatch::Miss {
+                rule: Some("demo/access".to_string()),
+                column: line.find(' ').map(|p| p + 2),
+                reason: "expect digit".to_string(),
+            },
+        }
+    }
+
+    fn run(input: &str, opts: OutputOpts, max: Option<f64>) -> (ParseSummary, String) {
+        let mut summary = ParseSummary {
+            input: "stdin".to_string(),
+            rules: "models/wpl (1 rules)".to_string(),
+            max_miss_rate: max,
+            ..ParseSummary::default()
+        };
+        let mut out = Vec::new();
+        parse_lines(input.as_bytes(), fake_eval, &mut summary, opts, &mut out).expect("parse");
+        (summary, String::from_utf8(out).expect("utf8"))
+    }
+
+    #[test]
+    fn table_output_reports_fields_and_failure_column() {
+        let (summary, out) = run(
+            "10.0.0.1 200\n\n10.0.0.2 GET\n",
+            OutputOpts {
+                json: false,
+                quiet: false,
+            },
+            None,
+        );
+        assert_eq!((summary.total, summary.hit, summary.miss), (2, 1, 1));
+        assert!(out.contains("     1  hit   demo/access  ip=10.0.0.1, status=200"));
+        assert!(out.contains("     3  miss  demo/access  col 10: expect digit"));
+        assert!(out.contains("Summary : 2 lines, 1 hit, 1 miss (50.00% miss)"));
+        assert!(check_miss_rate(&summary).is_ok());
+    }
+
+    #[test]
+    fn json_lines_end_with_summary_and_quiet_drops_hits() {
+        let (summary, out) = run(
+            "10.0.0.1 200\r\n10.0.0.2 GET\n10.0.0.3 404\n",
+            OutputOpts {
+                json: true,
+                quiet: true,
+            },
+            Some(20.0),
+        );
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("json line"))
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["line"], 2);
+        assert_eq!(lines[0]["ok"], false);
+        assert_eq!(lines[0]["column"], 10);
+        assert!(lines[0].get("fields").is_none());
+        assert_eq!(lines[1]["summary"]["miss"], 1);
+        assert_eq!(lines[1]["summary"]["max_miss_rate"], 20.0);
+
+        let err = check_miss_rate(&summary).expect_err("33% > 20%");
+        assert!(err.to_string().contains("exceeds --max-miss-rate"));
+    }
+
+    #[test]
+    fn long_field_values_are_clipped_in_tables() {
+        assert_eq!(clip("short"), "short");
+        let long = "x".repeat(64);
+        let clipped = clip(&long);
+        assert_eq!(clipped.chars().count(), FIELD_VALUE_MAX);
+        assert!(clipped.ends_with("..."));
+    }
+}
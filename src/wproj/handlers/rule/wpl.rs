@@ -0,0 +1,509 @@
+//! WPL 规则加载与逐行求值，供 `wproj rule` 各子命令共用。
+//!
+//! 与 wp-lang 的交互集中在本文件；其余逻辑只依赖 [`RuleSet`] 与 [`LineMatch`]。
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use orion_error::conversion::ToStructError;
+use serde::Serialize;
+use walkdir::WalkDir;
+use warp_parse::compat::UvsFrom;
+use wp_error::run_error::{RunReason, RunResult};
+use wp_lang::{WplCode, WplEvaluator, WplPackage, WplStatementType};
+use wp_model_core::model::DataRecord;
+use wp_model_core::raw::RawData;
+
+const WPL_EXT: &str = "wpl";
+const DEFAULT_RULE_DIR: &str = "models/wpl";
+
+/// 单个提取字段 | One extracted field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ParsedField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: String,
+}
+
+/// 单行求值结果 | Outcome of evaluating one input line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineMatch {
+    Hit {
+        rule: String,
+        fields: Vec<ParsedField>,
+    },
+    Miss {
+        /// 推进最远的候选规则 | The candidate rule that got furthest
+        rule: Option<String>,
+        /// 1 起始的字符列号 | 1-based character column
+        column: Option<usize>,
+        reason: String,
+    },
+}
+
+struct LoadedRule {
+    name: String,
+    evaluator: WplEvaluator,
+}
+
+/// 已编译的规则集合，按文件与声明顺序求值 | Compiled rules, tried in file and declaration order.
+pub struct RuleSet {
+    root: PathBuf,
+    rules: Vec<LoadedRule>,
+}
+
+impl RuleSet {
+    pub fn load(path: &Path) -> RunResult<Self> {
+        let mut rules = Vec::new();
+        for file in collect_wpl_files(path)? {
+            let code = read_source(&file)?;
+            rules.extend(compile_rules(&file, &code)?);
+        }
+        if rules.is_empty() {
+            return Err(rule_conf_err(format!(
+                "no wpl rule found under {}",
+                path.display()
+            )));
+        }
+        Ok(Self {
+            root: path.to_path_buf(),
+            rules,
+        })
+    }
+
+    /// 从内存中的 WPL 源码编译，`origin` 仅用于报错 | Compile WPL source held in memory.
+    pub fn from_code(origin: &Path, code: &str) -> RunResult<Self> {
+        let rules = compile_rules(origin, code)?;
+        if rules.is_empty() {
+            return Err(rule_conf_err(format!(
+                "no wpl rule found in {}",
+                origin.display()
+            )));
+        }
+        Ok(Self {
+            root: origin.to_path_buf(),
+            rules,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// 按求值顺序列出规则路径 | Rule paths in evaluation order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.name.as_str())
+    }
+
+    /// 依次尝试各规则，首个完整消费整行者命中；全部失败时返回推进最远的失败 |
+    /// The first rule that consumes the whole line wins; otherwise report the
+    /// furthest failure. A rule that leaves input behind counts as a miss at
+    /// the start of the leftover, as the engine would route it to residue.
+    pub fn eval_line(&self, line: &str) -> LineMatch {
+        self.eval_line_with(line, |_, record| record_fields(&record))
+    }
+
+    /// 与 [`RuleSet::eval_line`] 相同，命中时由 `output` 生成字段（如经 OML 转换）|
+    /// Like [`RuleSet::eval_line`], with `output` building the fields of a hit (e.g. through OML).
+    pub fn eval_line_with<F>(&self, line: &str, output: F) -> LineMatch
+    where
+        F: FnOnce(&str, DataRecord) -> Vec<ParsedField>,
+    {
+        match self.eval_record(line) {
+            Ok((rule, record)) => LineMatch::Hit {
+                rule: rule.to_string(),
+                fields: output(rule, record),
+            },
+            Err(miss) => miss,
+        }
+    }
+
+    /// 与 [`RuleSet::eval_line`] 相同，但保留原始记录供 OML 继续处理 |
+    /// Like [`RuleSet::eval_line`], keeping the record for OML.
+    pub fn eval_record(&self, line: &str) -> Result<(&str, DataRecord), LineMatch> {
+        let mut best: Option<LineMatch> = None;
+        for rule in &self.rules {
+            match rule
+                .evaluator
+                .proc(RawData::from_string(line.to_string()), 0)
+            {
+                Ok((record, residue)) => {
+                    let residue = residue.to_string();
+                    let Some(column) = residue_column(line, &residue) else {
+                        return Ok((rule.name.as_str(), record));
+                    };
+                    let miss = LineMatch::Miss {
+                        rule: Some(rule.name.clone()),
+                        column: Some(column),
+                        reason: format!("unparsed input left: \"{}\"", residue.trim()),
+                    };
+                    if further(&miss, best.as_ref()) {
+                        best = Some(miss);
+                    }
+                }
+                Err(err) => {
+                    let detail = err.to_string();
+                    let miss = LineMatch::Miss {
+                        rule: Some(rule.name.clone()),
+                        column: locate_failure(line, &detail),
+                        reason: first_line(&detail),
+                    };
+                    if further(&miss, best.as_ref()) {
+                        best = Some(miss);
+                    }
+                }
+            }
+        }
+        Err(best.unwrap_or_else(|| LineMatch::Miss {
+            rule: None,
+            column: None,
+            reason: "no rule loaded".to_string(),
+        }))
+    }
+}
+
+pub fn record_fields(record: &DataRecord) -> Vec<ParsedField> {
+    record
+        .items
+        .iter()
+        .map(|field| ParsedField {
+            name: field.get_name().to_string(),
+            kind: field.get_meta().to_string(),
+            value: field.get_value().to_string(),
+        })
+        .collect()
+}
+
+/// 把单条规则表达式包装成可独立编译、保存的 WPL 包 |
+/// Wrap a bare rule expression into a standalone WPL package.
+pub fn wrap_expression(package: &str, rule: &str, expr: &str) -> String {
+    format!(
+        "package {} {{\n   rule {} {{\n        {}\n   }}\n}}\n",
+        package,
+        rule,
+        expr.trim()
+    )
+}
+
+/// 未指定规则路径时使用 `<work_root>/models/wpl` | Default rule root under the work root.
+pub fn default_rule_root(work_root: &str) -> PathBuf {
+    Path::new(work_root).join(DEFAULT_RULE_DIR)
+}
+
+/// 收集 `.wpl` 文件：单文件直接返回，目录递归并按路径排序 |
+/// A file is returned as is; a directory is walked and sorted by path.
+pub fn collect_wpl_files(path: &Path) -> RunResult<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !path.is_dir() {
+        return Err(rule_conf_err(format!(
+            "wpl path not found: {}",
+            path.display()
+        )));
+    }
+    let mut files: Vec<PathBuf> = WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == WPL_EXT))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+pub fn read_source(path: &Path) -> RunResult<String> {
+    fs::read_to_string(path).map_err(|e| {
+        RunReason::from_conf()
+            .to_err()
+            .with_detail(format!("read {} failed", path.display()))
+            .with_source(e)
+    })
+}
+
+/// 规则在 wp-lang 语法树中声明的字段 | Fields a rule declares in its wp-lang syntax tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclaredFields {
+    pub rule: String,
+    /// 命名字段 | Named fields
+    pub names: BTreeSet<String>,
+    /// 出现过的字段类型，如 `ip`、`json` | Field types used, e.g. `ip`, `json`
+    pub types: BTreeSet<String>,
+}
+
+/// 用 wp-lang 解析源码，列出每条规则声明的字段 |
+/// Parse the source with wp-lang and list the fields each rule declares.
+pub fn declared_fields(origin: &Path, code: &str) -> RunResult<Vec<DeclaredFields>> {
+    let package = parse_package(origin, code)?;
+    Ok(package
+        .rules
+        .iter()
+        .map(|rule| {
+            let WplStatementType::Express(express) = &rule.statement;
+            let fields = express.group.iter().flat_map(|group| group.fields.iter());
+            let mut declared = DeclaredFields {
+                rule: rule_path(&package.name, &rule.name),
+                names: BTreeSet::new(),
+                types: BTreeSet::new(),
+            };
+            for field in fields {
+                declared.types.insert(field.meta_name.to_string());
+                if let Some(name) = field.name.as_deref() {
+                    declared.names.insert(name.to_string());
+                }
+            }
+            declared
+        })
+        .collect())
+}
+
+/// 用 wp-lang 解析源码，返回包名与各规则路径 |
+/// Parse the source with wp-lang and return the package name with its rule paths.
+pub fn package_rules(origin: &Path, code: &str) -> RunResult<(String, Vec<String>)> {
+    let package = parse_package(origin, code)?;
+    let rules = package
+        .rules
+        .iter()
+        .map(|rule| rule_path(&package.name, &rule.name))
+        .collect();
+    Ok((package.name.to_string(), rules))
+}
+
+/// wp-lang 语法树的文本形式，用于判断两份源码是否等价 |
+/// Text form of the wp-lang syntax tree, used to tell whether two sources are equivalent.
+pub fn syntax_tree(origin: &Path, code: &str) -> RunResult<String> {
+    Ok(format!("{:?}", parse_package(origin, code)?))
+}
+
+fn parse_package(origin: &Path, code: &str) -> RunResult<WplPackage> {
+    WplCode::build(origin.to_path_buf(), code)
+        .and_then(|code| code.parse_pkg())
+        .map_err(|e| rule_conf_err(format!("parse {} failed: {}", origin.display(), e)))
+}
+
+fn compile_rules(origin: &Path, code: &str) -> RunResult<Vec<LoadedRule>> {
+    let package = parse_package(origin, code)?;
+    let mut rules = Vec::with_capacity(package.rules.len());
+    for rule in &package.rules {
+        let name = rule_path(&package.name, &rule.name);
+        let WplStatementType::Express(express) = &rule.statement;
+        let evaluator = WplEvaluator::from(express, None)
+            .map_err(|e| rule_conf_err(format!("build rule {} failed: {}", name, e)))?;
+        rules.push(LoadedRule { name, evaluator });
+    }
+    Ok(rules)
+}
+
+/// 包名可写作 `/nginx/`，规则路径与 OML `rule :` 选择器保持一致 |
+/// Package names may be written as `/nginx/`; the path matches OML `rule :` selectors.
+fn rule_path(package: &str, rule: &str) -> String {
+    format!("{}/{}", package.trim_end_matches('/'), rule)
+}
+
+/// 剩余输入非空时返回其起始列（1 起始）；只剩空白视为完整消费 |
+/// 1-based column where the leftover input starts; `None` when only
+/// whitespace is left.
+fn residue_column(line: &str, residue: &str) -> Option<usize> {
+    let rest = residue.trim_start();
+    if rest.trim_end().is_empty() {
+        return None;
+    }
+    let offset = line.len().checked_sub(rest.len())?;
+    line.get(offset..)
+        .filter(|suffix| *suffix == rest)
+        .map(|_| line[..offset].chars().count() + 1)
+}
+
+/// wp-lang 报错时以引号给出解析停止处的剩余输入；取其中恰为行后缀的一段定位失败列 |
+/// wp-lang reports where parsing stopped by quoting the remaining input; the
+/// quoted fragment that is exactly a suffix of the line gives the column.
+fn locate_failure(line: &str, detail: &str) -> Option<usize> {
+    let line = line.trim_end();
+    quoted_fragments(detail)
+        .filter_map(|fragment| {
+            let fragment = fragment.trim_end();
+            if fragment.is_empty() {
+                return None;
+            }
+            let offset = line.strip_suffix(fragment).map(str::len)?;
+            Some(line[..offset].chars().count() + 1)
+        })
+        .min()
+}
+
+fn quoted_fragments(detail: &str) -> impl Iterator<Item = &str> {
+    detail.split('"').skip(1).step_by(2)
+}
+
+fn further(candidate: &LineMatch, current: Option<&LineMatch>) -> bool {
+    let column = |m: &LineMatch| match m {
+        LineMatch::Miss { column, .. } => column.unwrap_or(0),
+        LineMatch::Hit { .. } => 0,
+    };
+    match current {
+        Some(cur) => column(candidate) > column(cur),
+        None => true,
+    }
+}
+
+fn first_line(detail: &str) -> String {
+    detail.lines().next().unwrap_or("").trim().to_string()
+}
+
+fn rule_conf_err(detail: String) -> wp_error::RunError {
+    RunReason::from_conf().to_err().with_detail(detail)
+}
+
+/// 处理器测试共用的真实 nginx 规则包 | A real nginx package shared by the handler tests.
+#[cfg(test)]
+pub(super) mod fixtures {
+    use std::fs;
+    use std::path::Path;
+
+    pub const NGINX_RULE: &str = "/example/nginx";
+    pub const NGINX_WPL: &str = r#"package /example/ {
+   rule nginx {
+        (ip:sip,_^2,chars:timestamp<[,]>,http/request",chars:status,chars:size,chars:referer",http/agent",_")
+   }
+}
+"#;
+    pub const NGINX_LINE: &str = r#"222.133.52.20 - - [06/Aug/2019:12:12:19 +0800] "GET /nginx-logo.png HTTP/1.1" 200 368 "http://119.122.1.4/" "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/75.0.3770.142 Safari/537.36" "-""#;
+
+    /// 写入 `<root>/example/parse.wpl` 并返回包目录 | Writes `<root>/example/parse.wpl` and returns the package dir.
+    pub fn write_nginx_package(root: &Path) -> std::path::PathBuf {
+        let package = root.join("example");
+        fs::create_dir_all(&package).expect("mkdir package");
+        fs::write(package.join("parse.wpl"), NGINX_WPL).expect("write wpl");
+        package
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::*;
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn collect_wpl_files_walks_packages_in_path_order() {
+        let dir = tempdir().expect("tempdir");
+        fs::create_dir_all(dir.path().join("nginx")).expect("mkdir");
+        fs::write(dir.path().join("nginx/access.wpl"), "").expect("write");
+        fs::write(dir.path().join("apache.wpl"), "").expect("write");
+        fs::write(dir.path().join("nginx/sample.dat"), "").expect("write");
+
+        let files = collect_wpl_files(dir.path()).expect("collect");
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join("apache.wpl"),
+                dir.path().join("nginx/access.wpl")
+            ]
+        );
+        assert_eq!(
+            collect_wpl_files(&dir.path().join("apache.wpl")).expect("single file"),
+            vec![dir.path().join("apache.wpl")]
+        );
+        assert!(collect_wpl_files(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn locate_failure_uses_the_quoted_remaining_input() {
+        let line = "10.0.0.1 GET /index 200";
+        assert_eq!(
+            locate_failure(line, "expect digit at: \"GET /index 200\""),
+            Some(10)
+        );
+        // A quoted token that merely occurs in the line is not a position.
+        assert_eq!(locate_failure(line, "expect \"GET\" at: \"200\""), Some(21));
+        assert_eq!(locate_failure(line, "expect \"GET\""), None);
+        assert_eq!(locate_failure(line, "unexpected end of rule"), None);
+    }
+
+    #[test]
+    fn residue_column_points_at_the_leftover() {
+        let line = "10.0.0.1 GET /index";
+        assert_eq!(residue_column(line, " GET /index"), Some(10));
+        assert_eq!(residue_column(line, ""), None);
+        assert_eq!(residue_column(line, "  \n"), None);
+    }
+
+    #[test]
+    fn real_package_loads_and_evaluates_lines() {
+        let dir = tempdir().expect("tempdir");
+        write_nginx_package(dir.path());
+
+        let rules = RuleSet::load(dir.path()).expect("load rules");
+        assert_eq!(rules.names().collect::<Vec<_>>(), vec![NGINX_RULE]);
+        let LineMatch::Hit { rule, fields } = rules.eval_line(NGINX_LINE) else {
+            panic!("nginx line should match");
+        };
+        assert_eq!(rule, NGINX_RULE);
+        let value = |name: &str| {
+            fields
+                .iter()
+                .find(|field| field.name == name)
+                .map(|field| field.value.as_str())
+        };
+        assert_eq!(value("sip"), Some("222.133.52.20"));
+        assert_eq!(value("status"), Some("200"));
+        assert_eq!(value("size"), Some("368"));
+
+        match rules.eval_line("not an access log line") {
+            LineMatch::Miss { rule, .. } => assert_eq!(rule.as_deref(), Some(NGINX_RULE)),
+            hit => panic!("unexpected hit: {:?}", hit),
+        }
+    }
+
+    #[test]
+    fn leftover_input_is_reported_as_a_miss() {
+        let rules = RuleSet::from_code(
+            Path::new("inline.wpl"),
+            "package /inline/ {\n   rule ip {\n        (ip:sip)\n   }\n}\n",
+        )
+        .expect("compile rule");
+        match rules.eval_line("10.0.0.1 trailing words") {
+            LineMatch::Miss {
+                rule,
+                column,
+                reason,
+            } => {
+                assert_eq!(rule.as_deref(), Some("/inline/ip"));
+                assert_eq!(column, Some(10));
+                assert_eq!(reason, "unparsed input left: \"trailing words\"");
+            }
+            hit => panic!("partial match must not be a hit: {:?}", hit),
+        }
+        assert!(matches!(rules.eval_line("10.0.0.1"), LineMatch::Hit { .. }));
+    }
+
+    #[test]
+    fn rule_paths_and_wrapped_expressions() {
+        assert_eq!(rule_path("/nginx/", "example"), "/nginx/example");
+        assert_eq!(rule_path("nginx", "access"), "nginx/access");
+        assert_eq!(
+            wrap_expression("/draft/", "main", " (ip:sip,_^2) \n"),
+            "package /draft/ {\n   rule main {\n        (ip:sip,_^2)\n   }\n}\n"
+        );
+    }
+
+    #[test]
+    fn furthest_miss_is_kept() {
+        let miss = |column| LineMatch::Miss {
+            rule: None,
+            column,
+            reason: String::new(),
+        };
+        assert!(further(&miss(None), None));
+        assert!(further(&miss(Some(5)), Some(&miss(Some(2)))));
+        assert!(!further(&miss(Some(2)), Some(&miss(Some(5)))));
+        assert!(!further(&miss(None), Some(&miss(Some(1)))));
+    }
+}
@@ -1,4 +1,5 @@
 use clap::Parser;
+mod alloc_count;
 mod args;
 mod format;
 use std::env;
@@ -8,6 +9,10 @@ use wp_error::run_error::RunResult;
 
 use crate::args::WProjCli;
 mod handlers;
+
+#[global_allocator]
+static GLOBAL: alloc_count::CountingAlloc = alloc_count::CountingAlloc;
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
     if let Err(e) = do_main().await {
@@ -17,9 +22,10 @@ async fn main() {
 }
 
 async fn do_main() -> RunResult<()> {
-    let (_pre_quiet, filtered_args) = split_quiet_args(env::args().collect());
+    let (pre_quiet, filtered_args) = split_quiet_args(env::args().collect());
     warp_parse::feats::register_for_runtime();
-    let wcl = WProjCli::parse_from(&filtered_args);
+    let mut wcl = WProjCli::parse_from(&filtered_args);
+    wcl.quiet |= pre_quiet;
     handlers::cli::dispatch_cli(wcl).await
 }
 // Banner is centralized in wp-cli-utils
@@ -19,6 +19,8 @@ struct FixtureOptions {
     reload_timeout_ms: Option<u64>,
     broken_runtime_release: bool,
     invalid_conf_release: bool,
+    poll_interval_secs: Option<u64>,
+    webhook: bool,
 }
 
 fn reserve_local_addr() -> SocketAddr {
@@ -511,6 +513,23 @@ fn create_remote_project_repo_with_options(
         temp.path().to_str().expect("repo path utf8"),
         "1.4.2",
     );
+    if let Some(interval_secs) = options.poll_interval_secs {
+        let conf_path = temp.path().join("conf/wparse.toml");
+        let mut conf = fs::read_to_string(&conf_path).expect("read wparse.toml");
+        conf.push_str(&format!(
+            "\n[project_remote.poll]\ninterval_secs = {interval_secs}\npolicy = \"patch\"\n"
+        ));
+        fs::write(conf_path, conf).expect("write project_remote poll config");
+    }
+    if options.webhook {
+        let conf_path = temp.path().join("conf/wparse.toml");
+        let mut conf = fs::read_to_string(&conf_path).expect("read wparse.toml");
+        conf.push_str(
+            "\n[project_remote.webhook]\nenabled = true\nsecret_file = \"runtime/webhook.secret\"\n",
+        );
+        fs::write(conf_path, conf).expect("write project_remote webhook config");
+        write_file(temp.path(), "runtime/webhook.secret", "webhook-secret\n");
+    }
     write_file(temp.path(), "models/version.txt", "1.4.2\n");
     let repo = Repository::init(temp.path()).expect("init remote repo");
     commit_all(&repo, "release 1.4.2");
@@ -543,6 +562,11 @@ fn clone_project_repo(remote_path: &Path) -> tempfile::TempDir {
         .permissions();
     perms.set_mode(0o600);
     fs::set_permissions(&token_path, perms).expect("chmod cloned token");
+    let secret_path = temp.path().join("runtime/webhook.secret");
+    if secret_path.exists() {
+        fs::set_permissions(&secret_path, fs::Permissions::from_mode(0o600))
+            .expect("chmod cloned webhook secret");
+    }
     temp
 }
 
@@ -1032,6 +1056,175 @@ async fn daemon_admin_api_reload_with_update_moves_project_to_target_version() {
     shutdown_child(&mut child);
 }
 
+#[tokio::test]
+#[serial]
+async fn daemon_project_remote_poll_applies_newer_release_and_reports_status() {
+    let bind = reserve_local_addr();
+    let source_bind = reserve_local_addr();
+    let remote = create_remote_project_repo_with_options(
+        bind,
+        source_bind,
+        FixtureOptions {
+            poll_interval_secs: Some(1),
+            ..FixtureOptions::default()
+        },
+    );
+    let clone = clone_project_repo(remote.path());
+    checkout_tag(clone.path(), "v1.4.2");
+
+    let base_url = format!("http://{}", bind);
+    let mut child = spawn_wparse(clone.path(), "daemon");
+
+    wait_until_ready(
+        &mut child,
+        clone.path(),
+        &base_url,
+        "test-token",
+        Duration::from_secs(20),
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let status_body = loop {
+        let resp = client
+            .get(format!("{}/admin/v1/runtime/status", base_url))
+            .bearer_auth("test-token")
+            .send()
+            .await
+            .expect("send status request");
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = resp.json().await.expect("decode status response");
+        if body["project_version"] == "1.4.3" && !body["reloading"].as_bool().unwrap_or(true) {
+            break body;
+        }
+        if Instant::now() >= deadline {
+            shutdown_child(&mut child);
+            panic!(
+                "poller did not converge: {}\nlog:\n{}",
+                body,
+                read_wparse_log(clone.path())
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    };
+
+    let poll = &status_body["project_remote_poll"];
+    assert_eq!(poll["policy"], "patch");
+    assert_eq!(poll["interval_secs"], 1);
+    // A later tick may already have found nothing newer.
+    assert!(
+        poll["last_result"] == "updated" || poll["last_result"] == "up_to_date",
+        "unexpected poll status: {}",
+        poll
+    );
+    assert_eq!(poll["last_applied"], "1.4.3");
+    assert_eq!(poll["consecutive_failures"], 0);
+    assert_eq!(
+        fs::read_to_string(clone.path().join("models/version.txt"))
+            .expect("read updated version marker"),
+        "1.4.3\n"
+    );
+    assert!(read_wparse_log(clone.path()).contains("project remote poll updated"));
+
+    shutdown_child(&mut child);
+}
+
+#[tokio::test]
+#[serial]
+async fn daemon_project_webhook_tag_push_updates_and_reloads() {
+    let bind = reserve_local_addr();
+    let source_bind = reserve_local_addr();
+    let remote = create_remote_project_repo_with_options(
+        bind,
+        source_bind,
+        FixtureOptions {
+            webhook: true,
+            ..FixtureOptions::default()
+        },
+    );
+    let clone = clone_project_repo(remote.path());
+    checkout_tag(clone.path(), "v1.4.2");
+
+    let base_url = format!("http://{}", bind);
+    let mut child = spawn_wparse(clone.path(), "daemon");
+    wait_until_ready(
+        &mut child,
+        clone.path(),
+        &base_url,
+        "test-token",
+        Duration::from_secs(20),
+    )
+    .await;
+
+    // A Gitea tag push, replayed with the remote as the pushed repository.
+    let payload = serde_json::json!({
+        "ref": "refs/tags/v1.4.3",
+        "before": "0000000000000000000000000000000000000000",
+        "after": "9f1e2d3c4b5a69788796a5b4c3d2e1f009182736",
+        "commits": [],
+        "repository": {
+            "name": "wp-rule",
+            "clone_url": remote.path().to_str().expect("remote path utf8"),
+        },
+        "pusher": {"login": "release-bot"}
+    })
+    .to_string();
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, b"webhook-secret");
+    let signature: String = ring::hmac::sign(&key, payload.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("{}/admin/v1/project/webhook", base_url))
+        .header("X-Gitea-Event", "push")
+        .header("X-Gitea-Signature", signature)
+        .body(payload)
+        .send()
+        .await
+        .expect("send webhook");
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    let body: Value = resp.json().await.expect("decode webhook response");
+    assert_eq!(body["result"], "queued");
+    assert_eq!(body["provider"], "gitea");
+    assert_eq!(body["targets"][0]["version"], "1.4.3");
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        let status: Value = client
+            .get(format!("{}/admin/v1/runtime/status", base_url))
+            .bearer_auth("test-token")
+            .send()
+            .await
+            .expect("send status request")
+            .json()
+            .await
+            .expect("decode status response");
+        if status["project_version"] == "1.4.3" && !status["reloading"].as_bool().unwrap_or(true) {
+            break;
+        }
+        if Instant::now() >= deadline {
+            shutdown_child(&mut child);
+            panic!(
+                "webhook update did not converge: {}\nlog:\n{}",
+                status,
+                read_wparse_log(clone.path())
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    assert_eq!(
+        fs::read_to_string(clone.path().join("models/version.txt"))
+            .expect("read updated version marker"),
+        "1.4.3\n"
+    );
+    assert!(read_wparse_log(clone.path()).contains("project remote webhook updated"));
+
+    shutdown_child(&mut child);
+}
+
 #[tokio::test]
 #[serial]
 async fn daemon_admin_api_reload_with_update_rolls_back_on_reload_failure() {
@@ -1071,6 +1264,7 @@ async fn daemon_admin_api_reload_with_update_rolls_back_on_reload_failure() {
             "wait": true,
             "update": true,
             "version": "1.4.3",
+            "skip_validate": true,
             "timeout_ms": 15000,
             "reason": "integration reload rollback"
         }))
@@ -1112,6 +1306,79 @@ async fn daemon_admin_api_reload_with_update_rolls_back_on_reload_failure() {
     shutdown_child(&mut child);
 }
 
+#[tokio::test]
+#[serial]
+async fn daemon_admin_api_update_blocked_by_validation_keeps_current_version() {
+    let bind = reserve_local_addr();
+    let source_bind = reserve_local_addr();
+    let remote = create_remote_project_repo_with_options(
+        bind,
+        source_bind,
+        FixtureOptions {
+            invalid_conf_release: true,
+            ..FixtureOptions::default()
+        },
+    );
+    let clone = clone_project_repo(remote.path());
+    checkout_tag(clone.path(), "v1.4.2");
+
+    let base_url = format!("http://{}", bind);
+    let mut child = spawn_wparse(clone.path(), "daemon");
+
+    wait_until_ready(
+        &mut child,
+        clone.path(),
+        &base_url,
+        "test-token",
+        Duration::from_secs(20),
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    let resp = client
+        .post(format!("{}/admin/v1/reloads/model", base_url))
+        .bearer_auth("test-token")
+        .header("X-Request-Id", "integration-reload-update-blocked-1")
+        .json(&serde_json::json!({
+            "wait": true,
+            "update": true,
+            "version": "1.4.3",
+            "timeout_ms": 15000,
+            "reason": "integration reload blocked by validation"
+        }))
+        .send()
+        .await
+        .expect("send reload update request");
+
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = resp.json().await.expect("decode blocked response");
+    assert_eq!(body["accepted"], false);
+    assert_eq!(body["result"], "validation_failed");
+    assert_eq!(body["resolved_tag"], "v1.4.3");
+    assert_eq!(body["validation"]["status"], "failed");
+    assert!(
+        !body["validation"]["failures"]
+            .as_array()
+            .expect("failures array")
+            .is_empty(),
+        "expected validation failures, got: {}",
+        body
+    );
+    assert_eq!(
+        fs::read_to_string(clone.path().join("models/version.txt"))
+            .expect("read version marker after blocked update"),
+        "1.4.2\n"
+    );
+    assert!(
+        !fs::read_to_string(clone.path().join("conf/wparse.toml"))
+            .expect("read engine conf after blocked update")
+            .contains("this is not valid toml"),
+        "blocked release must not leave its conf behind"
+    );
+
+    shutdown_child(&mut child);
+}
+
 #[tokio::test]
 #[serial]
 async fn daemon_admin_api_rejects_busy_update_without_changing_project_version() {
@@ -1397,6 +1664,7 @@ async fn wproj_conf_update_rolls_back_when_runtime_load_check_fails() {
             clone.path().to_str().expect("work root utf8"),
             "--version",
             "1.4.3",
+            "--skip-validate",
         ],
     );
     assert!(
@@ -1544,7 +1812,33 @@ async fn wproj_conf_update_and_reload_update_flow_work_against_local_project_rem
         "test-token\n"
     );
 
+    // Checking out the old tag by hand is local drift against 1.4.3.
     checkout_tag(clone.path(), "v1.4.2");
+    let status = run_wproj(
+        clone.path(),
+        &[
+            "conf",
+            "status",
+            "--work-root",
+            clone.path().to_str().expect("work root utf8"),
+            "--json",
+        ],
+    );
+    assert!(
+        status.status.success(),
+        "wproj conf status failed: stdout=\n{}\nstderr=\n{}",
+        String::from_utf8_lossy(&status.stdout),
+        String::from_utf8_lossy(&status.stderr)
+    );
+    let status_body: Value = serde_json::from_slice(&status.stdout).expect("decode status json");
+    assert_eq!(status_body[0]["version"], "1.4.3");
+    assert_eq!(status_body[0]["status"], "drifted");
+    assert!(status_body[0]["modified"]
+        .as_array()
+        .expect("modified list")
+        .iter()
+        .any(|path| path == "models/version.txt"));
+
     let base_url = format!("http://{}", bind);
     let mut child = spawn_wparse(clone.path(), "daemon");
 
@@ -1569,6 +1863,7 @@ async fn wproj_conf_update_and_reload_update_flow_work_against_local_project_rem
             "--update",
             "--version",
             "1.4.3",
+            "--discard-local",
             "--json",
         ],
     );